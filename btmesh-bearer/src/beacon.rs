@@ -1,8 +1,9 @@
 use btmesh_common::{NetworkId, Uuid};
+use btmesh_pdu::provisioned::beacon::SecureNetworkBeacon;
 
 #[derive(Copy, Clone)]
 pub enum Beacon {
     Unprovisioned(Uuid),
     Provisioned(NetworkId),
-    Secure, /* (NetworkId?) */
+    Secure(SecureNetworkBeacon),
 }
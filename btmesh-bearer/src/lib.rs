@@ -55,4 +55,12 @@ pub trait GattBearer<const MTU: usize> {
 
     /// Transmit data on the bearer.
     fn advertise<'m>(&'m self, adv_data: &'m Vec<u8, 64>) -> Self::AdvertiseFuture<'m>;
+
+    /// Number of connections accepted so far, changing whenever a new
+    /// client connects so per-connection state may be reset.
+    fn connection_count(&self) -> u32;
+
+    /// Size of the largest PDU that may be transmitted over the current
+    /// connection, as allowed by its negotiated ATT MTU and never exceeding `MTU`.
+    fn mtu(&self) -> usize;
 }
@@ -8,11 +8,13 @@ use ccm::consts::U13;
 use ccm::consts::U4;
 use ccm::consts::U8;
 use ccm::Ccm;
-use cmac::crypto_mac::{InvalidKeyLength, Output};
+use cmac::crypto_mac::Output;
 use cmac::{Cmac, Mac, NewMac};
 use core::convert::TryInto;
 use heapless::Vec;
 
+pub use cmac::crypto_mac::InvalidKeyLength;
+
 pub mod application;
 pub mod device;
 pub mod network;
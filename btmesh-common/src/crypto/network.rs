@@ -1,7 +1,8 @@
-use crate::crypto::nonce::NetworkNonce;
+use crate::address::UnicastAddress;
+use crate::crypto::nonce::{NetworkNonce, ProxyNonce};
 use crate::crypto::{aes_ccm_decrypt_detached, aes_ccm_encrypt_detached};
 use crate::mic::InvalidLength;
-use crate::{crypto, NetworkId, ParseError};
+use crate::{crypto, IvIndex, NetworkId, ParseError};
 use ccm::aead::Error;
 use cmac::crypto_mac::InvalidKeyLength;
 use core::ops::Deref;
@@ -148,7 +149,28 @@ impl Deref for PrivacyKey {
     }
 }
 
-#[derive(Copy, Clone, Debug, Hash)]
+#[derive(Default, Eq, PartialEq, Copy, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(::defmt::Format))]
+pub struct BeaconKey([u8; 16]);
+
+impl BeaconKey {
+    pub fn new(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl Deref for BeaconKey {
+    type Target = [u8; 16];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+const ID128: [u8; 6] = [b'i', b'd', b'1', b'2', b'8', 0x01];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NetworkKey {
     network_key: [u8; 16],
@@ -213,6 +235,42 @@ impl NetworkKey {
     pub fn nid(&self) -> Nid {
         self.nid
     }
+
+    /// Derive the key used to authenticate secure network beacons.
+    pub fn beacon_key(&self) -> Result<BeaconKey, InvalidKeyLength> {
+        let salt = crypto::s1(b"nkbk")?;
+        let beacon_key = crypto::k1(&self.network_key, &salt.into_bytes(), &ID128)?.into_bytes();
+        Ok(BeaconKey(
+            beacon_key.try_into().map_err(|_| InvalidKeyLength)?,
+        ))
+    }
+
+    /// Derive the friendship security credentials of a friendship between a low
+    /// power node and its friend, established with the counters given.
+    pub fn friendship_credentials(
+        &self,
+        lpn_address: UnicastAddress,
+        friend_address: UnicastAddress,
+        lpn_counter: u16,
+        friend_counter: u16,
+    ) -> Result<Self, InvalidKeyLength> {
+        let mut p = [0; 9];
+        p[0] = 0x01;
+        p[1..3].copy_from_slice(&lpn_address.as_bytes());
+        p[3..5].copy_from_slice(&friend_address.as_bytes());
+        p[5..7].copy_from_slice(&lpn_counter.to_be_bytes());
+        p[7..9].copy_from_slice(&friend_counter.to_be_bytes());
+
+        let (nid, encryption_key, privacy_key) = crypto::k2(&self.network_key, &p)?;
+
+        Ok(Self {
+            network_key: self.network_key,
+            privacy_key: PrivacyKey(privacy_key),
+            encryption_key: EncryptionKey(encryption_key),
+            nid: Nid::new(nid),
+            network_id: self.network_id,
+        })
+    }
 }
 
 pub fn try_decrypt_network(
@@ -245,9 +303,61 @@ pub fn encrypt_network(
     )
 }
 
+pub fn try_decrypt_proxy(
+    network_key: &NetworkKey,
+    nonce: &ProxyNonce,
+    payload: &mut [u8],
+    mic: &NetMic,
+) -> Result<(), Error> {
+    aes_ccm_decrypt_detached(
+        &*network_key.encryption_key,
+        &**nonce,
+        payload,
+        mic.as_ref(),
+        None,
+    )
+}
+
+pub fn encrypt_proxy(
+    network_key: &NetworkKey,
+    nonce: &ProxyNonce,
+    payload: &mut [u8],
+    mic: &mut NetMic,
+) -> Result<(), Error> {
+    aes_ccm_encrypt_detached(
+        &*network_key.encryption_key,
+        &**nonce,
+        payload,
+        mic.as_mut(),
+        None,
+    )
+}
+
+/// Calculate the authentication value of a secure network beacon.
+pub fn beacon_authentication_value(
+    beacon_key: &BeaconKey,
+    flags: u8,
+    network_id: &NetworkId,
+    iv_index: IvIndex,
+) -> Result<[u8; 8], InvalidKeyLength> {
+    let mut input = [0; 13];
+    input[0] = flags;
+    input[1..9].copy_from_slice(network_id);
+    input[9..13].copy_from_slice(&iv_index.to_be_bytes());
+
+    let cmac = crypto::aes_cmac(&**beacon_key, &input)?.into_bytes();
+    let mut authentication_value = [0; 8];
+    authentication_value.copy_from_slice(&cmac[0..8]);
+    Ok(authentication_value)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::crypto::network::{EncryptionKey, NetworkKey, Nid, PrivacyKey};
+    use crate::address::UnicastAddress;
+    use crate::crypto::network::{
+        beacon_authentication_value, BeaconKey, EncryptionKey, NetworkKey, Nid, PrivacyKey,
+    };
+    use crate::{IvIndex, NetworkId};
 
     #[test]
     fn network_key_derivation() {
@@ -272,4 +382,81 @@ mod test {
         assert_eq!(privacy_key, network_key.privacy_key());
         assert_eq!(encryption_key, network_key.encryption_key());
     }
+
+    #[test]
+    fn friendship_credentials_derivation() {
+        // 8.1.4 k2 function (Friendship)
+        let network_key = NetworkKey::new([
+            0xf7, 0xa2, 0xa4, 0x4f, 0x8e, 0x8a, 0x80, 0x29, 0x06, 0x4f, 0x17, 0x3d, 0xdc, 0x1e,
+            0x2b, 0x00,
+        ])
+        .unwrap();
+
+        let credentials = network_key
+            .friendship_credentials(
+                UnicastAddress::new(0x0203).unwrap(),
+                UnicastAddress::new(0x0405).unwrap(),
+                0x0607,
+                0x0809,
+            )
+            .unwrap();
+
+        let encryption_key = EncryptionKey::new([
+            0x11, 0xef, 0xec, 0x06, 0x42, 0x77, 0x49, 0x92, 0x51, 0x0f, 0xb5, 0x92, 0x96, 0x46,
+            0xdf, 0x49,
+        ]);
+
+        let privacy_key = PrivacyKey::new([
+            0xd4, 0xd7, 0xcc, 0x0d, 0xfa, 0x77, 0x2d, 0x83, 0x6a, 0x8d, 0xf9, 0xdf, 0x55, 0x10,
+            0xd7, 0xa7,
+        ]);
+
+        assert_eq!(Nid::new(0x73), credentials.nid());
+        assert_eq!(privacy_key, credentials.privacy_key());
+        assert_eq!(encryption_key, credentials.encryption_key());
+        assert_eq!(network_key.network_id(), credentials.network_id());
+    }
+
+    #[test]
+    fn beacon_key_derivation() {
+        // 8.4.6.1 Secure Network beacon
+        let network_key = NetworkKey::new([
+            0x7d, 0xd7, 0x36, 0x4c, 0xd8, 0x42, 0xad, 0x18, 0xc1, 0x7c, 0x2b, 0x82, 0x0c, 0x84,
+            0xc3, 0xd6,
+        ])
+        .unwrap();
+
+        let beacon_key = BeaconKey::new([
+            0x54, 0x23, 0xd9, 0x67, 0xda, 0x63, 0x9a, 0x99, 0xcb, 0x02, 0x23, 0x1a, 0x83, 0xf7,
+            0xd2, 0x54,
+        ]);
+
+        assert_eq!(beacon_key, network_key.beacon_key().unwrap());
+    }
+
+    #[test]
+    fn secure_network_beacon_authentication() {
+        // 8.4.6.1 Secure Network beacon
+        let network_key = NetworkKey::new([
+            0x7d, 0xd7, 0x36, 0x4c, 0xd8, 0x42, 0xad, 0x18, 0xc1, 0x7c, 0x2b, 0x82, 0x0c, 0x84,
+            0xc3, 0xd6,
+        ])
+        .unwrap();
+
+        let network_id = NetworkId::new([0x3e, 0xca, 0xff, 0x67, 0x2f, 0x67, 0x33, 0x70]);
+        assert_eq!(network_id, network_key.network_id());
+
+        let authentication_value = beacon_authentication_value(
+            &network_key.beacon_key().unwrap(),
+            0x00,
+            &network_id,
+            IvIndex::new(0x12345678),
+        )
+        .unwrap();
+
+        assert_eq!(
+            [0x8e, 0xa2, 0x61, 0x58, 0x2f, 0x36, 0x4f, 0x6f],
+            authentication_value
+        );
+    }
 }
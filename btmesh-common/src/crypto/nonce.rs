@@ -164,6 +164,44 @@ impl Deref for DeviceNonce {
 
 pub struct ProxyNonce([u8; 13]);
 
+impl ProxyNonce {
+    const NONCE_TYPE: NonceType = NonceType(0x03);
+
+    pub fn new(seq: Seq, src: UnicastAddress, iv_index: IvIndex) -> Self {
+        let mut nonce = [0; 13];
+        nonce[0] = Self::NONCE_TYPE.0;
+        nonce[1] = 0x00;
+
+        let seq = seq.to_be_bytes();
+        nonce[2] = seq[1];
+        nonce[3] = seq[2];
+        nonce[4] = seq[3];
+
+        let src = src.as_bytes();
+        nonce[5] = src[0];
+        nonce[6] = src[1];
+
+        nonce[7] = 0x00;
+        nonce[8] = 0x00;
+
+        let iv_index = iv_index.to_be_bytes();
+        nonce[9] = iv_index[0];
+        nonce[10] = iv_index[1];
+        nonce[11] = iv_index[2];
+        nonce[12] = iv_index[3];
+
+        Self(nonce)
+    }
+}
+
+impl Deref for ProxyNonce {
+    type Target = [u8; 13];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::address::UnicastAddress;
@@ -205,7 +205,7 @@ impl From<Ivi> for u8 {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Ttl(u8);
@@ -261,7 +261,8 @@ impl Seq {
 
 impl From<Seq> for SeqZero {
     fn from(seq: Seq) -> Self {
-        Self(seq.0 as u16)
+        // only the 13 least-significant bits of the sequence number.
+        Self((seq.0 & 0x1FFF) as u16)
     }
 }
 
@@ -346,6 +347,7 @@ impl Deref for Uuid {
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct NetworkId([u8; 8]);
 
 impl NetworkId {
@@ -362,7 +364,7 @@ impl Deref for NetworkId {
     }
 }
 
-#[derive(Eq, PartialEq, Copy, Clone)]
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg_attr(feature = "darling", derive(darling::FromMeta))]
@@ -391,7 +393,7 @@ pub struct ProductIdentifier(pub u16);
 pub struct VersionIdentifier(pub u16);
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum ModelIdentifier {
     SIG(u16),
     Vendor(CompanyIdentifier, u16),
@@ -536,7 +538,8 @@ impl ElementDescriptor {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Features {
     pub relay: bool,
@@ -546,8 +549,18 @@ pub struct Features {
 }
 
 impl Features {
-    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
-        // bits 15-8 RFU
+    /// Parse the features bit field, ignoring the RFU bits.
+    pub fn parse(bits: u16) -> Self {
+        Self {
+            relay: bits & 0b0001 != 0,
+            proxy: bits & 0b0010 != 0,
+            friend: bits & 0b0100 != 0,
+            low_power: bits & 0b1000 != 0,
+        }
+    }
+
+    pub fn bits(&self) -> u16 {
+        // bits 15-4 RFU
         let mut val = 0;
         if self.relay {
             val |= 0b0001;
@@ -561,8 +574,12 @@ impl Features {
         if self.low_power {
             val |= 0b1000;
         }
-        xmit.push(val).map_err(|_| InsufficientBuffer)?;
-        xmit.push(0).map_err(|_| InsufficientBuffer)?;
+        val
+    }
+
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        xmit.extend_from_slice(&self.bits().to_le_bytes())
+            .map_err(|_| InsufficientBuffer)?;
         Ok(())
     }
 }
@@ -593,7 +610,8 @@ impl Default for Features {
 
 #[cfg(test)]
 mod test {
-    use crate::{IvIndex, IvUpdateFlag, Ivi};
+    use crate::{Features, IvIndex, IvUpdateFlag, Ivi, Seq, SeqZero};
+    use heapless::Vec;
 
     #[test]
     fn iv_index_zero() {
@@ -629,4 +647,25 @@ mod test {
         assert_eq!(iv_index, iv_index.accepted_iv_index(Ivi::One));
         assert_eq!(prev_iv_index, iv_index.accepted_iv_index(Ivi::Zero));
     }
+
+    #[test]
+    fn features() {
+        let features = Features::parse(0xFFF5);
+        assert!(features.relay);
+        assert!(!features.proxy);
+        assert!(features.friend);
+        assert!(!features.low_power);
+        assert_eq!(0x0005, features.bits());
+
+        let mut xmit = Vec::<u8, 2>::new();
+        features.emit(&mut xmit).unwrap();
+        assert_eq!(&[0x05, 0x00], &*xmit);
+    }
+
+    #[test]
+    fn seq_zero_from_seq() {
+        assert_eq!(0x0123, SeqZero::from(Seq::new(0x0123)).value());
+        assert_eq!(0x0123, SeqZero::from(Seq::new(0x4123)).value());
+        assert_eq!(0x1FFF, SeqZero::from(Seq::new(0xFFFFFF)).value());
+    }
 }
@@ -17,15 +17,30 @@ pub use btmesh_common::{
 use btmesh_common::{IvIndex, Ttl};
 pub use btmesh_models::Model;
 use core::future::Future;
+use core::sync::atomic::{AtomicU32, Ordering};
 use embassy_util::blocking_mutex::raw::CriticalSectionRawMutex;
 pub use embassy_util::channel::mpmc::{Channel, Receiver, Sender};
+use embassy_util::channel::signal::Signal;
+use embassy_util::mutex::Mutex;
 pub use futures::future::join;
 use heapless::Vec;
 
 pub type InboundChannelImpl = Channel<CriticalSectionRawMutex, InboundPayload, 1>;
 pub type InboundSenderImpl = Sender<'static, CriticalSectionRawMutex, InboundPayload, 1>;
 pub type InboundReceiverImpl = Receiver<'static, CriticalSectionRawMutex, InboundPayload, 1>;
-pub type InboundPayload = (Option<usize>, Opcode, Vec<u8, 380>, InboundMetadata);
+pub type InboundPayload = (Option<usize>, InboundBody);
+
+/// Models of an element eligible to receive an inbound message.
+pub type InboundModels = Vec<ModelIdentifier, 8>;
+
+/// Payload delivered towards the models of an element.
+#[derive(Clone)]
+pub enum InboundBody {
+    /// An access message addressed to the listed models of the element.
+    Message(Opcode, Vec<u8, 380>, InboundMetadata, InboundModels),
+    /// The publish period of the identified model has elapsed.
+    PublicationDue(ModelIdentifier),
+}
 
 pub type OutboundChannelImpl = Channel<CriticalSectionRawMutex, OutboundPayload, 1>;
 pub type OutboundSenderImpl = Sender<'static, CriticalSectionRawMutex, OutboundPayload, 1>;
@@ -34,9 +49,123 @@ pub type OutboundPayload = (
     (usize, ModelIdentifier),
     Opcode,
     Vec<u8, 379>,
-    OutboundMetadata,
+    OutboundTarget,
 );
 
+/// Where an outbound message from a model should be delivered.
+#[derive(Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OutboundTarget {
+    /// Send using explicitly provided metadata, such as a reply,
+    /// optionally notifying the sender once delivery has completed.
+    Send(OutboundMetadata, Option<CompletionToken>),
+    /// Publish using the publication state configured for the model,
+    /// notifying the publisher once the publication has completed.
+    Publish(CompletionToken),
+}
+
+/// Outcome of sending a message.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CompletionStatus {
+    /// The message was transmitted, and every segment acknowledged if it was
+    /// segmented towards a unicast address.
+    Complete,
+    /// Delivery of the message was cancelled before it completed.
+    Incomplete,
+    /// No publication is configured for the model, so nothing was published.
+    NotConfigured,
+}
+
+/// Reports the outcome of one request at a time back to its sender.
+///
+/// Requests are numbered, so the outcome of a request abandoned by its
+/// sender is never mistaken for the outcome of a later one.
+pub struct Completion {
+    lock: Mutex<CriticalSectionRawMutex, ()>,
+    request: AtomicU32,
+    signal: Signal<CompletionStatus>,
+}
+
+impl Default for Completion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Completion {
+    pub const fn new() -> Self {
+        Self {
+            lock: Mutex::new(()),
+            request: AtomicU32::new(0),
+            signal: Signal::new(),
+        }
+    }
+
+    /// Submit a request through `submit`, waiting until its outcome is reported
+    /// through the provided token.
+    pub async fn request<F: Future<Output = ()>>(
+        &'static self,
+        submit: impl FnOnce(CompletionToken) -> F,
+    ) -> CompletionStatus {
+        let _guard = self.lock.lock().await;
+        let request = self.request.load(Ordering::Relaxed).wrapping_add(1);
+        self.request.store(request, Ordering::Relaxed);
+        self.signal.reset();
+        submit(CompletionToken {
+            completion: self,
+            request,
+        })
+        .await;
+        self.signal.wait().await
+    }
+}
+
+/// Handle through which the driver reports the outcome of sending a message.
+#[derive(Copy, Clone)]
+pub struct CompletionToken {
+    completion: &'static Completion,
+    request: u32,
+}
+
+impl CompletionToken {
+    pub fn complete(&self) {
+        self.signal(CompletionStatus::Complete)
+    }
+
+    pub fn incomplete(&self) {
+        self.signal(CompletionStatus::Incomplete)
+    }
+
+    pub fn not_configured(&self) {
+        self.signal(CompletionStatus::NotConfigured)
+    }
+
+    fn signal(&self, status: CompletionStatus) {
+        // outcomes of abandoned requests are dropped.
+        if self.completion.request.load(Ordering::Relaxed) == self.request {
+            self.completion.signal.signal(status)
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for CompletionToken {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "CompletionToken({})", self.request)
+    }
+}
+
+/// Reasons a model publication may fail.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PublishError {
+    /// No publication has been configured for the model.
+    NotConfigured,
+    /// The publication could not be encoded or transmitted.
+    Failed,
+}
+
 pub trait BluetoothMeshDeviceContext {
     type ElementContext: BluetoothMeshElementContext;
 
@@ -108,6 +237,13 @@ pub trait BluetoothMeshModel<M: Model> {
     }
 }
 
+/// Events delivered to a model through `BluetoothMeshModelContext::receive_event`.
+pub enum ModelEvent<M: Model> {
+    Message(M::Message, InboundMetadata),
+    /// The configured publish period elapsed, and the model should publish its state.
+    PublicationDue,
+}
+
 pub trait BluetoothMeshModelContext<M: Model> {
     type ReceiveFuture<'f>: Future<Output = (M::Message, InboundMetadata)> + 'f
     where
@@ -116,6 +252,14 @@ pub trait BluetoothMeshModelContext<M: Model> {
 
     fn receive(&self) -> Self::ReceiveFuture<'_>;
 
+    type ReceiveEventFuture<'f>: Future<Output = ModelEvent<M>> + 'f
+    where
+        Self: 'f,
+        M: 'f;
+
+    /// Receive either the next message, or notification of periodic publication being due.
+    fn receive_event(&self) -> Self::ReceiveEventFuture<'_>;
+
     type SendFuture<'f>: Future<Output = Result<(), ()>> + 'f
     where
         Self: 'f,
@@ -123,11 +267,26 @@ pub trait BluetoothMeshModelContext<M: Model> {
 
     fn send(&self, message: M::Message, meta: OutboundMetadata) -> Self::SendFuture<'_>;
 
-    type PublishFuture<'f>: Future<Output = Result<(), ()>> + 'f
+    type SendWithCompletionFuture<'f>: Future<Output = CompletionStatus> + 'f
+    where
+        Self: 'f,
+        M: 'f;
+
+    /// Send a message, resolving once it has been delivered, or once its
+    /// delivery has been cancelled, such as when segments remain unacknowledged.
+    fn send_with_completion(
+        &self,
+        message: M::Message,
+        meta: OutboundMetadata,
+    ) -> Self::SendWithCompletionFuture<'_>;
+
+    type PublishFuture<'f>: Future<Output = Result<(), PublishError>> + 'f
     where
         Self: 'f,
         M: 'f;
 
+    /// Publish a message using the publication state configured for the model,
+    /// resolving once it has been published, or once publishing has failed.
     fn publish(&self, message: M::Message) -> Self::PublishFuture<'_>;
 }
 
@@ -176,6 +335,10 @@ impl InboundMetadata {
         self.ttl
     }
 
+    pub fn network_key_handle(&self) -> NetworkKeyHandle {
+        self.network_key_handle
+    }
+
     pub fn reply(&self) -> OutboundMetadata {
         OutboundMetadata {
             dst: self.src.into(),
@@ -184,6 +347,7 @@ impl InboundMetadata {
             key_handle: self.key_handle,
             label_uuid: self.label_uuid,
             ttl: None,
+            friendship_credentials: false,
         }
     }
 }
@@ -198,14 +362,40 @@ pub struct OutboundMetadata {
     key_handle: KeyHandle,
     label_uuid: Option<LabelUuid>,
     ttl: Option<Ttl>,
+    friendship_credentials: bool,
 }
 
 impl OutboundMetadata {
+    pub fn new(
+        dst: Address,
+        network_key_handle: NetworkKeyHandle,
+        iv_index: IvIndex,
+        key_handle: KeyHandle,
+        label_uuid: Option<LabelUuid>,
+        ttl: Option<Ttl>,
+    ) -> Self {
+        Self {
+            dst,
+            network_key_handle,
+            iv_index,
+            key_handle,
+            label_uuid,
+            ttl,
+            friendship_credentials: false,
+        }
+    }
+
     pub fn with_ttl(mut self, ttl: Ttl) -> Self {
         self.ttl.replace(ttl);
         self
     }
 
+    /// Secure the message using friendship credentials rather than master credentials.
+    pub fn with_friendship_credentials(mut self, friendship_credentials: bool) -> Self {
+        self.friendship_credentials = friendship_credentials;
+        self
+    }
+
     pub fn dst(&self) -> Address {
         self.dst
     }
@@ -229,6 +419,10 @@ impl OutboundMetadata {
     pub fn ttl(&self) -> Option<Ttl> {
         self.ttl
     }
+
+    pub fn friendship_credentials(&self) -> bool {
+        self.friendship_credentials
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, PartialOrd)]
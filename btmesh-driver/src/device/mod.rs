@@ -1,13 +1,29 @@
 use btmesh_common::ModelIdentifier;
 use btmesh_device::{
-    BluetoothMeshDeviceContext, BluetoothMeshElementContext, BluetoothMeshModelContext,
-    InboundMetadata, InboundPayload, InboundReceiverImpl, Model, OutboundMetadata,
-    OutboundSenderImpl,
+    BluetoothMeshDeviceContext, BluetoothMeshElementContext, BluetoothMeshModelContext, Completion,
+    CompletionStatus, InboundBody, InboundMetadata, InboundPayload, InboundReceiverImpl, Model,
+    ModelEvent, OutboundMetadata, OutboundSenderImpl, OutboundTarget, PublishError,
 };
 use btmesh_models::Message;
 use core::future::Future;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use heapless::Vec;
 
+#[allow(clippy::declare_interior_mutable_const)]
+const COMPLETION: Completion = Completion::new();
+
+/// Completions shared between model contexts, each serving one request at a time.
+static COMPLETIONS: [Completion; 8] = [COMPLETION; 8];
+
+static NEXT_COMPLETION: AtomicUsize = AtomicUsize::new(0);
+
+/// Hand out completions in turn, so models only share one once all are in use.
+fn next_completion() -> &'static Completion {
+    let next = NEXT_COMPLETION.load(Ordering::Relaxed);
+    NEXT_COMPLETION.store(next.wrapping_add(1), Ordering::Relaxed);
+    &COMPLETIONS[next % COMPLETIONS.len()]
+}
+
 pub(crate) struct DeviceContext {
     inbound: InboundReceiverImpl,
     outbound: OutboundSenderImpl,
@@ -57,12 +73,13 @@ impl BluetoothMeshElementContext for ElementContext {
         _index: usize,
         inbound: InboundReceiverImpl,
     ) -> Self::ModelContext<M> {
-        ModelContext {
-            element_index: self.element_index,
-            model_identifier: M::IDENTIFIER,
+        ModelContext::new(
+            self.element_index,
+            M::IDENTIFIER,
             inbound,
-            outbound: self.outbound.clone(),
-        }
+            self.outbound.clone(),
+            next_completion(),
+        )
     }
 
     type ReceiveFuture<'f> = impl Future<Output =InboundPayload> + 'f
@@ -79,6 +96,25 @@ pub(crate) struct ModelContext {
     model_identifier: ModelIdentifier,
     inbound: InboundReceiverImpl,
     outbound: OutboundSenderImpl,
+    completion: &'static Completion,
+}
+
+impl ModelContext {
+    pub(crate) fn new(
+        element_index: usize,
+        model_identifier: ModelIdentifier,
+        inbound: InboundReceiverImpl,
+        outbound: OutboundSenderImpl,
+        completion: &'static Completion,
+    ) -> Self {
+        Self {
+            element_index,
+            model_identifier,
+            inbound,
+            outbound,
+            completion,
+        }
+    }
 }
 
 impl<M: Model> BluetoothMeshModelContext<M> for ModelContext {
@@ -90,15 +126,44 @@ impl<M: Model> BluetoothMeshModelContext<M> for ModelContext {
     fn receive(&self) -> Self::ReceiveFuture<'_> {
         async move {
             loop {
-                let (_index, opcode, parameters, meta) = self.inbound.recv().await;
-
-                if let Ok(Some(message)) = M::parse(opcode, &*parameters) {
+                if let ModelEvent::Message(message, meta) =
+                    BluetoothMeshModelContext::<M>::receive_event(self).await
+                {
                     return (message, meta);
                 }
             }
         }
     }
 
+    type ReceiveEventFuture<'f> = impl Future<Output = ModelEvent<M>> + 'f
+    where
+        Self: 'f,
+        M: 'f;
+
+    fn receive_event(&self) -> Self::ReceiveEventFuture<'_> {
+        async move {
+            loop {
+                let (_index, body) = self.inbound.recv().await;
+
+                match body {
+                    InboundBody::Message(opcode, parameters, meta, models) => {
+                        if !models.contains(&self.model_identifier) {
+                            continue;
+                        }
+                        if let Ok(Some(message)) = M::parse(opcode, &*parameters) {
+                            return ModelEvent::Message(message, meta);
+                        }
+                    }
+                    InboundBody::PublicationDue(model_identifier) => {
+                        if model_identifier == self.model_identifier {
+                            return ModelEvent::PublicationDue;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     type SendFuture<'f> = impl Future<Output = Result<(), ()>> + 'f
     where
         Self: 'f,
@@ -114,7 +179,7 @@ impl<M: Model> BluetoothMeshModelContext<M> for ModelContext {
                         (self.element_index, self.model_identifier),
                         opcode,
                         parameters,
-                        meta,
+                        OutboundTarget::Send(meta, None),
                     ))
                     .await
             }
@@ -123,12 +188,204 @@ impl<M: Model> BluetoothMeshModelContext<M> for ModelContext {
         }
     }
 
-    type PublishFuture<'f> = impl Future<Output = Result<(), ()>> + 'f
+    type SendWithCompletionFuture<'f> = impl Future<Output = CompletionStatus> + 'f
     where
         Self: 'f,
         M: 'f;
 
-    fn publish(&self, _message: M::Message) -> Self::PublishFuture<'_> {
-        async move { todo!() }
+    fn send_with_completion(
+        &self,
+        message: M::Message,
+        meta: OutboundMetadata,
+    ) -> Self::SendWithCompletionFuture<'_> {
+        async move {
+            let opcode = message.opcode();
+            let mut parameters = Vec::new();
+            if message.emit_parameters(&mut parameters).is_err() {
+                return CompletionStatus::Incomplete;
+            }
+
+            self.completion
+                .request(|completion_token| {
+                    self.outbound.send((
+                        (self.element_index, self.model_identifier),
+                        opcode,
+                        parameters,
+                        OutboundTarget::Send(meta, Some(completion_token)),
+                    ))
+                })
+                .await
+        }
+    }
+
+    type PublishFuture<'f> = impl Future<Output = Result<(), PublishError>> + 'f
+    where
+        Self: 'f,
+        M: 'f;
+
+    fn publish(&self, message: M::Message) -> Self::PublishFuture<'_> {
+        async move {
+            let opcode = message.opcode();
+            let mut parameters = Vec::new();
+            message
+                .emit_parameters(&mut parameters)
+                .map_err(|_| PublishError::Failed)?;
+
+            let status = self
+                .completion
+                .request(|completion_token| {
+                    self.outbound.send((
+                        (self.element_index, self.model_identifier),
+                        opcode,
+                        parameters,
+                        OutboundTarget::Publish(completion_token),
+                    ))
+                })
+                .await;
+
+            match status {
+                CompletionStatus::Complete => Ok(()),
+                CompletionStatus::Incomplete => Err(PublishError::Failed),
+                CompletionStatus::NotConfigured => Err(PublishError::NotConfigured),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::ModelContext;
+    use btmesh_common::address::UnicastAddress;
+    use btmesh_common::crypto::network::Nid;
+    use btmesh_common::IvIndex;
+    use btmesh_device::{
+        BluetoothMeshModelContext, Completion, CompletionStatus, CompletionToken,
+        InboundChannelImpl, KeyHandle, NetworkKeyHandle, OutboundChannelImpl, OutboundMetadata,
+        OutboundTarget, PublishError,
+    };
+    use btmesh_models::generic::onoff::{
+        GenericOnOffMessage, GenericOnOffServer, GENERIC_ONOFF_SERVER,
+    };
+    use futures::executor::block_on;
+    use futures::future::join;
+    use futures::FutureExt;
+
+    fn model_context(
+        inbound: &'static InboundChannelImpl,
+        outbound: &'static OutboundChannelImpl,
+        completion: &'static Completion,
+    ) -> ModelContext {
+        ModelContext::new(
+            0,
+            GENERIC_ONOFF_SERVER,
+            inbound.receiver(),
+            outbound.sender(),
+            completion,
+        )
+    }
+
+    /// Receive a publication as the driver does, returning its completion token.
+    async fn receive_publication(outbound: &'static OutboundChannelImpl) -> CompletionToken {
+        match outbound.recv().await {
+            ((0, GENERIC_ONOFF_SERVER), _, _, OutboundTarget::Publish(completion_token)) => {
+                completion_token
+            }
+            _ => panic!("expected a publication"),
+        }
+    }
+
+    #[test]
+    fn send_with_completion() {
+        static INBOUND: InboundChannelImpl = InboundChannelImpl::new();
+        static OUTBOUND: OutboundChannelImpl = OutboundChannelImpl::new();
+        static COMPLETION: Completion = Completion::new();
+        let ctx = model_context(&INBOUND, &OUTBOUND, &COMPLETION);
+        let meta = OutboundMetadata::new(
+            UnicastAddress::new(0x0002).unwrap().into(),
+            NetworkKeyHandle(0, Nid::new(0)),
+            IvIndex::new(0),
+            KeyHandle::Device,
+            None,
+            None,
+        );
+
+        let (status, _) = block_on(join(
+            BluetoothMeshModelContext::<GenericOnOffServer>::send_with_completion(
+                &ctx,
+                GenericOnOffMessage::Get,
+                meta,
+            ),
+            async {
+                match OUTBOUND.recv().await {
+                    (_, _, _, OutboundTarget::Send(_, Some(completion_token))) => {
+                        completion_token.incomplete()
+                    }
+                    _ => panic!("expected a send awaiting completion"),
+                }
+            },
+        ));
+        assert_eq!(CompletionStatus::Incomplete, status);
+    }
+
+    #[test]
+    fn publish() {
+        static INBOUND: InboundChannelImpl = InboundChannelImpl::new();
+        static OUTBOUND: OutboundChannelImpl = OutboundChannelImpl::new();
+        static COMPLETION: Completion = Completion::new();
+        let ctx = model_context(&INBOUND, &OUTBOUND, &COMPLETION);
+
+        let (result, _) = block_on(join(
+            BluetoothMeshModelContext::<GenericOnOffServer>::publish(
+                &ctx,
+                GenericOnOffMessage::Get,
+            ),
+            async { receive_publication(&OUTBOUND).await.complete() },
+        ));
+        assert_eq!(Ok(()), result);
+
+        let (result, _) = block_on(join(
+            BluetoothMeshModelContext::<GenericOnOffServer>::publish(
+                &ctx,
+                GenericOnOffMessage::Get,
+            ),
+            async { receive_publication(&OUTBOUND).await.incomplete() },
+        ));
+        assert_eq!(Err(PublishError::Failed), result);
+    }
+
+    #[test]
+    fn publication_not_configured() {
+        static INBOUND: InboundChannelImpl = InboundChannelImpl::new();
+        static OUTBOUND: OutboundChannelImpl = OutboundChannelImpl::new();
+        static COMPLETION: Completion = Completion::new();
+        let ctx = model_context(&INBOUND, &OUTBOUND, &COMPLETION);
+
+        let (result, _) = block_on(join(
+            BluetoothMeshModelContext::<GenericOnOffServer>::publish(
+                &ctx,
+                GenericOnOffMessage::Get,
+            ),
+            async { receive_publication(&OUTBOUND).await.not_configured() },
+        ));
+        assert_eq!(Err(PublishError::NotConfigured), result);
+    }
+
+    #[test]
+    fn abandoned_request_is_ignored() {
+        static COMPLETION: Completion = Completion::new();
+
+        let mut abandoned = None;
+        let request = COMPLETION.request(|completion_token| {
+            abandoned.replace(completion_token);
+            async {}
+        });
+        assert!(request.now_or_never().is_none());
+        let abandoned = abandoned.unwrap();
+
+        let status = block_on(COMPLETION.request(|completion_token| async move {
+            completion_token.complete();
+            abandoned.not_configured();
+        }));
+        assert_eq!(CompletionStatus::Complete, status);
     }
 }
@@ -1,5 +1,14 @@
+use crate::storage::provisioned::ProvisionedConfiguration;
 use crate::{DriverError, ProvisionedStack};
-use btmesh_device::InboundSenderImpl;
+use btmesh_common::address::{Address, GroupAddress, LabelUuid};
+use btmesh_common::ModelIdentifier;
+use btmesh_device::{InboundBody, InboundModels, InboundSenderImpl, KeyHandle};
+#[cfg(feature = "proxy")]
+use btmesh_models::foundation::configuration::gatt_proxy::GattProxy;
+use btmesh_models::foundation::configuration::model_subscription::SubscriptionAddress;
+#[cfg(feature = "relay")]
+use btmesh_models::foundation::configuration::relay::Relay;
+use btmesh_models::foundation::configuration::CONFIGURATION_SERVER;
 use btmesh_pdu::provisioned::access::AccessMessage;
 use heapless::Vec;
 
@@ -16,41 +25,530 @@ impl Dispatcher {
         }
     }
 
+    /// Dispatch an access message to the models eligible to receive it.
+    ///
+    /// Messages secured with the device key only reach the configuration server,
+    /// while messages secured with an application key only reach the models
+    /// that key is bound to. Group and virtually-addressed messages additionally
+    /// only reach models subscribed to the destination.
     pub async fn dispatch(
         &self,
         message: AccessMessage<ProvisionedStack>,
+        config: &ProvisionedConfiguration,
     ) -> Result<(), DriverError> {
         let opcode = message.opcode();
         let parameters = message.parameters();
         let local_element_index = message.meta().local_element_index();
 
         let meta = message.meta().into();
+        let key_handle = message.meta().key_handle();
+        let dst = message.meta().dst();
+        let label_uuid = message.meta().label_uuid();
 
-        if let Some(local_element_index) = local_element_index {
-            debug!("dispatch message to element {}: {}", local_element_index, message);
-            if local_element_index == 0 {
-                self.foundation_sender
-                    .send((Some(0usize), opcode, Vec::from_slice(parameters)?, meta))
-                    .await;
+        for element_index in 0..config.device_info().number_of_elements() {
+            let models = recipients(
+                config,
+                key_handle,
+                dst,
+                label_uuid,
+                local_element_index,
+                element_index,
+            )?;
+
+            if models.is_empty() {
+                continue;
             }
-            self.device_sender
+
+            debug!("dispatch message to element {}: {}", element_index, message);
+            let sender = if let KeyHandle::Device = key_handle {
+                &self.foundation_sender
+            } else {
+                &self.device_sender
+            };
+            sender
                 .send((
-                    Some(local_element_index as usize),
-                    opcode,
-                    Vec::from_slice(parameters)?,
-                    meta,
+                    Some(element_index as usize),
+                    InboundBody::Message(opcode, Vec::from_slice(parameters)?, meta, models),
                 ))
                 .await;
-        } else {
-            debug!("dispatch message to all elements: {}", message);
-            self.foundation_sender
-                .send((None, opcode, Vec::from_slice(parameters)?, meta))
-                .await;
-            self.device_sender
-                .send((None, opcode, Vec::from_slice(parameters)?, meta))
-                .await;
         }
 
         Ok(())
     }
+
+    /// Notify a device model that its publish period has elapsed.
+    ///
+    /// Only device models are notified, as none of the foundation models
+    /// served by the driver publish periodically.
+    pub async fn dispatch_publication_due(
+        &self,
+        element_index: usize,
+        model_identifier: ModelIdentifier,
+    ) {
+        debug!(
+            "dispatch publication due to element {}: {}",
+            element_index, model_identifier
+        );
+        self.device_sender
+            .send((
+                Some(element_index),
+                InboundBody::PublicationDue(model_identifier),
+            ))
+            .await;
+    }
+}
+
+/// The models of an element which are eligible to receive a message secured
+/// with the given key, and addressed to the given local element if it was sent
+/// to a unicast address.
+fn recipients(
+    config: &ProvisionedConfiguration,
+    key_handle: KeyHandle,
+    dst: Address,
+    label_uuid: Option<LabelUuid>,
+    local_element_index: Option<u8>,
+    element_index: u8,
+) -> Result<InboundModels, DriverError> {
+    let mut models = InboundModels::new();
+
+    if local_element_index.is_some() && local_element_index != Some(element_index) {
+        return Ok(models);
+    }
+
+    match key_handle {
+        KeyHandle::Device => {
+            if local_element_index == Some(0) {
+                models.push(CONFIGURATION_SERVER).ok();
+            }
+        }
+        KeyHandle::Application(application_key_handle) => {
+            let app_key_index = config
+                .secrets
+                .application_key_index(application_key_handle)
+                .ok_or(DriverError::InvalidKeyHandle)?;
+
+            let subscriptions = config.foundation().configuration().subscriptions();
+
+            for model_identifier in config
+                .foundation()
+                .configuration()
+                .bindings()
+                .models_iter(element_index, app_key_index)
+            {
+                let subscribed = match dst {
+                    Address::Unassigned => false,
+                    Address::Unicast(_) => true,
+                    Address::Group(group @ GroupAddress::RFU(_)) => subscriptions.is_subscribed(
+                        element_index,
+                        model_identifier,
+                        SubscriptionAddress::Group(group),
+                    ),
+                    // fixed group addresses reach the primary element.
+                    Address::Group(group) => {
+                        element_index == 0 && fixed_group_enabled(config, group)
+                    }
+                    Address::Virtual(_) => label_uuid
+                        .map(|label_uuid| {
+                            subscriptions.is_subscribed(
+                                element_index,
+                                model_identifier,
+                                SubscriptionAddress::Virtual(label_uuid),
+                            )
+                        })
+                        .unwrap_or(false),
+                };
+
+                if !subscribed {
+                    continue;
+                }
+
+                if models.push(model_identifier).is_err() {
+                    warn!("too many models bound on element {}", element_index);
+                    break;
+                }
+            }
+        }
+        KeyHandle::Network(_) => {
+            // access messages are never secured with only a network key.
+        }
+    }
+
+    Ok(models)
+}
+
+/// Whether messages sent to a fixed group address are accepted, according to
+/// the state of the feature the address belongs to.
+#[cfg_attr(
+    not(any(feature = "relay", feature = "proxy")),
+    allow(unused_variables)
+)]
+fn fixed_group_enabled(config: &ProvisionedConfiguration, group: GroupAddress) -> bool {
+    match group {
+        GroupAddress::AllNodes => true,
+        #[cfg(feature = "relay")]
+        GroupAddress::AllRelays => {
+            config.foundation().configuration().relay().relay == Relay::SupportedEnabled
+        }
+        #[cfg(feature = "proxy")]
+        GroupAddress::AllProxies => {
+            *config.foundation().configuration().gatt_proxy() == GattProxy::Enabled
+        }
+        // the friend feature is enabled whenever it is supported.
+        GroupAddress::AllFriends => cfg!(feature = "friend"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dispatch::recipients;
+    use crate::stack::provisioned::secrets::application::ApplicationKeys;
+    use crate::stack::provisioned::secrets::network::NetworkKeys;
+    use crate::storage::provisioned::ProvisionedConfiguration;
+    use crate::{DeviceInfo, NetworkState, Secrets};
+    use btmesh_common::address::{Address, GroupAddress, LabelUuid, UnicastAddress};
+    use btmesh_common::crypto::application::ApplicationKey;
+    use btmesh_common::crypto::device::DeviceKey;
+    use btmesh_common::crypto::network::NetworkKey;
+    use btmesh_common::{IvIndex, IvUpdateFlag};
+    use btmesh_device::{InboundModels, KeyHandle};
+    #[cfg(feature = "proxy")]
+    use btmesh_models::foundation::configuration::gatt_proxy::GattProxy;
+    use btmesh_models::foundation::configuration::model_subscription::SubscriptionAddress;
+    #[cfg(feature = "relay")]
+    use btmesh_models::foundation::configuration::relay::Relay;
+    use btmesh_models::foundation::configuration::{
+        AppKeyIndex, NetKeyIndex, CONFIGURATION_SERVER,
+    };
+    use btmesh_models::generic::onoff::{GENERIC_ONOFF_CLIENT, GENERIC_ONOFF_SERVER};
+
+    const LABEL_UUID: [u8; 16] = [0x55; 16];
+
+    /// A configuration of two elements holding two application keys, where
+    /// the first key is bound to the generic on/off server of both elements,
+    /// and the second key is bound to the generic on/off client of the second.
+    /// The server of the second element is subscribed to the group address
+    /// 0xC000, and the server of the first to a virtual address.
+    fn config() -> ProvisionedConfiguration {
+        let mut network_keys = NetworkKeys::default();
+        network_keys
+            .add(NetKeyIndex::new(0), NetworkKey::new([0x11; 16]).unwrap())
+            .unwrap();
+
+        let mut config = ProvisionedConfiguration {
+            network_state: NetworkState::new(IvIndex::new(0), IvUpdateFlag::Normal),
+            secrets: Secrets::new(
+                DeviceKey::new([0x22; 16]),
+                network_keys,
+                ApplicationKeys::default(),
+            ),
+            device_info: DeviceInfo::new(UnicastAddress::new(0x0001).unwrap(), 2),
+            sequence: 0,
+            foundation: Default::default(),
+        };
+
+        for (app_key_index, key) in [(1, [0x33; 16]), (2, [0x44; 16])] {
+            config
+                .secrets
+                .application_keys_mut()
+                .add(
+                    AppKeyIndex::new(app_key_index),
+                    NetKeyIndex::new(0),
+                    ApplicationKey::new(key).unwrap(),
+                )
+                .unwrap();
+        }
+
+        let configuration = config.foundation_mut().configuration_mut();
+
+        let bindings = configuration.bindings_mut();
+        bindings
+            .bind(0, GENERIC_ONOFF_SERVER, AppKeyIndex::new(1))
+            .unwrap();
+        bindings
+            .bind(1, GENERIC_ONOFF_SERVER, AppKeyIndex::new(1))
+            .unwrap();
+        bindings
+            .bind(1, GENERIC_ONOFF_CLIENT, AppKeyIndex::new(2))
+            .unwrap();
+
+        let subscriptions = configuration.subscriptions_mut();
+        subscriptions
+            .add(1, GENERIC_ONOFF_SERVER, SubscriptionAddress::Group(group()))
+            .unwrap();
+        subscriptions
+            .add(
+                0,
+                GENERIC_ONOFF_SERVER,
+                SubscriptionAddress::Virtual(LabelUuid::new(LABEL_UUID).unwrap()),
+            )
+            .unwrap();
+
+        config
+    }
+
+    fn group() -> GroupAddress {
+        GroupAddress::parse([0xC0, 0x00]).unwrap()
+    }
+
+    fn unicast() -> Address {
+        Address::Unicast(UnicastAddress::new(0x0001).unwrap())
+    }
+
+    fn application(config: &ProvisionedConfiguration, app_key_index: u16) -> KeyHandle {
+        KeyHandle::Application(
+            config
+                .secrets
+                .application_key_handle(AppKeyIndex::new(app_key_index))
+                .unwrap(),
+        )
+    }
+
+    /// The models of each element which receive a message.
+    fn received(
+        config: &ProvisionedConfiguration,
+        key_handle: KeyHandle,
+        dst: Address,
+        label_uuid: Option<LabelUuid>,
+        local_element_index: Option<u8>,
+    ) -> [InboundModels; 2] {
+        [0, 1].map(|element_index| {
+            recipients(
+                config,
+                key_handle,
+                dst,
+                label_uuid,
+                local_element_index,
+                element_index,
+            )
+            .unwrap()
+        })
+    }
+
+    #[test]
+    fn device_key_reaches_only_configuration_server() {
+        let config = config();
+
+        let [primary, secondary] = received(&config, KeyHandle::Device, unicast(), None, Some(0));
+        assert_eq!(&primary[..], &[CONFIGURATION_SERVER]);
+        assert!(secondary.is_empty());
+
+        let [primary, secondary] = received(&config, KeyHandle::Device, unicast(), None, Some(1));
+        assert!(primary.is_empty());
+        assert!(secondary.is_empty());
+
+        let [primary, secondary] = received(
+            &config,
+            KeyHandle::Device,
+            Address::Group(GroupAddress::AllNodes),
+            None,
+            None,
+        );
+        assert!(primary.is_empty());
+        assert!(secondary.is_empty());
+    }
+
+    #[test]
+    fn application_key_reaches_only_bound_models() {
+        let config = config();
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 2),
+            Address::Group(GroupAddress::AllNodes),
+            None,
+            None,
+        );
+        assert!(primary.is_empty());
+        assert!(secondary.is_empty());
+
+        let [primary, secondary] =
+            received(&config, application(&config, 2), unicast(), None, Some(1));
+        assert!(primary.is_empty());
+        assert_eq!(&secondary[..], &[GENERIC_ONOFF_CLIENT]);
+    }
+
+    #[test]
+    fn unicast_reaches_only_addressed_element() {
+        let config = config();
+
+        let [primary, secondary] =
+            received(&config, application(&config, 1), unicast(), None, Some(1));
+        assert!(primary.is_empty());
+        assert_eq!(&secondary[..], &[GENERIC_ONOFF_SERVER]);
+
+        let [primary, secondary] =
+            received(&config, application(&config, 1), unicast(), None, Some(0));
+        assert_eq!(&primary[..], &[GENERIC_ONOFF_SERVER]);
+        assert!(secondary.is_empty());
+    }
+
+    #[test]
+    fn group_reaches_only_subscribed_models() {
+        let config = config();
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(group()),
+            None,
+            None,
+        );
+        assert!(primary.is_empty());
+        assert_eq!(&secondary[..], &[GENERIC_ONOFF_SERVER]);
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(GroupAddress::parse([0xC0, 0x01]).unwrap()),
+            None,
+            None,
+        );
+        assert!(primary.is_empty());
+        assert!(secondary.is_empty());
+    }
+
+    #[test]
+    fn virtual_reaches_only_subscribed_models() {
+        let config = config();
+        let label_uuid = LabelUuid::new(LABEL_UUID).unwrap();
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Virtual(label_uuid.virtual_address()),
+            Some(label_uuid),
+            None,
+        );
+        assert_eq!(&primary[..], &[GENERIC_ONOFF_SERVER]);
+        assert!(secondary.is_empty());
+
+        let other = LabelUuid::new([0x66; 16]).unwrap();
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Virtual(other.virtual_address()),
+            Some(other),
+            None,
+        );
+        assert!(primary.is_empty());
+        assert!(secondary.is_empty());
+    }
+
+    #[test]
+    fn fixed_group_reaches_primary_element_of_enabled_features() {
+        let config = config();
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(GroupAddress::AllNodes),
+            None,
+            None,
+        );
+        assert_eq!(&primary[..], &[GENERIC_ONOFF_SERVER]);
+        assert!(secondary.is_empty());
+
+        for group in [
+            GroupAddress::AllRelays,
+            GroupAddress::AllProxies,
+            GroupAddress::AllFriends,
+        ] {
+            let supported = match group {
+                GroupAddress::AllRelays => cfg!(feature = "relay"),
+                GroupAddress::AllProxies => cfg!(feature = "proxy"),
+                GroupAddress::AllFriends => cfg!(feature = "friend"),
+                _ => false,
+            };
+            if supported {
+                continue;
+            }
+
+            let [primary, secondary] = received(
+                &config,
+                application(&config, 1),
+                Address::Group(group),
+                None,
+                None,
+            );
+            assert!(primary.is_empty());
+            assert!(secondary.is_empty());
+        }
+    }
+
+    #[cfg(feature = "relay")]
+    #[test]
+    fn all_relays_follows_relay_state() {
+        let mut config = config();
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(GroupAddress::AllRelays),
+            None,
+            None,
+        );
+        assert_eq!(&primary[..], &[GENERIC_ONOFF_SERVER]);
+        assert!(secondary.is_empty());
+
+        config
+            .foundation_mut()
+            .configuration_mut()
+            .relay_mut()
+            .relay = Relay::SupportedDisabled;
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(GroupAddress::AllRelays),
+            None,
+            None,
+        );
+        assert!(primary.is_empty());
+        assert!(secondary.is_empty());
+    }
+
+    #[cfg(feature = "proxy")]
+    #[test]
+    fn all_proxies_follows_gatt_proxy_state() {
+        let mut config = config();
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(GroupAddress::AllProxies),
+            None,
+            None,
+        );
+        assert_eq!(&primary[..], &[GENERIC_ONOFF_SERVER]);
+        assert!(secondary.is_empty());
+
+        *config.foundation_mut().configuration_mut().gatt_proxy_mut() = GattProxy::Disabled;
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(GroupAddress::AllProxies),
+            None,
+            None,
+        );
+        assert!(primary.is_empty());
+        assert!(secondary.is_empty());
+    }
+
+    #[cfg(feature = "friend")]
+    #[test]
+    fn all_friends_received_with_friend_feature() {
+        let config = config();
+
+        let [primary, secondary] = received(
+            &config,
+            application(&config, 1),
+            Address::Group(GroupAddress::AllFriends),
+            None,
+            None,
+        );
+        assert_eq!(&primary[..], &[GENERIC_ONOFF_SERVER]);
+        assert!(secondary.is_empty());
+    }
 }
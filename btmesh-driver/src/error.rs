@@ -23,6 +23,7 @@ pub enum DriverError {
     Network(NetworkError),
     SeqRollover,
     Storage(StorageError),
+    PublicationNotConfigured,
 }
 
 impl From<StorageError> for DriverError {
@@ -7,6 +7,8 @@ use btmesh_bearer::beacon::Beacon;
 use btmesh_bearer::PB_ADV_MTU;
 use btmesh_bearer::{AdvertisingBearer, BearerError};
 use btmesh_common::Uuid;
+use btmesh_models::foundation::configuration::network_transmit::NetworkTransmitConfig;
+use btmesh_pdu::provisioned::beacon::SecureNetworkBeacon;
 use btmesh_pdu::provisioned::network::NetworkPDU;
 use btmesh_pdu::provisioning::advertising::AdvertisingPDU;
 use btmesh_pdu::provisioning::generic::{GenericProvisioningPDU, ProvisioningBearerControl};
@@ -15,10 +17,22 @@ use btmesh_pdu::{MESH_BEACON, MESH_MESSAGE, PB_ADV, PDU};
 use core::cell::Cell;
 use core::cell::RefCell;
 use core::iter::Iterator;
+use embassy_executor::time::{Duration, Instant};
 use heapless::Vec;
 
 mod segmentation;
 
+/// Maximum number of network PDUs awaiting repetition at any time.
+const MAX_REPEATED_PDUS: usize = 8;
+
+/// A network PDU which remains to be transmitted again.
+struct RepeatedPDU {
+    bytes: Vec<u8, 64>,
+    remaining: u8,
+    interval: Duration,
+    deadline: Instant,
+}
+
 pub struct AdvertisingBearerNetworkInterface<B: AdvertisingBearer> {
     bearer: B,
     segmentation: Segmentation,
@@ -27,6 +41,8 @@ pub struct AdvertisingBearerNetworkInterface<B: AdvertisingBearer> {
     acked_inbound_transaction_number: Cell<Option<u8>>,
     outbound_pdu: RefCell<Option<OutboundPDU>>,
     outbound_transaction_number: Cell<u8>,
+    network_transmit: Cell<NetworkTransmitConfig>,
+    repeats: RefCell<Vec<RepeatedPDU, MAX_REPEATED_PDUS>>,
 }
 
 impl<B: AdvertisingBearer> AdvertisingBearerNetworkInterface<B> {
@@ -39,9 +55,15 @@ impl<B: AdvertisingBearer> AdvertisingBearerNetworkInterface<B> {
             acked_inbound_transaction_number: Cell::new(None),
             outbound_pdu: RefCell::new(None),
             outbound_transaction_number: Cell::new(0x80),
+            network_transmit: Cell::new(Default::default()),
+            repeats: RefCell::new(Vec::new()),
         }
     }
 
+    pub fn set_network_transmit_config(&self, config: NetworkTransmitConfig) {
+        self.network_transmit.replace(config);
+    }
+
     pub async fn beacon(&self, beacon: Beacon) -> Result<(), BearerError> {
         match beacon {
             Beacon::Unprovisioned(uuid) => {
@@ -54,8 +76,8 @@ impl<B: AdvertisingBearer> AdvertisingBearerNetworkInterface<B> {
             Beacon::Provisioned(_network_id) => {
                 // not applicable to this role
             }
-            Beacon::Secure => {
-                // nothing yet.
+            Beacon::Secure(beacon) => {
+                self.transmit_secure_network_beacon(&beacon).await?;
             }
         }
         Ok(())
@@ -65,6 +87,11 @@ impl<B: AdvertisingBearer> AdvertisingBearerNetworkInterface<B> {
         match pdu {
             PDU::Provisioning(pdu) => self.transmit_provisioning_pdu(pdu).await,
             PDU::Network(pdu) => self.transmit_network_pdu(pdu).await,
+            PDU::ProxyConfiguration(_) => {
+                // only exchanged with a proxy client over GATT.
+                Ok(())
+            }
+            PDU::SecureNetworkBeacon(beacon) => self.transmit_secure_network_beacon(beacon).await,
         }
     }
 
@@ -90,13 +117,102 @@ impl<B: AdvertisingBearer> AdvertisingBearerNetworkInterface<B> {
         Ok(())
     }
 
+    async fn transmit_secure_network_beacon(
+        &self,
+        beacon: &SecureNetworkBeacon,
+    ) -> Result<(), BearerError> {
+        let mut bytes = Vec::<u8, PB_ADV_MTU>::new();
+        bytes.push(0x00)?;
+        bytes.push(MESH_BEACON)?;
+        beacon.emit(&mut bytes)?;
+        bytes[0] = bytes.len() as u8 - 1;
+        self.bearer.transmit(&bytes).await
+    }
+
     async fn transmit_network_pdu(&self, pdu: &NetworkPDU) -> Result<(), BearerError> {
+        self.repeat_network_pdu(pdu, self.network_transmit.get())
+            .await
+    }
+
+    fn network_pdu_bytes(pdu: &NetworkPDU) -> Result<Vec<u8, 64>, BearerError> {
         let mut bytes = Vec::<u8, 64>::new();
         bytes.push(0x00)?;
         bytes.push(MESH_MESSAGE)?;
         pdu.emit(&mut bytes)?;
         bytes[0] = bytes.len() as u8 - 1;
+        Ok(bytes)
+    }
+
+    /// Hold on to an already transmitted network PDU, to be repeated through `repeat()`.
+    fn schedule_repeats(&self, bytes: Vec<u8, 64>, network_transmit: NetworkTransmitConfig) {
+        if network_transmit.network_retransmit_count == 0 {
+            return;
+        }
+        let interval = Duration::from_millis(network_transmit.interval_ms());
+        if self
+            .repeats
+            .borrow_mut()
+            .push(RepeatedPDU {
+                bytes,
+                remaining: network_transmit.network_retransmit_count,
+                interval,
+                deadline: Instant::now() + interval,
+            })
+            .is_err()
+        {
+            warn!("too many network PDUs awaiting repetition, transmitting once");
+        }
+    }
+
+    /// Instant at which the next repetition of a network PDU is due, if any.
+    pub fn next_repeat_deadline(&self) -> Option<Instant> {
+        self.repeats
+            .borrow()
+            .iter()
+            .map(|repeat| repeat.deadline)
+            .min()
+    }
+
+    /// Transmit every network PDU whose repetition is due.
+    pub async fn repeat(&self) -> Result<(), BearerError> {
+        let now = Instant::now();
+        let mut due = Vec::<Vec<u8, 64>, MAX_REPEATED_PDUS>::new();
+        {
+            let mut repeats = self.repeats.borrow_mut();
+            for repeat in repeats.iter_mut().filter(|repeat| repeat.deadline <= now) {
+                due.push(repeat.bytes.clone()).ok();
+                repeat.remaining -= 1;
+                repeat.deadline = now + repeat.interval;
+            }
+            while let Some(index) = repeats.iter().position(|repeat| repeat.remaining == 0) {
+                repeats.swap_remove(index);
+            }
+        }
+        for bytes in due {
+            self.bearer.transmit(&bytes).await?;
+        }
+        Ok(())
+    }
+
+    /// Relay a network PDU on behalf of another node, repeated according to
+    /// the relay retransmit state rather than the network transmit state.
+    pub async fn relay(
+        &self,
+        pdu: &NetworkPDU,
+        retransmit: NetworkTransmitConfig,
+    ) -> Result<(), BearerError> {
+        self.repeat_network_pdu(pdu, retransmit).await
+    }
+
+    /// Transmit a network PDU once, leaving its repetitions to `repeat()`.
+    async fn repeat_network_pdu(
+        &self,
+        pdu: &NetworkPDU,
+        network_transmit: NetworkTransmitConfig,
+    ) -> Result<(), BearerError> {
+        let bytes = Self::network_pdu_bytes(pdu)?;
         self.bearer.transmit(&bytes).await?;
+        self.schedule_repeats(bytes, network_transmit);
         Ok(())
     }
 
@@ -121,6 +237,12 @@ impl<B: AdvertisingBearer> AdvertisingBearerNetworkInterface<B> {
                             return Ok(PDU::Network(pdu));
                         }
                     }
+                    (DeviceState::Provisioned, MESH_BEACON) => {
+                        // unprovisioned device beacons fail to parse and are ignored.
+                        if let Ok(beacon) = SecureNetworkBeacon::parse(&data[2..]) {
+                            return Ok(PDU::SecureNetworkBeacon(beacon));
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -1,19 +1,31 @@
+use crate::interface::gatt::segmentation::{OutboundSegments, Segmentation, PROXY_MESSAGE_MTU};
 use crate::interface::NetworkError;
 use btmesh_bearer::beacon::Beacon;
 use btmesh_bearer::{BearerError, GattBearer};
+use btmesh_pdu::provisioned::beacon::SecureNetworkBeacon;
 use btmesh_pdu::provisioned::network::NetworkPDU;
-use btmesh_pdu::provisioned::proxy::{MessageType, ProxyPDU, SAR};
+use btmesh_pdu::provisioned::proxy::{MessageType, ProxyPDU};
 use btmesh_pdu::provisioning::ProvisioningPDU;
 use btmesh_pdu::PDU;
 use heapless::Vec;
 
+mod segmentation;
+
 pub struct GattBearerNetworkInterface<B: GattBearer<MTU>, const MTU: usize> {
     bearer: B,
+    segmentation: Segmentation,
 }
 
 impl<B: GattBearer<MTU>, const MTU: usize> GattBearerNetworkInterface<B, MTU> {
     pub fn new(bearer: B) -> Self {
-        Self { bearer }
+        Self {
+            bearer,
+            segmentation: Default::default(),
+        }
+    }
+
+    pub fn connection_count(&self) -> u32 {
+        self.bearer.connection_count()
     }
 
     pub async fn run(&self) -> Result<(), NetworkError> {
@@ -25,19 +37,36 @@ impl<B: GattBearer<MTU>, const MTU: usize> GattBearerNetworkInterface<B, MTU> {
         loop {
             let data = self.bearer.receive().await?;
             let proxy_pdu = ProxyPDU::parse(&data)?;
-            if let SAR::Complete = proxy_pdu.sar {
-                match proxy_pdu.message_type {
-                    MessageType::NetworkPDU => {
-                        let pdu = NetworkPDU::parse(&proxy_pdu.data)?;
-                        return Ok(PDU::Network(pdu));
+            let (message_type, data) = if let Some(message) = self
+                .segmentation
+                .process_inbound(self.bearer.connection_count(), &proxy_pdu)?
+            {
+                message
+            } else {
+                continue;
+            };
+
+            match message_type {
+                MessageType::NetworkPDU => {
+                    let pdu = NetworkPDU::parse(&data)?;
+                    return Ok(PDU::Network(pdu));
+                }
+                MessageType::MeshBeacon => {
+                    if let Ok(beacon) = SecureNetworkBeacon::parse(&data) {
+                        return Ok(PDU::SecureNetworkBeacon(beacon));
                     }
-                    MessageType::MeshBeacon => {}
-                    MessageType::ProxyConfiguration => {}
-                    MessageType::ProvisioningPDU => {
-                        let pdu = ProvisioningPDU::parse(&proxy_pdu.data)?;
-                        return Ok(PDU::Provisioning(pdu));
+                }
+                MessageType::ProxyConfiguration => {
+                    #[cfg(feature = "proxy")]
+                    {
+                        let pdu = NetworkPDU::parse(&data)?;
+                        return Ok(PDU::ProxyConfiguration(pdu));
                     }
                 }
+                MessageType::ProvisioningPDU => {
+                    let pdu = ProvisioningPDU::parse(&data)?;
+                    return Ok(PDU::Provisioning(pdu));
+                }
             }
         }
     }
@@ -45,38 +74,62 @@ impl<B: GattBearer<MTU>, const MTU: usize> GattBearerNetworkInterface<B, MTU> {
     pub async fn transmit(&self, pdu: &PDU) -> Result<(), BearerError> {
         match pdu {
             PDU::Provisioning(pdu) => {
-                let mut all_proxy_data = Vec::<u8, 384>::new();
-                pdu.emit(&mut all_proxy_data)?;
-                let mut data = Vec::new();
-                data.extend_from_slice(&all_proxy_data)?;
-                let proxy_pdu = ProxyPDU {
-                    sar: SAR::Complete,
-                    message_type: MessageType::ProvisioningPDU,
-                    data,
-                };
-
-                self.transmit_proxy_pdu(&proxy_pdu).await
+                let mut data = Vec::<u8, PROXY_MESSAGE_MTU>::new();
+                pdu.emit(&mut data)?;
+                self.transmit_proxy_message(MessageType::ProvisioningPDU, &data)
+                    .await
             }
             PDU::Network(pdu) => {
-                let mut all_proxy_data = Vec::<u8, 384>::new();
-                pdu.emit(&mut all_proxy_data)?;
-                let mut data = Vec::new();
-                data.extend_from_slice(&all_proxy_data)?;
-                let proxy_pdu = ProxyPDU {
-                    sar: SAR::Complete,
-                    message_type: MessageType::NetworkPDU,
-                    data,
-                };
-
-                self.transmit_proxy_pdu(&proxy_pdu).await
+                self.transmit_network_pdu(pdu, MessageType::NetworkPDU)
+                    .await
+            }
+            PDU::ProxyConfiguration(pdu) => {
+                self.transmit_network_pdu(pdu, MessageType::ProxyConfiguration)
+                    .await
             }
+            PDU::SecureNetworkBeacon(beacon) => self.transmit_secure_network_beacon(beacon).await,
         }
     }
 
-    async fn transmit_proxy_pdu(&self, pdu: &ProxyPDU) -> Result<(), BearerError> {
-        let mut bytes = Vec::new();
-        pdu.emit(&mut bytes)?;
-        self.bearer.transmit(&bytes).await
+    /// Pass a network PDU received from another node on to the proxy client.
+    pub async fn proxy(&self, pdu: &NetworkPDU) -> Result<(), BearerError> {
+        self.transmit_network_pdu(pdu, MessageType::NetworkPDU)
+            .await
+    }
+
+    async fn transmit_network_pdu(
+        &self,
+        pdu: &NetworkPDU,
+        message_type: MessageType,
+    ) -> Result<(), BearerError> {
+        let mut data = Vec::<u8, PROXY_MESSAGE_MTU>::new();
+        pdu.emit(&mut data)?;
+        self.transmit_proxy_message(message_type, &data).await
+    }
+
+    async fn transmit_secure_network_beacon(
+        &self,
+        beacon: &SecureNetworkBeacon,
+    ) -> Result<(), BearerError> {
+        let mut data = Vec::<u8, PROXY_MESSAGE_MTU>::new();
+        beacon.emit(&mut data)?;
+        self.transmit_proxy_message(MessageType::MeshBeacon, &data)
+            .await
+    }
+
+    /// Transmit a proxy message, segmented to fit the MTU of the current connection.
+    async fn transmit_proxy_message(
+        &self,
+        message_type: MessageType,
+        data: &[u8],
+    ) -> Result<(), BearerError> {
+        let mtu = core::cmp::min(self.bearer.mtu(), MTU);
+        for proxy_pdu in OutboundSegments::new(message_type, data, mtu) {
+            let mut bytes = Vec::new();
+            proxy_pdu?.emit(&mut bytes)?;
+            self.bearer.transmit(&bytes).await?;
+        }
+        Ok(())
     }
 
     pub async fn beacon(&self, beacon: Beacon) -> Result<(), BearerError> {
@@ -113,8 +166,10 @@ impl<B: GattBearer<MTU>, const MTU: usize> GattBearerNetworkInterface<B, MTU> {
                 adv_data.extend_from_slice(&*network_id)?;
                 self.bearer.advertise(&adv_data).await?;
             }
-            Beacon::Secure => {
-                // nothing yet
+            Beacon::Secure(_beacon) => {
+                // beacons are sent to the proxy client over an existing connection.
+                #[cfg(feature = "proxy")]
+                self.transmit_secure_network_beacon(&_beacon).await?;
             }
         }
 
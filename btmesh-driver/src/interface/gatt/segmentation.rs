@@ -0,0 +1,225 @@
+use btmesh_common::InsufficientBuffer;
+use btmesh_pdu::provisioned::proxy::{MessageType, ProxyPDU, SAR};
+use core::cell::RefCell;
+use heapless::Vec;
+
+/// Largest proxy message reassembled from, or segmented into, proxy PDUs.
+pub const PROXY_MESSAGE_MTU: usize = 384;
+
+struct InboundSegments {
+    connection: u32,
+    message_type: MessageType,
+    data: Vec<u8, PROXY_MESSAGE_MTU>,
+}
+
+#[derive(Default)]
+pub struct Segmentation {
+    inbound_segments: RefCell<Option<InboundSegments>>,
+}
+
+impl Segmentation {
+    /// Accumulate a proxy PDU received over the given connection, producing
+    /// the complete proxy message once its last segment has arrived.
+    ///
+    /// Segments that do not continue the message currently being reassembled,
+    /// or that would grow it beyond `PROXY_MESSAGE_MTU`, cause it to be discarded.
+    pub fn process_inbound(
+        &self,
+        connection: u32,
+        pdu: &ProxyPDU,
+    ) -> Result<Option<(MessageType, Vec<u8, PROXY_MESSAGE_MTU>)>, InsufficientBuffer> {
+        let mut borrowed_segments = self.inbound_segments.borrow_mut();
+
+        if let Some(segments) = &*borrowed_segments {
+            if segments.connection != connection {
+                borrowed_segments.take();
+            }
+        }
+
+        match pdu.sar {
+            SAR::Complete => {
+                borrowed_segments.take();
+                Ok(Some((pdu.message_type, Vec::from_slice(&pdu.data)?)))
+            }
+            SAR::First => {
+                borrowed_segments.replace(InboundSegments {
+                    connection,
+                    message_type: pdu.message_type,
+                    data: Vec::from_slice(&pdu.data)?,
+                });
+                Ok(None)
+            }
+            SAR::Continuation | SAR::Last => {
+                let segments = match &mut *borrowed_segments {
+                    Some(segments) if segments.message_type == pdu.message_type => segments,
+                    _ => {
+                        borrowed_segments.take();
+                        return Ok(None);
+                    }
+                };
+
+                if segments.data.extend_from_slice(&pdu.data).is_err() {
+                    borrowed_segments.take();
+                    return Ok(None);
+                }
+
+                if let SAR::Last = pdu.sar {
+                    Ok(borrowed_segments
+                        .take()
+                        .map(|segments| (segments.message_type, segments.data)))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+/// Proxy PDUs carrying a proxy message, each fitting within the given MTU.
+pub struct OutboundSegments<'a> {
+    message_type: MessageType,
+    data: &'a [u8],
+    chunk_len: usize,
+    cur: usize,
+}
+
+impl<'a> OutboundSegments<'a> {
+    pub fn new(message_type: MessageType, data: &'a [u8], mtu: usize) -> Self {
+        Self {
+            message_type,
+            data,
+            // one octet of each proxy PDU is taken by its header.
+            chunk_len: core::cmp::max(mtu, 2) - 1,
+            cur: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for OutboundSegments<'a> {
+    type Item = Result<ProxyPDU, InsufficientBuffer>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk_start = self.cur * self.chunk_len;
+        if self.cur > 0 && chunk_start >= self.data.len() {
+            return None;
+        }
+        let chunk_end = core::cmp::min(chunk_start + self.chunk_len, self.data.len());
+        let is_first = self.cur == 0;
+        let is_last = chunk_end == self.data.len();
+        self.cur += 1;
+
+        let sar = match (is_first, is_last) {
+            (true, true) => SAR::Complete,
+            (true, false) => SAR::First,
+            (false, false) => SAR::Continuation,
+            (false, true) => SAR::Last,
+        };
+
+        Some(
+            Vec::from_slice(&self.data[chunk_start..chunk_end])
+                .map(|data| ProxyPDU {
+                    sar,
+                    message_type: self.message_type,
+                    data,
+                })
+                .map_err(|_| InsufficientBuffer),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interface::gatt::segmentation::{OutboundSegments, Segmentation, PROXY_MESSAGE_MTU};
+    use btmesh_pdu::provisioned::proxy::{MessageType, ProxyPDU, SAR};
+    use heapless::Vec;
+
+    fn proxy_pdu(sar: SAR, message_type: MessageType, data: &[u8]) -> ProxyPDU {
+        ProxyPDU {
+            sar,
+            message_type,
+            data: Vec::from_slice(data).unwrap(),
+        }
+    }
+
+    #[test]
+    fn outbound_fits_single_pdu() {
+        let data = [0x03, 0x01, 0x02];
+        let mut segments = OutboundSegments::new(MessageType::ProvisioningPDU, &data, 20);
+
+        let pdu = segments.next().unwrap().unwrap();
+        assert_eq!(SAR::Complete, pdu.sar);
+        assert_eq!(MessageType::ProvisioningPDU, pdu.message_type);
+        assert_eq!(&data, &*pdu.data);
+        assert!(segments.next().is_none());
+    }
+
+    #[test]
+    fn outbound_segmented() {
+        let data: Vec<u8, 65> = (0..65).collect();
+        let segments: Vec<ProxyPDU, 8> =
+            OutboundSegments::new(MessageType::ProvisioningPDU, &data, 20)
+                .map(Result::unwrap)
+                .collect();
+
+        assert_eq!(4, segments.len());
+        assert_eq!(SAR::First, segments[0].sar);
+        assert_eq!(SAR::Continuation, segments[1].sar);
+        assert_eq!(SAR::Continuation, segments[2].sar);
+        assert_eq!(SAR::Last, segments[3].sar);
+        assert_eq!(&data[0..19], &*segments[0].data);
+        assert_eq!(&data[57..65], &*segments[3].data);
+    }
+
+    #[test]
+    fn round_trip() {
+        let data: Vec<u8, 65> = (0..65).collect();
+        let segmentation = Segmentation::default();
+
+        let mut result = None;
+        for pdu in OutboundSegments::new(MessageType::ProvisioningPDU, &data, 20) {
+            assert!(result.is_none());
+            result = segmentation.process_inbound(1, &pdu.unwrap()).unwrap();
+        }
+
+        let (message_type, reassembled) = result.unwrap();
+        assert_eq!(MessageType::ProvisioningPDU, message_type);
+        assert_eq!(&*data, &*reassembled);
+    }
+
+    #[test]
+    fn mismatched_segments_are_discarded() {
+        let segmentation = Segmentation::default();
+
+        let first = proxy_pdu(SAR::First, MessageType::ProvisioningPDU, &[0x01]);
+        let last = proxy_pdu(SAR::Last, MessageType::NetworkPDU, &[0x02]);
+        assert!(segmentation.process_inbound(1, &first).unwrap().is_none());
+        assert!(segmentation.process_inbound(1, &last).unwrap().is_none());
+
+        // a continuation without a first segment is ignored.
+        let last = proxy_pdu(SAR::Last, MessageType::ProvisioningPDU, &[0x02]);
+        assert!(segmentation.process_inbound(1, &last).unwrap().is_none());
+
+        // segments do not carry over to a new connection.
+        assert!(segmentation.process_inbound(1, &first).unwrap().is_none());
+        assert!(segmentation.process_inbound(2, &last).unwrap().is_none());
+    }
+
+    #[test]
+    fn oversized_message_is_discarded() {
+        let segmentation = Segmentation::default();
+
+        let first = proxy_pdu(SAR::First, MessageType::NetworkPDU, &[0x01; 64]);
+        let continuation = proxy_pdu(SAR::Continuation, MessageType::NetworkPDU, &[0x02; 64]);
+        assert!(segmentation.process_inbound(1, &first).unwrap().is_none());
+        for _ in 0..PROXY_MESSAGE_MTU / 64 {
+            assert!(segmentation
+                .process_inbound(1, &continuation)
+                .unwrap()
+                .is_none());
+        }
+
+        // nothing remains of the discarded message.
+        let last = proxy_pdu(SAR::Last, MessageType::NetworkPDU, &[0x03]);
+        assert!(segmentation.process_inbound(1, &last).unwrap().is_none());
+    }
+}
@@ -4,8 +4,11 @@ use crate::DeviceState;
 use btmesh_bearer::beacon::Beacon;
 use btmesh_bearer::{AdvertisingBearer, BearerError, GattBearer};
 use btmesh_device::join;
+use btmesh_models::foundation::configuration::network_transmit::NetworkTransmitConfig;
+use btmesh_pdu::provisioned::network::NetworkPDU;
 use btmesh_pdu::PDU;
 use core::future::Future;
+use embassy_executor::time::Instant;
 use embassy_util::{select, Either};
 
 pub mod advertising;
@@ -23,11 +26,11 @@ pub trait NetworkInterfaces {
     /// Run the network interfaces, stopping when the future is dropped.
     fn run(&self) -> Self::RunFuture<'_>;
 
-    type ReceiveFuture<'m>: Future<Output = Result<PDU, NetworkError>> + 'm
+    type ReceiveFuture<'m>: Future<Output = Result<(PDU, Bearer), NetworkError>> + 'm
     where
         Self: 'm;
 
-    /// Receive data from any of the network interfaces.
+    /// Receive data from any of the network interfaces, along with the bearer it arrived on.
     fn receive<'m>(&'m self, state: &'m DeviceState) -> Self::ReceiveFuture<'m>;
 
     type TransmitFuture<'m>: Future<Output = Result<(), NetworkError>> + 'm
@@ -37,6 +40,24 @@ pub trait NetworkInterfaces {
     /// Transmit data on all of the network interfaces.
     fn transmit<'m>(&'m self, pdu: &'m PDU) -> Self::TransmitFuture<'m>;
 
+    type RelayFuture<'m>: Future<Output = Result<(), NetworkError>> + 'm
+    where
+        Self: 'm;
+
+    /// Relay a network PDU received from another node, repeating it as configured by `retransmit`.
+    fn relay<'m>(
+        &'m self,
+        pdu: &'m NetworkPDU,
+        retransmit: NetworkTransmitConfig,
+    ) -> Self::RelayFuture<'m>;
+
+    type ProxyFuture<'m>: Future<Output = Result<(), NetworkError>> + 'm
+    where
+        Self: 'm;
+
+    /// Pass a network PDU received from another node on to the connected proxy client, if any.
+    fn proxy<'m>(&'m self, pdu: &'m NetworkPDU) -> Self::ProxyFuture<'m>;
+
     type RetransmitFuture<'m>: Future<Output = Result<(), NetworkError>> + 'm
     where
         Self: 'm;
@@ -44,12 +65,37 @@ pub trait NetworkInterfaces {
     /// Retransmit any necessary network-level packets held by the interfaces.
     fn retransmit(&self) -> Self::RetransmitFuture<'_>;
 
+    /// Instant at which the interfaces are next due to repeat a network PDU, if any.
+    fn next_repeat_deadline(&self) -> Option<Instant>;
+
+    type RepeatFuture<'m>: Future<Output = Result<(), NetworkError>> + 'm
+    where
+        Self: 'm;
+
+    /// Repeat the network PDUs which are due, as configured by the network transmit state.
+    fn repeat(&self) -> Self::RepeatFuture<'_>;
+
     type BeaconFuture<'m>: Future<Output = Result<(), NetworkError>> + 'm
     where
         Self: 'm;
 
     /// Perform beaconing on all of the network interfaces.
     fn beacon(&self, beacon: Beacon) -> Self::BeaconFuture<'_>;
+
+    /// Configure how often network PDUs are repeated by the interfaces which repeat them.
+    fn set_network_transmit_config(&self, config: NetworkTransmitConfig);
+
+    /// Number of connections made by proxy clients so far, changing whenever
+    /// a new client connects.
+    fn proxy_connection_count(&self) -> u32;
+}
+
+/// The bearer an inbound PDU arrived on.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Bearer {
+    Advertising,
+    Gatt,
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -98,7 +144,7 @@ impl<AB: AdvertisingBearer, GB: GattBearer<MTU>, const MTU: usize> NetworkInterf
         self.gatt_interface.run()
     }
 
-    type ReceiveFuture<'m> = impl Future<Output=Result<PDU, NetworkError>> + 'm
+    type ReceiveFuture<'m> = impl Future<Output=Result<(PDU, Bearer), NetworkError>> + 'm
     where
     Self: 'm;
 
@@ -109,7 +155,8 @@ impl<AB: AdvertisingBearer, GB: GattBearer<MTU>, const MTU: usize> NetworkInterf
             let result = select(adv_fut, gatt_fut).await;
 
             match result {
-                Either::First(result) | Either::Second(result) => Ok(result?),
+                Either::First(result) => Ok((result?, Bearer::Advertising)),
+                Either::Second(result) => Ok((result?, Bearer::Gatt)),
             }
         }
     }
@@ -120,6 +167,11 @@ impl<AB: AdvertisingBearer, GB: GattBearer<MTU>, const MTU: usize> NetworkInterf
 
     fn transmit<'m>(&'m self, pdu: &'m PDU) -> Self::TransmitFuture<'m> {
         async move {
+            // network PDUs reach a proxy client through `proxy(...)`, subject to its filter.
+            if cfg!(feature = "proxy") && matches!(pdu, PDU::Network(_)) {
+                return Ok(self.advertising_interface.transmit(pdu).await?);
+            }
+
             let gatt_fut = self.gatt_interface.transmit(pdu);
             let adv_fut = self.advertising_interface.transmit(pdu);
 
@@ -128,6 +180,26 @@ impl<AB: AdvertisingBearer, GB: GattBearer<MTU>, const MTU: usize> NetworkInterf
         }
     }
 
+    type RelayFuture<'m> = impl Future<Output=Result<(), NetworkError>> + 'm
+    where
+    Self: 'm;
+
+    fn relay<'m>(
+        &'m self,
+        pdu: &'m NetworkPDU,
+        retransmit: NetworkTransmitConfig,
+    ) -> Self::RelayFuture<'m> {
+        async move { Ok(self.advertising_interface.relay(pdu, retransmit).await?) }
+    }
+
+    type ProxyFuture<'m> = impl Future<Output=Result<(), NetworkError>> + 'm
+    where
+    Self: 'm;
+
+    fn proxy<'m>(&'m self, pdu: &'m NetworkPDU) -> Self::ProxyFuture<'m> {
+        async move { Ok(self.gatt_interface.proxy(pdu).await?) }
+    }
+
     type RetransmitFuture<'m> = impl Future<Output = Result<(), NetworkError>> + 'm
     where
     Self: 'm;
@@ -136,6 +208,18 @@ impl<AB: AdvertisingBearer, GB: GattBearer<MTU>, const MTU: usize> NetworkInterf
         async move { Ok(self.advertising_interface.retransmit().await?) }
     }
 
+    fn next_repeat_deadline(&self) -> Option<Instant> {
+        self.advertising_interface.next_repeat_deadline()
+    }
+
+    type RepeatFuture<'m> = impl Future<Output = Result<(), NetworkError>> + 'm
+    where
+    Self: 'm;
+
+    fn repeat(&self) -> Self::RepeatFuture<'_> {
+        async move { Ok(self.advertising_interface.repeat().await?) }
+    }
+
     type BeaconFuture<'m> = impl Future<Output=Result<(), NetworkError>> + 'm
     where
     Self: 'm;
@@ -147,6 +231,15 @@ impl<AB: AdvertisingBearer, GB: GattBearer<MTU>, const MTU: usize> NetworkInterf
             Ok(())
         }
     }
+
+    fn set_network_transmit_config(&self, config: NetworkTransmitConfig) {
+        self.advertising_interface
+            .set_network_transmit_config(config);
+    }
+
+    fn proxy_connection_count(&self) -> u32 {
+        self.gatt_interface.connection_count()
+    }
 }
 
 pub struct AdvertisingOnlyNetworkInterfaces<B: AdvertisingBearer> {
@@ -173,12 +266,12 @@ impl<B: AdvertisingBearer> NetworkInterfaces for AdvertisingOnlyNetworkInterface
         }
     }
 
-    type ReceiveFuture<'m> = impl Future<Output=Result<PDU, NetworkError>> + 'm
+    type ReceiveFuture<'m> = impl Future<Output=Result<(PDU, Bearer), NetworkError>> + 'm
     where
     Self: 'm;
 
     fn receive<'m>(&'m self, state: &'m DeviceState) -> Self::ReceiveFuture<'m> {
-        async move { Ok(self.interface.receive(state).await?) }
+        async move { Ok((self.interface.receive(state).await?, Bearer::Advertising)) }
     }
 
     type TransmitFuture<'m> = impl Future<Output=Result<(), NetworkError>> + 'm
@@ -189,6 +282,29 @@ impl<B: AdvertisingBearer> NetworkInterfaces for AdvertisingOnlyNetworkInterface
         async move { Ok(self.interface.transmit(pdu).await?) }
     }
 
+    type RelayFuture<'m> = impl Future<Output=Result<(), NetworkError>> + 'm
+    where
+    Self: 'm;
+
+    fn relay<'m>(
+        &'m self,
+        pdu: &'m NetworkPDU,
+        retransmit: NetworkTransmitConfig,
+    ) -> Self::RelayFuture<'m> {
+        async move { Ok(self.interface.relay(pdu, retransmit).await?) }
+    }
+
+    type ProxyFuture<'m> = impl Future<Output=Result<(), NetworkError>> + 'm
+    where
+    Self: 'm;
+
+    fn proxy<'m>(&'m self, _pdu: &'m NetworkPDU) -> Self::ProxyFuture<'m> {
+        async move {
+            /* no proxy client */
+            Ok(())
+        }
+    }
+
     type RetransmitFuture<'m> = impl Future<Output = Result<(), NetworkError>> + 'm
     where
     Self: 'm;
@@ -197,6 +313,18 @@ impl<B: AdvertisingBearer> NetworkInterfaces for AdvertisingOnlyNetworkInterface
         async move { Ok(self.interface.retransmit().await?) }
     }
 
+    fn next_repeat_deadline(&self) -> Option<Instant> {
+        self.interface.next_repeat_deadline()
+    }
+
+    type RepeatFuture<'m> = impl Future<Output = Result<(), NetworkError>> + 'm
+    where
+    Self: 'm;
+
+    fn repeat(&self) -> Self::RepeatFuture<'_> {
+        async move { Ok(self.interface.repeat().await?) }
+    }
+
     type BeaconFuture<'m> = impl Future<Output=Result<(), NetworkError>> + 'm
     where
     Self: 'm;
@@ -204,4 +332,12 @@ impl<B: AdvertisingBearer> NetworkInterfaces for AdvertisingOnlyNetworkInterface
     fn beacon(&self, beacon: Beacon) -> Self::BeaconFuture<'_> {
         async move { Ok(self.interface.beacon(beacon).await?) }
     }
+
+    fn set_network_transmit_config(&self, config: NetworkTransmitConfig) {
+        self.interface.set_network_transmit_config(config);
+    }
+
+    fn proxy_connection_count(&self) -> u32 {
+        0
+    }
 }
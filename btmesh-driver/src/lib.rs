@@ -6,20 +6,23 @@
 #![allow(clippy::await_holding_refcell_ref)]
 
 use btmesh_bearer::beacon::Beacon;
-use btmesh_common::{Composition, Seq, Uuid};
+use btmesh_common::opcode::Opcode;
+use btmesh_common::{Composition, ModelIdentifier, Seq, Uuid};
 use btmesh_device::{
-    BluetoothMeshDevice, InboundChannelImpl, InboundReceiverImpl, OutboundChannelImpl,
-    OutboundPayload,
+    BluetoothMeshDevice, CompletionToken, InboundChannelImpl, InboundReceiverImpl,
+    OutboundChannelImpl, OutboundMetadata, OutboundPayload, OutboundTarget,
 };
 use btmesh_pdu::provisioned::access::AccessMessage;
 use btmesh_pdu::provisioned::Message;
-use btmesh_pdu::provisioning::Capabilities;
+use btmesh_pdu::provisioning::{Capabilities, PublicKeyType, StaticOOBType};
 use btmesh_pdu::PDU;
 use core::borrow::Borrow;
 use core::cell::RefCell;
 use core::future::{pending, Future};
-use embassy_executor::time::{Duration, Timer};
-use embassy_util::{select, select3, select4, Either3, Either4};
+use embassy_executor::time::{Duration, Instant, Timer};
+use embassy_util::channel::signal::Signal;
+use embassy_util::{select, select3, select4, Either, Either3, Either4};
+use heapless::Vec;
 use rand_core::{CryptoRng, RngCore};
 
 mod error;
@@ -30,23 +33,28 @@ pub mod stack;
 mod device;
 pub(crate) mod dispatch;
 mod models;
+mod publisher;
 pub mod storage;
 mod util;
 
 use crate::device::DeviceContext;
 use crate::dispatch::Dispatcher;
-use crate::interface::{NetworkError, NetworkInterfaces};
+use crate::interface::{Bearer, NetworkError, NetworkInterfaces};
 use crate::models::FoundationDevice;
+use crate::publisher::Publisher;
+#[cfg(feature = "low_power")]
+use crate::stack::provisioned::low_power::LowPowerConfig;
 use crate::stack::provisioned::network::DeviceInfo;
 use crate::stack::provisioned::secrets::Secrets;
 use crate::stack::provisioned::sequence::Sequence;
 use crate::stack::provisioned::system::UpperMetadata;
 use crate::stack::provisioned::{NetworkState, ProvisionedStack};
+use crate::stack::unprovisioned::oob::{OobConfig, OobEvent, OobInput};
 use crate::stack::unprovisioned::{ProvisioningState, UnprovisionedStack};
 use crate::stack::Stack;
 use crate::storage::provisioned::ProvisionedConfiguration;
 use crate::storage::unprovisioned::UnprovisionedConfiguration;
-use crate::storage::{BackingStore, Configuration, Storage};
+use crate::storage::{BackingStore, Configuration, Storage, StorageError};
 use crate::util::hash::hash_of;
 pub use error::DriverError;
 
@@ -55,6 +63,7 @@ enum DesiredStack {
     Unchanged,
     Unprovisioned(UnprovisionedConfiguration),
     Provisioned(ProvisionedConfiguration),
+    Reconfigured(ProvisionedConfiguration),
 }
 
 enum CurrentStack<'s> {
@@ -76,6 +85,9 @@ pub struct Driver<N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore>
     network: Option<N>,
     rng: Option<R>,
     storage: Storage<B>,
+    oob: OobConfig,
+    #[cfg(feature = "low_power")]
+    low_power: LowPowerConfig,
 }
 
 impl<N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> Driver<N, R, B> {
@@ -84,8 +96,28 @@ impl<N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> Driver<N, R,
             network: Some(network),
             rng: Some(rng),
             storage: Storage::new(backing_store),
+            oob: Default::default(),
+            #[cfg(feature = "low_power")]
+            low_power: Default::default(),
         }
     }
+
+    /// Set the out-of-band information advertised for authenticating the provisioner.
+    /// The device performs output and input actions as told through [`oob_event`],
+    /// and reports what the user entered through [`oob_input`].
+    pub fn set_oob_config(&mut self, config: OobConfig) {
+        self.oob = config;
+    }
+
+    /// Set the friend criteria and polling schedule of the low power node,
+    /// rejecting a receive delay under 10 milliseconds or a poll interval
+    /// not within the poll timeout.
+    #[cfg(feature = "low_power")]
+    pub fn set_low_power_config(&mut self, config: LowPowerConfig) -> Result<(), DriverError> {
+        config.validate()?;
+        self.low_power = config;
+        Ok(())
+    }
 }
 
 pub struct InnerDriver<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore + 's> {
@@ -94,6 +126,10 @@ pub struct InnerDriver<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: Back
     rng: RefCell<R>,
     storage: &'s Storage<B>,
     dispatcher: Dispatcher,
+    publisher: RefCell<Publisher>,
+    oob: OobConfig,
+    #[cfg(feature = "low_power")]
+    low_power: LowPowerConfig,
 }
 
 impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDriver<'s, N, R, B> {
@@ -104,22 +140,38 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
             rng: RefCell::new(rng),
             storage,
             dispatcher: Dispatcher::new(FOUNDATION_INBOUND.sender(), DEVICE_INBOUND.sender()),
+            publisher: RefCell::new(Default::default()),
+            oob: Default::default(),
+            #[cfg(feature = "low_power")]
+            low_power: Default::default(),
         }
     }
 
-    async fn receive_pdu(&self, pdu: &PDU) -> Result<(), DriverError> {
+    async fn receive_pdu(&self, pdu: &PDU, bearer: Bearer) -> Result<(), DriverError> {
         let mut current_stack = &mut *self.stack.borrow_mut();
 
         match (&pdu, &mut current_stack) {
             (PDU::Provisioning(pdu), Stack::Unprovisioned { stack, uuid }) => {
                 debug!( "inbound provisioning pdu: {}", pdu);
-                if let Some(provisioning_state) = stack.process(pdu, &mut *self.rng.borrow_mut())? {
+                let provisioning_state = stack.process(pdu, &mut *self.rng.borrow_mut())?;
+                // actions are due even without a response, such as when the public key was obtained out-of-band.
+                if let Some(event) = stack.take_oob_event() {
+                    debug!("out-of-band authentication action due");
+                    // anything entered before being asked for is disregarded.
+                    OOB_INPUT.reset();
+                    OOB_EVENT.signal(event);
+                }
+                if let Some(provisioning_state) = provisioning_state {
                     match provisioning_state {
                         ProvisioningState::Failed => {
                             warn!( "provisioning failed");
+                            if stack.oob_event_raised() {
+                                OOB_EVENT.signal(OobEvent::Complete);
+                            }
                             *current_stack = Stack::Unprovisioned {
                                 stack: UnprovisionedStack::new(
                                     self.storage.borrow().capabilities(),
+                                    &self.oob,
                                 ),
                                 uuid: *uuid,
                             };
@@ -130,6 +182,9 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
                         }
                         ProvisioningState::Data(device_key, provisioning_data, pdu) => {
                             debug!( "received provisioning data: {}", provisioning_data);
+                            if stack.oob_event_raised() {
+                                OOB_EVENT.signal(OobEvent::Complete);
+                            }
                             let primary_unicast_addr = provisioning_data.unicast_address;
                             let device_info = DeviceInfo::new(
                                 primary_unicast_addr,
@@ -149,19 +204,27 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
                                 stack: ProvisionedStack::new(device_info, secrets, network_state),
                                 sequence: Sequence::new(Seq::new(800)),
                             };
+                            let config: Configuration = (&*current_stack).try_into()?;
+                            self.storage.put(&config).await?;
                         }
                     }
                 }
             }
             (PDU::Network(pdu), Stack::Provisioned { stack, sequence }) => {
                 debug!("inbound network pdu: {}", pdu);
-                if let Some(result) = stack.process_inbound_network_pdu(pdu)? {
+                if let Some(result) = stack.process_inbound_network_pdu(pdu, bearer)? {
                     if let Some((block_ack, meta)) = result.block_ack {
+                        #[cfg(feature = "proxy")]
+                        let dst = meta.src().into();
                         // send outbound block-ack
                         for network_pdu in
                             stack.process_outbound_block_ack(sequence, block_ack, meta)?
                         {
                             debug!("outbound network block-ack pdu: {}", pdu);
+                            #[cfg(feature = "proxy")]
+                            if stack.proxy_filter_accepts(dst) {
+                                self.network.proxy(&network_pdu).await.ok();
+                            }
                             // don't error if we can't send.
                             self.network.transmit(&PDU::Network(network_pdu)).await.ok();
                         }
@@ -171,11 +234,57 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
                         // dispatch to element(s)
                         match message {
                             Message::Access(message) => {
-                                self.dispatcher.dispatch(message).await?;
+                                if let Configuration::Provisioned(config) =
+                                    self.storage.get().await?
+                                {
+                                    self.dispatcher.dispatch(message, &config).await?;
+                                }
+                            }
+                            Message::Control(message) => {
+                                stack.process_inbound_control_message(sequence, &message);
                             }
-                            Message::Control(_) => {}
                         }
                     }
+
+                    if let Some((relay_pdu, retransmit)) = result.relay {
+                        debug!("relaying network pdu: {}", relay_pdu);
+                        // don't error if we can't relay.
+                        self.network.relay(&relay_pdu, retransmit).await.ok();
+                    }
+
+                    if let Some(proxy_pdu) = result.proxy {
+                        debug!("proxying network pdu: {}", proxy_pdu);
+                        // don't error if there's no proxy client to receive it.
+                        self.network.proxy(&proxy_pdu).await.ok();
+                    }
+                }
+            }
+            #[cfg(feature = "proxy")]
+            (PDU::ProxyConfiguration(pdu), Stack::Provisioned { stack, sequence }) => {
+                if let Some(status_pdu) =
+                    stack.process_inbound_proxy_configuration_pdu(sequence, pdu)?
+                {
+                    debug!("outbound proxy configuration pdu: {}", status_pdu);
+                    self.network
+                        .transmit(&PDU::ProxyConfiguration(status_pdu))
+                        .await?;
+                }
+            }
+            (PDU::SecureNetworkBeacon(beacon), Stack::Provisioned { stack, sequence }) => {
+                if let Some((net_key_index, transition)) =
+                    stack.process_inbound_secure_network_beacon(sequence, beacon)?
+                {
+                    // keys are owned by the configuration, the stack picks up
+                    // the transition once reconfigured.
+                    self.storage
+                        .modify(|config| {
+                            config
+                                .secrets
+                                .key_refresh_transition(net_key_index, transition)
+                                .map(|_| ())
+                                .map_err(|_| ())
+                        })
+                        .await?;
                 }
             }
             _ => {
@@ -189,24 +298,149 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
     async fn process_outbound_payload(
         &self,
         outbound_payload: OutboundPayload,
+    ) -> Result<(), DriverError> {
+        let ((element_index, model_identifier), opcode, parameters, target) = outbound_payload;
+        match target {
+            OutboundTarget::Send(meta, completion_token) => {
+                let result = self
+                    .process_outbound_access(
+                        element_index,
+                        opcode,
+                        parameters,
+                        meta,
+                        completion_token,
+                    )
+                    .await;
+                if let (Err(_), Some(completion_token)) = (result, completion_token) {
+                    completion_token.incomplete();
+                }
+                result
+            }
+            OutboundTarget::Publish(completion_token) => {
+                let result = self
+                    .process_outbound_publish(
+                        element_index,
+                        model_identifier,
+                        opcode,
+                        parameters,
+                        completion_token,
+                    )
+                    .await;
+                match result {
+                    Err(DriverError::PublicationNotConfigured) => {
+                        warn!("no publication configured for model {}", model_identifier);
+                        completion_token.not_configured();
+                        Ok(())
+                    }
+                    Err(_) => {
+                        completion_token.incomplete();
+                        result
+                    }
+                    Ok(_) => result,
+                }
+            }
+        }
+    }
+
+    async fn process_outbound_publish(
+        &self,
+        element_index: usize,
+        model_identifier: ModelIdentifier,
+        opcode: Opcode,
+        parameters: Vec<u8, 379>,
+        completion_token: CompletionToken,
+    ) -> Result<(), DriverError> {
+        let config = self.storage.get().await?;
+        if let Configuration::Provisioned(config) = config {
+            let publication = config
+                .foundation()
+                .configuration()
+                .publications()
+                .get(element_index as u8, model_identifier)
+                .ok_or(DriverError::PublicationNotConfigured)?;
+
+            let meta = if let Stack::Provisioned { stack, .. } = &*self.stack.borrow() {
+                stack.publication_metadata(publication)?
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            self.process_outbound_access(
+                element_index,
+                opcode,
+                parameters.clone(),
+                meta,
+                Some(completion_token),
+            )
+            .await?;
+
+            self.publisher.borrow_mut().schedule_retransmission(
+                element_index,
+                model_identifier,
+                opcode,
+                parameters,
+                meta,
+                publication.publish_retransmit_count(),
+                publication.retransmit_interval(),
+                Instant::now(),
+            );
+
+            Ok(())
+        } else {
+            Err(DriverError::InvalidState)
+        }
+    }
+
+    async fn publish(&self) -> Result<(), DriverError> {
+        let due = self.publisher.borrow_mut().process(Instant::now());
+
+        for (element_index, model_identifier) in due.models {
+            self.dispatcher
+                .dispatch_publication_due(element_index, model_identifier)
+                .await;
+        }
+
+        for retransmission in due.retransmissions {
+            self.process_outbound_access(
+                retransmission.element_index,
+                retransmission.opcode,
+                retransmission.parameters,
+                retransmission.meta,
+                None,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn process_outbound_access(
+        &self,
+        element_index: usize,
+        opcode: Opcode,
+        parameters: Vec<u8, 379>,
+        meta: OutboundMetadata,
+        completion_token: Option<CompletionToken>,
     ) -> Result<(), DriverError> {
         let config = self.storage.borrow().get().await?;
         if let Configuration::Provisioned(config) = config {
             let element_address = config
                 .device_info()
-                .local_element_address(outbound_payload.0 .0 as u8)
+                .local_element_address(element_index as u8)
                 .ok_or(DriverError::InvalidState)?;
             let default_ttl = config.foundation().configuration().default_ttl();
-            let message: AccessMessage<ProvisionedStack> = AccessMessage::new(
-                outbound_payload.1,
-                outbound_payload.2,
-                (element_address, outbound_payload.3, *default_ttl),
-            );
+            let message: AccessMessage<ProvisionedStack> =
+                AccessMessage::new(opcode, parameters, (element_address, meta, *default_ttl));
 
             if let Stack::Provisioned { stack, sequence } = &mut *self.stack.borrow_mut() {
-                let network_pdus = stack.process_outbound(sequence, &(message.into()));
+                let network_pdus =
+                    stack.process_outbound(sequence, &(message.into()), completion_token);
                 for pdu in network_pdus? {
                     debug!("outbound network pdu: {}", pdu);
+                    #[cfg(feature = "proxy")]
+                    if stack.proxy_filter_accepts(meta.dst()) {
+                        self.network.proxy(&pdu).await.ok();
+                    }
                     self.network.transmit(&(pdu.into())).await?;
                 }
             }
@@ -216,19 +450,67 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
     }
 
     async fn retransmit(&self) -> Result<(), DriverError> {
-        match &*self.stack.borrow() {
+        match &mut *self.stack.borrow_mut() {
             Stack::None => {}
             Stack::Unprovisioned { stack, .. } => {
                 if let Some(pdu) = stack.retransmit() {
                     self.network.transmit(&(pdu.into())).await?;
                 }
             }
-            Stack::Provisioned { .. } => {}
+            Stack::Provisioned { stack, sequence } => {
+                if let Some((network_pdus, dst)) = stack.process_retransmit(sequence)? {
+                    #[cfg(not(feature = "proxy"))]
+                    let _ = dst;
+                    for pdu in network_pdus {
+                        debug!("retransmit network pdu: {}", pdu);
+                        #[cfg(feature = "proxy")]
+                        if stack.proxy_filter_accepts(dst) {
+                            self.network.proxy(&pdu).await.ok();
+                        }
+                        self.network.transmit(&(pdu.into())).await?;
+                    }
+                }
+            }
         }
 
         Ok(())
     }
 
+    async fn process_oob_input(&self, input: OobInput) -> Result<(), DriverError> {
+        let pdu = if let Stack::Unprovisioned { stack, .. } = &mut *self.stack.borrow_mut() {
+            match stack.input(input) {
+                Err(DriverError::InvalidFormat) => {
+                    // the user may enter it again.
+                    warn!("out-of-band input does not fit the authentication action");
+                    None
+                }
+                result => result?,
+            }
+        } else {
+            None
+        };
+        if let Some(pdu) = pdu {
+            debug!("outbound provisioning pdu: {}", pdu);
+            self.network.transmit(&(pdu.into())).await?;
+        }
+        Ok(())
+    }
+
+    async fn reset(&self) -> Result<(), DriverError> {
+        // transmit whatever is still queued, such as the Config Node Reset Status,
+        // while the keys to secure it with remain available.
+        while let Ok(outbound_payload) = OUTBOUND.try_recv() {
+            self.process_outbound_payload(outbound_payload).await?;
+        }
+
+        info!("node reset");
+        let config = Configuration::Unprovisioned(UnprovisionedConfiguration {
+            uuid: Uuid::new_random(&mut *self.rng.borrow_mut()),
+        });
+        self.storage.put(&config).await?;
+        Ok(())
+    }
+
     async fn send_beacon(&self) -> Result<(), DriverError> {
         match &*self.stack.borrow() {
             Stack::None => {
@@ -239,13 +521,60 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
             }
 
             Stack::Provisioned { stack, .. } => {
-                let network_id = stack.secrets().network_key_by_index(0)?.network_id();
-                self.network.beacon(Beacon::Provisioned(network_id)).await?;
+                if let Some(network_id) = stack.advertised_network_id() {
+                    self.network.beacon(Beacon::Provisioned(network_id)).await?;
+                }
+                for beacon in stack.secure_network_beacons()? {
+                    self.network.beacon(Beacon::Secure(beacon)).await?;
+                }
             }
         }
         Ok(())
     }
 
+    async fn send_heartbeat(&self) -> Result<(), DriverError> {
+        if let Stack::Provisioned { stack, sequence } = &mut *self.stack.borrow_mut() {
+            if let Some((network_pdus, dst)) = stack.process_heartbeat(sequence)? {
+                #[cfg(not(feature = "proxy"))]
+                let _ = dst;
+                for pdu in network_pdus {
+                    debug!("outbound heartbeat pdu: {}", pdu);
+                    #[cfg(feature = "proxy")]
+                    if stack.proxy_filter_accepts(dst) {
+                        self.network.proxy(&pdu).await.ok();
+                    }
+                    self.network.transmit(&(pdu.into())).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_friend(&self) -> Result<(), DriverError> {
+        #[cfg(feature = "friend")]
+        if let Stack::Provisioned { stack, sequence } = &mut *self.stack.borrow_mut() {
+            for pdu in stack.process_friend(sequence)? {
+                debug!("outbound friend pdu: {}", pdu);
+                self.network.transmit(&(pdu.into())).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_low_power(&self) -> Result<(), DriverError> {
+        #[cfg(feature = "low_power")]
+        if let Stack::Provisioned { stack, sequence } = &mut *self.stack.borrow_mut() {
+            for pdu in stack.process_low_power(sequence)? {
+                debug!("outbound low power pdu: {}", pdu);
+                self.network.transmit(&(pdu.into())).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn next_beacon(&self) -> BeaconFuture<'_, N, R, B> {
         async move {
             if let Some(next_beacon_deadline) = self.stack.borrow().next_beacon_deadline() {
@@ -266,6 +595,93 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
         }
     }
 
+    fn next_repeat(&self) -> RepeatFuture<'_, N, R, B> {
+        async move {
+            if let Some(next_deadline) = self.network.next_repeat_deadline() {
+                Timer::at(next_deadline).await
+            } else {
+                pending().await
+            }
+        }
+    }
+
+    fn next_publication(&self) -> PublicationFuture<'_, N, R, B> {
+        async move {
+            let next_deadline = self.publisher.borrow().next_deadline();
+            if let Some(next_deadline) = next_deadline {
+                Timer::at(next_deadline).await
+            } else {
+                pending().await
+            }
+        }
+    }
+
+    fn next_heartbeat(&self) -> HeartbeatFuture<'_, N, R, B> {
+        async move {
+            let next_deadline = if let Stack::Provisioned { stack, .. } = &*self.stack.borrow() {
+                stack.next_heartbeat()
+            } else {
+                None
+            };
+            if let Some(next_deadline) = next_deadline {
+                Timer::at(next_deadline).await
+            } else {
+                pending().await
+            }
+        }
+    }
+
+    fn next_friend(&self) -> FriendFuture<'_, N, R, B> {
+        async move {
+            #[cfg(feature = "friend")]
+            let next_deadline = if let Stack::Provisioned { stack, .. } = &*self.stack.borrow() {
+                stack.next_friend_deadline()
+            } else {
+                None
+            };
+            #[cfg(not(feature = "friend"))]
+            let next_deadline: Option<Instant> = None;
+            if let Some(next_deadline) = next_deadline {
+                Timer::at(next_deadline).await
+            } else {
+                pending().await
+            }
+        }
+    }
+
+    fn next_low_power(&self) -> LowPowerFuture<'_, N, R, B> {
+        async move {
+            #[cfg(feature = "low_power")]
+            let next_deadline = if let Stack::Provisioned { stack, .. } = &*self.stack.borrow() {
+                stack.next_low_power_deadline()
+            } else {
+                None
+            };
+            #[cfg(not(feature = "low_power"))]
+            let next_deadline: Option<Instant> = None;
+            if let Some(next_deadline) = next_deadline {
+                Timer::at(next_deadline).await
+            } else {
+                pending().await
+            }
+        }
+    }
+
+    fn next_oob_input(&self) -> OobInputFuture<'_, N, R, B> {
+        async move {
+            let awaiting_input = if let Stack::Unprovisioned { stack, .. } = &*self.stack.borrow() {
+                stack.awaiting_input()
+            } else {
+                false
+            };
+            if awaiting_input {
+                OOB_INPUT.wait().await
+            } else {
+                pending().await
+            }
+        }
+    }
+
     fn run_device<D: BluetoothMeshDevice>(
         device: &mut D,
         receiver: InboundReceiverImpl,
@@ -283,12 +699,16 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
         let capabilities = Capabilities {
             number_of_elements: composition.number_of_elements(),
             algorithms: Default::default(),
-            public_key_type: Default::default(),
-            static_oob_type: Default::default(),
-            output_oob_size: Default::default(),
-            output_oob_action: Default::default(),
-            input_oob_size: Default::default(),
-            input_oob_action: Default::default(),
+            public_key_type: PublicKeyType {
+                available: self.oob.private_key.is_some(),
+            },
+            static_oob_type: StaticOOBType {
+                available: self.oob.static_oob.is_some(),
+            },
+            output_oob_size: self.oob.output_oob_size,
+            output_oob_action: self.oob.output_oob_action.clone(),
+            input_oob_size: self.oob.input_oob_size,
+            input_oob_action: self.oob.input_oob_action.clone(),
         };
 
         self.storage.set_composition(composition.clone());
@@ -300,6 +720,11 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
         loop {
             let config = match self.storage.borrow().get().await {
                 Ok(config) => config,
+                Err(StorageError::IncompatibleFormat) => {
+                    // never overwrite what might be a provisioned node's configuration.
+                    error!("stored config has an incompatible format");
+                    return Err(StorageError::IncompatibleFormat.into());
+                }
                 Err(_) => {
                     info!("failed to load config");
                     let config = Configuration::Unprovisioned(UnprovisionedConfiguration {
@@ -312,13 +737,25 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
             };
 
             let current_hash = hash_of(&config);
+            let config_changed = last_config_hash != Some(current_hash);
 
-            if let Some(previous_hash) = last_config_hash {
-                if previous_hash != current_hash {
-                    config.display(&composition);
-                }
-            } else {
+            if config_changed {
                 config.display(&composition);
+                match &config {
+                    Configuration::Provisioned(config) => {
+                        self.publisher.borrow_mut().reconfigure(
+                            config.foundation().configuration().publications(),
+                            Instant::now(),
+                        );
+                        self.network.set_network_transmit_config(
+                            *config.foundation().configuration().network_transmit(),
+                        );
+                    }
+                    Configuration::Unprovisioned(_) => {
+                        *self.publisher.borrow_mut() = Default::default();
+                        self.network.set_network_transmit_config(Default::default());
+                    }
+                }
             }
 
             last_config_hash.replace(current_hash);
@@ -335,6 +772,11 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
                     info!("heading to provisioned with seq {}", config.sequence);
                     desired = DesiredStack::Provisioned(config);
                 }
+                (Stack::Provisioned { .. }, Configuration::Provisioned(config))
+                    if config_changed =>
+                {
+                    desired = DesiredStack::Reconfigured(config);
+                }
                 _ => {
                     // unchanged, don't reconfigure the stack.
                 }
@@ -345,7 +787,10 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
                 DesiredStack::Unprovisioned(config) => {
                     info!("setting up unprovisioned stack");
                     *self.stack.borrow_mut() = Stack::Unprovisioned {
-                        stack: UnprovisionedStack::new(self.storage.borrow().capabilities()),
+                        stack: UnprovisionedStack::new(
+                            self.storage.borrow().capabilities(),
+                            &self.oob,
+                        ),
                         uuid: config.uuid(),
                     }
                 }
@@ -354,6 +799,18 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
                     *self.stack.borrow_mut() = Stack::Provisioned {
                         sequence: Sequence::new(Seq::new(config.sequence())),
                         stack: config.into(),
+                    };
+                    #[cfg(feature = "low_power")]
+                    if let Stack::Provisioned { stack, .. } = &mut *self.stack.borrow_mut() {
+                        stack.set_low_power_config(self.low_power);
+                    }
+                }
+                DesiredStack::Reconfigured(config) => {
+                    // also reached right after provisioning, once the configuration is stored.
+                    if let Stack::Provisioned { stack, .. } = &mut *self.stack.borrow_mut() {
+                        stack.reconfigure(&config);
+                        #[cfg(feature = "low_power")]
+                        stack.set_low_power_config(self.low_power);
                     }
                 }
             }
@@ -361,14 +818,60 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
             let device_state = self.stack.borrow().device_state();
 
             if let Some(device_state) = device_state {
-                let receive_fut = self.network.receive(&device_state);
+                // a befriended low power node only listens while awaiting its friend.
+                #[cfg(feature = "low_power")]
+                let listening = if let Stack::Provisioned { stack, .. } = &*self.stack.borrow() {
+                    stack.low_power_listening()
+                } else {
+                    true
+                };
+                #[cfg(not(feature = "low_power"))]
+                let listening = true;
+
+                let receive_fut = async {
+                    if listening {
+                        self.network.receive(&device_state).await
+                    } else {
+                        pending().await
+                    }
+                };
                 let transmit_fut = OUTBOUND.recv();
                 let beacon_fut = self.next_beacon();
+                let publication_fut = self.next_publication();
+                let heartbeat_fut = self.next_heartbeat();
                 let retransmit_fut = self.next_retransmit();
+                let repeat_fut = self.next_repeat();
+                let friend_fut = self.next_friend();
+                let low_power_fut = self.next_low_power();
+                let oob_input_fut = self.next_oob_input();
+
+                let event = select4(
+                    receive_fut,
+                    transmit_fut,
+                    select4(
+                        beacon_fut,
+                        publication_fut,
+                        heartbeat_fut,
+                        NODE_RESET.wait(),
+                    ),
+                    select4(
+                        select(retransmit_fut, repeat_fut),
+                        friend_fut,
+                        low_power_fut,
+                        oob_input_fut,
+                    ),
+                )
+                .await;
+
+                // the proxy filter only applies to the connection it was configured through.
+                #[cfg(feature = "proxy")]
+                if let Stack::Provisioned { stack, .. } = &mut *self.stack.borrow_mut() {
+                    stack.proxy_connection(self.network.proxy_connection_count());
+                }
 
-                match select4(receive_fut, transmit_fut, beacon_fut, retransmit_fut).await {
-                    Either4::First(Ok(pdu)) => {
-                        self.receive_pdu(&pdu).await?;
+                match event {
+                    Either4::First(Ok((pdu, bearer))) => {
+                        self.receive_pdu(&pdu, bearer).await?;
                     }
                     Either4::First(Err(err)) => {
                         return Err(err.into());
@@ -376,21 +879,61 @@ impl<'s, N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> InnerDri
                     Either4::Second(outbound_payload) => {
                         self.process_outbound_payload(outbound_payload).await?;
                     }
-                    Either4::Third(_) => {
+                    Either4::Third(Either4::First(_)) => {
                         self.send_beacon().await?;
                     }
-                    Either4::Fourth(_) => {
+                    Either4::Third(Either4::Second(_)) => {
+                        self.publish().await?;
+                    }
+                    Either4::Third(Either4::Third(_)) => {
+                        self.send_heartbeat().await?;
+                    }
+                    Either4::Third(Either4::Fourth(_)) => {
+                        self.reset().await?;
+                    }
+                    Either4::Fourth(Either4::First(Either::First(_))) => {
                         self.retransmit().await?;
                     }
+                    Either4::Fourth(Either4::First(Either::Second(_))) => {
+                        self.network.repeat().await?;
+                    }
+                    Either4::Fourth(Either4::Second(_)) => {
+                        self.send_friend().await?;
+                    }
+                    Either4::Fourth(Either4::Third(_)) => {
+                        self.send_low_power().await?;
+                    }
+                    Either4::Fourth(Either4::Fourth(input)) => {
+                        self.process_oob_input(input).await?;
+                    }
                 }
 
-                let config: Option<Configuration> = (&*self.stack.borrow()).try_into().ok();
-                if let Some(config) = config {
-                    let current_hash = hash_of(&config);
-                    if let Some(_previous_hash) = last_config_hash {
-                        self.storage.borrow().put(&config).await?;
-                    }
-                    last_config_hash.replace(current_hash);
+                if let Stack::Provisioned { stack, sequence } = &mut *self.stack.borrow_mut() {
+                    stack.update_iv_index(sequence);
+                }
+
+                // write back the state owned by the stack, leaving everything
+                // else (keys, foundation state) as managed by the configuration server.
+                let stack_state =
+                    if let Stack::Provisioned { stack, sequence } = &mut *self.stack.borrow_mut() {
+                        Some((
+                            stack.network_state(),
+                            sequence.current(),
+                            stack.heartbeat_write_back(),
+                        ))
+                    } else {
+                        None
+                    };
+
+                if let Some((network_state, sequence, heartbeat)) = stack_state {
+                    self.storage
+                        .modify(|config| {
+                            config.network_state = network_state;
+                            config.sequence = sequence;
+                            heartbeat.apply(config.foundation_mut().configuration_mut());
+                            Ok(())
+                        })
+                        .await?;
                 }
             }
         }
@@ -442,13 +985,20 @@ impl<N: NetworkInterfaces, R: RngCore + CryptoRng, B: BackingStore> BluetoothMes
 
     fn run<'r, D: BluetoothMeshDevice>(&'r mut self, device: &'r mut D) -> Self::RunFuture<'_, D> {
         async move {
-            InnerDriver::new(
-                unwrap!(self.network.take()),
-                unwrap!(self.rng.take()),
-                &self.storage,
-            )
-            .run(device)
-            .await
+            let driver = InnerDriver {
+                oob: self.oob.clone(),
+                ..InnerDriver::new(
+                    unwrap!(self.network.take()),
+                    unwrap!(self.rng.take()),
+                    &self.storage,
+                )
+            };
+            #[cfg(feature = "low_power")]
+            let driver = InnerDriver {
+                low_power: self.low_power,
+                ..driver
+            };
+            driver.run(device).await
         }
     }
 }
@@ -460,6 +1010,20 @@ where
     B: BackingStore + 'f,
 = impl Future<Output = ()> + 'f;
 
+type PublicationFuture<'f, N, R, B>
+where
+    N: NetworkInterfaces + 'f,
+    R: CryptoRng + RngCore + 'f,
+    B: BackingStore + 'f,
+= impl Future<Output = ()> + 'f;
+
+type HeartbeatFuture<'f, N, R, B>
+where
+    N: NetworkInterfaces + 'f,
+    R: CryptoRng + RngCore + 'f,
+    B: BackingStore + 'f,
+= impl Future<Output = ()> + 'f;
+
 type RetransmitFuture<'f, N, R, B>
 where
     N: NetworkInterfaces + 'f,
@@ -467,6 +1031,34 @@ where
     B: BackingStore + 'f,
 = impl Future<Output = ()> + 'f;
 
+type RepeatFuture<'f, N, R, B>
+where
+    N: NetworkInterfaces + 'f,
+    R: CryptoRng + RngCore + 'f,
+    B: BackingStore + 'f,
+= impl Future<Output = ()> + 'f;
+
+type FriendFuture<'f, N, R, B>
+where
+    N: NetworkInterfaces + 'f,
+    R: CryptoRng + RngCore + 'f,
+    B: BackingStore + 'f,
+= impl Future<Output = ()> + 'f;
+
+type LowPowerFuture<'f, N, R, B>
+where
+    N: NetworkInterfaces + 'f,
+    R: CryptoRng + RngCore + 'f,
+    B: BackingStore + 'f,
+= impl Future<Output = ()> + 'f;
+
+type OobInputFuture<'f, N, R, B>
+where
+    N: NetworkInterfaces + 'f,
+    R: CryptoRng + RngCore + 'f,
+    B: BackingStore + 'f,
+= impl Future<Output = OobInput> + 'f;
+
 pub enum DeviceState {
     Unprovisioned { uuid: Uuid, in_progress: bool },
     Provisioned,
@@ -476,3 +1068,25 @@ static FOUNDATION_INBOUND: InboundChannelImpl = InboundChannelImpl::new();
 static DEVICE_INBOUND: InboundChannelImpl = InboundChannelImpl::new();
 
 static OUTBOUND: OutboundChannelImpl = OutboundChannelImpl::new();
+
+/// Raised by the configuration server once the Config Node Reset Status has been
+/// queued. The outbound queue is drained before resetting, so the status is
+/// transmitted first.
+static NODE_RESET: Signal<()> = Signal::new();
+
+/// Out-of-band authentication actions, from the driver to the device, and
+/// the values entered by the user, from the device to the driver.
+static OOB_EVENT: Signal<OobEvent> = Signal::new();
+static OOB_INPUT: Signal<OobInput> = Signal::new();
+
+/// Wait for the next out-of-band authentication action the device should
+/// perform, or ask of the user, while being provisioned.
+pub async fn oob_event() -> OobEvent {
+    OOB_EVENT.wait().await
+}
+
+/// Report the value the user entered on the device, once asked for by an
+/// input action through [`oob_event`].
+pub fn oob_input(input: OobInput) {
+    OOB_INPUT.signal(input)
+}
@@ -0,0 +1,349 @@
+use crate::storage::provisioned::ProvisionedConfiguration;
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_common::crypto::application::ApplicationKey;
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::app_key::{
+    AppKeyAddMessage, AppKeyDeleteMessage, AppKeyListMessage, AppKeyMessage, AppKeyUpdateMessage,
+};
+use btmesh_models::foundation::configuration::key_refresh_phase::KeyRefreshPhase;
+use btmesh_models::foundation::configuration::ConfigurationServer;
+use btmesh_models::Status;
+use heapless::Vec;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: AppKeyMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        AppKeyMessage::Add(add) => {
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| add_key(config, &add).map_err(|err| status = err))
+                .await?;
+
+            ctx.send(
+                AppKeyMessage::Status(add.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        AppKeyMessage::Update(update) => {
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| update_key(config, &update).map_err(|err| status = err))
+                .await?;
+
+            ctx.send(
+                AppKeyMessage::Status(update.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        AppKeyMessage::Delete(delete) => {
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| delete_key(config, &delete).map_err(|err| status = err))
+                .await?;
+
+            ctx.send(
+                AppKeyMessage::Status(delete.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        AppKeyMessage::Get(get) => {
+            let list = if let Configuration::Provisioned(config) = storage.get().await? {
+                if config.secrets.has_network_key(get.net_key_index) {
+                    AppKeyListMessage {
+                        status: Status::Success,
+                        net_key_index: get.net_key_index,
+                        app_key_indexes: config
+                            .secrets
+                            .application_keys()
+                            .indexes_iter(get.net_key_index)
+                            .collect(),
+                    }
+                } else {
+                    AppKeyListMessage {
+                        status: Status::InvalidNetKeyIndex,
+                        net_key_index: get.net_key_index,
+                        app_key_indexes: Vec::new(),
+                    }
+                }
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(AppKeyMessage::List(list).into(), meta.reply())
+                .await?;
+        }
+        AppKeyMessage::List(_) | AppKeyMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
+
+/// Add an application key, failing with the status to report if the
+/// configuration is left unmodified.
+fn add_key(config: &mut ProvisionedConfiguration, add: &AppKeyAddMessage) -> Result<(), Status> {
+    let net_key_index = add.indexes.net_key();
+    let app_key_index = add.indexes.app_key();
+
+    if !config.secrets.has_network_key(net_key_index) {
+        return Err(Status::InvalidNetKeyIndex);
+    }
+
+    if let Some((_, entry)) = config.secrets.application_keys().get(app_key_index) {
+        return if entry.net_key_index != net_key_index {
+            Err(Status::InvalidNetKeyIndex)
+        } else if *entry.key != add.app_key {
+            Err(Status::KeyIndexAlreadyStored)
+        } else {
+            // adding the identical key again is a successful no-op.
+            Err(Status::Success)
+        };
+    }
+
+    let application_key = ApplicationKey::new(add.app_key).map_err(|_| Status::UnspecifiedError)?;
+
+    config
+        .secrets
+        .application_keys_mut()
+        .add(app_key_index, net_key_index, application_key)
+        .map_err(|_| Status::InsufficientResources)
+}
+
+/// Distribute the new key of an application key, failing with the status
+/// to report if the configuration is left unmodified.
+fn update_key(
+    config: &mut ProvisionedConfiguration,
+    update: &AppKeyUpdateMessage,
+) -> Result<(), Status> {
+    if !config.secrets.has_network_key(update.net_key_index) {
+        return Err(Status::InvalidNetKeyIndex);
+    }
+
+    match config.secrets.application_keys().get(update.app_key_index) {
+        None => return Err(Status::InvalidAppKeyIndex),
+        Some((_, entry)) if entry.net_key_index != update.net_key_index => {
+            return Err(Status::InvalidBinding)
+        }
+        Some(_) => {}
+    }
+
+    // keys may only be updated during the first phase of the
+    // key refresh procedure of the bound network key.
+    if config.secrets.key_refresh_phase(update.net_key_index) != Some(KeyRefreshPhase::First) {
+        return Err(Status::CannotUpdate);
+    }
+
+    let application_key =
+        ApplicationKey::new(update.app_key).map_err(|_| Status::UnspecifiedError)?;
+
+    config
+        .secrets
+        .application_keys_mut()
+        .update(update.app_key_index, application_key)
+        .map_err(|_| Status::KeyIndexAlreadyStored)
+}
+
+/// Delete an application key, failing with the status to report if the
+/// configuration is left unmodified.
+fn delete_key(
+    config: &mut ProvisionedConfiguration,
+    delete: &AppKeyDeleteMessage,
+) -> Result<(), Status> {
+    let net_key_index = delete.indexes.net_key();
+    let app_key_index = delete.indexes.app_key();
+
+    if !config.secrets.has_network_key(net_key_index) {
+        return Err(Status::InvalidNetKeyIndex);
+    }
+
+    match config.secrets.application_keys().get(app_key_index) {
+        // deleting a key which does not exist is a successful no-op.
+        None => return Err(Status::Success),
+        Some((_, entry)) if entry.net_key_index != net_key_index => {
+            return Err(Status::InvalidBinding)
+        }
+        Some(_) => {}
+    }
+
+    config.secrets.application_keys_mut().remove(app_key_index);
+    let configuration = config.foundation_mut().configuration_mut();
+    configuration.bindings_mut().remove_app_key(app_key_index);
+    configuration
+        .publications_mut()
+        .remove_app_key(app_key_index);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::configuration::app_key::{add_key, delete_key, update_key};
+    use crate::stack::provisioned::secrets::application::ApplicationKeys;
+    use crate::stack::provisioned::secrets::network::NetworkKeys;
+    use crate::storage::provisioned::ProvisionedConfiguration;
+    use crate::{DeviceInfo, NetworkState, Secrets};
+    use btmesh_common::address::UnicastAddress;
+    use btmesh_common::crypto::device::DeviceKey;
+    use btmesh_common::crypto::network::NetworkKey;
+    use btmesh_common::{IvIndex, IvUpdateFlag};
+    use btmesh_models::foundation::configuration::app_key::{
+        AppKeyAddMessage, AppKeyDeleteMessage, AppKeyUpdateMessage,
+    };
+    use btmesh_models::foundation::configuration::{
+        AppKeyIndex, NetKeyAppKeyIndexesPair, NetKeyIndex,
+    };
+    use btmesh_models::Status;
+
+    const APP_KEY: [u8; 16] = [0x63; 16];
+
+    /// Network keys with the key indexes 1 and 2, but none with the key index 0.
+    fn network_keys() -> NetworkKeys {
+        let mut network_keys = NetworkKeys::default();
+        network_keys
+            .add(NetKeyIndex::new(1), NetworkKey::new([0x11; 16]).unwrap())
+            .unwrap();
+        network_keys
+            .add(NetKeyIndex::new(2), NetworkKey::new([0x22; 16]).unwrap())
+            .unwrap();
+        network_keys
+    }
+
+    fn config() -> ProvisionedConfiguration {
+        config_with(network_keys())
+    }
+
+    fn config_with(network_keys: NetworkKeys) -> ProvisionedConfiguration {
+        ProvisionedConfiguration {
+            network_state: NetworkState::new(IvIndex::new(0), IvUpdateFlag::Normal),
+            secrets: Secrets::new(
+                DeviceKey::new([0x33; 16]),
+                network_keys,
+                ApplicationKeys::default(),
+            ),
+            device_info: DeviceInfo::new(UnicastAddress::new(0x0001).unwrap(), 1),
+            sequence: 0,
+            foundation: Default::default(),
+        }
+    }
+
+    fn add(net_key_index: u16, app_key_index: u16, app_key: [u8; 16]) -> AppKeyAddMessage {
+        AppKeyAddMessage {
+            indexes: NetKeyAppKeyIndexesPair::new(
+                NetKeyIndex::new(net_key_index),
+                AppKeyIndex::new(app_key_index),
+            ),
+            app_key,
+        }
+    }
+
+    #[test]
+    fn add_status() {
+        let mut config = config();
+
+        // network keys are looked up by key index, rather than by slot.
+        assert!(matches!(
+            add_key(&mut config, &add(0, 0x123, APP_KEY)),
+            Err(Status::InvalidNetKeyIndex)
+        ));
+        assert!(add_key(&mut config, &add(2, 0x123, APP_KEY)).is_ok());
+        assert!(matches!(
+            add_key(&mut config, &add(2, 0x123, APP_KEY)),
+            Err(Status::Success)
+        ));
+        assert!(matches!(
+            add_key(&mut config, &add(2, 0x123, [0x64; 16])),
+            Err(Status::KeyIndexAlreadyStored)
+        ));
+        assert!(matches!(
+            add_key(&mut config, &add(1, 0x123, APP_KEY)),
+            Err(Status::InvalidNetKeyIndex)
+        ));
+    }
+
+    fn update(net_key_index: u16, app_key_index: u16, app_key: [u8; 16]) -> AppKeyUpdateMessage {
+        AppKeyUpdateMessage {
+            net_key_index: NetKeyIndex::new(net_key_index),
+            app_key_index: AppKeyIndex::new(app_key_index),
+            app_key,
+        }
+    }
+
+    #[test]
+    fn update_status() {
+        let mut config = config();
+        add_key(&mut config, &add(2, 0x123, APP_KEY)).unwrap();
+
+        assert!(matches!(
+            update_key(&mut config, &update(0, 0x123, [0x64; 16])),
+            Err(Status::InvalidNetKeyIndex)
+        ));
+        assert!(matches!(
+            update_key(&mut config, &update(2, 0x456, [0x64; 16])),
+            Err(Status::InvalidAppKeyIndex)
+        ));
+        assert!(matches!(
+            update_key(&mut config, &update(1, 0x123, [0x64; 16])),
+            Err(Status::InvalidBinding)
+        ));
+        // the bound network key is not being refreshed.
+        assert!(matches!(
+            update_key(&mut config, &update(2, 0x123, [0x64; 16])),
+            Err(Status::CannotUpdate)
+        ));
+    }
+
+    #[test]
+    fn update_during_key_refresh() {
+        let mut network_keys = network_keys();
+        network_keys
+            .update(NetKeyIndex::new(2), NetworkKey::new([0x44; 16]).unwrap())
+            .unwrap();
+        let mut config = config_with(network_keys);
+        add_key(&mut config, &add(2, 0x123, APP_KEY)).unwrap();
+
+        assert!(update_key(&mut config, &update(2, 0x123, [0x64; 16])).is_ok());
+        // distributing the same new key again is a successful no-op.
+        assert!(update_key(&mut config, &update(2, 0x123, [0x64; 16])).is_ok());
+        assert!(matches!(
+            update_key(&mut config, &update(2, 0x123, [0x65; 16])),
+            Err(Status::KeyIndexAlreadyStored)
+        ));
+    }
+
+    #[test]
+    fn delete_status() {
+        let mut config = config();
+        add_key(&mut config, &add(2, 0x123, APP_KEY)).unwrap();
+
+        let delete = |net_key_index, app_key_index| AppKeyDeleteMessage {
+            indexes: NetKeyAppKeyIndexesPair::new(
+                NetKeyIndex::new(net_key_index),
+                AppKeyIndex::new(app_key_index),
+            ),
+        };
+
+        assert!(matches!(
+            delete_key(&mut config, &delete(0, 0x123)),
+            Err(Status::InvalidNetKeyIndex)
+        ));
+        assert!(matches!(
+            delete_key(&mut config, &delete(1, 0x123)),
+            Err(Status::InvalidBinding)
+        ));
+        assert!(delete_key(&mut config, &delete(2, 0x123)).is_ok());
+        assert!(matches!(
+            delete_key(&mut config, &delete(2, 0x123)),
+            Err(Status::Success)
+        ));
+    }
+}
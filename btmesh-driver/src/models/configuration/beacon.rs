@@ -1,4 +1,4 @@
-use crate::{BackingStore, DriverError, Storage};
+use crate::{BackingStore, Configuration, DriverError, Storage};
 use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
 use btmesh_models::foundation::configuration::beacon::BeaconMessage;
 use btmesh_models::foundation::configuration::ConfigurationServer;
@@ -11,11 +11,29 @@ pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: Back
 ) -> Result<(), DriverError> {
     match message {
         BeaconMessage::Get => {
-            ctx.send(BeaconMessage::Status(true).into(), meta.reply())
+            let beacon = if let Configuration::Provisioned(config) = storage.get().await? {
+                *config.foundation().configuration().beacon()
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(BeaconMessage::Status(beacon).into(), meta.reply())
+                .await?;
+        }
+        BeaconMessage::Set(beacon) => {
+            storage
+                .modify(|config| {
+                    *config.foundation_mut().configuration_mut().beacon_mut() = beacon;
+                    Ok(())
+                })
                 .await?;
+
+            ctx.send(BeaconMessage::Status(beacon).into(), meta.reply())
+                .await?;
+        }
+        BeaconMessage::Status(_) => {
+            // not applicable to server role
         }
-        BeaconMessage::Set(_) => {}
-        BeaconMessage::Status(_) => {}
     }
     Ok(())
 }
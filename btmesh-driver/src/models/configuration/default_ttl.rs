@@ -0,0 +1,47 @@
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_common::Ttl;
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::default_ttl::DefaultTTLMessage;
+use btmesh_models::foundation::configuration::ConfigurationServer;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: DefaultTTLMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        DefaultTTLMessage::Get => {
+            let default_ttl = if let Configuration::Provisioned(config) = storage.get().await? {
+                *config.foundation().configuration().default_ttl()
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(
+                DefaultTTLMessage::Status(default_ttl.value()).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        DefaultTTLMessage::Set(ttl) => {
+            // prohibited values were already rejected when parsing.
+            storage
+                .modify(|config| {
+                    *config
+                        .foundation_mut()
+                        .configuration_mut()
+                        .default_ttl_mut() = Ttl::new(ttl);
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(DefaultTTLMessage::Status(ttl).into(), meta.reply())
+                .await?;
+        }
+        DefaultTTLMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
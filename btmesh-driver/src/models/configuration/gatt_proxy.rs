@@ -0,0 +1,130 @@
+use crate::{BackingStore, DriverError, Storage};
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::gatt_proxy::{GattProxy, GattProxyMessage};
+use btmesh_models::foundation::configuration::ConfigurationServer;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: GattProxyMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    let mut gatt_proxy = current(storage).await?;
+    let status = if let Some(status) = apply(&mut gatt_proxy, &message) {
+        status
+    } else {
+        return Ok(());
+    };
+
+    #[cfg(feature = "proxy")]
+    if let GattProxyMessage::Set(_) = message {
+        storage
+            .modify(|config| {
+                *config.foundation_mut().configuration_mut().gatt_proxy_mut() = gatt_proxy;
+                Ok(())
+            })
+            .await?;
+    }
+
+    ctx.send(GattProxyMessage::Status(status).into(), meta.reply())
+        .await?;
+    Ok(())
+}
+
+#[cfg(feature = "proxy")]
+async fn current<B: BackingStore>(storage: &Storage<B>) -> Result<GattProxy, DriverError> {
+    if let crate::Configuration::Provisioned(config) = storage.get().await? {
+        Ok(*config.foundation().configuration().gatt_proxy())
+    } else {
+        Err(DriverError::InvalidState)
+    }
+}
+
+/// Without the proxy feature the state is fixed at `NotSupported`.
+#[cfg(not(feature = "proxy"))]
+async fn current<B: BackingStore>(_storage: &Storage<B>) -> Result<GattProxy, DriverError> {
+    Ok(GattProxy::NotSupported)
+}
+
+/// Apply `message` to the GATT proxy state, returning the state to report,
+/// or `None` if the message is ignored.
+///
+/// A `NotSupported` state is never changed by a set.
+fn apply(gatt_proxy: &mut GattProxy, message: &GattProxyMessage) -> Option<GattProxy> {
+    match message {
+        GattProxyMessage::Get => Some(*gatt_proxy),
+        GattProxyMessage::Set(GattProxy::NotSupported) => {
+            // prohibited value, ignore the message.
+            None
+        }
+        GattProxyMessage::Set(requested) => {
+            if *gatt_proxy != GattProxy::NotSupported {
+                *gatt_proxy = *requested;
+            }
+            Some(*gatt_proxy)
+        }
+        GattProxyMessage::Status(_) => {
+            // not applicable to server role
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_updates_supported_state() {
+        let mut gatt_proxy = GattProxy::Enabled;
+        assert_eq!(
+            Some(GattProxy::Disabled),
+            apply(&mut gatt_proxy, &GattProxyMessage::Set(GattProxy::Disabled))
+        );
+        assert_eq!(GattProxy::Disabled, gatt_proxy);
+        assert_eq!(
+            Some(GattProxy::Disabled),
+            apply(&mut gatt_proxy, &GattProxyMessage::Get)
+        );
+    }
+
+    #[test]
+    fn set_does_not_change_unsupported_state() {
+        let mut gatt_proxy = GattProxy::NotSupported;
+        assert_eq!(
+            Some(GattProxy::NotSupported),
+            apply(&mut gatt_proxy, &GattProxyMessage::Set(GattProxy::Enabled))
+        );
+        assert_eq!(GattProxy::NotSupported, gatt_proxy);
+    }
+
+    #[test]
+    fn prohibited_and_status_messages_are_ignored() {
+        let mut gatt_proxy = GattProxy::Enabled;
+        assert_eq!(
+            None,
+            apply(
+                &mut gatt_proxy,
+                &GattProxyMessage::Set(GattProxy::NotSupported)
+            )
+        );
+        assert_eq!(
+            None,
+            apply(
+                &mut gatt_proxy,
+                &GattProxyMessage::Status(GattProxy::Disabled)
+            )
+        );
+        assert_eq!(GattProxy::Enabled, gatt_proxy);
+    }
+
+    #[cfg(not(feature = "proxy"))]
+    #[test]
+    fn reports_not_supported_without_proxy_feature() {
+        let mut gatt_proxy = GattProxy::default();
+        assert_eq!(
+            Some(GattProxy::NotSupported),
+            apply(&mut gatt_proxy, &GattProxyMessage::Set(GattProxy::Enabled))
+        );
+    }
+}
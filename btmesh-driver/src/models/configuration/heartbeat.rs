@@ -0,0 +1,160 @@
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_common::address::Address;
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::heartbeat::{
+    HeartbeatPublicationMessage, HeartbeatPublicationStatusMessage, HeartbeatSubscription,
+    HeartbeatSubscriptionMessage, HeartbeatSubscriptionStatusMessage,
+};
+use btmesh_models::foundation::configuration::ConfigurationServer;
+use btmesh_models::Status;
+
+/// Fewest hops a subscription reports before having received any heartbeat.
+const INITIAL_MIN_HOPS: u8 = 0x7F;
+
+pub async fn dispatch_publication<
+    C: BluetoothMeshModelContext<ConfigurationServer>,
+    B: BackingStore,
+>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: HeartbeatPublicationMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        HeartbeatPublicationMessage::Get => {
+            let publication = if let Configuration::Provisioned(config) = storage.get().await? {
+                *config.foundation().configuration().heartbeat_publication()
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(
+                HeartbeatPublicationMessage::Status(HeartbeatPublicationStatusMessage {
+                    status: Status::Success,
+                    publication,
+                })
+                .into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        HeartbeatPublicationMessage::Set(publication) => {
+            // prohibited values were already rejected when parsing.
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| {
+                    if !config.secrets.has_network_key(publication.net_key_index) {
+                        status = Status::InvalidNetKeyIndex;
+                        return Err(());
+                    }
+
+                    *config
+                        .foundation_mut()
+                        .configuration_mut()
+                        .heartbeat_publication_mut() = publication;
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                HeartbeatPublicationMessage::Status(HeartbeatPublicationStatusMessage {
+                    status,
+                    publication,
+                })
+                .into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        HeartbeatPublicationMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
+
+pub async fn dispatch_subscription<
+    C: BluetoothMeshModelContext<ConfigurationServer>,
+    B: BackingStore,
+>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: HeartbeatSubscriptionMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        HeartbeatSubscriptionMessage::Get => {
+            let subscription = if let Configuration::Provisioned(config) = storage.get().await? {
+                *config.foundation().configuration().heartbeat_subscription()
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(
+                HeartbeatSubscriptionMessage::Status(HeartbeatSubscriptionStatusMessage {
+                    status: Status::Success,
+                    subscription,
+                })
+                .into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        HeartbeatSubscriptionMessage::Set(set) => {
+            let mut subscription = None;
+
+            storage
+                .modify(|config| {
+                    let primary_address = config.device_info().local_element_address(0);
+                    if let Address::Unicast(destination) = set.destination {
+                        if Some(destination) != primary_address {
+                            // only heartbeats to the primary element may be subscribed to.
+                            return Err(());
+                        }
+                    }
+
+                    let current = config
+                        .foundation_mut()
+                        .configuration_mut()
+                        .heartbeat_subscription_mut();
+
+                    if set.source == Address::Unassigned || set.destination == Address::Unassigned {
+                        *current = Default::default();
+                    } else if set.period_log == 0 {
+                        // stop the subscription, retaining its statistics.
+                        current.period_log = 0;
+                    } else {
+                        *current = HeartbeatSubscription {
+                            source: set.source,
+                            destination: set.destination,
+                            period_log: set.period_log,
+                            count_log: 0,
+                            min_hops: INITIAL_MIN_HOPS,
+                            max_hops: 0,
+                        };
+                    }
+
+                    subscription.replace(*current);
+                    Ok(())
+                })
+                .await?;
+
+            if let Some(subscription) = subscription {
+                ctx.send(
+                    HeartbeatSubscriptionMessage::Status(HeartbeatSubscriptionStatusMessage {
+                        status: Status::Success,
+                        subscription,
+                    })
+                    .into(),
+                    meta.reply(),
+                )
+                .await?;
+            }
+        }
+        HeartbeatSubscriptionMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::key_refresh_phase::{
+    KeyRefreshPhase, KeyRefreshPhaseMessage, KeyRefreshPhaseStatusMessage,
+};
+use btmesh_models::foundation::configuration::ConfigurationServer;
+use btmesh_models::Status;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: KeyRefreshPhaseMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        KeyRefreshPhaseMessage::Get(net_key_index) => {
+            let phase = if let Configuration::Provisioned(config) = storage.get().await? {
+                config.secrets.key_refresh_phase(net_key_index)
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            let status = KeyRefreshPhaseStatusMessage {
+                status: if phase.is_some() {
+                    Status::Success
+                } else {
+                    Status::InvalidNetKeyIndex
+                },
+                net_key_index,
+                phase: phase.unwrap_or(KeyRefreshPhase::Normal),
+            };
+
+            ctx.send(KeyRefreshPhaseMessage::Status(status).into(), meta.reply())
+                .await?;
+        }
+        KeyRefreshPhaseMessage::Set(set) => {
+            let mut status = Some(Status::Success);
+            let mut phase = KeyRefreshPhase::Normal;
+
+            storage
+                .modify(|config| {
+                    if !config.secrets.has_network_key(set.net_key_index) {
+                        status.replace(Status::InvalidNetKeyIndex);
+                        return Err(());
+                    }
+
+                    match config
+                        .secrets
+                        .key_refresh_transition(set.net_key_index, set.transition)
+                    {
+                        Ok(transitioned) => {
+                            phase = transitioned;
+                            Ok(())
+                        }
+                        Err(_) => {
+                            // prohibited transitions are ignored.
+                            status.take();
+                            Err(())
+                        }
+                    }
+                })
+                .await?;
+
+            if let Some(status) = status {
+                ctx.send(
+                    KeyRefreshPhaseMessage::Status(KeyRefreshPhaseStatusMessage {
+                        status,
+                        net_key_index: set.net_key_index,
+                        phase,
+                    })
+                    .into(),
+                    meta.reply(),
+                )
+                .await?;
+            }
+        }
+        KeyRefreshPhaseMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
@@ -1,11 +1,56 @@
 #![allow(clippy::single_match)]
+use crate::storage::provisioned::ProvisionedConfiguration;
 use crate::{BackingStore, Storage};
+use btmesh_common::address::UnicastAddress;
+use btmesh_common::{Composition, ModelIdentifier};
 use btmesh_device::{BluetoothMeshModel, BluetoothMeshModelContext};
 use btmesh_models::foundation::configuration::{ConfigurationMessage, ConfigurationServer};
+use btmesh_models::Status;
 use core::future::Future;
 
+pub mod app_key;
 pub mod beacon;
 pub mod composition_data;
+pub mod default_ttl;
+pub mod gatt_proxy;
+pub mod heartbeat;
+pub mod key_refresh_phase;
+pub mod model_app;
+pub mod model_publication;
+pub mod model_subscription;
+pub mod net_key;
+pub mod network_transmit;
+pub mod node_reset;
+#[cfg(feature = "relay")]
+pub mod relay;
+
+/// Locate the index of the element at `element_address` hosting the model,
+/// or the status explaining why it could not be located.
+fn element_index_of(
+    config: &ProvisionedConfiguration,
+    composition: &Composition,
+    element_address: UnicastAddress,
+    model_identifier: ModelIdentifier,
+) -> Result<u8, Status> {
+    let element_index = config
+        .device_info()
+        .local_element_index(element_address.into())
+        .ok_or(Status::InvalidAddress)?;
+
+    let element = composition
+        .elements_iter()
+        .nth(element_index as usize)
+        .ok_or(Status::InvalidAddress)?;
+
+    if element
+        .models_iter()
+        .any(|model| *model == model_identifier)
+    {
+        Ok(element_index)
+    } else {
+        Err(Status::InvalidModel)
+    }
+}
 
 pub struct Configuration<'s, B: BackingStore + 's> {
     storage: &'s Storage<B>,
@@ -35,17 +80,87 @@ impl<'s, B: BackingStore + 's> BluetoothMeshModel<ConfigurationServer> for Confi
                             .await
                             .map_err(|_| ())?;
                     }
-                    ConfigurationMessage::DefaultTTL(_default_ttl) => {}
+                    ConfigurationMessage::DefaultTTL(default_ttl) => {
+                        default_ttl::dispatch(&ctx, self.storage, default_ttl, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
                     ConfigurationMessage::CompositionData(composition_data) => {
                         composition_data::dispatch(&ctx, self.storage, composition_data, meta)
                             .await
                             .map_err(|_| ())?;
                     }
-                    ConfigurationMessage::AppKey(_app_key) => {}
-                    ConfigurationMessage::ModelApp(_model_app) => {}
-                    ConfigurationMessage::ModelPublication(_model_publication) => {}
-                    ConfigurationMessage::ModelSubscription(_model_subscription) => {}
-                    ConfigurationMessage::NodeReset(_node_reset) => {}
+                    ConfigurationMessage::AppKey(app_key) => {
+                        app_key::dispatch(&ctx, self.storage, app_key, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::GattProxy(gatt_proxy) => {
+                        gatt_proxy::dispatch(&ctx, self.storage, gatt_proxy, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::HeartbeatPublication(heartbeat_publication) => {
+                        heartbeat::dispatch_publication(
+                            &ctx,
+                            self.storage,
+                            heartbeat_publication,
+                            meta,
+                        )
+                        .await
+                        .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::HeartbeatSubscription(heartbeat_subscription) => {
+                        heartbeat::dispatch_subscription(
+                            &ctx,
+                            self.storage,
+                            heartbeat_subscription,
+                            meta,
+                        )
+                        .await
+                        .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::KeyRefreshPhase(key_refresh_phase) => {
+                        key_refresh_phase::dispatch(&ctx, self.storage, key_refresh_phase, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::ModelApp(model_app) => {
+                        model_app::dispatch(&ctx, self.storage, model_app, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::ModelPublication(model_publication) => {
+                        model_publication::dispatch(&ctx, self.storage, model_publication, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::ModelSubscription(model_subscription) => {
+                        model_subscription::dispatch(&ctx, self.storage, model_subscription, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::NetKey(net_key) => {
+                        net_key::dispatch(&ctx, self.storage, net_key, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::NetworkTransmit(network_transmit) => {
+                        network_transmit::dispatch(&ctx, self.storage, network_transmit, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    #[cfg(feature = "relay")]
+                    ConfigurationMessage::Relay(relay) => {
+                        relay::dispatch(&ctx, self.storage, relay, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
+                    ConfigurationMessage::NodeReset(node_reset) => {
+                        node_reset::dispatch(&ctx, node_reset, meta)
+                            .await
+                            .map_err(|_| ())?;
+                    }
                 }
             }
         }
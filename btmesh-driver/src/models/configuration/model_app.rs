@@ -0,0 +1,100 @@
+use crate::models::configuration::element_index_of;
+use crate::{BackingStore, DriverError, Storage};
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::model_app::ModelAppMessage;
+use btmesh_models::foundation::configuration::{ConfigurationServer, CONFIGURATION_SERVER};
+use btmesh_models::Status;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: ModelAppMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        ModelAppMessage::Bind(payload) | ModelAppMessage::Unbind(payload) => {
+            let bind = matches!(message, ModelAppMessage::Bind(_));
+            let composition = storage.composition();
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| {
+                    let element_index = match element_index_of(
+                        config,
+                        &composition,
+                        payload.element_address,
+                        payload.model_identifier,
+                    ) {
+                        Ok(element_index) => element_index,
+                        Err(err) => {
+                            status = err;
+                            return Err(());
+                        }
+                    };
+
+                    if config
+                        .secrets
+                        .application_key_handle(payload.app_key_index)
+                        .is_err()
+                    {
+                        status = Status::InvalidAppKeyIndex;
+                        return Err(());
+                    }
+
+                    // the configuration server only ever uses the device key.
+                    if payload.model_identifier == CONFIGURATION_SERVER {
+                        status = Status::CannotBind;
+                        return Err(());
+                    }
+
+                    let configuration = config.foundation_mut().configuration_mut();
+
+                    if bind {
+                        if configuration
+                            .bindings_mut()
+                            .bind(
+                                element_index,
+                                payload.model_identifier,
+                                payload.app_key_index,
+                            )
+                            .is_err()
+                        {
+                            status = Status::InsufficientResources;
+                            return Err(());
+                        }
+                    } else {
+                        configuration.bindings_mut().unbind(
+                            element_index,
+                            payload.model_identifier,
+                            payload.app_key_index,
+                        );
+
+                        // publishing with a key no longer bound is disabled.
+                        if let Some(publication) = configuration
+                            .publications()
+                            .get(element_index, payload.model_identifier)
+                        {
+                            if publication.app_key_index() == payload.app_key_index {
+                                configuration
+                                    .publications_mut()
+                                    .remove(element_index, payload.model_identifier);
+                            }
+                        }
+                    }
+
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                ModelAppMessage::Status(payload.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        ModelAppMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use crate::models::configuration::element_index_of;
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::model_publication::{
+    ModelPublicationMessage, ModelPublicationStatusMessage, PublishAddress,
+};
+use btmesh_models::foundation::configuration::ConfigurationServer;
+use btmesh_models::Status;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: ModelPublicationMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        ModelPublicationMessage::Get(get) => {
+            let composition = storage.composition();
+            let status = if let Configuration::Provisioned(config) = storage.get().await? {
+                match element_index_of(
+                    &config,
+                    &composition,
+                    get.element_address,
+                    get.model_identifier,
+                ) {
+                    Ok(element_index) => {
+                        if let Some(publication) = config
+                            .foundation()
+                            .configuration()
+                            .publications()
+                            .get(element_index, get.model_identifier)
+                        {
+                            ModelPublicationStatusMessage {
+                                status: Status::Success,
+                                element_address: get.element_address,
+                                publish_address: publication.publish_address(),
+                                app_key_index: publication.app_key_index(),
+                                credential_flag: publication.credential_flag(),
+                                publish_ttl: publication.publish_ttl(),
+                                publish_period: publication.publish_period(),
+                                publish_retransmit_count: publication.publish_retransmit_count(),
+                                publish_retransmit_interval_steps: publication
+                                    .publish_retransmit_interval_steps(),
+                                model_identifier: get.model_identifier,
+                            }
+                        } else {
+                            ModelPublicationStatusMessage::unassigned(
+                                Status::Success,
+                                get.element_address,
+                                get.model_identifier,
+                            )
+                        }
+                    }
+                    Err(status) => ModelPublicationStatusMessage::unassigned(
+                        status,
+                        get.element_address,
+                        get.model_identifier,
+                    ),
+                }
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(ModelPublicationMessage::Status(status).into(), meta.reply())
+                .await?;
+        }
+        ModelPublicationMessage::Set(set) | ModelPublicationMessage::VirtualAddressSet(set) => {
+            let composition = storage.composition();
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| {
+                    let element_index = match element_index_of(
+                        config,
+                        &composition,
+                        set.element_address,
+                        set.model_identifier,
+                    ) {
+                        Ok(element_index) => element_index,
+                        Err(err) => {
+                            status = err;
+                            return Err(());
+                        }
+                    };
+
+                    if !matches!(set.publish_address, PublishAddress::Unassigned)
+                        && config
+                            .secrets()
+                            .application_key_handle(set.app_key_index)
+                            .is_err()
+                    {
+                        status = Status::InvalidAppKeyIndex;
+                        return Err(());
+                    }
+
+                    if config
+                        .foundation_mut()
+                        .configuration_mut()
+                        .publications_mut()
+                        .set(element_index, set.model_identifier, (&set).into())
+                        .is_err()
+                    {
+                        status = Status::InsufficientResources;
+                        return Err(());
+                    }
+
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                ModelPublicationMessage::Status(set.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        ModelPublicationMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
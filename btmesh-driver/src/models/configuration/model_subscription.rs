@@ -0,0 +1,196 @@
+use crate::models::configuration::element_index_of;
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_common::address::GroupAddress;
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::model_subscription::{
+    ModelSubscriptionListMessage, ModelSubscriptionMessage, SubscriptionAddress,
+};
+use btmesh_models::foundation::configuration::{ConfigurationServer, CONFIGURATION_SERVER};
+use btmesh_models::Status;
+use heapless::Vec;
+
+enum Operation {
+    Add,
+    Delete,
+    Overwrite,
+}
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: ModelSubscriptionMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    let (operation, payload) = match message {
+        ModelSubscriptionMessage::Add(payload)
+        | ModelSubscriptionMessage::VirtualAddressAdd(payload) => (Operation::Add, payload),
+        ModelSubscriptionMessage::Delete(payload)
+        | ModelSubscriptionMessage::VirtualAddressDelete(payload) => (Operation::Delete, payload),
+        ModelSubscriptionMessage::Overwrite(payload)
+        | ModelSubscriptionMessage::VirtualAddressOverwrite(payload) => {
+            (Operation::Overwrite, payload)
+        }
+        ModelSubscriptionMessage::DeleteAll(delete_all) => {
+            let composition = storage.composition();
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| {
+                    let element_index = match element_index_of(
+                        config,
+                        &composition,
+                        delete_all.element_address,
+                        delete_all.model_identifier,
+                    ) {
+                        Ok(element_index) => element_index,
+                        Err(err) => {
+                            status = err;
+                            return Err(());
+                        }
+                    };
+
+                    if delete_all.model_identifier == CONFIGURATION_SERVER {
+                        status = Status::NotASubscribeModel;
+                        return Err(());
+                    }
+
+                    config
+                        .foundation_mut()
+                        .configuration_mut()
+                        .subscriptions_mut()
+                        .remove_all(element_index, delete_all.model_identifier);
+
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                ModelSubscriptionMessage::Status(delete_all.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+            return Ok(());
+        }
+        ModelSubscriptionMessage::Get(get) => {
+            let composition = storage.composition();
+            let list = if let Configuration::Provisioned(config) = storage.get().await? {
+                let mut list = ModelSubscriptionListMessage {
+                    status: Status::Success,
+                    element_address: get.element_address,
+                    model_identifier: get.model_identifier,
+                    addresses: Vec::new(),
+                };
+                match element_index_of(
+                    &config,
+                    &composition,
+                    get.element_address,
+                    get.model_identifier,
+                ) {
+                    Ok(_) if get.model_identifier == CONFIGURATION_SERVER => {
+                        list.status = Status::NotASubscribeModel;
+                    }
+                    Ok(element_index) => {
+                        for address in config
+                            .foundation()
+                            .configuration()
+                            .subscriptions()
+                            .addresses_iter(element_index, get.model_identifier)
+                        {
+                            list.addresses.push(address).ok();
+                        }
+                    }
+                    Err(status) => {
+                        list.status = status;
+                    }
+                }
+                list
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(ModelSubscriptionMessage::List(list).into(), meta.reply())
+                .await?;
+            return Ok(());
+        }
+        ModelSubscriptionMessage::Status(_) | ModelSubscriptionMessage::List(_) => {
+            // not applicable to server role
+            return Ok(());
+        }
+    };
+
+    let composition = storage.composition();
+    let mut status = Status::Success;
+
+    storage
+        .modify(|config| {
+            let element_index = match element_index_of(
+                config,
+                &composition,
+                payload.element_address,
+                payload.model_identifier,
+            ) {
+                Ok(element_index) => element_index,
+                Err(err) => {
+                    status = err;
+                    return Err(());
+                }
+            };
+
+            if payload.model_identifier == CONFIGURATION_SERVER {
+                status = Status::NotASubscribeModel;
+                return Err(());
+            }
+
+            if matches!(
+                payload.subscription_address,
+                SubscriptionAddress::Unassigned
+                    | SubscriptionAddress::Unicast(_)
+                    | SubscriptionAddress::Group(GroupAddress::AllNodes)
+            ) {
+                status = Status::InvalidAddress;
+                return Err(());
+            }
+
+            let subscriptions = config
+                .foundation_mut()
+                .configuration_mut()
+                .subscriptions_mut();
+
+            let result = match operation {
+                Operation::Add => subscriptions.add(
+                    element_index,
+                    payload.model_identifier,
+                    payload.subscription_address,
+                ),
+                Operation::Delete => {
+                    subscriptions.remove(
+                        element_index,
+                        payload.model_identifier,
+                        payload.subscription_address,
+                    );
+                    Ok(())
+                }
+                Operation::Overwrite => subscriptions.overwrite(
+                    element_index,
+                    payload.model_identifier,
+                    payload.subscription_address,
+                ),
+            };
+
+            if result.is_err() {
+                status = Status::InsufficientResources;
+                return Err(());
+            }
+
+            Ok(())
+        })
+        .await?;
+
+    ctx.send(
+        ModelSubscriptionMessage::Status(payload.create_status_response(status)).into(),
+        meta.reply(),
+    )
+    .await?;
+
+    Ok(())
+}
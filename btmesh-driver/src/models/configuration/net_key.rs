@@ -0,0 +1,167 @@
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_common::crypto::network::NetworkKey;
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::key_refresh_phase::KeyRefreshPhase;
+use btmesh_models::foundation::configuration::net_key::{NetKeyListMessage, NetKeyMessage};
+use btmesh_models::foundation::configuration::ConfigurationServer;
+use btmesh_models::Status;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: NetKeyMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        NetKeyMessage::Add(add) => {
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| {
+                    let network_key = match NetworkKey::new(add.net_key) {
+                        Ok(network_key) => network_key,
+                        Err(_) => {
+                            status = Status::UnspecifiedError;
+                            return Err(());
+                        }
+                    };
+
+                    if let Some((_, entry)) = config.secrets.network_keys().get(add.net_key_index) {
+                        if entry.key != network_key {
+                            status = Status::KeyIndexAlreadyStored;
+                        }
+                        // adding the identical key again is a successful no-op.
+                        return Err(());
+                    }
+
+                    if config
+                        .secrets
+                        .network_keys_mut()
+                        .add(add.net_key_index, network_key)
+                        .is_err()
+                    {
+                        status = Status::InsufficientResources;
+                        return Err(());
+                    }
+
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                NetKeyMessage::Status(add.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        NetKeyMessage::Update(update) => {
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| {
+                    let network_key = match NetworkKey::new(update.net_key) {
+                        Ok(network_key) => network_key,
+                        Err(_) => {
+                            status = Status::UnspecifiedError;
+                            return Err(());
+                        }
+                    };
+
+                    match config.secrets.network_keys().get(update.net_key_index) {
+                        None => {
+                            status = Status::InvalidNetKeyIndex;
+                            return Err(());
+                        }
+                        Some((_, entry)) => match (entry.phase, entry.new_key) {
+                            (KeyRefreshPhase::Normal, _) => {}
+                            (KeyRefreshPhase::First, Some(new_key)) if new_key == network_key => {
+                                // distributing the identical new key again is a successful no-op.
+                                return Err(());
+                            }
+                            (KeyRefreshPhase::First, _) => {
+                                status = Status::KeyIndexAlreadyStored;
+                                return Err(());
+                            }
+                            (KeyRefreshPhase::Second, _) => {
+                                status = Status::CannotUpdate;
+                                return Err(());
+                            }
+                        },
+                    }
+
+                    if config
+                        .secrets
+                        .network_keys_mut()
+                        .update(update.net_key_index, network_key)
+                        .is_err()
+                    {
+                        status = Status::CannotUpdate;
+                        return Err(());
+                    }
+
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                NetKeyMessage::Status(update.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        NetKeyMessage::Delete(delete) => {
+            let mut status = Status::Success;
+
+            storage
+                .modify(|config| {
+                    if !config.secrets.has_network_key(delete.net_key_index) {
+                        // deleting a key which does not exist is a successful no-op.
+                        return Err(());
+                    }
+
+                    // the key securing this very message may not be deleted,
+                    // which also prevents deleting the last remaining key.
+                    if let Ok(entry) = config.secrets.network_key_entry(meta.network_key_handle()) {
+                        if entry.net_key_index == delete.net_key_index {
+                            status = Status::CannotRemove;
+                            return Err(());
+                        }
+                    }
+
+                    let app_key_indexes = config.secrets.remove_network_key(delete.net_key_index);
+                    let configuration = config.foundation_mut().configuration_mut();
+                    for app_key_index in app_key_indexes {
+                        configuration.bindings_mut().remove_app_key(app_key_index);
+                        configuration
+                            .publications_mut()
+                            .remove_app_key(app_key_index);
+                    }
+
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                NetKeyMessage::Status(delete.create_status_response(status)).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        NetKeyMessage::Get => {
+            let list = if let Configuration::Provisioned(config) = storage.get().await? {
+                NetKeyListMessage {
+                    net_key_indexes: config.secrets.network_keys().indexes_iter().collect(),
+                }
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(NetKeyMessage::List(list).into(), meta.reply())
+                .await?;
+        }
+        NetKeyMessage::List(_) | NetKeyMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::network_transmit::NetworkTransmitMessage;
+use btmesh_models::foundation::configuration::ConfigurationServer;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: NetworkTransmitMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        NetworkTransmitMessage::Get => {
+            let network_transmit = if let Configuration::Provisioned(config) = storage.get().await?
+            {
+                *config.foundation().configuration().network_transmit()
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(
+                NetworkTransmitMessage::Status(network_transmit).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        NetworkTransmitMessage::Set(network_transmit) => {
+            storage
+                .modify(|config| {
+                    *config
+                        .foundation_mut()
+                        .configuration_mut()
+                        .network_transmit_mut() = network_transmit;
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(
+                NetworkTransmitMessage::Status(network_transmit).into(),
+                meta.reply(),
+            )
+            .await?;
+        }
+        NetworkTransmitMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::{DriverError, NODE_RESET};
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::node_reset::NodeResetMessage;
+use btmesh_models::foundation::configuration::ConfigurationServer;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>>(
+    ctx: &C,
+    message: NodeResetMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        NodeResetMessage::Reset => {
+            ctx.send(NodeResetMessage::Status.into(), meta.reply())
+                .await?;
+            // the driver transmits the status before performing the reset.
+            NODE_RESET.signal(());
+        }
+        NodeResetMessage::Status => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::device::ModelContext;
+    use crate::models::configuration::node_reset::dispatch;
+    use crate::NODE_RESET;
+    use btmesh_common::address::{Address, UnicastAddress};
+    use btmesh_common::crypto::network::Nid;
+    use btmesh_common::{IvIndex, Ttl};
+    use btmesh_device::{
+        Completion, InboundChannelImpl, InboundMetadata, KeyHandle, NetworkKeyHandle,
+        OutboundChannelImpl, OutboundTarget,
+    };
+    use btmesh_models::foundation::configuration::node_reset::{
+        NodeResetMessage, CONFIG_NODE_RESET_STATUS,
+    };
+    use btmesh_models::foundation::configuration::CONFIGURATION_SERVER;
+    use futures::executor::block_on;
+
+    #[test]
+    fn status_is_queued_before_reset() {
+        static INBOUND: InboundChannelImpl = InboundChannelImpl::new();
+        static OUTBOUND: OutboundChannelImpl = OutboundChannelImpl::new();
+        static COMPLETION: Completion = Completion::new();
+        let ctx = ModelContext::new(
+            0,
+            CONFIGURATION_SERVER,
+            INBOUND.receiver(),
+            OUTBOUND.sender(),
+            &COMPLETION,
+        );
+
+        let meta = InboundMetadata::new(
+            UnicastAddress::new(0x0001).unwrap(),
+            Address::Unicast(UnicastAddress::new(0x00A1).unwrap()),
+            Ttl::new(5),
+            NetworkKeyHandle(0, Nid::new(0x42)),
+            IvIndex::new(0),
+            KeyHandle::Device,
+            None,
+        );
+
+        block_on(dispatch(&ctx, NodeResetMessage::Reset, meta)).unwrap();
+
+        assert!(NODE_RESET.signaled());
+        match OUTBOUND.try_recv() {
+            Ok((_, opcode, _, OutboundTarget::Send(_, _))) => {
+                assert_eq!(CONFIG_NODE_RESET_STATUS, opcode)
+            }
+            _ => panic!("expected the status to be queued"),
+        }
+    }
+}
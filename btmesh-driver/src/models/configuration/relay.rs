@@ -0,0 +1,44 @@
+use crate::{BackingStore, Configuration, DriverError, Storage};
+use btmesh_device::{BluetoothMeshModelContext, InboundMetadata};
+use btmesh_models::foundation::configuration::relay::{Relay, RelayMessage};
+use btmesh_models::foundation::configuration::ConfigurationServer;
+
+pub async fn dispatch<C: BluetoothMeshModelContext<ConfigurationServer>, B: BackingStore>(
+    ctx: &C,
+    storage: &Storage<B>,
+    message: RelayMessage,
+    meta: InboundMetadata,
+) -> Result<(), DriverError> {
+    match message {
+        RelayMessage::Get => {
+            let relay = if let Configuration::Provisioned(config) = storage.get().await? {
+                *config.foundation().configuration().relay()
+            } else {
+                return Err(DriverError::InvalidState);
+            };
+
+            ctx.send(RelayMessage::Status(relay).into(), meta.reply())
+                .await?;
+        }
+        RelayMessage::Set(relay) => {
+            if relay.relay == Relay::NotSupported {
+                // prohibited value, ignore the message.
+                return Ok(());
+            }
+
+            storage
+                .modify(|config| {
+                    *config.foundation_mut().configuration_mut().relay_mut() = relay;
+                    Ok(())
+                })
+                .await?;
+
+            ctx.send(RelayMessage::Status(relay).into(), meta.reply())
+                .await?;
+        }
+        RelayMessage::Status(_) => {
+            // not applicable to server role
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,359 @@
+use crate::storage::provisioned::foundation::configuration::publications::Publications;
+use btmesh_common::opcode::Opcode;
+use btmesh_common::ModelIdentifier;
+use btmesh_device::OutboundMetadata;
+use embassy_executor::time::{Duration, Instant};
+use heapless::Vec;
+
+struct PeriodicPublication {
+    element_index: u8,
+    model_identifier: ModelIdentifier,
+    period: Duration,
+    next: Instant,
+}
+
+/// A published message awaiting further retransmissions.
+pub struct Retransmission {
+    pub element_index: usize,
+    model_identifier: ModelIdentifier,
+    pub opcode: Opcode,
+    pub parameters: Vec<u8, 379>,
+    pub meta: OutboundMetadata,
+    remaining: u8,
+    interval: Duration,
+    next: Instant,
+}
+
+/// Work which has come due at a given instant.
+#[derive(Default)]
+pub struct DuePublications<const N: usize> {
+    pub models: Vec<(usize, ModelIdentifier), N>,
+    pub retransmissions: Vec<Retransmission, N>,
+}
+
+/// Tracks periodic publication deadlines of models, along with
+/// pending retransmissions of published messages.
+#[derive(Default)]
+pub struct Publisher<const N: usize = 8> {
+    periodic: Vec<PeriodicPublication, N>,
+    retransmissions: Vec<Retransmission, N>,
+}
+
+impl<const N: usize> Publisher<N> {
+    /// Synchronize periodic publication with the configured state.
+    ///
+    /// Models which keep the same period keep their current deadline, while
+    /// pending retransmissions of models no longer publishing are cancelled.
+    pub fn reconfigure(&mut self, publications: &Publications, now: Instant) {
+        let mut periodic = Vec::new();
+
+        for (element_index, model_identifier, publication) in publications.iter() {
+            if let Some(period) = publication.period() {
+                let next = self
+                    .periodic
+                    .iter()
+                    .find(|e| {
+                        e.element_index == *element_index
+                            && e.model_identifier == *model_identifier
+                            && e.period == period
+                    })
+                    .map(|e| e.next)
+                    .unwrap_or(now + period);
+
+                if periodic
+                    .push(PeriodicPublication {
+                        element_index: *element_index,
+                        model_identifier: *model_identifier,
+                        period,
+                        next,
+                    })
+                    .is_err()
+                {
+                    warn!(
+                        "unable to schedule periodic publication for model {}",
+                        model_identifier
+                    );
+                }
+            }
+        }
+
+        self.periodic = periodic;
+
+        let mut index = 0;
+        while index < self.retransmissions.len() {
+            let retransmission = &self.retransmissions[index];
+            if publications
+                .get(
+                    retransmission.element_index as u8,
+                    retransmission.model_identifier,
+                )
+                .is_none()
+            {
+                self.retransmissions.swap_remove(index);
+                continue;
+            }
+            index += 1;
+        }
+    }
+
+    /// Schedule retransmissions of a just-published message.
+    #[allow(clippy::too_many_arguments)]
+    pub fn schedule_retransmission(
+        &mut self,
+        element_index: usize,
+        model_identifier: ModelIdentifier,
+        opcode: Opcode,
+        parameters: Vec<u8, 379>,
+        meta: OutboundMetadata,
+        count: u8,
+        interval: Duration,
+        now: Instant,
+    ) {
+        if count == 0 {
+            return;
+        }
+
+        if self
+            .retransmissions
+            .push(Retransmission {
+                element_index,
+                model_identifier,
+                opcode,
+                parameters,
+                meta,
+                remaining: count,
+                interval,
+                next: now + interval,
+            })
+            .is_err()
+        {
+            warn!("unable to schedule publication retransmission");
+        }
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.periodic
+            .iter()
+            .map(|e| e.next)
+            .chain(self.retransmissions.iter().map(|e| e.next))
+            .min()
+    }
+
+    /// Collect the models due to publish, and the retransmissions due to be sent,
+    /// advancing their deadlines.
+    pub fn process(&mut self, now: Instant) -> DuePublications<N> {
+        let mut due = DuePublications::default();
+
+        for periodic in self.periodic.iter_mut().filter(|e| e.next <= now) {
+            if due
+                .models
+                .push((periodic.element_index as usize, periodic.model_identifier))
+                .is_err()
+            {
+                warn!(
+                    "unable to publish periodically for model {}",
+                    periodic.model_identifier
+                );
+            }
+            periodic.next = now + periodic.period;
+        }
+
+        let mut index = 0;
+        while index < self.retransmissions.len() {
+            let retransmission = &mut self.retransmissions[index];
+            if retransmission.next <= now {
+                retransmission.remaining -= 1;
+                retransmission.next = now + retransmission.interval;
+                let due_retransmission = Retransmission {
+                    element_index: retransmission.element_index,
+                    model_identifier: retransmission.model_identifier,
+                    opcode: retransmission.opcode,
+                    parameters: retransmission.parameters.clone(),
+                    meta: retransmission.meta,
+                    remaining: 0,
+                    interval: retransmission.interval,
+                    next: now,
+                };
+                if due.retransmissions.push(due_retransmission).is_err() {
+                    warn!("unable to retransmit publication");
+                }
+                if retransmission.remaining == 0 {
+                    self.retransmissions.swap_remove(index);
+                    continue;
+                }
+            }
+            index += 1;
+        }
+
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::publisher::Publisher;
+    use crate::storage::provisioned::foundation::configuration::publications::{
+        Publication, Publications,
+    };
+    use btmesh_common::address::UnicastAddress;
+    use btmesh_common::crypto::network::Nid;
+    use btmesh_common::opcode::Opcode;
+    use btmesh_common::{IvIndex, ModelIdentifier};
+    use btmesh_device::{KeyHandle, NetworkKeyHandle, OutboundMetadata};
+    use btmesh_models::foundation::configuration::model_publication::{
+        ModelPublicationSetMessage, PublishAddress,
+    };
+    use btmesh_models::foundation::configuration::AppKeyIndex;
+    use embassy_executor::time::{Duration, Instant};
+    use heapless::Vec;
+
+    const MODEL: ModelIdentifier = ModelIdentifier::SIG(0x1000);
+
+    fn publication(
+        publish_period: u8,
+        publish_retransmit_count: u8,
+        publish_retransmit_interval_steps: u8,
+    ) -> Publication {
+        (&ModelPublicationSetMessage {
+            element_address: UnicastAddress::new(0x0001).unwrap(),
+            publish_address: PublishAddress::Unicast(UnicastAddress::new(0x0002).unwrap()),
+            app_key_index: AppKeyIndex::new(0),
+            credential_flag: false,
+            publish_ttl: None,
+            publish_period,
+            publish_retransmit_count,
+            publish_retransmit_interval_steps,
+            model_identifier: MODEL,
+        })
+            .into()
+    }
+
+    fn publications(publish_period: u8) -> Publications {
+        let mut publications = Publications::default();
+        publications
+            .set(0, MODEL, publication(publish_period, 0, 0))
+            .unwrap();
+        publications
+    }
+
+    fn meta() -> OutboundMetadata {
+        OutboundMetadata::new(
+            UnicastAddress::new(0x0002).unwrap().into(),
+            NetworkKeyHandle(0, Nid::new(0)),
+            IvIndex::new(0),
+            KeyHandle::Device,
+            None,
+            None,
+        )
+    }
+
+    fn at(millis: u64) -> Instant {
+        Instant::from_millis(millis)
+    }
+
+    #[test]
+    fn period_steps_and_resolution() {
+        assert_eq!(None, publication(0b00_000000, 0, 0).period());
+        assert_eq!(
+            Some(Duration::from_millis(500)),
+            publication(0b00_000101, 0, 0).period()
+        );
+        assert_eq!(
+            Some(Duration::from_secs(5)),
+            publication(0b01_000101, 0, 0).period()
+        );
+        assert_eq!(
+            Some(Duration::from_secs(50)),
+            publication(0b10_000101, 0, 0).period()
+        );
+        assert_eq!(
+            Some(Duration::from_secs(3000)),
+            publication(0b11_000101, 0, 0).period()
+        );
+        assert_eq!(
+            Some(Duration::from_millis(6300)),
+            publication(0b00_111111, 0, 0).period()
+        );
+    }
+
+    #[test]
+    fn periodic_publication() {
+        let mut publisher = Publisher::<8>::default();
+        publisher.reconfigure(&publications(0b01_000010), at(0));
+        assert_eq!(Some(at(2_000)), publisher.next_deadline());
+
+        assert!(publisher.process(at(1_999)).models.is_empty());
+        assert_eq!(&[(0, MODEL)], &*publisher.process(at(2_000)).models);
+        assert_eq!(Some(at(4_000)), publisher.next_deadline());
+    }
+
+    #[test]
+    fn retransmission_count_and_interval() {
+        // 2 retransmissions, 4 steps of 50 milliseconds apart.
+        let retransmitting = publication(0, 2, 3);
+        assert_eq!(
+            Duration::from_millis(200),
+            retransmitting.retransmit_interval()
+        );
+
+        let mut publisher = Publisher::<8>::default();
+        publisher.schedule_retransmission(
+            0,
+            MODEL,
+            Opcode::OneOctet(0x42),
+            Vec::new(),
+            meta(),
+            retransmitting.publish_retransmit_count(),
+            retransmitting.retransmit_interval(),
+            at(0),
+        );
+
+        assert_eq!(Some(at(200)), publisher.next_deadline());
+        assert!(publisher.process(at(199)).retransmissions.is_empty());
+        assert_eq!(1, publisher.process(at(200)).retransmissions.len());
+        assert_eq!(Some(at(400)), publisher.next_deadline());
+        assert_eq!(1, publisher.process(at(400)).retransmissions.len());
+        assert_eq!(None, publisher.next_deadline());
+        assert!(publisher.process(at(600)).retransmissions.is_empty());
+    }
+
+    #[test]
+    fn reconfigure_cancels_stale_entries() {
+        let mut publisher = Publisher::<8>::default();
+        publisher.reconfigure(&publications(0b01_000010), at(0));
+        publisher.schedule_retransmission(
+            0,
+            MODEL,
+            Opcode::OneOctet(0x42),
+            Vec::new(),
+            meta(),
+            2,
+            Duration::from_millis(50),
+            at(0),
+        );
+
+        // an unchanged period keeps its deadline.
+        publisher.reconfigure(&publications(0b01_000010), at(1_000));
+        assert_eq!(Some(at(50)), publisher.next_deadline());
+        assert_eq!(1, publisher.process(at(50)).retransmissions.len());
+
+        // a changed period starts over.
+        publisher.reconfigure(&publications(0b01_000011), at(1_000));
+        assert!(publisher.process(at(2_000)).models.is_empty());
+        assert_eq!(1, publisher.process(at(4_000)).models.len());
+
+        // removing the publication cancels periodic publication and retransmissions.
+        publisher.schedule_retransmission(
+            0,
+            MODEL,
+            Opcode::OneOctet(0x42),
+            Vec::new(),
+            meta(),
+            2,
+            Duration::from_millis(50),
+            at(4_000),
+        );
+        publisher.reconfigure(&Publications::default(), at(4_000));
+        assert_eq!(None, publisher.next_deadline());
+    }
+}
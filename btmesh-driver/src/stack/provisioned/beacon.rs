@@ -0,0 +1,109 @@
+use crate::stack::provisioned::sequence::Sequence;
+use crate::stack::provisioned::{DriverError, ProvisionedStack};
+use btmesh_common::{KeyRefreshFlag, NetworkId};
+use btmesh_device::NetworkKeyHandle;
+use btmesh_models::foundation::configuration::key_refresh_phase::{
+    KeyRefreshPhase, KeyRefreshTransition,
+};
+use btmesh_models::foundation::configuration::NetKeyIndex;
+use btmesh_pdu::provisioned::beacon::SecureNetworkBeacon;
+use heapless::Vec;
+
+impl ProvisionedStack {
+    /// Secure network beacons for every subnet, if beaconing is enabled
+    /// and the next ones are due to be broadcast.
+    pub fn secure_network_beacons(&self) -> Result<Vec<SecureNetworkBeacon, 4>, DriverError> {
+        let mut beacons = Vec::new();
+        if !self.secure_beacon_enabled || !self.secure_beacon.elapsed() {
+            return Ok(beacons);
+        }
+
+        let iv_index_state = self.network_state.iv_index();
+        for (_, entry) in self.secrets.network_keys().iter() {
+            let network_key = entry.transmit_key();
+            beacons
+                .push(SecureNetworkBeacon::new(
+                    KeyRefreshFlag(matches!(entry.phase, KeyRefreshPhase::Second)),
+                    iv_index_state.iv_update_flag(),
+                    network_key.network_id(),
+                    iv_index_state.iv_index(),
+                    &network_key.beacon_key()?,
+                )?)
+                .map_err(|_| DriverError::InsufficientSpace)?;
+        }
+
+        Ok(beacons)
+    }
+
+    /// Network ID to advertise to prospective proxy clients, taking turns
+    /// between the subnets the node is a member of.
+    pub fn advertised_network_id(&self) -> Option<NetworkId> {
+        let network_keys = self.secrets.network_keys();
+        let subnets = network_keys.iter().count();
+        if subnets == 0 {
+            return None;
+        }
+
+        let subnet = self.advertised_subnet.get() % subnets;
+        self.advertised_subnet.set(subnet + 1);
+
+        network_keys
+            .iter()
+            .nth(subnet)
+            .map(|(_, entry)| entry.transmit_key().network_id())
+    }
+
+    /// Authenticate a secure network beacon against the subnet it identifies,
+    /// resolving the handle of that subnet's network key.
+    ///
+    /// Beacons of unknown subnets, or failing authentication, resolve to `None`.
+    pub fn authenticate_secure_network_beacon(
+        &self,
+        beacon: &SecureNetworkBeacon,
+    ) -> Result<Option<NetworkKeyHandle>, DriverError> {
+        if let Some(network_key_handle) =
+            self.secrets.network_key_by_network_id(beacon.network_id())
+        {
+            let network_key = self.secrets.network_key(network_key_handle)?;
+            if beacon.authenticate(&network_key.beacon_key()?)? {
+                return Ok(Some(network_key_handle));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Process an inbound secure network beacon, following the IV index it conveys.
+    ///
+    /// A beacon secured with the new key of a subnet undergoing the key refresh
+    /// procedure may move the procedure along. Since keys are owned by the
+    /// configuration, the resulting transition is returned to be applied there.
+    pub fn process_inbound_secure_network_beacon(
+        &mut self,
+        sequence: &Sequence,
+        beacon: &SecureNetworkBeacon,
+    ) -> Result<Option<(NetKeyIndex, KeyRefreshTransition)>, DriverError> {
+        let network_key_handle =
+            if let Some(network_key_handle) = self.authenticate_secure_network_beacon(beacon)? {
+                network_key_handle
+            } else {
+                debug!("ignoring unauthenticated secure network beacon");
+                return Ok(None);
+            };
+
+        debug!("inbound secure network beacon: {}", beacon);
+        self.process_beacon_iv_index(sequence, beacon.iv_index(), beacon.iv_update_flag());
+
+        let entry = self.secrets.network_key_entry(network_key_handle)?;
+        if !entry.is_new_key(&self.secrets.network_key(network_key_handle)?) {
+            return Ok(None);
+        }
+
+        let transition = match (entry.phase, beacon.key_refresh_flag()) {
+            (KeyRefreshPhase::First, KeyRefreshFlag(true)) => KeyRefreshTransition::UseNewKeys,
+            (_, KeyRefreshFlag(false)) => KeyRefreshTransition::RevokeOldKeys,
+            _ => return Ok(None),
+        };
+
+        Ok(Some((entry.net_key_index, transition)))
+    }
+}
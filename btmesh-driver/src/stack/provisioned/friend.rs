@@ -0,0 +1,1137 @@
+use crate::stack::provisioned::network::Credentials;
+use crate::stack::provisioned::sequence::Sequence;
+use crate::stack::provisioned::system::UpperMetadata;
+use crate::stack::provisioned::{DriverError, IvIndexState, ProvisionedStack};
+use btmesh_common::address::{Address, GroupAddress, UnicastAddress};
+use btmesh_common::crypto::network::NetworkKey;
+use btmesh_common::{IvIndex, IvUpdateFlag, KeyRefreshFlag, Ttl};
+use btmesh_device::NetworkKeyHandle;
+use btmesh_models::foundation::configuration::key_refresh_phase::KeyRefreshPhase;
+use btmesh_models::foundation::configuration::NetKeyIndex;
+use btmesh_pdu::provisioned::control::ControlMessage;
+use btmesh_pdu::provisioned::friend::{
+    FriendClear, FriendMessage, FriendOffer, FriendRequest, FriendSubscriptionList, FriendUpdate,
+};
+use btmesh_pdu::provisioned::lower::BlockAck;
+use btmesh_pdu::provisioned::network::{CleartextNetworkPDU, NetworkPDU};
+use core::cmp::{max, min};
+use embassy_executor::time::{Duration, Instant};
+use heapless::{Deque, Vec};
+
+/// Messages stored for each low power node.
+const FRIEND_QUEUE_SIZE: usize = 16;
+
+/// Group and virtual addresses each low power node may subscribe to.
+const SUBSCRIPTION_LIST_SIZE: usize = 8;
+
+/// Time a low power node listens for the response to a friend poll, in milliseconds.
+const RECEIVE_WINDOW: u8 = 255;
+
+/// Shortest delay before answering a friend request, giving the
+/// low power node time to start listening.
+const MIN_OFFER_DELAY: Duration = Duration::from_millis(100);
+
+/// Time a low power node has to accept an offer with its first friend poll.
+const OFFER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Initial interval between friend clear messages to the previous friend,
+/// doubled after each one until confirmed.
+const CLEAR_REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest difference between the counter of a friend clear message and
+/// the counter of the friend request it terminates the friendship of.
+const MAX_LPN_COUNTER_DELTA: u16 = 255;
+
+/// Weight of a factor of the friend request criteria, in tenths.
+fn factor_tenths(factor: u8) -> u64 {
+    10 + 5 * factor as u64
+}
+
+/// Message due to a low power node, or to its previous friend.
+pub enum FriendTransmission {
+    /// A friend message to send to the address given.
+    Message(Credentials, UnicastAddress, FriendMessage),
+    /// A friend update to send to the low power node, indicating whether more messages are stored.
+    Update(Credentials, UnicastAddress, bool),
+    /// A message stored for the low power node, ready to be encrypted.
+    Stored(Credentials, CleartextNetworkPDU<ProvisionedStack>),
+}
+
+/// Security parameters conveyed to a low power node by friend updates.
+#[derive(Copy, Clone, PartialEq)]
+struct Security {
+    key_refresh_flag: KeyRefreshFlag,
+    iv_index: IvIndex,
+    iv_update_flag: IvUpdateFlag,
+}
+
+/// Response last sent to a friend poll, until the next poll acknowledges it.
+enum Pending {
+    Stored(CleartextNetworkPDU<ProvisionedStack>),
+    Update,
+}
+
+/// Response due to a low power node, once its receive delay has elapsed.
+enum Response {
+    Offer(FriendOffer),
+    Poll,
+    SubscriptionListConfirm(u8),
+}
+
+struct Friendship {
+    lpn_address: UnicastAddress,
+    num_elements: u8,
+    lpn_counter: u16,
+    friend_counter: u16,
+    /// Key index of the subnet, followed through the key refresh procedure.
+    net_key_index: NetKeyIndex,
+    network_key_handle: NetworkKeyHandle,
+    /// Friendship security credentials, derived from the master credentials.
+    credentials: NetworkKey,
+    previous_address: Option<UnicastAddress>,
+    receive_delay: Duration,
+    poll_timeout: Duration,
+    established: bool,
+    /// When the friendship lapses, unless polled in the meantime.
+    timeout: Instant,
+    response: Option<(Instant, Response)>,
+    /// When to next tell the previous friend of the low power node to stop storing
+    /// its messages, and the interval to the following attempt.
+    clear_previous: Option<(Instant, Duration)>,
+    /// When to give up on the previous friend confirming.
+    clear_until: Instant,
+    /// Security parameters last seen, so changes are conveyed with a friend update.
+    security: Option<Security>,
+    update_due: bool,
+    fsn: Option<bool>,
+    last: Option<Pending>,
+    subscriptions: Vec<Address, SUBSCRIPTION_LIST_SIZE>,
+    queue: Deque<CleartextNetworkPDU<ProvisionedStack>, FRIEND_QUEUE_SIZE>,
+}
+
+impl Friendship {
+    fn is_lpn_element(&self, address: Address) -> bool {
+        if let Address::Unicast(address) = address {
+            let address: u16 = address.into();
+            let lpn_address: u16 = self.lpn_address.into();
+            address >= lpn_address && address - lpn_address < self.num_elements as u16
+        } else {
+            false
+        }
+    }
+
+    /// Whether messages to `dst` are stored for the low power node.
+    fn is_destination(&self, dst: Address) -> bool {
+        self.is_lpn_element(dst)
+            || dst == Address::Group(GroupAddress::AllNodes)
+            || self.subscriptions.contains(&dst)
+    }
+
+    fn store(&mut self, pdu: CleartextNetworkPDU<ProvisionedStack>) {
+        if self.queue.is_full() {
+            // the oldest message makes room for the newest.
+            self.queue.pop_front();
+        }
+        self.queue.push_back(pdu).ok();
+    }
+
+    fn friendship_credentials(&self) -> Credentials {
+        Credentials::Friendship(self.network_key_handle, self.credentials)
+    }
+
+    fn next_deadline(&self) -> Instant {
+        [
+            self.response.as_ref().map(|(deadline, _)| *deadline),
+            self.clear_previous.map(|(deadline, _)| deadline),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(self.timeout, min)
+    }
+}
+
+/// Friendships with low power nodes, storing the messages destined to them
+/// until they poll for them.
+///
+/// Friend requests, offers and clears use the master security credentials,
+/// everything else exchanged with a low power node its friendship credentials.
+pub struct Friend<const N: usize = 2> {
+    friendships: Vec<Friendship, N>,
+    friend_counter: u16,
+    /// Friend clear confirmations due, the friendships having already been terminated.
+    clear_confirms: Vec<(NetworkKeyHandle, UnicastAddress, FriendClear), N>,
+}
+
+impl<const N: usize> Default for Friend<N> {
+    fn default() -> Self {
+        Self {
+            friendships: Vec::new(),
+            friend_counter: 0,
+            clear_confirms: Vec::new(),
+        }
+    }
+}
+
+impl<const N: usize> Friend<N> {
+    pub fn next_deadline(&self, now: Instant) -> Option<Instant> {
+        if !self.clear_confirms.is_empty() {
+            return Some(now);
+        }
+        self.friendships
+            .iter()
+            .map(|friendship| friendship.next_deadline())
+            .min()
+    }
+
+    /// Whether `dst` is an element of a low power node, whose segmented
+    /// messages are acknowledged on its behalf.
+    pub fn is_lpn_element(&self, dst: Address) -> bool {
+        self.friendships
+            .iter()
+            .any(|friendship| friendship.established && friendship.is_lpn_element(dst))
+    }
+
+    /// Whether messages to `dst` are stored for any low power node.
+    pub fn is_destination(&self, dst: Address) -> bool {
+        self.friendships
+            .iter()
+            .any(|friendship| friendship.established && friendship.is_destination(dst))
+    }
+
+    /// Store a message for the low power nodes it is destined to, unless it
+    /// was sent by the low power node itself.
+    pub fn store(&mut self, pdu: &CleartextNetworkPDU<ProvisionedStack>) {
+        for friendship in self.friendships.iter_mut().filter(|friendship| {
+            friendship.established
+                && friendship.is_destination(pdu.dst())
+                && !friendship.is_lpn_element(pdu.src().into())
+        }) {
+            friendship.store(pdu.clone());
+        }
+    }
+
+    /// Friendship credentials of every friendship, including those offered.
+    pub fn credentials_iter(&self) -> impl Iterator<Item = (NetworkKeyHandle, NetworkKey)> + '_ {
+        self.friendships
+            .iter()
+            .map(|friendship| (friendship.network_key_handle, friendship.credentials))
+    }
+
+    /// Follow the network key of each friendship through the key refresh procedure,
+    /// deriving its friendship credentials anew whenever the key used for transmission
+    /// changes, and queue friend updates as the security parameters change.
+    ///
+    /// Friendships whose network key has been deleted are terminated.
+    pub fn refresh<F>(
+        &mut self,
+        friend_address: UnicastAddress,
+        iv_index_state: &IvIndexState,
+        resolve: F,
+    ) where
+        F: Fn(NetKeyIndex) -> Option<(NetworkKeyHandle, NetworkKey, KeyRefreshFlag)>,
+    {
+        let mut index = 0;
+        while index < self.friendships.len() {
+            let friendship = &mut self.friendships[index];
+            let (network_key_handle, network_key, key_refresh_flag) =
+                if let Some(resolved) = resolve(friendship.net_key_index) {
+                    resolved
+                } else {
+                    warn!(
+                        "network key of friendship with {} deleted, terminating it",
+                        friendship.lpn_address
+                    );
+                    self.friendships.swap_remove(index);
+                    continue;
+                };
+
+            if network_key_handle != friendship.network_key_handle {
+                if let Ok(credentials) = network_key.friendship_credentials(
+                    friendship.lpn_address,
+                    friend_address,
+                    friendship.lpn_counter,
+                    friendship.friend_counter,
+                ) {
+                    friendship.network_key_handle = network_key_handle;
+                    friendship.credentials = credentials;
+                } else {
+                    warn!("unable to derive friendship credentials");
+                }
+            }
+
+            let security = Security {
+                key_refresh_flag,
+                iv_index: iv_index_state.iv_index(),
+                iv_update_flag: iv_index_state.iv_update_flag(),
+            };
+            if matches!(friendship.security.replace(security), Some(previous) if previous != security)
+            {
+                friendship.update_due = true;
+            }
+
+            index += 1;
+        }
+    }
+
+    /// Process a friend message received from `src`, on the subnet of
+    /// `network_key`. Friendship credentials are derived from it along with
+    /// the `friend_address` of this node.
+    #[allow(clippy::too_many_arguments)]
+    pub fn receive(
+        &mut self,
+        network_key_handle: NetworkKeyHandle,
+        net_key_index: NetKeyIndex,
+        network_key: &NetworkKey,
+        friend_address: UnicastAddress,
+        src: UnicastAddress,
+        message: FriendMessage,
+        now: Instant,
+    ) {
+        match message {
+            FriendMessage::Request(request) => {
+                if let Ok(credentials) = network_key.friendship_credentials(
+                    src,
+                    friend_address,
+                    request.lpn_counter,
+                    self.friend_counter,
+                ) {
+                    self.request(
+                        network_key_handle,
+                        net_key_index,
+                        credentials,
+                        src,
+                        request,
+                        now,
+                    );
+                } else {
+                    warn!("unable to derive friendship credentials");
+                }
+            }
+            FriendMessage::Poll { fsn } => {
+                if let Some(friendship) = self.friendship_mut(src) {
+                    if !friendship.established {
+                        info!("friendship established with {}", src);
+                        friendship.established = true;
+                        friendship.clear_previous = friendship
+                            .previous_address
+                            .map(|_| (now, CLEAR_REPEAT_INTERVAL));
+                        friendship.clear_until = now + friendship.poll_timeout * 2;
+                    }
+
+                    if friendship.fsn != Some(fsn) {
+                        // the previous response was received, move on to the next one,
+                        // conveying changed security parameters first.
+                        friendship.fsn.replace(fsn);
+                        friendship.last = if core::mem::take(&mut friendship.update_due) {
+                            Some(Pending::Update)
+                        } else {
+                            friendship.queue.pop_front().map(Pending::Stored)
+                        };
+                    }
+
+                    friendship.timeout = now + friendship.poll_timeout;
+                    friendship.response = Some((now + friendship.receive_delay, Response::Poll));
+                }
+            }
+            FriendMessage::SubscriptionListAdd(list) => {
+                self.subscription_list(src, list, now, |subscriptions, address| {
+                    if !subscriptions.contains(&address) && subscriptions.push(address).is_err() {
+                        warn!("friend subscription list full");
+                    }
+                });
+            }
+            FriendMessage::SubscriptionListRemove(list) => {
+                self.subscription_list(src, list, now, |subscriptions, address| {
+                    if let Some(index) = subscriptions.iter().position(|e| *e == address) {
+                        subscriptions.swap_remove(index);
+                    }
+                });
+            }
+            FriendMessage::Clear(clear) => {
+                if let Some(index) = self.friendships.iter().position(|friendship| {
+                    friendship.lpn_address == clear.lpn_address
+                        && clear.lpn_counter.wrapping_sub(friendship.lpn_counter)
+                            <= MAX_LPN_COUNTER_DELTA
+                }) {
+                    info!("friendship with {} cleared", clear.lpn_address);
+                    self.friendships.swap_remove(index);
+                    // confirmations beyond capacity are dropped.
+                    self.clear_confirms
+                        .push((network_key_handle, src, clear))
+                        .ok();
+                }
+            }
+            FriendMessage::ClearConfirm(confirm) => {
+                if let Some(friendship) = self.friendships.iter_mut().find(|friendship| {
+                    friendship.previous_address == Some(src)
+                        && friendship.lpn_address == confirm.lpn_address
+                }) {
+                    friendship.clear_previous.take();
+                }
+            }
+            FriendMessage::Update(_)
+            | FriendMessage::Offer(_)
+            | FriendMessage::SubscriptionListConfirm { .. } => {
+                // not applicable to friend role
+            }
+        }
+    }
+
+    fn request(
+        &mut self,
+        network_key_handle: NetworkKeyHandle,
+        net_key_index: NetKeyIndex,
+        credentials: NetworkKey,
+        src: UnicastAddress,
+        request: FriendRequest,
+        now: Instant,
+    ) {
+        // a new request from a low power node supersedes any previous friendship.
+        if let Some(index) = self
+            .friendships
+            .iter()
+            .position(|friendship| friendship.lpn_address == src)
+        {
+            self.friendships.swap_remove(index);
+        }
+
+        if request.criteria.min_queue_size() > FRIEND_QUEUE_SIZE {
+            return;
+        }
+
+        // the signal strength is not reported by the bearer, so only the receive window counts.
+        let offer_delay = max(
+            Duration::from_millis(
+                factor_tenths(request.criteria.receive_window_factor) * RECEIVE_WINDOW as u64 / 10,
+            ),
+            MIN_OFFER_DELAY,
+        );
+
+        let offer = FriendOffer {
+            receive_window: RECEIVE_WINDOW,
+            queue_size: FRIEND_QUEUE_SIZE as u8,
+            subscription_list_size: SUBSCRIPTION_LIST_SIZE as u8,
+            rssi: i8::MAX,
+            friend_counter: self.friend_counter,
+        };
+
+        if self
+            .friendships
+            .push(Friendship {
+                lpn_address: src,
+                num_elements: request.num_elements,
+                lpn_counter: request.lpn_counter,
+                friend_counter: self.friend_counter,
+                net_key_index,
+                network_key_handle,
+                credentials,
+                previous_address: request.previous_address,
+                receive_delay: Duration::from_millis(request.receive_delay as u64),
+                poll_timeout: Duration::from_millis(request.poll_timeout as u64 * 100),
+                established: false,
+                timeout: now + offer_delay + OFFER_TIMEOUT,
+                response: Some((now + offer_delay, Response::Offer(offer))),
+                clear_previous: None,
+                clear_until: now,
+                security: None,
+                update_due: false,
+                fsn: None,
+                last: None,
+                subscriptions: Vec::new(),
+                queue: Deque::new(),
+            })
+            .is_ok()
+        {
+            self.friend_counter = self.friend_counter.wrapping_add(1);
+        } else {
+            debug!("no room for another friendship");
+        }
+    }
+
+    fn subscription_list<F: Fn(&mut Vec<Address, SUBSCRIPTION_LIST_SIZE>, Address)>(
+        &mut self,
+        src: UnicastAddress,
+        list: FriendSubscriptionList,
+        now: Instant,
+        apply: F,
+    ) {
+        if let Some(friendship) = self.friendship_mut(src) {
+            if !friendship.established {
+                return;
+            }
+            for address in list.addresses {
+                apply(&mut friendship.subscriptions, address);
+            }
+            friendship.timeout = now + friendship.poll_timeout;
+            friendship.response = Some((
+                now + friendship.receive_delay,
+                Response::SubscriptionListConfirm(list.transaction_number),
+            ));
+        }
+    }
+
+    fn friendship_mut(&mut self, lpn_address: UnicastAddress) -> Option<&mut Friendship> {
+        self.friendships
+            .iter_mut()
+            .find(|friendship| friendship.lpn_address == lpn_address)
+    }
+
+    /// Select the next transmission due, terminating lapsed friendships along the way.
+    pub fn next_transmission(&mut self, now: Instant) -> Option<FriendTransmission> {
+        if let Some((network_key_handle, dst, clear)) = self.clear_confirms.pop() {
+            return Some(FriendTransmission::Message(
+                Credentials::Master(network_key_handle),
+                dst,
+                FriendMessage::ClearConfirm(clear),
+            ));
+        }
+
+        loop {
+            let index = self
+                .friendships
+                .iter()
+                .position(|friendship| friendship.next_deadline() <= now)?;
+
+            let friendship = &mut self.friendships[index];
+
+            if matches!(friendship.clear_previous, Some((deadline, _)) if deadline <= now) {
+                if let Some((_, interval)) = friendship.clear_previous.take() {
+                    if now < friendship.clear_until {
+                        // repeated until confirmed, at ever longer intervals.
+                        friendship.clear_previous = Some((now + interval, interval * 2));
+                        if let Some(previous_address) = friendship.previous_address {
+                            return Some(FriendTransmission::Message(
+                                Credentials::Master(friendship.network_key_handle),
+                                previous_address,
+                                FriendMessage::Clear(FriendClear {
+                                    lpn_address: friendship.lpn_address,
+                                    lpn_counter: friendship.lpn_counter,
+                                }),
+                            ));
+                        }
+                    } else {
+                        debug!(
+                            "previous friend of {} did not confirm",
+                            friendship.lpn_address
+                        );
+                    }
+                }
+            }
+
+            if matches!(friendship.response, Some((deadline, _)) if deadline <= now) {
+                if let Some((_, response)) = friendship.response.take() {
+                    let credentials = friendship.friendship_credentials();
+                    let lpn_address = friendship.lpn_address;
+                    return Some(match response {
+                        Response::Offer(offer) => FriendTransmission::Message(
+                            Credentials::Master(friendship.network_key_handle),
+                            lpn_address,
+                            FriendMessage::Offer(offer),
+                        ),
+                        Response::Poll => match &friendship.last {
+                            Some(Pending::Stored(last)) => {
+                                FriendTransmission::Stored(credentials, last.clone())
+                            }
+                            Some(Pending::Update) | None => FriendTransmission::Update(
+                                credentials,
+                                lpn_address,
+                                !friendship.queue.is_empty(),
+                            ),
+                        },
+                        Response::SubscriptionListConfirm(transaction_number) => {
+                            FriendTransmission::Message(
+                                credentials,
+                                lpn_address,
+                                FriendMessage::SubscriptionListConfirm { transaction_number },
+                            )
+                        }
+                    });
+                }
+            }
+
+            if friendship.timeout <= now {
+                if friendship.established {
+                    warn!("friendship with {} lapsed", friendship.lpn_address);
+                }
+                self.friendships.swap_remove(index);
+            }
+        }
+    }
+}
+
+impl ProvisionedStack {
+    pub fn next_friend_deadline(&self) -> Option<Instant> {
+        self.friend.next_deadline(Instant::now())
+    }
+
+    /// Store a message destined to a low power node until it polls for it.
+    /// Messages received from other nodes are stored as they are to be relayed.
+    pub(crate) fn store_for_friends(&mut self, pdu: &CleartextNetworkPDU<ProvisionedStack>) {
+        if !self.friend.is_destination(pdu.dst()) {
+            return;
+        }
+        if self
+            .network
+            .device_info()
+            .local_element_index(pdu.src().into())
+            .is_some()
+        {
+            self.friend.store(pdu);
+        } else if pdu.ttl().value() >= 2 && !pdu.meta().is_replay_protected() {
+            if let Ok(relayed) = CleartextNetworkPDU::new(
+                pdu.ivi(),
+                pdu.nid(),
+                pdu.ctl(),
+                Ttl::new(pdu.ttl().value() - 1),
+                pdu.seq(),
+                pdu.src(),
+                pdu.dst(),
+                pdu.transport_pdu(),
+                *pdu.meta(),
+            ) {
+                self.friend.store(&relayed);
+            }
+        }
+    }
+
+    pub(crate) fn process_inbound_friend_message(
+        &mut self,
+        message: &ControlMessage<ProvisionedStack>,
+    ) {
+        let friend_message = match FriendMessage::parse(message.opcode(), message.parameters()) {
+            Ok(friend_message) => friend_message,
+            Err(_) => {
+                warn!("ignoring malformed friend message");
+                return;
+            }
+        };
+
+        self.refresh_friendships();
+
+        let network_key_handle = message.meta().network_key_handle();
+        if let (Ok(network_key), Ok(entry), Some(friend_address)) = (
+            self.secrets.network_key(network_key_handle),
+            self.secrets.network_key_entry(network_key_handle),
+            self.network.device_info().local_element_address(0),
+        ) {
+            self.friend.receive(
+                network_key_handle,
+                entry.net_key_index,
+                &network_key,
+                friend_address,
+                message.meta().src(),
+                friend_message,
+                Instant::now(),
+            );
+        }
+    }
+
+    /// Track the segments of a segmented message destined to a low power node,
+    /// so that they are acknowledged on its behalf.
+    pub(crate) fn acknowledge_for_friends(
+        &mut self,
+        pdu: &CleartextNetworkPDU<ProvisionedStack>,
+    ) -> Option<(BlockAck, UpperMetadata)> {
+        if self.friend.is_lpn_element(pdu.dst()) {
+            self.process_inbound_lpn_segment(pdu)
+        } else {
+            None
+        }
+    }
+
+    /// Bring friendships up to date with the network keys and security parameters,
+    /// so those whose key has been deleted are terminated rather than failing.
+    fn refresh_friendships(&mut self) {
+        if let Some(friend_address) = self.network.device_info().local_element_address(0) {
+            let secrets = &self.secrets;
+            self.friend.refresh(
+                friend_address,
+                self.network_state.iv_index(),
+                |net_key_index| {
+                    let (slot, entry) = secrets.network_keys().get(net_key_index)?;
+                    let network_key = entry.transmit_key();
+                    Some((
+                        NetworkKeyHandle(slot, network_key.nid()),
+                        network_key,
+                        key_refresh_flag(entry.phase),
+                    ))
+                },
+            );
+        }
+    }
+
+    /// Produce the network PDUs due to low power nodes, or on their behalf.
+    pub fn process_friend(
+        &mut self,
+        sequence: &Sequence,
+    ) -> Result<Vec<NetworkPDU, 8>, DriverError> {
+        self.refresh_friendships();
+
+        let mut network_pdus = Vec::new();
+        // a transmission taken is sent, so only take those there is room for;
+        // the remainder is picked up right away.
+        while !network_pdus.is_full() {
+            let transmission = match self.friend.next_transmission(Instant::now()) {
+                Some(transmission) => transmission,
+                None => break,
+            };
+            match self.friend_transmission_pdu(sequence, transmission) {
+                Ok(network_pdu) => network_pdus.push(network_pdu).ok(),
+                Err(DriverError::InvalidKeyHandle) => {
+                    warn!("dropping friend message, its network key is gone");
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+        }
+        Ok(network_pdus)
+    }
+
+    fn friend_transmission_pdu(
+        &mut self,
+        sequence: &Sequence,
+        transmission: FriendTransmission,
+    ) -> Result<NetworkPDU, DriverError> {
+        match transmission {
+            FriendTransmission::Message(credentials, dst, message) => {
+                self.friend_message_pdu(sequence, credentials, dst.into(), &message)
+            }
+            FriendTransmission::Update(credentials, dst, more_data) => {
+                let entry = self
+                    .secrets
+                    .network_key_entry(credentials.network_key_handle())?;
+                let iv_index_state = self.network_state.iv_index();
+                let update = FriendMessage::Update(FriendUpdate {
+                    key_refresh_flag: key_refresh_flag(entry.phase),
+                    iv_update_flag: iv_index_state.iv_update_flag(),
+                    iv_index: iv_index_state.iv_index(),
+                    more_data,
+                });
+                self.friend_message_pdu(sequence, credentials, dst.into(), &update)
+            }
+            FriendTransmission::Stored(credentials, pdu) => {
+                self.encrypt_network_pdu_with_credentials(&pdu, &credentials)
+            }
+        }
+    }
+}
+
+fn key_refresh_flag(phase: KeyRefreshPhase) -> KeyRefreshFlag {
+    KeyRefreshFlag(matches!(phase, KeyRefreshPhase::Second))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stack::provisioned::friend::{Friend, FriendTransmission, FRIEND_QUEUE_SIZE};
+    use crate::stack::provisioned::network::Credentials;
+    use crate::stack::provisioned::system::NetworkMetadata;
+    use crate::stack::provisioned::{IvIndexState, ProvisionedStack};
+    use btmesh_common::address::{Address, UnicastAddress};
+    use btmesh_common::crypto::network::{NetworkKey, Nid};
+    use btmesh_common::{Ctl, IvIndex, IvUpdateFlag, Ivi, KeyRefreshFlag, Seq, Ttl};
+    use btmesh_device::NetworkKeyHandle;
+    use btmesh_models::foundation::configuration::NetKeyIndex;
+    use btmesh_pdu::provisioned::friend::{
+        FriendClear, FriendCriteria, FriendMessage, FriendRequest, FriendSubscriptionList,
+    };
+    use btmesh_pdu::provisioned::network::CleartextNetworkPDU;
+    use embassy_executor::time::{Duration, Instant};
+    use heapless::Vec;
+
+    fn network_key_handle() -> NetworkKeyHandle {
+        NetworkKeyHandle(0, Nid::new(42))
+    }
+
+    fn net_key_index() -> NetKeyIndex {
+        NetKeyIndex::new(0)
+    }
+
+    fn network_key() -> NetworkKey {
+        NetworkKey::new([
+            0x7d, 0xd7, 0x36, 0x4c, 0xd8, 0x42, 0xad, 0x18, 0xc1, 0x7c, 0x2b, 0x82, 0x0c, 0x84,
+            0xc3, 0xd6,
+        ])
+        .unwrap()
+    }
+
+    fn friend_address() -> UnicastAddress {
+        UnicastAddress::parse([0x00, 0x10]).unwrap()
+    }
+
+    fn lpn() -> UnicastAddress {
+        UnicastAddress::parse([0x00, 0x20]).unwrap()
+    }
+
+    fn millis(millis: u64) -> Instant {
+        Instant::from_millis(millis)
+    }
+
+    fn request() -> FriendMessage {
+        FriendMessage::Request(FriendRequest {
+            criteria: FriendCriteria {
+                rssi_factor: 0,
+                receive_window_factor: 0,
+                min_queue_size_log: 2,
+            },
+            receive_delay: 100,
+            // 10 seconds.
+            poll_timeout: 100,
+            previous_address: None,
+            num_elements: 2,
+            lpn_counter: 7,
+        })
+    }
+
+    fn pdu(seq: u32, dst: Address) -> CleartextNetworkPDU<ProvisionedStack> {
+        CleartextNetworkPDU::new(
+            Ivi::Zero,
+            Nid::new(42),
+            Ctl::Access,
+            Ttl::new(5),
+            Seq::parse(seq).unwrap(),
+            UnicastAddress::parse([0x00, 0x0A]).unwrap(),
+            dst,
+            &[0; 8],
+            NetworkMetadata::new(IvIndex::new(0), None, network_key_handle()),
+        )
+        .unwrap()
+    }
+
+    fn poll(friend: &mut Friend, fsn: bool, now: Instant) -> FriendTransmission {
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            lpn(),
+            FriendMessage::Poll { fsn },
+            now,
+        );
+        assert!(friend.next_transmission(now).is_none());
+        friend
+            .next_transmission(now + Duration::from_millis(100))
+            .unwrap()
+    }
+
+    fn established() -> Friend {
+        let mut friend = Friend::default();
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            lpn(),
+            request(),
+            millis(0),
+        );
+        friend.next_transmission(millis(255)).unwrap();
+        poll(&mut friend, false, millis(500));
+        friend
+    }
+
+    #[test]
+    fn offer() {
+        let mut friend = Friend::<2>::default();
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            lpn(),
+            request(),
+            millis(0),
+        );
+
+        // a receive window of 255ms, weighted by a factor of 1.
+        assert_eq!(Some(millis(255)), friend.next_deadline(millis(0)));
+        assert!(friend.next_transmission(millis(254)).is_none());
+
+        if let Some(FriendTransmission::Message(_, dst, FriendMessage::Offer(offer))) =
+            friend.next_transmission(millis(255))
+        {
+            assert_eq!(lpn(), dst);
+            assert_eq!(FRIEND_QUEUE_SIZE as u8, offer.queue_size);
+            assert_eq!(0, offer.friend_counter);
+        } else {
+            panic!("expected a friend offer");
+        }
+
+        // nothing is stored until the offer has been accepted.
+        friend.store(&pdu(1, lpn().into()));
+        assert!(!friend.is_destination(lpn().into()));
+
+        // the offer lapses without a friend poll.
+        assert!(friend.next_transmission(millis(1255)).is_none());
+        assert_eq!(None, friend.next_deadline(millis(1255)));
+    }
+
+    #[test]
+    fn friendship_credentials() {
+        let mut friend = Friend::<2>::default();
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            lpn(),
+            request(),
+            millis(0),
+        );
+
+        let credentials = network_key()
+            .friendship_credentials(lpn(), friend_address(), 7, 0)
+            .unwrap();
+        let mut iter = friend.credentials_iter();
+        let (handle, key) = iter.next().unwrap();
+        assert!(network_key_handle() == handle);
+        assert_eq!(credentials, key);
+        assert!(iter.next().is_none());
+
+        // the offer is secured with the master credentials.
+        assert!(matches!(
+            friend.next_transmission(millis(255)),
+            Some(FriendTransmission::Message(Credentials::Master(_), _, _))
+        ));
+
+        // the response to the poll with the friendship credentials.
+        match poll(&mut friend, false, millis(500)) {
+            FriendTransmission::Update(Credentials::Friendship(_, key), _, _) => {
+                assert_eq!(credentials, key)
+            }
+            _ => panic!("expected a friend update"),
+        }
+    }
+
+    #[test]
+    fn poll_responses() {
+        let mut friend = established();
+
+        // messages to any element of the low power node are stored.
+        friend.store(&pdu(1, lpn().into()));
+        friend.store(&pdu(2, UnicastAddress::parse([0x00, 0x21]).unwrap().into()));
+        friend.store(&pdu(3, UnicastAddress::parse([0x00, 0x22]).unwrap().into()));
+
+        match poll(&mut friend, true, millis(1000)) {
+            FriendTransmission::Stored(_, pdu) => assert_eq!(Seq::parse(1).unwrap(), pdu.seq()),
+            _ => panic!("expected a stored message"),
+        }
+
+        // the same sequence number asks for the previous response again.
+        match poll(&mut friend, true, millis(2000)) {
+            FriendTransmission::Stored(_, pdu) => assert_eq!(Seq::parse(1).unwrap(), pdu.seq()),
+            _ => panic!("expected a stored message"),
+        }
+
+        match poll(&mut friend, false, millis(3000)) {
+            FriendTransmission::Stored(_, pdu) => assert_eq!(Seq::parse(2).unwrap(), pdu.seq()),
+            _ => panic!("expected a stored message"),
+        }
+
+        match poll(&mut friend, true, millis(4000)) {
+            FriendTransmission::Update(_, dst, more_data) => {
+                assert_eq!(lpn(), dst);
+                assert!(!more_data);
+            }
+            _ => panic!("expected a friend update"),
+        }
+    }
+
+    #[test]
+    fn oldest_message_discarded() {
+        let mut friend = established();
+
+        for seq in 0..FRIEND_QUEUE_SIZE as u32 + 2 {
+            friend.store(&pdu(seq, lpn().into()));
+        }
+
+        match poll(&mut friend, true, millis(1000)) {
+            FriendTransmission::Stored(_, pdu) => assert_eq!(Seq::parse(2).unwrap(), pdu.seq()),
+            _ => panic!("expected a stored message"),
+        }
+    }
+
+    #[test]
+    fn subscription_list() {
+        let mut friend = established();
+        let group = Address::parse([0xC0, 0x01]);
+        assert!(!friend.is_destination(group));
+
+        let mut addresses = Vec::new();
+        addresses.push(group).unwrap();
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            lpn(),
+            FriendMessage::SubscriptionListAdd(FriendSubscriptionList {
+                transaction_number: 3,
+                addresses: addresses.clone(),
+            }),
+            millis(1000),
+        );
+        assert!(friend.is_destination(group));
+
+        if let Some(FriendTransmission::Message(
+            _,
+            _,
+            FriendMessage::SubscriptionListConfirm { transaction_number },
+        )) = friend.next_transmission(millis(1100))
+        {
+            assert_eq!(3, transaction_number);
+        } else {
+            panic!("expected a subscription list confirmation");
+        }
+
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            lpn(),
+            FriendMessage::SubscriptionListRemove(FriendSubscriptionList {
+                transaction_number: 4,
+                addresses,
+            }),
+            millis(2000),
+        );
+        assert!(!friend.is_destination(group));
+    }
+
+    #[test]
+    fn poll_timeout() {
+        let mut friend = established();
+        assert_eq!(Some(millis(10500)), friend.next_deadline(millis(600)));
+        assert!(friend.next_transmission(millis(10500)).is_none());
+        assert!(!friend.is_destination(lpn().into()));
+    }
+
+    #[test]
+    fn cleared_by_new_friend() {
+        let mut friend = established();
+        let new_friend = UnicastAddress::parse([0x00, 0x30]).unwrap();
+        let clear = FriendClear {
+            lpn_address: lpn(),
+            lpn_counter: 8,
+        };
+
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            new_friend,
+            FriendMessage::Clear(clear),
+            millis(1000),
+        );
+        assert!(!friend.is_destination(lpn().into()));
+
+        if let Some(FriendTransmission::Message(_, dst, FriendMessage::ClearConfirm(confirm))) =
+            friend.next_transmission(millis(1000))
+        {
+            assert_eq!(new_friend, dst);
+            assert_eq!(clear, confirm);
+        } else {
+            panic!("expected a friend clear confirmation");
+        }
+    }
+
+    #[test]
+    fn previous_friend_cleared_until_confirmed() {
+        let previous_friend = UnicastAddress::parse([0x00, 0x30]).unwrap();
+        let mut friend = Friend::default();
+        if let FriendMessage::Request(request) = request() {
+            friend.receive(
+                network_key_handle(),
+                net_key_index(),
+                &network_key(),
+                friend_address(),
+                lpn(),
+                FriendMessage::Request(FriendRequest {
+                    previous_address: Some(previous_friend),
+                    ..request
+                }),
+                millis(0),
+            );
+        }
+        friend.next_transmission(millis(255)).unwrap();
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            lpn(),
+            FriendMessage::Poll { fsn: false },
+            millis(500),
+        );
+
+        let clear = FriendClear {
+            lpn_address: lpn(),
+            lpn_counter: 7,
+        };
+        for at in [500, 1500, 3500] {
+            match friend.next_transmission(millis(at)) {
+                Some(FriendTransmission::Message(_, dst, FriendMessage::Clear(sent))) => {
+                    assert_eq!(previous_friend, dst);
+                    assert_eq!(clear, sent);
+                }
+                _ => panic!("expected a friend clear"),
+            }
+            // the poll response is due in between.
+            friend.next_transmission(millis(at + 100));
+        }
+
+        friend.receive(
+            network_key_handle(),
+            net_key_index(),
+            &network_key(),
+            friend_address(),
+            previous_friend,
+            FriendMessage::ClearConfirm(clear),
+            millis(4000),
+        );
+        assert!(friend.next_transmission(millis(7500)).is_none());
+    }
+
+    #[test]
+    fn security_changes_conveyed() {
+        let mut friend = established();
+        let resolve = |_| Some((network_key_handle(), network_key(), KeyRefreshFlag(false)));
+
+        friend.refresh(
+            friend_address(),
+            &IvIndexState::new(IvIndex::new(5), IvUpdateFlag::Normal),
+            resolve,
+        );
+        friend.store(&pdu(1, lpn().into()));
+
+        friend.refresh(
+            friend_address(),
+            &IvIndexState::new(IvIndex::new(6), IvUpdateFlag::InProgress),
+            resolve,
+        );
+
+        // the friend update goes ahead of the stored message.
+        match poll(&mut friend, true, millis(1000)) {
+            FriendTransmission::Update(_, _, more_data) => assert!(more_data),
+            _ => panic!("expected a friend update"),
+        }
+        match poll(&mut friend, false, millis(2000)) {
+            FriendTransmission::Stored(_, pdu) => assert_eq!(Seq::parse(1).unwrap(), pdu.seq()),
+            _ => panic!("expected a stored message"),
+        }
+    }
+
+    #[test]
+    fn terminated_with_network_key() {
+        let mut friend = established();
+
+        friend.refresh(
+            friend_address(),
+            &IvIndexState::new(IvIndex::new(5), IvUpdateFlag::Normal),
+            |_| None,
+        );
+        assert!(!friend.is_destination(lpn().into()));
+        assert_eq!(None, friend.next_deadline(millis(1000)));
+    }
+}
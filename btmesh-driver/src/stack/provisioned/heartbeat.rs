@@ -0,0 +1,477 @@
+use crate::stack::provisioned::sequence::Sequence;
+use crate::stack::provisioned::system::ControlMetadata;
+use crate::stack::provisioned::{DriverError, ProvisionedStack};
+use crate::storage::provisioned::foundation::configuration::Configuration;
+use btmesh_common::address::{Address, UnicastAddress};
+use btmesh_common::{Features, Ttl};
+use btmesh_models::foundation::configuration::heartbeat::{
+    from_log, to_log, HeartbeatPublication, HeartbeatSubscription,
+};
+use btmesh_pdu::provisioned::control::ControlMessage;
+use btmesh_pdu::provisioned::network::NetworkPDU;
+use btmesh_pdu::provisioned::upper::control::ControlOpcode;
+use core::cmp::{max, min};
+use embassy_executor::time::{Duration, Instant};
+use heapless::Vec;
+
+/// Publication count meaning "publish indefinitely".
+const INDEFINITE_COUNT: u16 = 0xFFFF;
+
+fn period(period_log: u8) -> Option<Duration> {
+    match period_log {
+        0 => None,
+        log => Some(Duration::from_secs(1 << (log - 1))),
+    }
+}
+
+/// Heartbeat state owned by the stack, to be written back to the configuration
+/// unless it has been modified through the configuration server in the meantime.
+pub struct HeartbeatWriteBack {
+    previous: (HeartbeatPublication, HeartbeatSubscription),
+    current: (HeartbeatPublication, HeartbeatSubscription),
+}
+
+impl HeartbeatWriteBack {
+    pub fn apply(&self, configuration: &mut Configuration) {
+        if *configuration.heartbeat_publication() == self.previous.0 {
+            *configuration.heartbeat_publication_mut() = self.current.0;
+        }
+        if *configuration.heartbeat_subscription() == self.previous.1 {
+            *configuration.heartbeat_subscription_mut() = self.current.1;
+        }
+    }
+}
+
+/// Periodic and triggered publication of heartbeats, along with the
+/// statistics of heartbeats received through the subscription.
+///
+/// The configured state lives in storage, while the remaining publication
+/// count, the remaining subscription period and the statistics are tracked
+/// here and written back as they evolve.
+#[derive(Default)]
+pub struct Heartbeat {
+    publication: HeartbeatPublication,
+    remaining: u16,
+    next_publication: Option<Instant>,
+    triggered: Option<Instant>,
+    subscription: HeartbeatSubscription,
+    subscription_end: Option<Instant>,
+    received: u16,
+    persisted: (HeartbeatPublication, HeartbeatSubscription),
+}
+
+impl Heartbeat {
+    /// Adopt the state found in storage, unless it is merely what was last written back.
+    pub fn reconfigure(
+        &mut self,
+        publication: &HeartbeatPublication,
+        subscription: &HeartbeatSubscription,
+        now: Instant,
+    ) {
+        if *publication != self.persisted.0 {
+            self.publication = *publication;
+            self.remaining = from_log(publication.count_log);
+            self.triggered.take();
+            // the first periodic heartbeat is sent right away.
+            self.next_publication = match period(publication.period_log) {
+                Some(_) if publication.destination != Address::Unassigned && self.remaining > 0 => {
+                    Some(now)
+                }
+                _ => None,
+            };
+            self.persisted.0 = *publication;
+        }
+
+        if *subscription != self.persisted.1 {
+            self.subscription = *subscription;
+            self.subscription_end = period(subscription.period_log).map(|period| now + period);
+            self.received = from_log(subscription.count_log);
+            self.persisted.1 = *subscription;
+        }
+    }
+
+    /// Produce the state to write back, remembering it so that it is
+    /// not mistaken for a reconfiguration.
+    pub fn write_back(&mut self, now: Instant) -> HeartbeatWriteBack {
+        let publication = HeartbeatPublication {
+            count_log: to_log(self.remaining.into()),
+            ..self.publication
+        };
+
+        let subscription = HeartbeatSubscription {
+            // 0xFFFF seconds share their logarithm with 0xFFFE, yet would be
+            // mistaken for the largest count.
+            period_log: to_log(min(self.remaining_subscription_secs(now), 0xFFFE)),
+            count_log: to_log(self.received.into()),
+            ..self.subscription
+        };
+
+        let previous = core::mem::replace(&mut self.persisted, (publication, subscription));
+
+        HeartbeatWriteBack {
+            previous,
+            current: self.persisted,
+        }
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        [self.triggered, self.next_publication]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    /// Trigger a heartbeat if any feature of interest to the publication
+    /// has been enabled or disabled.
+    pub fn features_changed(&mut self, previous: Features, current: Features, now: Instant) {
+        if (previous.bits() ^ current.bits()) & self.publication.features.bits() != 0
+            && self.publication.destination != Address::Unassigned
+        {
+            self.triggered.replace(now);
+        }
+    }
+
+    /// Determine whether a heartbeat is due, advancing periodic publication if so.
+    pub fn publish(&mut self, now: Instant) -> Option<HeartbeatPublication> {
+        let mut due = false;
+
+        if matches!(self.triggered, Some(triggered) if triggered <= now) {
+            self.triggered.take();
+            due = true;
+        }
+
+        if matches!(self.next_publication, Some(next) if next <= now) {
+            if self.remaining != INDEFINITE_COUNT {
+                self.remaining = self.remaining.saturating_sub(1);
+            }
+            self.next_publication = match period(self.publication.period_log) {
+                Some(period) if self.remaining > 0 => Some(now + period),
+                _ => None,
+            };
+            due = true;
+        }
+
+        if due && self.publication.destination != Address::Unassigned {
+            Some(self.publication)
+        } else {
+            None
+        }
+    }
+
+    /// Account for a heartbeat, if the subscription is active and matches it.
+    pub fn receive(
+        &mut self,
+        src: UnicastAddress,
+        dst: Address,
+        init_ttl: u8,
+        ttl: u8,
+        now: Instant,
+    ) {
+        if self.remaining_subscription_secs(now) == 0
+            || self.subscription.source != Address::Unicast(src)
+            || self.subscription.destination != dst
+        {
+            return;
+        }
+
+        let hops = init_ttl.saturating_sub(ttl).saturating_add(1);
+        self.received = self.received.saturating_add(1);
+        self.subscription.min_hops = min(self.subscription.min_hops, hops);
+        self.subscription.max_hops = max(self.subscription.max_hops, hops);
+    }
+
+    fn remaining_subscription_secs(&self, now: Instant) -> u32 {
+        match self.subscription_end {
+            Some(end) if end > now => (((end - now).as_millis() + 999) / 1000) as u32,
+            _ => 0,
+        }
+    }
+}
+
+impl ProvisionedStack {
+    /// Features currently in use, as conveyed by heartbeats.
+    pub(crate) fn features(&self) -> Features {
+        Features {
+            relay: self.relay_retransmit().is_some(),
+            proxy: self.gatt_proxy_enabled(),
+            friend: cfg!(feature = "friend"),
+            #[cfg(feature = "low_power")]
+            low_power: self.low_power.is_established(),
+            #[cfg(not(feature = "low_power"))]
+            low_power: false,
+        }
+    }
+
+    pub fn next_heartbeat(&self) -> Option<Instant> {
+        self.heartbeat.next_deadline()
+    }
+
+    pub fn heartbeat_write_back(&mut self) -> HeartbeatWriteBack {
+        self.heartbeat.write_back(Instant::now())
+    }
+
+    /// Produce the heartbeat due, if any, along with its destination.
+    pub fn process_heartbeat(
+        &mut self,
+        sequence: &Sequence,
+    ) -> Result<Option<(Vec<NetworkPDU, 32>, Address)>, DriverError> {
+        let publication = if let Some(publication) = self.heartbeat.publish(Instant::now()) {
+            publication
+        } else {
+            return Ok(None);
+        };
+
+        let network_key_handle =
+            if let Ok(handle) = self.secrets.network_key_handle(publication.net_key_index) {
+                handle
+            } else {
+                warn!("no network key for heartbeat publication");
+                return Ok(None);
+            };
+
+        let features = self.features().bits().to_be_bytes();
+        let parameters = [publication.ttl, features[0], features[1]];
+
+        let src = self
+            .network
+            .device_info()
+            .local_element_address(0)
+            .ok_or(DriverError::InvalidState)?;
+
+        let message = ControlMessage::new(
+            ControlOpcode::Heartbeat,
+            &parameters,
+            ControlMetadata::new(
+                network_key_handle,
+                self.network_state.iv_index_state.transmission_iv_index(),
+                src,
+                publication.destination,
+                Ttl::new(publication.ttl),
+            ),
+        )?;
+
+        let upper_pdu = self.process_outbound_message(sequence, &message.into())?;
+        let network_pdus = self
+            .process_outbound_upper_pdu(sequence, &upper_pdu, None)?
+            .iter()
+            .map_while(|pdu| self.encrypt_network_pdu(pdu).ok())
+            .collect();
+
+        Ok(Some((network_pdus, publication.destination)))
+    }
+
+    pub(crate) fn process_inbound_control_message(
+        &mut self,
+        sequence: &Sequence,
+        message: &ControlMessage<ProvisionedStack>,
+    ) {
+        if let ControlOpcode::Heartbeat = message.opcode() {
+            let parameters = message.parameters();
+            if parameters.len() != 3 {
+                warn!("ignoring malformed heartbeat");
+                return;
+            }
+
+            self.heartbeat.receive(
+                message.meta().src(),
+                message.meta().dst(),
+                parameters[0] & 0x7F,
+                message.meta().ttl().value(),
+                Instant::now(),
+            );
+        } else {
+            // segment acknowledgements are consumed by the lower transport layer,
+            // leaving only friend messages.
+            #[cfg(feature = "friend")]
+            self.process_inbound_friend_message(message);
+            #[cfg(feature = "low_power")]
+            self.process_inbound_low_power_message(sequence, message);
+            #[cfg(not(feature = "low_power"))]
+            let _ = sequence;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::stack::provisioned::heartbeat::Heartbeat;
+    use crate::storage::provisioned::foundation::configuration::Configuration;
+    use btmesh_common::address::{Address, UnicastAddress};
+    use btmesh_common::Features;
+    use btmesh_models::foundation::configuration::heartbeat::{
+        HeartbeatPublication, HeartbeatSubscription, INDEFINITE_COUNT_LOG,
+    };
+    use embassy_executor::time::Instant;
+
+    fn secs(secs: u64) -> Instant {
+        Instant::from_secs(secs)
+    }
+
+    fn unicast(address: u8) -> UnicastAddress {
+        UnicastAddress::parse([0x00, address]).unwrap()
+    }
+
+    fn publication(count_log: u8, period_log: u8) -> HeartbeatPublication {
+        HeartbeatPublication {
+            destination: unicast(0x0A).into(),
+            count_log,
+            period_log,
+            ttl: 5,
+            features: Features::parse(0b0001),
+            ..Default::default()
+        }
+    }
+
+    fn subscription(period_log: u8) -> HeartbeatSubscription {
+        HeartbeatSubscription {
+            source: unicast(0x0B).into(),
+            destination: unicast(0x01).into(),
+            period_log,
+            count_log: 0,
+            min_hops: 0x7F,
+            max_hops: 0,
+        }
+    }
+
+    #[test]
+    fn periodic_publication() {
+        let mut heartbeat = Heartbeat::default();
+        // 4 heartbeats, every 2 seconds.
+        heartbeat.reconfigure(&publication(0x03, 0x02), &Default::default(), secs(0));
+
+        assert_eq!(Some(secs(0)), heartbeat.next_deadline());
+        assert!(heartbeat.publish(secs(0)).is_some());
+        assert_eq!(Some(secs(2)), heartbeat.next_deadline());
+        assert!(heartbeat.publish(secs(1)).is_none());
+        assert!(heartbeat.publish(secs(2)).is_some());
+        assert!(heartbeat.publish(secs(4)).is_some());
+        assert!(heartbeat.publish(secs(6)).is_some());
+        assert_eq!(None, heartbeat.next_deadline());
+
+        let mut configuration = Configuration::default();
+        *configuration.heartbeat_publication_mut() = publication(0x03, 0x02);
+        heartbeat.write_back(secs(6)).apply(&mut configuration);
+        assert_eq!(0, configuration.heartbeat_publication().count_log);
+    }
+
+    #[test]
+    fn indefinite_publication() {
+        let mut heartbeat = Heartbeat::default();
+        heartbeat.reconfigure(
+            &publication(INDEFINITE_COUNT_LOG, 0x01),
+            &Default::default(),
+            secs(0),
+        );
+
+        for now in 0..100 {
+            assert!(heartbeat.publish(secs(now)).is_some());
+        }
+
+        let write_back = heartbeat.write_back(secs(100));
+        let mut configuration = Configuration::default();
+        write_back.apply(&mut configuration);
+        assert_eq!(
+            INDEFINITE_COUNT_LOG,
+            configuration.heartbeat_publication().count_log
+        );
+    }
+
+    #[test]
+    fn triggered_by_features_of_interest() {
+        let mut heartbeat = Heartbeat::default();
+        // not periodic, only triggered by changes of the relay feature.
+        heartbeat.reconfigure(&publication(0x00, 0x00), &Default::default(), secs(0));
+        assert_eq!(None, heartbeat.next_deadline());
+
+        heartbeat.features_changed(Features::parse(0b0001), Features::parse(0b0011), secs(1));
+        assert_eq!(None, heartbeat.next_deadline());
+
+        heartbeat.features_changed(Features::parse(0b0011), Features::parse(0b0010), secs(1));
+        assert_eq!(Some(secs(1)), heartbeat.next_deadline());
+        assert!(heartbeat.publish(secs(1)).is_some());
+        assert_eq!(None, heartbeat.next_deadline());
+    }
+
+    #[test]
+    fn subscription_statistics() {
+        let mut heartbeat = Heartbeat::default();
+        // subscribed for 4 seconds.
+        heartbeat.reconfigure(&Default::default(), &subscription(0x03), secs(0));
+
+        heartbeat.receive(unicast(0x0B), unicast(0x01).into(), 10, 8, secs(1));
+        heartbeat.receive(unicast(0x0B), unicast(0x01).into(), 10, 10, secs(1));
+        heartbeat.receive(unicast(0x0B), unicast(0x01).into(), 10, 5, secs(2));
+        // other sources and destinations are not accounted for.
+        heartbeat.receive(unicast(0x0C), unicast(0x01).into(), 10, 1, secs(2));
+        heartbeat.receive(unicast(0x0B), Address::parse([0xC0, 0x00]), 10, 1, secs(2));
+
+        let mut configuration = Configuration::default();
+        *configuration.heartbeat_subscription_mut() = subscription(0x03);
+        heartbeat.write_back(secs(2)).apply(&mut configuration);
+
+        let written = configuration.heartbeat_subscription();
+        assert_eq!(0x02, written.period_log);
+        assert_eq!(0x02, written.count_log);
+        assert_eq!(1, written.min_hops);
+        assert_eq!(6, written.max_hops);
+
+        // nothing is accounted for once the period has elapsed.
+        heartbeat.receive(unicast(0x0B), unicast(0x01).into(), 10, 1, secs(4));
+        heartbeat.write_back(secs(4)).apply(&mut configuration);
+
+        let written = configuration.heartbeat_subscription();
+        assert_eq!(0x00, written.period_log);
+        assert_eq!(0x02, written.count_log);
+        assert_eq!(6, written.max_hops);
+        assert_eq!(Address::Unicast(unicast(0x0B)), written.source);
+    }
+
+    #[test]
+    fn saturated_subscription_logs() {
+        let mut heartbeat = Heartbeat::default();
+        let subscribed = HeartbeatSubscription {
+            count_log: 0xFF,
+            ..subscription(0x11)
+        };
+        heartbeat.reconfigure(&Default::default(), &subscribed, secs(0));
+
+        let mut configuration = Configuration::default();
+        *configuration.heartbeat_subscription_mut() = subscribed;
+        heartbeat.write_back(secs(1)).apply(&mut configuration);
+
+        let written = configuration.heartbeat_subscription();
+        assert_eq!(0x10, written.period_log);
+        assert_eq!(0xFF, written.count_log);
+    }
+
+    #[test]
+    fn write_back_yields_to_reconfiguration() {
+        let mut heartbeat = Heartbeat::default();
+        heartbeat.reconfigure(&publication(0x03, 0x02), &Default::default(), secs(0));
+        heartbeat.publish(secs(0));
+
+        // reconfigured in the meantime.
+        let mut configuration = Configuration::default();
+        *configuration.heartbeat_publication_mut() = publication(0x05, 0x01);
+        heartbeat.write_back(secs(0)).apply(&mut configuration);
+        assert_eq!(0x05, configuration.heartbeat_publication().count_log);
+
+        // picked up upon reconfiguration, rather than mistaken for written-back state.
+        heartbeat.reconfigure(
+            configuration.heartbeat_publication(),
+            configuration.heartbeat_subscription(),
+            secs(1),
+        );
+        assert_eq!(Some(secs(1)), heartbeat.next_deadline());
+
+        // while written-back state is not adopted again.
+        heartbeat.publish(secs(1));
+        heartbeat.write_back(secs(1)).apply(&mut configuration);
+        heartbeat.reconfigure(
+            configuration.heartbeat_publication(),
+            configuration.heartbeat_subscription(),
+            secs(1),
+        );
+        assert_eq!(Some(secs(2)), heartbeat.next_deadline());
+    }
+}
@@ -0,0 +1,376 @@
+use crate::stack::provisioned::sequence::Sequence;
+use crate::stack::provisioned::{IvIndexState, IvUpdateTiming, ProvisionedStack};
+use btmesh_common::{IvIndex, IvUpdateFlag};
+use embassy_executor::time::{Duration, Instant};
+
+/// Minimum time spent in either state of the IV update procedure.
+const MINIMUM_STATE_DURATION: Duration = Duration::from_secs(96 * 60 * 60);
+
+/// Minimum time between two IV index recoveries.
+const MINIMUM_RECOVERY_INTERVAL: Duration = Duration::from_secs(192 * 60 * 60);
+
+/// Sequence number beyond which an IV update is initiated, leaving plenty
+/// of room before the sequence number is exhausted.
+const SEQUENCE_THRESHOLD: u32 = 0x800000;
+
+/// Largest jump in IV index accepted from a secure network beacon.
+const MAXIMUM_IV_INDEX_JUMP: u32 = 42;
+
+/// Time spent since some event, including time carried over from before a restart.
+#[derive(Copy, Clone)]
+struct Elapsed {
+    since: Instant,
+    carried: Duration,
+}
+
+impl Elapsed {
+    fn start(now: Instant) -> Self {
+        Self {
+            since: now,
+            carried: Duration::from_secs(0),
+        }
+    }
+
+    fn restore(hours: u16, now: Instant) -> Self {
+        Self {
+            since: now,
+            carried: Duration::from_secs(hours as u64 * 60 * 60),
+        }
+    }
+
+    fn at(&self, now: Instant) -> Duration {
+        self.carried + (now - self.since)
+    }
+
+    /// Whole hours elapsed, saturating once no timer cares any longer.
+    fn hours(&self, now: Instant) -> u16 {
+        let hours = self.at(now).as_secs() / (60 * 60);
+        hours.min(MINIMUM_RECOVERY_INTERVAL.as_secs() / (60 * 60)) as u16
+    }
+}
+
+/// Timing of the IV update procedure.
+///
+/// Durations are persisted as whole hours, so time lost to a restart
+/// errs on the side of staying longer in each state.
+pub struct IvUpdate {
+    state: Elapsed,
+    last_recovery: Option<Elapsed>,
+}
+
+impl IvUpdate {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            state: Elapsed::start(now),
+            last_recovery: None,
+        }
+    }
+
+    /// Resume the procedure with timing persisted before a restart.
+    pub fn restore(timing: &IvUpdateTiming, now: Instant) -> Self {
+        Self {
+            state: Elapsed::restore(timing.state_hours, now),
+            last_recovery: timing
+                .recovery_hours
+                .map(|hours| Elapsed::restore(hours, now)),
+        }
+    }
+
+    /// Timing to persist, so the procedure survives a restart.
+    pub fn timing(&self, now: Instant) -> IvUpdateTiming {
+        IvUpdateTiming {
+            state_hours: self.state.hours(now),
+            recovery_hours: self.last_recovery.map(|elapsed| elapsed.hours(now)),
+        }
+    }
+
+    fn minimum_duration_elapsed(&self, now: Instant) -> bool {
+        self.state.at(now) >= MINIMUM_STATE_DURATION
+    }
+
+    fn transition(
+        &mut self,
+        state: &mut IvIndexState,
+        iv_index: IvIndex,
+        iv_update_flag: IvUpdateFlag,
+        now: Instant,
+    ) {
+        info!(
+            "iv index {} -> {}, iv update {} -> {}",
+            state.iv_index, iv_index, state.iv_update_flag, iv_update_flag
+        );
+        state.iv_index = iv_index;
+        state.iv_update_flag = iv_update_flag;
+        self.state = Elapsed::start(now);
+    }
+
+    /// Move through the procedure as time passes and sequence numbers are used up.
+    ///
+    /// Returns `true` if the sequence number must be reset.
+    pub fn tick(&mut self, state: &mut IvIndexState, seq: u32, now: Instant) -> bool {
+        if !self.minimum_duration_elapsed(now) {
+            return false;
+        }
+
+        match state.iv_update_flag {
+            IvUpdateFlag::Normal if seq >= SEQUENCE_THRESHOLD => {
+                let iv_index = state.iv_index + 1;
+                self.transition(state, iv_index, IvUpdateFlag::InProgress, now);
+                false
+            }
+            IvUpdateFlag::Normal => false,
+            IvUpdateFlag::InProgress => {
+                let iv_index = state.iv_index;
+                self.transition(state, iv_index, IvUpdateFlag::Normal, now);
+                true
+            }
+        }
+    }
+
+    /// Follow the IV index state conveyed by an authenticated secure network beacon.
+    ///
+    /// Returns `true` if the sequence number must be reset.
+    pub fn beacon(
+        &mut self,
+        state: &mut IvIndexState,
+        iv_index: IvIndex,
+        iv_update_flag: IvUpdateFlag,
+        now: Instant,
+    ) -> bool {
+        let current = state.iv_index.value();
+        let received = iv_index.value();
+
+        if received < current || received > current + MAXIMUM_IV_INDEX_JUMP {
+            return false;
+        }
+
+        match (state.iv_update_flag, iv_update_flag) {
+            (IvUpdateFlag::Normal, IvUpdateFlag::InProgress) if received == current + 1 => {
+                if self.minimum_duration_elapsed(now) {
+                    self.transition(state, iv_index, IvUpdateFlag::InProgress, now);
+                }
+                false
+            }
+            (IvUpdateFlag::InProgress, IvUpdateFlag::Normal) if received == current => {
+                if self.minimum_duration_elapsed(now) {
+                    self.transition(state, iv_index, IvUpdateFlag::Normal, now);
+                    true
+                } else {
+                    false
+                }
+            }
+            _ if received > current => self.recover(state, iv_index, iv_update_flag, now),
+            _ => false,
+        }
+    }
+
+    /// Catch up with the rest of the network after having missed an IV update.
+    fn recover(
+        &mut self,
+        state: &mut IvIndexState,
+        iv_index: IvIndex,
+        iv_update_flag: IvUpdateFlag,
+        now: Instant,
+    ) -> bool {
+        if let Some(last_recovery) = self.last_recovery {
+            if last_recovery.at(now) < MINIMUM_RECOVERY_INTERVAL {
+                return false;
+            }
+        }
+
+        info!("iv index recovery");
+        self.transition(state, iv_index, iv_update_flag, now);
+        self.last_recovery.replace(Elapsed::start(now));
+        true
+    }
+}
+
+impl ProvisionedStack {
+    /// Advance the IV update procedure, initiating it when the sequence
+    /// number nears exhaustion.
+    pub fn update_iv_index(&mut self, sequence: &Sequence) {
+        if self.iv_update.tick(
+            &mut self.network_state.iv_index_state,
+            sequence.current(),
+            Instant::now(),
+        ) {
+            sequence.reset();
+        }
+    }
+
+    pub(crate) fn process_beacon_iv_index(
+        &mut self,
+        sequence: &Sequence,
+        iv_index: IvIndex,
+        iv_update_flag: IvUpdateFlag,
+    ) {
+        if self.iv_update.beacon(
+            &mut self.network_state.iv_index_state,
+            iv_index,
+            iv_update_flag,
+            Instant::now(),
+        ) {
+            sequence.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::stack::provisioned::iv_update::{IvUpdate, SEQUENCE_THRESHOLD};
+    use crate::stack::provisioned::{IvIndexState, IvUpdateTiming};
+    use btmesh_common::{IvIndex, IvUpdateFlag};
+    use embassy_executor::time::{Duration, Instant};
+
+    fn hours(hours: u64) -> Instant {
+        Instant::from_secs(hours * 60 * 60)
+    }
+
+    fn state(iv_index: u32, iv_update_flag: IvUpdateFlag) -> IvIndexState {
+        IvIndexState::new(IvIndex::new(iv_index), iv_update_flag)
+    }
+
+    fn assert_state(state: &IvIndexState, iv_index: u32, iv_update_flag: IvUpdateFlag) {
+        assert_eq!(IvIndex::new(iv_index), state.iv_index());
+        assert_eq!(iv_update_flag, state.iv_update_flag());
+    }
+
+    #[test]
+    fn initiated_when_sequence_nears_exhaustion() {
+        let mut iv_update = IvUpdate::new(hours(0));
+        let mut state = state(5, IvUpdateFlag::Normal);
+
+        // not before having been in normal operation for 96 hours.
+        assert!(!iv_update.tick(&mut state, SEQUENCE_THRESHOLD, hours(95)));
+        assert_state(&state, 5, IvUpdateFlag::Normal);
+
+        assert!(!iv_update.tick(&mut state, 100, hours(100)));
+        assert_state(&state, 5, IvUpdateFlag::Normal);
+
+        assert!(!iv_update.tick(&mut state, SEQUENCE_THRESHOLD, hours(101)));
+        assert_state(&state, 6, IvUpdateFlag::InProgress);
+        assert_eq!(IvIndex::new(5), state.transmission_iv_index());
+
+        assert!(!iv_update.tick(&mut state, SEQUENCE_THRESHOLD, hours(196)));
+        assert_state(&state, 6, IvUpdateFlag::InProgress);
+
+        // completing the procedure resets the sequence number.
+        assert!(iv_update.tick(&mut state, SEQUENCE_THRESHOLD, hours(197)));
+        assert_state(&state, 6, IvUpdateFlag::Normal);
+        assert_eq!(IvIndex::new(6), state.transmission_iv_index());
+    }
+
+    #[test]
+    fn follows_beacons() {
+        let mut iv_update = IvUpdate::new(hours(0));
+        let mut state = state(5, IvUpdateFlag::Normal);
+
+        assert!(!iv_update.beacon(
+            &mut state,
+            IvIndex::new(6),
+            IvUpdateFlag::InProgress,
+            hours(10)
+        ));
+        assert_state(&state, 5, IvUpdateFlag::Normal);
+
+        assert!(!iv_update.beacon(
+            &mut state,
+            IvIndex::new(6),
+            IvUpdateFlag::InProgress,
+            hours(100)
+        ));
+        assert_state(&state, 6, IvUpdateFlag::InProgress);
+
+        assert!(!iv_update.beacon(
+            &mut state,
+            IvIndex::new(6),
+            IvUpdateFlag::Normal,
+            hours(150)
+        ));
+        assert_state(&state, 6, IvUpdateFlag::InProgress);
+
+        assert!(iv_update.beacon(
+            &mut state,
+            IvIndex::new(6),
+            IvUpdateFlag::Normal,
+            hours(196)
+        ));
+        assert_state(&state, 6, IvUpdateFlag::Normal);
+    }
+
+    #[test]
+    fn recovery() {
+        let mut iv_update = IvUpdate::new(hours(0));
+        let mut state = state(5, IvUpdateFlag::Normal);
+
+        // stale and implausible IV indexes are ignored.
+        assert!(!iv_update.beacon(&mut state, IvIndex::new(4), IvUpdateFlag::Normal, hours(1)));
+        assert!(!iv_update.beacon(&mut state, IvIndex::new(48), IvUpdateFlag::Normal, hours(1)));
+        assert_state(&state, 5, IvUpdateFlag::Normal);
+
+        assert!(iv_update.beacon(&mut state, IvIndex::new(9), IvUpdateFlag::Normal, hours(1)));
+        assert_state(&state, 9, IvUpdateFlag::Normal);
+
+        // at most one recovery every 192 hours.
+        assert!(!iv_update.beacon(&mut state, IvIndex::new(12), IvUpdateFlag::Normal, hours(2)));
+        assert_state(&state, 9, IvUpdateFlag::Normal);
+
+        assert!(iv_update.beacon(
+            &mut state,
+            IvIndex::new(12),
+            IvUpdateFlag::Normal,
+            hours(193)
+        ));
+        assert_state(&state, 12, IvUpdateFlag::Normal);
+    }
+
+    #[test]
+    fn restored_timing() {
+        let mut iv_update = IvUpdate::new(hours(0));
+        let mut state = state(5, IvUpdateFlag::Normal);
+
+        assert!(iv_update.beacon(&mut state, IvIndex::new(9), IvUpdateFlag::Normal, hours(1)));
+        assert_eq!(
+            IvUpdateTiming {
+                state_hours: 60,
+                recovery_hours: Some(60),
+            },
+            iv_update.timing(hours(61) + Duration::from_secs(59 * 60))
+        );
+
+        // restarted, with the clock starting over.
+        let mut iv_update = IvUpdate::restore(&iv_update.timing(hours(61)), hours(0));
+
+        assert!(!iv_update.tick(&mut state, SEQUENCE_THRESHOLD, hours(35)));
+        assert_state(&state, 9, IvUpdateFlag::Normal);
+
+        assert!(!iv_update.tick(&mut state, SEQUENCE_THRESHOLD, hours(36)));
+        assert_state(&state, 10, IvUpdateFlag::InProgress);
+
+        // the recovery interval carried over as well.
+        assert!(!iv_update.beacon(
+            &mut state,
+            IvIndex::new(14),
+            IvUpdateFlag::Normal,
+            hours(131)
+        ));
+        assert_state(&state, 10, IvUpdateFlag::InProgress);
+
+        assert!(iv_update.beacon(
+            &mut state,
+            IvIndex::new(14),
+            IvUpdateFlag::Normal,
+            hours(132)
+        ));
+        assert_state(&state, 14, IvUpdateFlag::Normal);
+
+        // saturates once the timers no longer matter.
+        assert_eq!(
+            IvUpdateTiming {
+                state_hours: 192,
+                recovery_hours: Some(192),
+            },
+            iv_update.timing(hours(1000))
+        );
+    }
+}
@@ -0,0 +1,1011 @@
+use crate::stack::provisioned::network::Credentials;
+use crate::stack::provisioned::sequence::Sequence;
+use crate::stack::provisioned::{DriverError, ProvisionedStack};
+use crate::storage::provisioned::ProvisionedConfiguration;
+use btmesh_common::address::{Address, GroupAddress, UnicastAddress};
+use btmesh_common::crypto::network::NetworkKey;
+use btmesh_device::NetworkKeyHandle;
+use btmesh_pdu::provisioned::control::ControlMessage;
+use btmesh_pdu::provisioned::friend::{
+    FriendCriteria, FriendMessage, FriendOffer, FriendRequest, FriendSubscriptionList,
+    MAX_SUBSCRIPTION_LIST_ADDRESSES,
+};
+use btmesh_pdu::provisioned::network::NetworkPDU;
+use core::cmp::Reverse;
+use embassy_executor::time::{Duration, Instant};
+use heapless::Vec;
+
+/// Group and virtual addresses kept in sync with the subscription list of the friend.
+const SUBSCRIPTION_LIST_SIZE: usize = 16;
+
+/// Friend offers considered in response to a friend request.
+const MAX_OFFERS: usize = 4;
+
+/// Friend offers are received from 100 milliseconds up to 1 second after the friend request.
+const OFFER_DELAY: Duration = Duration::from_millis(100);
+const OFFER_WINDOW_END: Duration = Duration::from_secs(1);
+
+/// Time between friend requests, while no friend is found.
+const SEARCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Times a message awaiting a response from the friend is sent before
+/// the friendship is considered lost.
+const TRANSMISSIONS: u8 = 3;
+
+/// Shortest receive delay, in milliseconds.
+const MIN_RECEIVE_DELAY: u8 = 10;
+
+/// Parameters of the friendships established by a low power node.
+#[derive(Copy, Clone, Debug)]
+pub struct LowPowerConfig {
+    pub criteria: FriendCriteria,
+    /// Delay between a friend poll and the response, in milliseconds, at least 10.
+    pub receive_delay: u8,
+    /// Longest interval between two friend polls before the friend terminates
+    /// the friendship, in units of 100 milliseconds.
+    pub poll_timeout: u32,
+    /// Interval between two friend polls, well within the poll timeout.
+    pub poll_interval: Duration,
+}
+
+impl LowPowerConfig {
+    /// Check the receive delay is at least 10 milliseconds, and friend polls
+    /// are due before the friend would terminate the friendship.
+    pub fn validate(&self) -> Result<(), DriverError> {
+        if self.receive_delay < MIN_RECEIVE_DELAY {
+            warn!("receive delay below {} milliseconds", MIN_RECEIVE_DELAY);
+            return Err(DriverError::InvalidFormat);
+        }
+        if self.poll_interval >= Duration::from_millis(self.poll_timeout as u64 * 100) {
+            warn!("poll interval not within the poll timeout");
+            return Err(DriverError::InvalidFormat);
+        }
+        Ok(())
+    }
+}
+
+impl Default for LowPowerConfig {
+    fn default() -> Self {
+        Self {
+            criteria: FriendCriteria {
+                rssi_factor: 0,
+                receive_window_factor: 0,
+                min_queue_size_log: 3,
+            },
+            receive_delay: 100,
+            // 30 seconds.
+            poll_timeout: 300,
+            poll_interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Message sent to the friend, awaiting its response.
+#[derive(Clone)]
+enum Request {
+    Poll,
+    SubscriptionListAdd(FriendSubscriptionList),
+    SubscriptionListRemove(FriendSubscriptionList),
+}
+
+struct Pending {
+    request: Request,
+    sent: Instant,
+    /// Transmissions left before the friendship is considered lost.
+    transmissions: u8,
+}
+
+struct Friendship {
+    friend_address: UnicastAddress,
+    network_key_handle: NetworkKeyHandle,
+    /// Friendship security credentials, derived from the master credentials.
+    credentials: NetworkKey,
+    receive_delay: Duration,
+    receive_window: Duration,
+    subscription_list_size: usize,
+    established: bool,
+    fsn: bool,
+    /// Addresses the friend has confirmed storing messages for.
+    subscribed: Vec<Address, SUBSCRIPTION_LIST_SIZE>,
+    transaction_number: u8,
+    /// When the next friend poll is due, unless awaiting a response.
+    next_poll: Instant,
+    pending: Option<Pending>,
+}
+
+impl Friendship {
+    /// When the response to the pending message is expected.
+    fn receive_window(&self) -> Option<(Instant, Instant)> {
+        self.pending.as_ref().map(|pending| {
+            let start = pending.sent + self.receive_delay;
+            (start, start + self.receive_window)
+        })
+    }
+
+    fn awaiting_poll_response(&self) -> bool {
+        matches!(
+            self.pending,
+            Some(Pending {
+                request: Request::Poll,
+                ..
+            })
+        )
+    }
+
+    fn friendship_credentials(&self) -> Credentials {
+        Credentials::Friendship(self.network_key_handle, self.credentials)
+    }
+
+    fn message(&self, request: &Request) -> FriendMessage {
+        match request {
+            Request::Poll => FriendMessage::Poll { fsn: self.fsn },
+            Request::SubscriptionListAdd(list) => FriendMessage::SubscriptionListAdd(list.clone()),
+            Request::SubscriptionListRemove(list) => {
+                FriendMessage::SubscriptionListRemove(list.clone())
+            }
+        }
+    }
+
+    fn send(&mut self, request: Request, now: Instant) -> LowPowerTransmission {
+        let message = self.message(&request);
+        self.pending = Some(Pending {
+            request,
+            sent: now,
+            transmissions: TRANSMISSIONS - 1,
+        });
+        (
+            self.friendship_credentials(),
+            self.friend_address.into(),
+            message,
+        )
+    }
+
+    /// The friend responded to the last friend poll, possibly having more messages stored.
+    fn poll_response(&mut self, now: Instant) {
+        if !self.established {
+            info!("friendship established with {}", self.friend_address);
+            self.established = true;
+        }
+        self.fsn = !self.fsn;
+        self.pending.take();
+        self.next_poll = now;
+    }
+
+    fn subscription_list(
+        &self,
+        addresses: impl Iterator<Item = Address>,
+    ) -> FriendSubscriptionList {
+        FriendSubscriptionList {
+            transaction_number: self.transaction_number,
+            addresses: addresses.take(MAX_SUBSCRIPTION_LIST_ADDRESSES).collect(),
+        }
+    }
+
+    /// Addresses to remove from, or else to add to, the subscription list of the friend.
+    fn subscription_list_update(&self, subscriptions: &[Address]) -> Option<Request> {
+        let removed = self.subscription_list(
+            self.subscribed
+                .iter()
+                .filter(|address| !subscriptions.contains(address))
+                .cloned(),
+        );
+        if !removed.addresses.is_empty() {
+            return Some(Request::SubscriptionListRemove(removed));
+        }
+
+        let room = self
+            .subscription_list_size
+            .saturating_sub(self.subscribed.len());
+        let added = self.subscription_list(
+            subscriptions
+                .iter()
+                .filter(|address| !self.subscribed.contains(address))
+                .take(room)
+                .cloned(),
+        );
+        if !added.addresses.is_empty() {
+            return Some(Request::SubscriptionListAdd(added));
+        }
+
+        None
+    }
+
+    fn subscription_list_confirm(&mut self, transaction_number: u8) {
+        match self.pending.take() {
+            Some(Pending {
+                request: Request::SubscriptionListAdd(list),
+                ..
+            }) if list.transaction_number == transaction_number => {
+                for address in list.addresses {
+                    if !self.subscribed.contains(&address) {
+                        self.subscribed.push(address).ok();
+                    }
+                }
+            }
+            Some(Pending {
+                request: Request::SubscriptionListRemove(list),
+                ..
+            }) if list.transaction_number == transaction_number => {
+                for address in list.addresses {
+                    if let Some(index) = self.subscribed.iter().position(|e| *e == address) {
+                        self.subscribed.swap_remove(index);
+                    }
+                }
+            }
+            pending => {
+                // not the confirmation awaited.
+                self.pending = pending;
+                return;
+            }
+        }
+        self.transaction_number = self.transaction_number.wrapping_add(1);
+    }
+}
+
+enum State {
+    /// Looking for a friend, the next friend request being due at the instant given.
+    Searching(Instant),
+    /// Collecting the offers of friends in response to a friend request.
+    Requested {
+        network_key_handle: NetworkKeyHandle,
+        lpn_counter: u16,
+        sent: Instant,
+        offers: Vec<(UnicastAddress, FriendOffer), MAX_OFFERS>,
+    },
+    Friendship(Friendship),
+}
+
+/// A friend message to send to the address given.
+type LowPowerTransmission = (Credentials, Address, FriendMessage);
+
+/// Friendship of a low power node, which only listens while awaiting a
+/// response from its friend, polling it for the messages stored in the meantime.
+///
+/// Friend requests are sent using the master security credentials, and
+/// everything else exchanged with the friend using its friendship credentials.
+pub struct LowPower {
+    config: LowPowerConfig,
+    lpn_counter: u16,
+    state: State,
+    /// Friend of the friendship last lost, which the next friend clears.
+    previous_address: Option<UnicastAddress>,
+    /// Group and virtual addresses subscribed to, for the friend to store messages for.
+    subscriptions: Vec<Address, SUBSCRIPTION_LIST_SIZE>,
+}
+
+impl LowPower {
+    pub fn new(config: LowPowerConfig, now: Instant) -> Self {
+        Self {
+            config,
+            lpn_counter: 0,
+            state: State::Searching(now),
+            previous_address: None,
+            subscriptions: Vec::new(),
+        }
+    }
+
+    /// Replace the parameters of future friendships.
+    pub fn set_config(&mut self, config: LowPowerConfig) {
+        self.config = config;
+    }
+
+    pub fn set_subscriptions(&mut self, subscriptions: impl Iterator<Item = Address>) {
+        self.subscriptions.clear();
+        for address in subscriptions {
+            if !self.subscriptions.contains(&address) && self.subscriptions.push(address).is_err() {
+                warn!("too many subscriptions for the friend subscription list");
+                break;
+            }
+        }
+    }
+
+    pub fn is_established(&self) -> bool {
+        matches!(&self.state, State::Friendship(friendship) if friendship.established)
+    }
+
+    pub fn is_friend(&self, address: UnicastAddress) -> bool {
+        matches!(&self.state, State::Friendship(friendship) if friendship.friend_address == address)
+    }
+
+    /// The subnet friend messages are exchanged on, once a friend request has been sent.
+    pub fn network_key_handle(&self) -> Option<NetworkKeyHandle> {
+        match &self.state {
+            State::Searching(_) => None,
+            State::Requested {
+                network_key_handle, ..
+            } => Some(*network_key_handle),
+            State::Friendship(friendship) => Some(friendship.network_key_handle),
+        }
+    }
+
+    /// Friendship credentials of the friendship, once an offer has been accepted.
+    pub fn credentials(&self) -> Option<(NetworkKeyHandle, NetworkKey)> {
+        match &self.state {
+            State::Friendship(friendship) => {
+                Some((friendship.network_key_handle, friendship.credentials))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the radio should be listening. Until a friendship is established
+    /// the node keeps listening, and afterwards only while awaiting a response from its friend.
+    pub fn is_listening(&self, now: Instant) -> bool {
+        match &self.state {
+            State::Friendship(friendship) if friendship.established => {
+                matches!(friendship.receive_window(), Some((start, end)) if now >= start && now < end)
+            }
+            _ => true,
+        }
+    }
+
+    pub fn next_deadline(&self, now: Instant) -> Option<Instant> {
+        Some(match &self.state {
+            State::Searching(deadline) => *deadline,
+            State::Requested { sent, .. } => *sent + OFFER_WINDOW_END,
+            State::Friendship(friendship) => {
+                if let Some((start, end)) = friendship.receive_window() {
+                    // wake up to start listening, as well as once the response is overdue.
+                    if now < start {
+                        start
+                    } else {
+                        end
+                    }
+                } else if friendship
+                    .subscription_list_update(&self.subscriptions)
+                    .is_some()
+                {
+                    now
+                } else {
+                    friendship.next_poll
+                }
+            }
+        })
+    }
+
+    /// A network PDU destined to this node has been received, which while awaiting
+    /// the response to a friend poll is a message the friend stored, provided it
+    /// was secured using the friendship credentials.
+    ///
+    /// Stored messages keep the source address of their originator, so only the
+    /// friendship credentials tell them apart from messages heard directly.
+    pub fn receive_network_pdu(&mut self, friendship_credentials: bool, now: Instant) {
+        if !friendship_credentials {
+            return;
+        }
+        if let State::Friendship(friendship) = &mut self.state {
+            if matches!(friendship.receive_window(), Some((start, _)) if now >= start)
+                && friendship.awaiting_poll_response()
+            {
+                friendship.poll_response(now);
+            }
+        }
+    }
+
+    /// Process a friend message received from `src`.
+    pub fn receive(&mut self, src: UnicastAddress, message: FriendMessage, now: Instant) {
+        match (&mut self.state, message) {
+            (State::Requested { sent, offers, .. }, FriendMessage::Offer(offer)) => {
+                if now >= *sent + OFFER_DELAY
+                    && now <= *sent + OFFER_WINDOW_END
+                    && offers.push((src, offer)).is_err()
+                {
+                    debug!("ignoring friend offer from {}", src);
+                }
+            }
+            (State::Friendship(friendship), FriendMessage::Update(update))
+                if friendship.friend_address == src =>
+            {
+                if friendship.awaiting_poll_response() {
+                    friendship.poll_response(now);
+                }
+                if !update.more_data {
+                    friendship.next_poll = now + self.config.poll_interval;
+                }
+            }
+            (
+                State::Friendship(friendship),
+                FriendMessage::SubscriptionListConfirm { transaction_number },
+            ) if friendship.friend_address == src => {
+                friendship.subscription_list_confirm(transaction_number);
+            }
+            _ => {
+                // not applicable to the low power role, or not from the friend.
+            }
+        }
+    }
+
+    /// The network key of the subnet friend messages are exchanged on is gone,
+    /// so start over looking for a friend.
+    pub fn network_key_lost(&mut self, now: Instant) {
+        if let State::Friendship(friendship) = &self.state {
+            if friendship.established {
+                self.previous_address = Some(friendship.friend_address);
+            }
+        }
+        self.state = State::Searching(now);
+    }
+
+    /// Select the next friend message due, moving on to the best friend offer
+    /// once the offers are in, and starting over once the friendship is lost.
+    ///
+    /// `network_key` is the master key of the subnet identified by `network_key_handle`.
+    pub fn next_transmission(
+        &mut self,
+        lpn_address: UnicastAddress,
+        num_elements: u8,
+        network_key_handle: NetworkKeyHandle,
+        network_key: &NetworkKey,
+        now: Instant,
+    ) -> Option<LowPowerTransmission> {
+        match &mut self.state {
+            State::Searching(deadline) => {
+                if *deadline > now {
+                    return None;
+                }
+                let lpn_counter = self.lpn_counter;
+                self.lpn_counter = self.lpn_counter.wrapping_add(1);
+                self.state = State::Requested {
+                    network_key_handle,
+                    lpn_counter,
+                    sent: now,
+                    offers: Vec::new(),
+                };
+                Some((
+                    Credentials::Master(network_key_handle),
+                    GroupAddress::AllFriends.into(),
+                    FriendMessage::Request(FriendRequest {
+                        criteria: self.config.criteria,
+                        receive_delay: self.config.receive_delay,
+                        poll_timeout: self.config.poll_timeout,
+                        previous_address: self.previous_address,
+                        num_elements,
+                        lpn_counter,
+                    }),
+                ))
+            }
+            State::Requested {
+                lpn_counter,
+                sent,
+                offers,
+                ..
+            } => {
+                if *sent + OFFER_WINDOW_END > now {
+                    return None;
+                }
+                // the shortest receive window keeps the radio off the longest.
+                let (friend_address, offer) = if let Some(best) =
+                    offers.iter().max_by_key(|(_, offer)| {
+                        (Reverse(offer.receive_window), offer.rssi, offer.queue_size)
+                    }) {
+                    *best
+                } else {
+                    debug!("no friend offers");
+                    self.state = State::Searching(now + SEARCH_INTERVAL);
+                    return None;
+                };
+
+                let credentials = if let Ok(credentials) = network_key.friendship_credentials(
+                    lpn_address,
+                    friend_address,
+                    *lpn_counter,
+                    offer.friend_counter,
+                ) {
+                    credentials
+                } else {
+                    warn!("unable to derive friendship credentials");
+                    self.state = State::Searching(now + SEARCH_INTERVAL);
+                    return None;
+                };
+
+                let mut friendship = Friendship {
+                    friend_address,
+                    network_key_handle,
+                    credentials,
+                    receive_delay: Duration::from_millis(self.config.receive_delay as u64),
+                    receive_window: Duration::from_millis(offer.receive_window as u64),
+                    subscription_list_size: offer.subscription_list_size as usize,
+                    established: false,
+                    fsn: false,
+                    subscribed: Vec::new(),
+                    transaction_number: 0,
+                    next_poll: now,
+                    pending: None,
+                };
+                // the first friend poll accepts the offer.
+                let transmission = friendship.send(Request::Poll, now);
+                self.state = State::Friendship(friendship);
+                Some(transmission)
+            }
+            State::Friendship(friendship) => {
+                if let Some((_, end)) = friendship.receive_window() {
+                    if end > now {
+                        return None;
+                    }
+                    if let Some(pending) = &mut friendship.pending {
+                        if pending.transmissions > 0 {
+                            pending.transmissions -= 1;
+                            pending.sent = now;
+                            let request = pending.request.clone();
+                            return Some((
+                                friendship.friendship_credentials(),
+                                friendship.friend_address.into(),
+                                friendship.message(&request),
+                            ));
+                        }
+                    }
+
+                    if friendship.established {
+                        warn!("friendship with {} lost", friendship.friend_address);
+                        self.previous_address = Some(friendship.friend_address);
+                        self.state = State::Searching(now);
+                    } else {
+                        debug!("friend {} did not respond", friendship.friend_address);
+                        self.state = State::Searching(now + SEARCH_INTERVAL);
+                    }
+                    return None;
+                }
+
+                if let Some(request) = friendship.subscription_list_update(&self.subscriptions) {
+                    return Some(friendship.send(request, now));
+                }
+
+                if friendship.next_poll <= now {
+                    return Some(friendship.send(Request::Poll, now));
+                }
+
+                None
+            }
+        }
+    }
+}
+
+impl ProvisionedStack {
+    /// Replace the parameters of the friendships established by the low power node.
+    pub fn set_low_power_config(&mut self, config: LowPowerConfig) {
+        self.low_power.set_config(config);
+    }
+
+    pub fn next_low_power_deadline(&self) -> Option<Instant> {
+        self.low_power.next_deadline(Instant::now())
+    }
+
+    /// Whether the node should be listening, which once befriended is only
+    /// while awaiting a response from the friend.
+    pub fn low_power_listening(&self) -> bool {
+        self.low_power.is_listening(Instant::now())
+    }
+
+    /// Keep the subscription list of the friend in line with the subscriptions of the models.
+    pub(crate) fn sync_low_power_subscriptions(&mut self, content: &ProvisionedConfiguration) {
+        self.low_power.set_subscriptions(
+            content
+                .foundation()
+                .configuration()
+                .subscriptions()
+                .all_addresses_iter()
+                .map(Address::from)
+                .filter(|address| *address != Address::Unassigned),
+        );
+    }
+
+    pub(crate) fn process_inbound_low_power_message(
+        &mut self,
+        sequence: &Sequence,
+        message: &ControlMessage<ProvisionedStack>,
+    ) {
+        let friend_message = match FriendMessage::parse(message.opcode(), message.parameters()) {
+            Ok(friend_message) => friend_message,
+            Err(_) => {
+                warn!("ignoring malformed friend message");
+                return;
+            }
+        };
+
+        let src = message.meta().src();
+        if let FriendMessage::Update(update) = &friend_message {
+            // the friend keeps the low power node abreast of IV updates.
+            if self.low_power.is_friend(src) {
+                self.process_beacon_iv_index(sequence, update.iv_index, update.iv_update_flag);
+            }
+        }
+
+        let features = self.features();
+        let now = Instant::now();
+        self.low_power.receive(src, friend_message, now);
+        self.heartbeat
+            .features_changed(features, self.features(), now);
+    }
+
+    /// Produce the network PDUs due to the friend, or to prospective friends.
+    pub fn process_low_power(
+        &mut self,
+        sequence: &Sequence,
+    ) -> Result<Vec<NetworkPDU, 4>, DriverError> {
+        let mut network_pdus = Vec::new();
+
+        let device_info = self.network.device_info();
+        let lpn_address = device_info
+            .local_element_address(0)
+            .ok_or(DriverError::InvalidState)?;
+
+        // friends are looked for on the first subnet.
+        let network_key_handle = if let Some(network_key_handle) =
+            self.low_power.network_key_handle().or_else(|| {
+                self.secrets
+                    .network_keys()
+                    .iter()
+                    .next()
+                    .map(|(slot, entry)| NetworkKeyHandle(slot, entry.transmit_key().nid()))
+            }) {
+            network_key_handle
+        } else {
+            return Ok(network_pdus);
+        };
+        let features = self.features();
+        let now = Instant::now();
+        let network_key = if let Ok(network_key) = self.secrets.network_key(network_key_handle) {
+            network_key
+        } else {
+            warn!("network key of the friendship removed, looking for a friend");
+            self.low_power.network_key_lost(now);
+            self.heartbeat
+                .features_changed(features, self.features(), now);
+            return Ok(network_pdus);
+        };
+        // a transmission taken is sent, so only take those there is room for;
+        // the remainder is picked up right away.
+        while !network_pdus.is_full() {
+            let (credentials, dst, message) = match self.low_power.next_transmission(
+                lpn_address,
+                device_info.number_of_elements(),
+                network_key_handle,
+                &network_key,
+                now,
+            ) {
+                Some(transmission) => transmission,
+                None => break,
+            };
+            let network_pdu = self.friend_message_pdu(sequence, credentials, dst, &message)?;
+            network_pdus.push(network_pdu).ok();
+        }
+        self.heartbeat
+            .features_changed(features, self.features(), now);
+
+        Ok(network_pdus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stack::provisioned::low_power::{
+        LowPower, LowPowerConfig, OFFER_WINDOW_END, SEARCH_INTERVAL, TRANSMISSIONS,
+    };
+    use crate::stack::provisioned::network::Credentials;
+    use btmesh_common::address::{Address, GroupAddress, UnicastAddress};
+    use btmesh_common::crypto::network::{NetworkKey, Nid};
+    use btmesh_common::{IvIndex, IvUpdateFlag, KeyRefreshFlag};
+    use btmesh_device::NetworkKeyHandle;
+    use btmesh_pdu::provisioned::friend::{FriendMessage, FriendOffer, FriendUpdate};
+    use embassy_executor::time::{Duration, Instant};
+
+    fn network_key_handle() -> NetworkKeyHandle {
+        NetworkKeyHandle(0, Nid::new(42))
+    }
+
+    fn network_key() -> NetworkKey {
+        NetworkKey::new([
+            0x7d, 0xd7, 0x36, 0x4c, 0xd8, 0x42, 0xad, 0x18, 0xc1, 0x7c, 0x2b, 0x82, 0x0c, 0x84,
+            0xc3, 0xd6,
+        ])
+        .unwrap()
+    }
+
+    fn lpn() -> UnicastAddress {
+        UnicastAddress::parse([0x00, 0x20]).unwrap()
+    }
+
+    fn friend() -> UnicastAddress {
+        UnicastAddress::parse([0x00, 0x30]).unwrap()
+    }
+
+    fn millis(millis: u64) -> Instant {
+        Instant::from_millis(millis)
+    }
+
+    fn next(low_power: &mut LowPower, now: Instant) -> Option<(Address, FriendMessage)> {
+        low_power
+            .next_transmission(lpn(), 2, network_key_handle(), &network_key(), now)
+            .map(|(_, dst, message)| (dst, message))
+    }
+
+    fn offer(receive_window: u8, friend_counter: u16) -> FriendMessage {
+        FriendMessage::Offer(FriendOffer {
+            receive_window,
+            queue_size: 16,
+            subscription_list_size: 8,
+            rssi: i8::MAX,
+            friend_counter,
+        })
+    }
+
+    fn update(more_data: bool) -> FriendMessage {
+        FriendMessage::Update(FriendUpdate {
+            key_refresh_flag: KeyRefreshFlag(false),
+            iv_update_flag: IvUpdateFlag::Normal,
+            iv_index: IvIndex::new(0),
+            more_data,
+        })
+    }
+
+    /// Befriended at 1 second, the friend poll having been answered at 1.2 seconds.
+    fn established() -> LowPower {
+        let mut low_power = LowPower::new(Default::default(), millis(0));
+        next(&mut low_power, millis(0)).unwrap();
+        low_power.receive(friend(), offer(100, 5), millis(200));
+        next(&mut low_power, millis(1000)).unwrap();
+        low_power.receive(friend(), update(false), millis(1200));
+        low_power
+    }
+
+    #[test]
+    fn best_offer_accepted() {
+        let mut low_power = LowPower::new(Default::default(), millis(0));
+
+        if let Some((dst, FriendMessage::Request(request))) = next(&mut low_power, millis(0)) {
+            assert_eq!(Address::Group(GroupAddress::AllFriends), dst);
+            assert_eq!(None, request.previous_address);
+            assert_eq!(2, request.num_elements);
+            assert_eq!(0, request.lpn_counter);
+        } else {
+            panic!("expected a friend request");
+        }
+
+        let other = UnicastAddress::parse([0x00, 0x31]).unwrap();
+        low_power.receive(other, offer(200, 3), millis(150));
+        low_power.receive(friend(), offer(100, 5), millis(300));
+
+        assert_eq!(Some(millis(1000)), low_power.next_deadline(millis(300)));
+        assert!(next(&mut low_power, millis(999)).is_none());
+
+        // the offer with the shorter receive window wins.
+        assert_eq!(
+            Some((friend().into(), FriendMessage::Poll { fsn: false })),
+            next(&mut low_power, millis(1000))
+        );
+        assert!(low_power.is_friend(friend()));
+        assert!(!low_power.is_established());
+
+        if let Some((_, credentials)) = low_power.credentials() {
+            assert_eq!(
+                network_key()
+                    .friendship_credentials(lpn(), friend(), 0, 5)
+                    .unwrap(),
+                credentials
+            );
+        } else {
+            panic!("expected friendship credentials");
+        }
+    }
+
+    #[test]
+    fn friendship_credentials() {
+        let mut low_power = LowPower::new(Default::default(), millis(0));
+        assert!(matches!(
+            low_power.next_transmission(lpn(), 2, network_key_handle(), &network_key(), millis(0)),
+            Some((Credentials::Master(_), _, FriendMessage::Request(_)))
+        ));
+        assert!(low_power.credentials().is_none());
+
+        low_power.receive(friend(), offer(100, 5), millis(200));
+        let credentials = network_key()
+            .friendship_credentials(lpn(), friend(), 0, 5)
+            .unwrap();
+        match low_power.next_transmission(
+            lpn(),
+            2,
+            network_key_handle(),
+            &network_key(),
+            millis(1000),
+        ) {
+            Some((Credentials::Friendship(_, key), _, FriendMessage::Poll { .. })) => {
+                assert_eq!(credentials, key)
+            }
+            _ => panic!("expected a friend poll"),
+        }
+    }
+
+    #[test]
+    fn no_offers() {
+        let mut low_power = LowPower::new(Default::default(), millis(0));
+        next(&mut low_power, millis(0)).unwrap();
+
+        let deadline = millis(0) + OFFER_WINDOW_END;
+        assert!(next(&mut low_power, deadline).is_none());
+        assert_eq!(
+            Some(deadline + SEARCH_INTERVAL),
+            low_power.next_deadline(deadline)
+        );
+
+        if let Some((_, FriendMessage::Request(request))) =
+            next(&mut low_power, deadline + SEARCH_INTERVAL)
+        {
+            assert_eq!(1, request.lpn_counter);
+        } else {
+            panic!("expected a friend request");
+        }
+    }
+
+    #[test]
+    fn polling() {
+        let mut low_power = established();
+        assert!(low_power.is_established());
+
+        // the radio is off until the next friend poll, 10 seconds later.
+        assert!(!low_power.is_listening(millis(1300)));
+        assert_eq!(Some(millis(11200)), low_power.next_deadline(millis(1300)));
+        assert!(next(&mut low_power, millis(11199)).is_none());
+        assert_eq!(
+            Some((friend().into(), FriendMessage::Poll { fsn: true })),
+            next(&mut low_power, millis(11200))
+        );
+
+        // listening for 100ms, after the receive delay of 100ms.
+        assert_eq!(Some(millis(11300)), low_power.next_deadline(millis(11200)));
+        assert!(!low_power.is_listening(millis(11299)));
+        assert!(low_power.is_listening(millis(11300)));
+        assert!(!low_power.is_listening(millis(11400)));
+
+        // only messages secured with the friendship credentials were stored by the friend.
+        low_power.receive_network_pdu(false, millis(11350));
+        assert!(next(&mut low_power, millis(11350)).is_none());
+
+        // a stored message is followed by another friend poll right away.
+        low_power.receive_network_pdu(true, millis(11350));
+        assert_eq!(
+            Some((friend().into(), FriendMessage::Poll { fsn: false })),
+            next(&mut low_power, millis(11350))
+        );
+
+        low_power.receive(friend(), update(true), millis(11450));
+        assert_eq!(
+            Some((friend().into(), FriendMessage::Poll { fsn: true })),
+            next(&mut low_power, millis(11450))
+        );
+    }
+
+    #[test]
+    fn friendship_lost() {
+        let mut low_power = established();
+        let mut now = millis(11200);
+        assert!(next(&mut low_power, now).is_some());
+
+        // the same friend poll is repeated while unanswered.
+        for _ in 1..TRANSMISSIONS {
+            now += Duration::from_millis(200);
+            assert_eq!(
+                Some((friend().into(), FriendMessage::Poll { fsn: true })),
+                next(&mut low_power, now)
+            );
+        }
+
+        now += Duration::from_millis(200);
+        assert!(next(&mut low_power, now).is_none());
+        assert!(!low_power.is_established());
+        assert!(low_power.is_listening(now));
+
+        if let Some((_, FriendMessage::Request(request))) = next(&mut low_power, now) {
+            assert_eq!(Some(friend()), request.previous_address);
+            assert_eq!(1, request.lpn_counter);
+        } else {
+            panic!("expected a friend request");
+        }
+    }
+
+    #[test]
+    fn network_key_lost() {
+        let mut low_power = established();
+        low_power.network_key_lost(millis(2000));
+        assert!(!low_power.is_friend(friend()));
+        assert!(low_power.network_key_handle().is_none());
+
+        if let Some((_, FriendMessage::Request(request))) = next(&mut low_power, millis(2000)) {
+            assert_eq!(Some(friend()), request.previous_address);
+            assert_eq!(1, request.lpn_counter);
+        } else {
+            panic!("expected a friend request");
+        }
+    }
+
+    #[test]
+    fn config_validated() {
+        assert!(LowPowerConfig::default().validate().is_ok());
+        assert!(LowPowerConfig {
+            receive_delay: 9,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(LowPowerConfig {
+            poll_timeout: 100,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(LowPowerConfig {
+            poll_timeout: 101,
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+    }
+
+    #[test]
+    fn subscription_list() {
+        let mut low_power = established();
+        let group = Address::parse([0xC0, 0x01]);
+        low_power.set_subscriptions([group].into_iter());
+        assert_eq!(Some(millis(1300)), low_power.next_deadline(millis(1300)));
+
+        let add = if let Some((_, FriendMessage::SubscriptionListAdd(list))) =
+            next(&mut low_power, millis(1300))
+        {
+            assert_eq!(0, list.transaction_number);
+            assert_eq!(&[group], &*list.addresses);
+            list
+        } else {
+            panic!("expected a subscription list add");
+        };
+
+        // a confirmation of another transaction is ignored.
+        low_power.receive(
+            friend(),
+            FriendMessage::SubscriptionListConfirm {
+                transaction_number: 7,
+            },
+            millis(1400),
+        );
+        assert_eq!(
+            Some((friend().into(), FriendMessage::SubscriptionListAdd(add))),
+            next(&mut low_power, millis(1500))
+        );
+
+        low_power.receive(
+            friend(),
+            FriendMessage::SubscriptionListConfirm {
+                transaction_number: 0,
+            },
+            millis(1600),
+        );
+        assert!(next(&mut low_power, millis(1600)).is_none());
+
+        low_power.set_subscriptions(core::iter::empty());
+        if let Some((_, FriendMessage::SubscriptionListRemove(list))) =
+            next(&mut low_power, millis(2000))
+        {
+            assert_eq!(1, list.transaction_number);
+            assert_eq!(&[group], &*list.addresses);
+        } else {
+            panic!("expected a subscription list remove");
+        }
+    }
+
+    #[test]
+    fn friend_not_responding() {
+        let mut low_power = LowPower::new(LowPowerConfig::default(), millis(0));
+        next(&mut low_power, millis(0)).unwrap();
+        low_power.receive(friend(), offer(100, 5), millis(200));
+        let mut now = millis(1000);
+        assert!(next(&mut low_power, now).is_some());
+
+        for _ in 1..TRANSMISSIONS {
+            now += Duration::from_millis(200);
+            assert!(next(&mut low_power, now).is_some());
+        }
+
+        // no friendship is lost, so the next friend is looked for at leisure.
+        now += Duration::from_millis(200);
+        assert!(next(&mut low_power, now).is_none());
+        assert_eq!(Some(now + SEARCH_INTERVAL), low_power.next_deadline(now));
+    }
+}
@@ -4,22 +4,28 @@ mod outbound_segmentation;
 use crate::stack::provisioned::lower::inbound_segmentation::InboundSegmentation;
 use crate::stack::provisioned::lower::outbound_segmentation::OutboundSegmentation;
 use crate::stack::provisioned::sequence::Sequence;
-use crate::stack::provisioned::system::{LowerMetadata, UpperMetadata};
+use crate::stack::provisioned::system::{ControlMetadata, LowerMetadata, UpperMetadata};
 use crate::stack::provisioned::ProvisionedStack;
 use crate::DriverError;
+use btmesh_common::address::Address;
 use btmesh_common::mic::SzMic;
 use btmesh_common::InsufficientBuffer;
+use btmesh_pdu::provisioned::control::ControlMessage;
 use btmesh_pdu::provisioned::lower::{BlockAck, LowerPDU, UnsegmentedLowerPDU};
 use btmesh_pdu::provisioned::network::{CleartextNetworkPDU, NetworkPDU};
 use btmesh_pdu::provisioned::upper::access::UpperAccessPDU;
 use btmesh_pdu::provisioned::upper::control::{ControlOpcode, UpperControlPDU};
 use btmesh_pdu::provisioned::upper::UpperPDU;
+use embassy_executor::time::Instant;
 use heapless::Vec;
 
 #[derive(Default)]
 pub struct LowerDriver {
     inbound_segmentation: InboundSegmentation,
     outbound_segmentation: OutboundSegmentation,
+    /// Segmented messages destined to low power nodes, acknowledged on their behalf.
+    #[cfg(feature = "friend")]
+    lpn_segmentation: InboundSegmentation,
 }
 
 impl ProvisionedStack {
@@ -51,6 +57,16 @@ impl ProvisionedStack {
                         .into(),
                     ),
                 )),
+                UnsegmentedLowerPDU::Control(control_pdu)
+                    if control_pdu.opcode() == ControlOpcode::SegmentAcknowledgement =>
+                {
+                    self.transmit_queue.receive_ack(
+                        network_pdu.src(),
+                        BlockAck::parse(control_pdu.parameters())?,
+                        Instant::now(),
+                    );
+                    Ok((None, None))
+                }
                 UnsegmentedLowerPDU::Control(control_pdu) => Ok((
                     None,
                     Some(
@@ -70,18 +86,75 @@ impl ProvisionedStack {
         }
     }
 
+    /// Track a segment of a message destined to a low power node, producing
+    /// the acknowledgement of the segments received so far.
+    #[cfg(feature = "friend")]
+    pub(crate) fn process_inbound_lpn_segment(
+        &mut self,
+        network_pdu: &CleartextNetworkPDU<ProvisionedStack>,
+    ) -> Option<(BlockAck, UpperMetadata)> {
+        let lower_pdu =
+            LowerPDU::parse(network_pdu, LowerMetadata::from_network_pdu(network_pdu)).ok()?;
+        if let LowerPDU::Segmented(inner) = &lower_pdu {
+            // the reassembled message is of no interest, the low power node polls for the segments.
+            let result = self.lower.lpn_segmentation.process(inner).ok()?;
+            Some((result.block_ack, result.meta))
+        } else {
+            None
+        }
+    }
+
+    /// Acknowledge the segments received so far of a segmented message
+    /// described by its inbound metadata, addressing the acknowledgement
+    /// back to its originator.
+    ///
+    /// Segments destined to a low power node are acknowledged on its behalf,
+    /// from the primary element of its friend.
     pub fn process_outbound_block_ack(
         &mut self,
         sequence: &Sequence,
         block_ack: BlockAck,
         meta: UpperMetadata,
     ) -> Result<Vec<NetworkPDU, 32>, DriverError> {
-        let network_pdus = self.process_outbound_upper_pdu(
-            sequence,
-            &block_ack_to_upper_pdu(block_ack, meta)?,
-            false,
+        let (src, obo) = match (meta.dst(), meta.local_element_index()) {
+            (Address::Unicast(dst), Some(_)) => (dst, false),
+            (Address::Unicast(_), None) => {
+                if let Some(src) = self.network.device_info().local_element_address(0) {
+                    (src, true)
+                } else {
+                    return Ok(Vec::new());
+                }
+            }
+            // only segmented messages sent to a unicast address are acknowledged.
+            _ => return Ok(Vec::new()),
+        };
+
+        // acknowledgements of segments sent with a TTL of 0 do not travel further either.
+        let ttl = if meta.ttl().value() == 0 {
+            meta.ttl()
+        } else {
+            self.default_ttl
+        };
+
+        let message = block_ack_to_control_message(
+            block_ack,
+            obo,
+            ControlMetadata::new(
+                meta.network_key_handle(),
+                meta.iv_index(),
+                src,
+                meta.src().into(),
+                ttl,
+            ),
         )?;
 
+        let upper_pdu = self.process_outbound_message(sequence, &message.into())?;
+        let network_pdus = self.process_outbound_upper_pdu(sequence, &upper_pdu, None)?;
+        #[cfg(feature = "friend")]
+        for pdu in &network_pdus {
+            self.store_for_friends(pdu);
+        }
+
         let network_pdus = network_pdus
             .iter()
             .map_while(|pdu| self.encrypt_network_pdu(pdu).ok())
@@ -94,22 +167,49 @@ impl ProvisionedStack {
         &mut self,
         sequence: &Sequence,
         upper_pdu: &UpperPDU<ProvisionedStack>,
-        is_retransmit: bool,
+        acked: Option<BlockAck>,
     ) -> Result<Vec<CleartextNetworkPDU<ProvisionedStack>, 32>, DriverError> {
         self.lower
             .outbound_segmentation
-            .process(sequence, upper_pdu, is_retransmit)
+            .process(sequence, upper_pdu, acked)
+    }
+
+    /// Retransmit the unacknowledged segments of a segmented message whose
+    /// segment transmission timer has expired, if any, along with its destination.
+    pub fn process_retransmit(
+        &mut self,
+        sequence: &Sequence,
+    ) -> Result<Option<(Vec<NetworkPDU, 32>, Address)>, DriverError> {
+        let (network_pdus, dst) =
+            if let Some((upper_pdu, acked)) = self.transmit_queue.retransmit(Instant::now()) {
+                (
+                    self.lower
+                        .outbound_segmentation
+                        .process(sequence, upper_pdu, Some(acked))?,
+                    upper_pdu.meta().dst(),
+                )
+            } else {
+                return Ok(None);
+            };
+
+        let network_pdus = network_pdus
+            .iter()
+            .map_while(|pdu| self.encrypt_network_pdu(pdu).ok())
+            .collect();
+
+        Ok(Some((network_pdus, dst)))
     }
 }
 
-fn block_ack_to_upper_pdu(
+fn block_ack_to_control_message(
     block_ack: BlockAck,
-    meta: UpperMetadata,
-) -> Result<UpperPDU<ProvisionedStack>, InsufficientBuffer> {
+    obo: bool,
+    meta: ControlMetadata,
+) -> Result<ControlMessage<ProvisionedStack>, InsufficientBuffer> {
     let mut parameters = [0; 6];
 
     let seq_zero = (block_ack.seq_zero().value() << 2).to_be_bytes();
-    parameters[0] = seq_zero[0];
+    parameters[0] = seq_zero[0] | if obo { 0x80 } else { 0x00 };
     parameters[1] = seq_zero[1];
 
     let block_ack = block_ack.value().to_be_bytes();
@@ -118,5 +218,5 @@ fn block_ack_to_upper_pdu(
     parameters[4] = block_ack[2];
     parameters[5] = block_ack[3];
 
-    Ok(UpperControlPDU::new(ControlOpcode::SegmentAcknowledgement, &parameters, meta)?.into())
+    ControlMessage::new(ControlOpcode::SegmentAcknowledgement, &parameters, meta)
 }
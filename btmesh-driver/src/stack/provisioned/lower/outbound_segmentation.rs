@@ -5,6 +5,10 @@ use crate::DriverError;
 use btmesh_common::mic::SzMic;
 use btmesh_common::{Ctl, InsufficientBuffer};
 use btmesh_pdu::provisioned::lower::access::{SegmentedLowerAccessPDU, UnsegmentedLowerAccessPDU};
+use btmesh_pdu::provisioned::lower::control::{
+    SegmentedLowerControlPDU, UnsegmentedLowerControlPDU,
+};
+use btmesh_pdu::provisioned::lower::BlockAck;
 use btmesh_pdu::provisioned::network::CleartextNetworkPDU;
 use btmesh_pdu::provisioned::upper::UpperPDU;
 use heapless::Vec;
@@ -14,15 +18,20 @@ const NONSEGMENTED_ACCESS_MUT: usize = 15;
 
 const SEGMENT_LOWER_PDU_SIZE: usize = SEGMENTED_ACCESS_MTU + 4;
 
+const SEGMENTED_CONTROL_MTU: usize = 8;
+const NONSEGMENTED_CONTROL_MTU: usize = 11;
+
 #[derive(Default)]
 pub struct OutboundSegmentation {}
 
 impl OutboundSegmentation {
+    /// Segment an upper PDU as needed. When retransmitting, the segments acknowledged
+    /// so far are provided, and only the remaining segments are produced.
     pub fn process(
         &mut self,
         sequence: &Sequence,
         pdu: &UpperPDU<ProvisionedStack>,
-        is_retransmit: bool,
+        acked: Option<BlockAck>,
     ) -> Result<Vec<CleartextNetworkPDU<ProvisionedStack>, 32>, DriverError> {
         let meta = NetworkMetadata::from_upper_pdu(pdu);
         let mut result = Vec::new();
@@ -58,7 +67,13 @@ impl OutboundSegmentation {
                     let seg_n = payload.len() - 1;
 
                     for (seg_o, segment_m) in payload.enumerate() {
-                        let seq = if !is_retransmit && seg_o == 0 {
+                        if let Some(acked) = acked {
+                            if acked.is_acked(seg_o as u8)? {
+                                continue;
+                            }
+                        }
+
+                        let seq = if acked.is_none() && seg_o == 0 {
                             pdu.meta().seq()
                         } else {
                             sequence.next()
@@ -94,8 +109,237 @@ impl OutboundSegmentation {
                     }
                 }
             }
-            UpperPDU::Control(_inner) => {}
+            UpperPDU::Control(inner) => {
+                if inner.parameters().len() <= NONSEGMENTED_CONTROL_MTU {
+                    let lower_pdu = UnsegmentedLowerControlPDU::<()>::new(
+                        inner.opcode(),
+                        inner.parameters(),
+                        (),
+                    )?;
+
+                    let mut transport_pdu = Vec::<_, 16>::new();
+                    lower_pdu.emit(&mut transport_pdu)?;
+
+                    result
+                        .push(CleartextNetworkPDU::new(
+                            pdu.meta().iv_index().ivi(),
+                            pdu.meta().network_key_handle().nid(),
+                            Ctl::Control,
+                            pdu.meta().ttl(),
+                            pdu.meta().seq(),
+                            pdu.meta().src(),
+                            pdu.meta().dst(),
+                            &*transport_pdu,
+                            meta,
+                        )?)
+                        .map_err(|_| InsufficientBuffer)?;
+                } else {
+                    let seq_zero = inner.meta().seq().into();
+                    let parameters = inner.parameters().chunks(SEGMENTED_CONTROL_MTU);
+                    let seg_n = parameters.len() - 1;
+
+                    for (seg_o, segment_m) in parameters.enumerate() {
+                        if let Some(acked) = acked {
+                            if acked.is_acked(seg_o as u8)? {
+                                continue;
+                            }
+                        }
+
+                        let seq = if acked.is_none() && seg_o == 0 {
+                            pdu.meta().seq()
+                        } else {
+                            sequence.next()
+                        };
+
+                        let lower_pdu = SegmentedLowerControlPDU::<()>::new(
+                            inner.opcode(),
+                            seq_zero,
+                            seg_o as u8,
+                            seg_n as u8,
+                            segment_m,
+                            (),
+                        )?;
+
+                        let mut transport_pdu = Vec::<_, SEGMENT_LOWER_PDU_SIZE>::new();
+                        lower_pdu.emit(&mut transport_pdu)?;
+
+                        result
+                            .push(CleartextNetworkPDU::new(
+                                pdu.meta().iv_index().ivi(),
+                                pdu.meta().network_key_handle().nid(),
+                                Ctl::Control,
+                                pdu.meta().ttl(),
+                                seq,
+                                pdu.meta().src(),
+                                pdu.meta().dst(),
+                                &*transport_pdu,
+                                meta,
+                            )?)
+                            .map_err(|_| InsufficientBuffer)?;
+                    }
+                }
+            }
         }
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::stack::provisioned::lower::outbound_segmentation::OutboundSegmentation;
+    use crate::stack::provisioned::sequence::Sequence;
+    use crate::stack::provisioned::system::{ControlMetadata, UpperMetadata};
+    use crate::stack::provisioned::ProvisionedStack;
+    use btmesh_common::address::UnicastAddress;
+    use btmesh_common::crypto::application::Aid;
+    use btmesh_common::crypto::network::Nid;
+    use btmesh_common::mic::TransMic;
+    use btmesh_common::opcode::Opcode;
+    use btmesh_common::{Ctl, IvIndex, Seq, SeqZero, Ttl};
+    use btmesh_device::{ApplicationKeyHandle, KeyHandle, NetworkKeyHandle, OutboundMetadata};
+    use btmesh_pdu::provisioned::access::AccessMessage;
+    use btmesh_pdu::provisioned::control::ControlMessage;
+    use btmesh_pdu::provisioned::lower::control::{
+        SegmentedLowerControlPDU, UnsegmentedLowerControlPDU,
+    };
+    use btmesh_pdu::provisioned::lower::BlockAck;
+    use btmesh_pdu::provisioned::upper::access::UpperAccessPDU;
+    use btmesh_pdu::provisioned::upper::control::{ControlOpcode, UpperControlPDU};
+    use btmesh_pdu::provisioned::upper::UpperPDU;
+
+    fn control_pdu(parameters: &[u8], seq: u32) -> UpperPDU<ProvisionedStack> {
+        let message = ControlMessage::<ProvisionedStack>::new(
+            ControlOpcode::FriendOffer,
+            parameters,
+            ControlMetadata::new(
+                NetworkKeyHandle(0, Nid::new(42)),
+                IvIndex::parse(&[1, 2, 3, 4]).unwrap(),
+                UnicastAddress::parse([0x00, 0x0A]).unwrap(),
+                UnicastAddress::parse([0x00, 0x0B]).unwrap().into(),
+                Ttl::new(5),
+            ),
+        )
+        .unwrap();
+
+        UpperControlPDU::new(
+            message.opcode(),
+            message.parameters(),
+            UpperMetadata::from_control_message(&message, Seq::parse(seq).unwrap()),
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn unsegmented_control() {
+        let mut segmentation = OutboundSegmentation::default();
+        let sequence = Sequence::new(Seq::new(2000));
+        let pdu = control_pdu(&[0; 11], 1000);
+
+        let result = segmentation.process(&sequence, &pdu, None).unwrap();
+
+        assert_eq!(1, result.len());
+        assert!(matches!(result[0].ctl(), Ctl::Control));
+        assert_eq!(1000, result[0].seq().value());
+        let lower_pdu =
+            UnsegmentedLowerControlPDU::<()>::parse(result[0].transport_pdu(), ()).unwrap();
+        assert_eq!(ControlOpcode::FriendOffer, lower_pdu.opcode());
+        assert_eq!(&[0; 11], lower_pdu.parameters());
+    }
+
+    #[test]
+    fn segmented_control() {
+        let mut segmentation = OutboundSegmentation::default();
+        let sequence = Sequence::new(Seq::new(2000));
+        let parameters = [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+        ];
+        let pdu = control_pdu(&parameters, 1000);
+
+        let result = segmentation.process(&sequence, &pdu, None).unwrap();
+
+        assert_eq!(3, result.len());
+        assert_eq!(1000, result[0].seq().value());
+        assert_eq!(2000, result[1].seq().value());
+        assert_eq!(2001, result[2].seq().value());
+
+        let mut reassembled = heapless::Vec::<u8, 24>::new();
+        for (seg_o, network_pdu) in result.iter().enumerate() {
+            assert!(matches!(network_pdu.ctl(), Ctl::Control));
+            let lower_pdu =
+                SegmentedLowerControlPDU::<()>::parse(network_pdu.transport_pdu(), ()).unwrap();
+            assert_eq!(ControlOpcode::FriendOffer, lower_pdu.opcode());
+            assert_eq!(1000, lower_pdu.seq_zero().value());
+            assert_eq!(seg_o as u8, lower_pdu.seg_o());
+            assert_eq!(2, lower_pdu.seg_n());
+            reassembled
+                .extend_from_slice(lower_pdu.segment_m())
+                .unwrap();
+        }
+        assert_eq!(&parameters, &*reassembled);
+    }
+
+    #[test]
+    fn segmented_control_retransmit() {
+        let mut segmentation = OutboundSegmentation::default();
+        let sequence = Sequence::new(Seq::new(2000));
+        let pdu = control_pdu(&[0; 20], 1000);
+
+        let mut acked = BlockAck::new(SeqZero::new(1000));
+        acked.ack(0).unwrap();
+        acked.ack(2).unwrap();
+
+        let result = segmentation.process(&sequence, &pdu, Some(acked)).unwrap();
+
+        assert_eq!(1, result.len());
+        assert_eq!(2000, result[0].seq().value());
+        let lower_pdu =
+            SegmentedLowerControlPDU::<()>::parse(result[0].transport_pdu(), ()).unwrap();
+        assert_eq!(1000, lower_pdu.seq_zero().value());
+        assert_eq!(1, lower_pdu.seg_o());
+        assert_eq!(2, lower_pdu.seg_n());
+    }
+
+    #[test]
+    fn friendship_credentials() {
+        let sequence = Sequence::new(Seq::new(2000));
+        let meta = OutboundMetadata::new(
+            UnicastAddress::parse([0x00, 0x0B]).unwrap().into(),
+            NetworkKeyHandle(0, Nid::new(42)),
+            IvIndex::parse(&[1, 2, 3, 4]).unwrap(),
+            KeyHandle::Application(ApplicationKeyHandle(0, Aid::parse(0x41).unwrap().unwrap())),
+            None,
+            None,
+        );
+
+        for (friendship_credentials, parameters) in [(false, &[0; 4][..]), (true, &[0; 20][..])] {
+            let message = AccessMessage::<ProvisionedStack>::new(
+                Opcode::OneOctet(0x04),
+                heapless::Vec::from_slice(parameters).unwrap(),
+                (
+                    UnicastAddress::parse([0x00, 0x0A]).unwrap(),
+                    meta.with_friendship_credentials(friendship_credentials),
+                    Ttl::new(5),
+                ),
+            );
+            let pdu: UpperPDU<ProvisionedStack> = UpperAccessPDU::new(
+                parameters,
+                TransMic::new32(),
+                UpperMetadata::from_access_message(&message, Seq::parse(1000).unwrap()),
+            )
+            .unwrap()
+            .into();
+
+            let result = OutboundSegmentation::default()
+                .process(&sequence, &pdu, None)
+                .unwrap();
+            assert!(!result.is_empty());
+            for network_pdu in &result {
+                assert_eq!(
+                    friendship_credentials,
+                    network_pdu.meta().is_lpn_friendship()
+                );
+            }
+        }
+    }
+}
@@ -1,18 +1,46 @@
+use crate::interface::Bearer;
+#[cfg(feature = "friend")]
+use crate::stack::provisioned::friend::Friend;
+use crate::stack::provisioned::heartbeat::Heartbeat;
+use crate::stack::provisioned::iv_update::IvUpdate;
+#[cfg(feature = "low_power")]
+use crate::stack::provisioned::low_power::LowPower;
 use crate::stack::provisioned::lower::LowerDriver;
 use crate::stack::provisioned::network::replay_protection::ReplayProtection;
+#[cfg(any(feature = "friend", feature = "low_power"))]
+use crate::stack::provisioned::network::Credentials;
 use crate::stack::provisioned::network::{DeviceInfo, NetworkDriver};
+#[cfg(feature = "proxy")]
+use crate::stack::provisioned::proxy::ProxyFilter;
 use crate::stack::provisioned::sequence::Sequence;
+#[cfg(any(feature = "friend", feature = "low_power"))]
+use crate::stack::provisioned::system::ControlMetadata;
 use crate::stack::provisioned::transmit_queue::TransmitQueue;
 use crate::stack::provisioned::upper::UpperDriver;
+use crate::storage::provisioned::foundation::configuration::publications::Publication;
 use crate::storage::provisioned::ProvisionedConfiguration;
 use crate::{DriverError, UpperMetadata};
-use btmesh_common::{IvIndex, IvUpdateFlag, Ivi};
+use btmesh_common::address::Address;
+#[cfg(any(feature = "friend", feature = "low_power"))]
+use btmesh_common::crypto::network::NetworkKey;
+use btmesh_common::{IvIndex, IvUpdateFlag, Ivi, Ttl};
+use btmesh_device::{CompletionToken, KeyHandle, NetworkKeyHandle, OutboundMetadata};
+#[cfg(feature = "proxy")]
+use btmesh_models::foundation::configuration::gatt_proxy::GattProxy;
+use btmesh_models::foundation::configuration::network_transmit::NetworkTransmitConfig;
+#[cfg(feature = "relay")]
+use btmesh_models::foundation::configuration::relay::{Relay, RelayConfig};
+#[cfg(any(feature = "friend", feature = "low_power"))]
+use btmesh_pdu::provisioned::control::ControlMessage;
+#[cfg(any(feature = "friend", feature = "low_power"))]
+use btmesh_pdu::provisioned::friend::FriendMessage;
 use btmesh_pdu::provisioned::lower::BlockAck;
-use btmesh_pdu::provisioned::network::NetworkPDU;
+use btmesh_pdu::provisioned::network::{CleartextNetworkPDU, NetworkPDU};
 use btmesh_pdu::provisioned::Message;
 use btmesh_pdu::provisioning::ProvisioningData;
+use core::cell::Cell;
 use core::future::Future;
-use embassy_executor::time::{Duration, Timer};
+use embassy_executor::time::{Duration, Instant, Timer};
 use heapless::Vec;
 use secrets::Secrets;
 
@@ -20,8 +48,17 @@ use crate::util::deadline::{Deadline, DeadlineFuture};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+pub mod beacon;
+#[cfg(feature = "friend")]
+pub mod friend;
+pub mod heartbeat;
+pub mod iv_update;
+#[cfg(feature = "low_power")]
+pub mod low_power;
 pub mod lower;
 pub mod network;
+#[cfg(feature = "proxy")]
+pub mod proxy;
 pub mod secrets;
 pub mod sequence;
 pub mod system;
@@ -50,23 +87,45 @@ impl IvIndexState {
     pub fn transmission_iv_index(&self) -> IvIndex {
         self.iv_index.transmission_iv_index(self.iv_update_flag)
     }
+
+    pub fn iv_index(&self) -> IvIndex {
+        self.iv_index
+    }
+
+    pub fn iv_update_flag(&self) -> IvUpdateFlag {
+        self.iv_update_flag
+    }
+}
+
+/// Hours spent in the current IV update state and since the last IV index recovery.
+#[derive(Copy, Clone, Default, Hash, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IvUpdateTiming {
+    state_hours: u16,
+    recovery_hours: Option<u16>,
 }
 
 #[derive(Copy, Clone, Hash, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NetworkState {
     iv_index_state: IvIndexState,
+    iv_update_timing: IvUpdateTiming,
 }
 
 impl NetworkState {
     pub fn display(&self) {
         info!("iv_index: {}", self.iv_index_state.iv_index);
         info!("iv_update_flag: {}", self.iv_index_state.iv_update_flag);
+        info!(
+            "iv_update_state_hours: {}",
+            self.iv_update_timing.state_hours
+        );
     }
 
     pub(crate) fn new(iv_index: IvIndex, iv_update_flag: IvUpdateFlag) -> Self {
         Self {
             iv_index_state: IvIndexState::new(iv_index, iv_update_flag),
+            iv_update_timing: Default::default(),
         }
     }
 
@@ -79,6 +138,7 @@ impl From<ProvisioningData> for NetworkState {
     fn from(data: ProvisioningData) -> Self {
         Self {
             iv_index_state: data.into(),
+            iv_update_timing: Default::default(),
         }
     }
 }
@@ -92,40 +152,93 @@ impl From<ProvisioningData> for IvIndexState {
     }
 }
 
+/// How often secure network beacons are broadcast, while enabled.
+const SECURE_BEACON_INTERVAL: Duration = Duration::from_secs(10);
+
 pub struct ProvisionedStack {
     network_state: NetworkState,
+    iv_update: IvUpdate,
     secrets: Secrets,
     upper: UpperDriver,
     lower: LowerDriver,
     network: NetworkDriver,
+    #[cfg(feature = "relay")]
+    relay: RelayConfig,
+    #[cfg(feature = "proxy")]
+    network_transmit: NetworkTransmitConfig,
+    #[cfg(feature = "proxy")]
+    gatt_proxy: GattProxy,
+    #[cfg(feature = "proxy")]
+    proxy_filter: ProxyFilter,
+    #[cfg(feature = "friend")]
+    friend: Friend,
+    #[cfg(feature = "low_power")]
+    low_power: LowPower,
     //
     transmit_queue: TransmitQueue,
     beacon: Deadline,
+    secure_beacon: Deadline,
+    secure_beacon_enabled: bool,
+    advertised_subnet: Cell<usize>,
+    heartbeat: Heartbeat,
+    default_ttl: Ttl,
 }
 
 impl From<ProvisionedConfiguration> for ProvisionedStack {
     fn from(content: ProvisionedConfiguration) -> Self {
-        Self {
+        let mut stack = Self {
             network_state: content.network_state(),
+            iv_update: IvUpdate::restore(&content.network_state().iv_update_timing, Instant::now()),
             secrets: content.secrets(),
             upper: Default::default(),
             lower: Default::default(),
             network: NetworkDriver::new(content.device_info()),
+            #[cfg(feature = "relay")]
+            relay: Default::default(),
+            #[cfg(feature = "proxy")]
+            network_transmit: Default::default(),
+            #[cfg(feature = "proxy")]
+            gatt_proxy: Default::default(),
+            #[cfg(feature = "proxy")]
+            proxy_filter: Default::default(),
+            #[cfg(feature = "friend")]
+            friend: Default::default(),
+            #[cfg(feature = "low_power")]
+            low_power: LowPower::new(Default::default(), Instant::now()),
             transmit_queue: Default::default(),
             beacon: Deadline::new(Duration::from_secs(3), true),
-        }
+            secure_beacon: Deadline::new(SECURE_BEACON_INTERVAL, true),
+            secure_beacon_enabled: true,
+            advertised_subnet: Cell::new(0),
+            heartbeat: Default::default(),
+            default_ttl: Ttl::new(127),
+        };
+        stack.reconfigure(&content);
+        stack
     }
 }
 
+/// Network PDUs to relay on the advertising bearer and to pass on to the proxy client.
+type Forwarded = (
+    Option<(NetworkPDU, NetworkTransmitConfig)>,
+    Option<NetworkPDU>,
+);
+
 pub struct ReceiveResult {
     pub block_ack: Option<(BlockAck, UpperMetadata)>,
     pub message: Option<Message<ProvisionedStack>>,
+    /// A network PDU to relay on the advertising bearer, along with how it should be retransmitted.
+    pub relay: Option<(NetworkPDU, NetworkTransmitConfig)>,
+    /// A network PDU to pass on to the connected proxy client.
+    pub proxy: Option<NetworkPDU>,
 }
 
 impl
     TryFrom<(
         Option<(BlockAck, UpperMetadata)>,
         Option<Message<ProvisionedStack>>,
+        Option<(NetworkPDU, NetworkTransmitConfig)>,
+        Option<NetworkPDU>,
     )> for ReceiveResult
 {
     type Error = ();
@@ -134,13 +247,17 @@ impl
         value: (
             Option<(BlockAck, UpperMetadata)>,
             Option<Message<ProvisionedStack>>,
+            Option<(NetworkPDU, NetworkTransmitConfig)>,
+            Option<NetworkPDU>,
         ),
     ) -> Result<Self, Self::Error> {
         match value {
-            (None, None) => Err(()),
+            (None, None, None, None) => Err(()),
             _ => Ok(ReceiveResult {
                 block_ack: value.0,
                 message: value.1,
+                relay: value.2,
+                proxy: value.3,
             }),
         }
     }
@@ -151,16 +268,78 @@ impl ProvisionedStack {
         Self {
             secrets,
             network_state,
+            iv_update: IvUpdate::new(Instant::now()),
             upper: Default::default(),
             lower: Default::default(),
             network: NetworkDriver::new(device_info),
+            #[cfg(feature = "relay")]
+            relay: Default::default(),
+            #[cfg(feature = "proxy")]
+            network_transmit: Default::default(),
+            #[cfg(feature = "proxy")]
+            gatt_proxy: Default::default(),
+            #[cfg(feature = "proxy")]
+            proxy_filter: Default::default(),
+            #[cfg(feature = "friend")]
+            friend: Default::default(),
+            #[cfg(feature = "low_power")]
+            low_power: LowPower::new(Default::default(), Instant::now()),
             transmit_queue: Default::default(),
             beacon: Deadline::new(Duration::from_secs(3), true),
+            secure_beacon: Deadline::new(SECURE_BEACON_INTERVAL, true),
+            secure_beacon_enabled: true,
+            advertised_subnet: Cell::new(0),
+            heartbeat: Default::default(),
+            default_ttl: Ttl::new(127),
+        }
+    }
+
+    /// Apply state owned by storage, such as keys modified through
+    /// the configuration server, to the running stack.
+    pub(crate) fn reconfigure(&mut self, content: &ProvisionedConfiguration) {
+        self.secrets = content.secrets();
+        if self
+            .sync_label_uuids(
+                content
+                    .foundation()
+                    .configuration()
+                    .subscriptions()
+                    .label_uuids_iter(),
+            )
+            .is_err()
+        {
+            warn!("unable to register all subscribed label uuids");
+        }
+        self.secure_beacon_enabled = *content.foundation().configuration().beacon();
+        self.default_ttl = *content.foundation().configuration().default_ttl();
+        let features = self.features();
+        #[cfg(feature = "relay")]
+        {
+            self.relay = *content.foundation().configuration().relay();
         }
+        #[cfg(feature = "proxy")]
+        {
+            self.network_transmit = *content.foundation().configuration().network_transmit();
+            self.gatt_proxy = *content.foundation().configuration().gatt_proxy();
+        }
+        #[cfg(feature = "low_power")]
+        self.sync_low_power_subscriptions(content);
+        let configuration = content.foundation().configuration();
+        let now = Instant::now();
+        self.heartbeat.reconfigure(
+            configuration.heartbeat_publication(),
+            configuration.heartbeat_subscription(),
+            now,
+        );
+        self.heartbeat
+            .features_changed(features, self.features(), now);
     }
 
     pub fn network_state(&self) -> NetworkState {
-        self.network_state
+        NetworkState {
+            iv_update_timing: self.iv_update.timing(Instant::now()),
+            ..self.network_state
+        }
     }
 
     pub fn device_info(&self) -> DeviceInfo {
@@ -172,12 +351,13 @@ impl ProvisionedStack {
     }
 
     pub fn next_retransmit(&self) -> Option<impl Future<Output = ()>> {
-        Some(Timer::after(Duration::from_millis(500)))
+        self.transmit_queue.next_deadline().map(Timer::at)
     }
 
     pub fn process_inbound_network_pdu(
         &mut self,
         network_pdu: &NetworkPDU,
+        bearer: Bearer,
     ) -> Result<Option<ReceiveResult>, DriverError> {
         let iv_index = self
             .network_state
@@ -185,6 +365,39 @@ impl ProvisionedStack {
             .accepted_iv_index(network_pdu.ivi());
 
         if let Some(cleartext_network_pdu) = self.try_decrypt_network_pdu(network_pdu, iv_index)? {
+            #[cfg(feature = "proxy")]
+            if bearer == Bearer::Gatt {
+                self.proxy_filter
+                    .client_address(cleartext_network_pdu.src());
+            }
+
+            #[cfg(feature = "low_power")]
+            if !matches!(cleartext_network_pdu.dst(), Address::Unicast(_))
+                || cleartext_network_pdu.meta().local_element_index().is_some()
+            {
+                self.low_power.receive_network_pdu(
+                    cleartext_network_pdu.meta().is_lpn_friendship(),
+                    Instant::now(),
+                );
+            }
+
+            let (relay, proxy) = self.forward_network_pdu(&cleartext_network_pdu, bearer)?;
+
+            #[cfg(feature = "friend")]
+            self.store_for_friends(&cleartext_network_pdu);
+
+            // unicast PDUs addressed to other nodes are only of interest for relaying,
+            // or to be acknowledged on behalf of low power nodes.
+            if matches!(cleartext_network_pdu.dst(), Address::Unicast(_))
+                && cleartext_network_pdu.meta().local_element_index().is_none()
+            {
+                #[cfg(feature = "friend")]
+                let block_ack_meta = self.acknowledge_for_friends(&cleartext_network_pdu);
+                #[cfg(not(feature = "friend"))]
+                let block_ack_meta = None;
+                return Ok((block_ack_meta, None, relay, proxy).try_into().ok());
+            }
+
             let (block_ack_meta, upper_pdu) =
                 self.process_inbound_cleartext_network_pdu(&cleartext_network_pdu)?;
 
@@ -194,26 +407,156 @@ impl ProvisionedStack {
                 None
             };
 
-            Ok((block_ack_meta, message).try_into().ok())
+            Ok((block_ack_meta, message, relay, proxy).try_into().ok())
         } else {
             // nothing doing, bad result, nothing parsed, keep on truckin'
             Ok(None)
         }
     }
 
+    /// Decide where a network PDU from another node is retransmitted, depending
+    /// upon the bearer it arrived on. PDUs heard on the advertising bearer are
+    /// relayed if the relay feature is enabled and passed on to the proxy client
+    /// if the GATT proxy is enabled and its filter accepts them, while PDUs from
+    /// the proxy client are retransmitted on the advertising bearer.
+    fn forward_network_pdu(
+        &mut self,
+        pdu: &CleartextNetworkPDU<ProvisionedStack>,
+        bearer: Bearer,
+    ) -> Result<Forwarded, DriverError> {
+        let retransmit = match bearer {
+            Bearer::Advertising => self.relay_retransmit(),
+            Bearer::Gatt => self.proxy_retransmit(),
+        };
+        #[cfg(feature = "proxy")]
+        let proxy = bearer == Bearer::Advertising
+            && self.gatt_proxy_enabled()
+            && self.proxy_filter.accepts(pdu.dst());
+        #[cfg(not(feature = "proxy"))]
+        let proxy = false;
+
+        if retransmit.is_none() && !proxy {
+            return Ok((None, None));
+        }
+
+        if let Some(forwarded) = self.relay_network_pdu(pdu)? {
+            let relay = retransmit.map(|retransmit| (forwarded.clone(), retransmit));
+            let proxy = if proxy { Some(forwarded) } else { None };
+            Ok((relay, proxy))
+        } else {
+            Ok((None, None))
+        }
+    }
+
+    /// Relay retransmit state, expressed as a transmit configuration,
+    /// if relaying is enabled.
+    #[cfg(feature = "relay")]
+    fn relay_retransmit(&self) -> Option<NetworkTransmitConfig> {
+        if self.relay.relay == Relay::SupportedEnabled {
+            Some(NetworkTransmitConfig {
+                network_retransmit_count: self.relay.relay_retransmit_count,
+                network_retransmit_interval_steps: self.relay.relay_retransmit_interval_steps,
+            })
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(feature = "relay"))]
+    fn relay_retransmit(&self) -> Option<NetworkTransmitConfig> {
+        None
+    }
+
+    /// PDUs from the proxy client are retransmitted according to the network transmit state,
+    /// if the GATT proxy is enabled.
+    #[cfg(feature = "proxy")]
+    fn proxy_retransmit(&self) -> Option<NetworkTransmitConfig> {
+        if self.gatt_proxy_enabled() {
+            Some(self.network_transmit)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(feature = "proxy"))]
+    fn proxy_retransmit(&self) -> Option<NetworkTransmitConfig> {
+        None
+    }
+
+    #[cfg(feature = "proxy")]
+    pub(crate) fn gatt_proxy_enabled(&self) -> bool {
+        self.gatt_proxy == GattProxy::Enabled
+    }
+
+    #[cfg(not(feature = "proxy"))]
+    pub(crate) fn gatt_proxy_enabled(&self) -> bool {
+        false
+    }
+
     pub(crate) fn secrets(&self) -> &Secrets {
         &self.secrets
     }
 
+    /// Resolve the outbound metadata used for a model publication.
+    pub(crate) fn publication_metadata(
+        &self,
+        publication: &Publication,
+    ) -> Result<OutboundMetadata, DriverError> {
+        let application_key_handle = self
+            .secrets
+            .application_key_handle(publication.app_key_index())?;
+        let network_key_handle = self
+            .secrets
+            .bound_network_key_handle(publication.app_key_index())?;
+
+        Ok(OutboundMetadata::new(
+            publication.publish_address().into(),
+            network_key_handle,
+            self.network_state.iv_index_state.transmission_iv_index(),
+            KeyHandle::Application(application_key_handle),
+            publication.publish_address().label_uuid(),
+            publication.publish_ttl().map(Ttl::new),
+        )
+        .with_friendship_credentials(
+            publication.credential_flag()
+                && self.friendship_credentials_available(network_key_handle),
+        ))
+    }
+
+    /// Process an outbound message, optionally notifying its sender once it has
+    /// been delivered. Segmented messages are retransmitted until acknowledged.
     pub fn process_outbound(
         &mut self,
         sequence: &Sequence,
         message: &Message<ProvisionedStack>,
+        completion_token: Option<CompletionToken>,
     ) -> Result<Vec<NetworkPDU, 32>, DriverError> {
         let upper_pdu = self.process_outbound_message(sequence, message)?;
-        let network_pdus = self.process_outbound_upper_pdu(sequence, &upper_pdu, false)?;
-        self.transmit_queue
-            .add(upper_pdu, network_pdus.len() as u8)?;
+        let network_pdus = self.process_outbound_upper_pdu(sequence, &upper_pdu, None)?;
+        #[cfg(feature = "friend")]
+        for pdu in &network_pdus {
+            self.store_for_friends(pdu);
+        }
+        if network_pdus.len() > 1 {
+            if self
+                .transmit_queue
+                .add(
+                    upper_pdu,
+                    network_pdus.len() as u8,
+                    completion_token,
+                    Instant::now(),
+                )
+                .is_err()
+            {
+                warn!("transmit queue full, dropping segmented message");
+                if let Some(completion_token) = completion_token {
+                    completion_token.incomplete();
+                }
+                return Ok(Vec::new());
+            }
+        } else if let Some(completion_token) = completion_token {
+            completion_token.complete();
+        }
 
         let network_pdus = network_pdus
             .iter()
@@ -222,4 +565,72 @@ impl ProvisionedStack {
 
         Ok(network_pdus)
     }
+
+    /// Friend messages are unsegmented control messages with a TTL of 0.
+    #[cfg(any(feature = "friend", feature = "low_power"))]
+    fn friend_message_pdu(
+        &mut self,
+        sequence: &Sequence,
+        credentials: Credentials,
+        dst: Address,
+        message: &FriendMessage,
+    ) -> Result<NetworkPDU, DriverError> {
+        let src = self
+            .network
+            .device_info()
+            .local_element_address(0)
+            .ok_or(DriverError::InvalidState)?;
+
+        let mut parameters = Vec::<u8, 11>::new();
+        message.emit(&mut parameters)?;
+
+        let message = ControlMessage::new(
+            message.opcode(),
+            &parameters,
+            ControlMetadata::new(
+                credentials.network_key_handle(),
+                self.network_state.iv_index_state.transmission_iv_index(),
+                src,
+                dst,
+                Ttl::new(0),
+            ),
+        )?;
+
+        let upper_pdu = self.process_outbound_message(sequence, &message.into())?;
+        let network_pdus = self.process_outbound_upper_pdu(sequence, &upper_pdu, None)?;
+        let network_pdu = network_pdus.first().ok_or(DriverError::InvalidState)?;
+        self.encrypt_network_pdu_with_credentials(network_pdu, &credentials)
+    }
+
+    /// Whether this low power node has an established friendship on the subnet,
+    /// whose friendship credentials publications may use.
+    #[cfg(feature = "low_power")]
+    fn friendship_credentials_available(&self, network_key_handle: NetworkKeyHandle) -> bool {
+        self.low_power.is_established()
+            && self.low_power.network_key_handle() == Some(network_key_handle)
+    }
+
+    #[cfg(not(feature = "low_power"))]
+    fn friendship_credentials_available(&self, _network_key_handle: NetworkKeyHandle) -> bool {
+        false
+    }
+
+    /// Friendship credentials of every friendship of this node, along with
+    /// the subnet they were derived from.
+    #[cfg(any(feature = "friend", feature = "low_power"))]
+    fn friendship_credentials_iter(
+        &self,
+    ) -> impl Iterator<Item = (NetworkKeyHandle, NetworkKey)> + '_ {
+        #[cfg(feature = "friend")]
+        let friend = self.friend.credentials_iter();
+        #[cfg(not(feature = "friend"))]
+        let friend = core::iter::empty();
+
+        #[cfg(feature = "low_power")]
+        let low_power = self.low_power.credentials();
+        #[cfg(not(feature = "low_power"))]
+        let low_power = None;
+
+        friend.chain(low_power)
+    }
 }
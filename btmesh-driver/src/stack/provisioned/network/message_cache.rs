@@ -0,0 +1,80 @@
+use btmesh_pdu::provisioned::network::NetworkPDU;
+use uluru::LRUCache;
+
+#[derive(PartialEq)]
+struct CacheEntry {
+    obfuscated: [u8; 6],
+    net_mic: [u8; 4],
+}
+
+impl From<&NetworkPDU> for CacheEntry {
+    fn from(pdu: &NetworkPDU) -> Self {
+        let encrypted_and_mic = pdu.encrypted_and_mic();
+        let mut net_mic = [0; 4];
+        if encrypted_and_mic.len() >= 4 {
+            net_mic.copy_from_slice(&encrypted_and_mic[encrypted_and_mic.len() - 4..]);
+        }
+        Self {
+            obfuscated: *pdu.obfuscated(),
+            net_mic,
+        }
+    }
+}
+
+/// Cache of recently seen network PDUs, identified by their obfuscated header
+/// and the tail of their NetMIC, so duplicates may be dropped before decryption.
+#[derive(Default)]
+pub struct NetworkMessageCache<const N: usize> {
+    lru: LRUCache<CacheEntry, N>,
+}
+
+impl<const N: usize> NetworkMessageCache<N> {
+    pub fn has_seen(&mut self, pdu: &NetworkPDU) -> bool {
+        let entry = CacheEntry::from(pdu);
+        self.lru.find(|e| *e == entry).is_some()
+    }
+
+    pub fn add(&mut self, pdu: &NetworkPDU) {
+        if !self.has_seen(pdu) {
+            self.lru.insert(pdu.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::stack::provisioned::network::message_cache::NetworkMessageCache;
+    use btmesh_common::crypto::network::Nid;
+    use btmesh_common::Ivi;
+    use btmesh_pdu::provisioned::network::NetworkPDU;
+
+    fn pdu(seed: u8) -> NetworkPDU {
+        NetworkPDU::new(
+            Ivi::Zero,
+            Nid::new(0x68),
+            [seed, 1, 2, 3, 4, 5],
+            &[seed; 13],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn duplicates_are_seen() {
+        let mut cache = NetworkMessageCache::<4>::default();
+        assert!(!cache.has_seen(&pdu(1)));
+        cache.add(&pdu(1));
+        assert!(cache.has_seen(&pdu(1)));
+        assert!(!cache.has_seen(&pdu(2)));
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted() {
+        let mut cache = NetworkMessageCache::<2>::default();
+        cache.add(&pdu(1));
+        cache.add(&pdu(2));
+        cache.add(&pdu(3));
+        assert!(!cache.has_seen(&pdu(1)));
+        assert!(cache.has_seen(&pdu(2)));
+        assert!(cache.has_seen(&pdu(3)));
+    }
+}
@@ -1,8 +1,9 @@
+use crate::stack::provisioned::network::message_cache::NetworkMessageCache;
 use crate::stack::provisioned::system::NetworkMetadata;
 use crate::stack::provisioned::{DriverError, ProvisionedStack, ReplayProtection};
 use btmesh_common::address::{Address, UnicastAddress};
 use btmesh_common::crypto::network::{NetMic, NetworkKey, Nid};
-use btmesh_common::crypto::nonce::NetworkNonce;
+use btmesh_common::crypto::nonce::{NetworkNonce, ProxyNonce};
 use btmesh_common::{crypto, Ctl, IvIndex, Seq, Ttl};
 use btmesh_pdu::provisioned::network::{CleartextNetworkPDU, NetworkPDU};
 use heapless::Vec;
@@ -11,6 +12,7 @@ use btmesh_device::NetworkKeyHandle;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+pub mod message_cache;
 pub mod replay_protection;
 
 #[derive(Copy, Clone, Hash, Debug)]
@@ -32,6 +34,10 @@ impl DeviceInfo {
         }
     }
 
+    pub fn number_of_elements(&self) -> u8 {
+        self.number_of_elements
+    }
+
     pub fn local_element_index(&self, dst: Address) -> Option<u8> {
         if let Address::Unicast(dst) = dst {
             if dst >= self.primary_unicast_address {
@@ -53,16 +59,38 @@ impl DeviceInfo {
     }
 }
 
-pub struct NetworkDriver {
+/// Security credentials a network PDU is encrypted with.
+#[derive(Copy, Clone)]
+pub enum Credentials {
+    /// The master security credentials of the subnet.
+    Master(NetworkKeyHandle),
+    /// Friendship security credentials, derived from those of the subnet
+    /// for a particular friendship.
+    Friendship(NetworkKeyHandle, NetworkKey),
+}
+
+impl Credentials {
+    pub fn network_key_handle(&self) -> NetworkKeyHandle {
+        match self {
+            Credentials::Master(network_key_handle) => *network_key_handle,
+            Credentials::Friendship(network_key_handle, _) => *network_key_handle,
+        }
+    }
+}
+
+/// Network layer state, caching up to `C` recently seen network PDUs.
+pub struct NetworkDriver<const C: usize = 32> {
     device_info: DeviceInfo,
     replay_protection: ReplayProtection,
+    message_cache: NetworkMessageCache<C>,
 }
 
-impl NetworkDriver {
+impl<const C: usize> NetworkDriver<C> {
     pub(crate) fn new(device_info: DeviceInfo) -> Self {
         Self {
             device_info,
             replay_protection: Default::default(),
+            message_cache: Default::default(),
         }
     }
 
@@ -74,6 +102,16 @@ impl NetworkDriver {
     fn local_element_index(&self, dst: Address) -> Option<u8> {
         self.device_info.local_element_index(dst)
     }
+
+    /// Whether an inbound network PDU may be relayed, which it may not if
+    /// it cannot travel any further, has been seen before, or was sent from
+    /// or to a local element.
+    fn is_relayable(&self, pdu: &CleartextNetworkPDU<ProvisionedStack>) -> bool {
+        pdu.ttl().value() >= 2
+            && !pdu.meta().is_replay_protected()
+            && pdu.meta().local_element_index().is_none()
+            && self.local_element_index(pdu.src().into()).is_none()
+    }
 }
 
 impl ProvisionedStack {
@@ -85,6 +123,16 @@ impl ProvisionedStack {
         self.secrets.network_key(handle)
     }
 
+    fn credentials_network_key(
+        &self,
+        credentials: &Credentials,
+    ) -> Result<NetworkKey, DriverError> {
+        match credentials {
+            Credentials::Master(network_key_handle) => self.network_key(*network_key_handle),
+            Credentials::Friendship(_, network_key) => Ok(*network_key),
+        }
+    }
+
     pub fn validate_cleartext_network_pdu(&mut self, pdu: &mut CleartextNetworkPDU<Self>) {
         self.network.replay_protection.check(pdu);
     }
@@ -92,6 +140,68 @@ impl ProvisionedStack {
     pub fn encrypt_network_pdu(
         &mut self,
         cleartext_pdu: &CleartextNetworkPDU<ProvisionedStack>,
+    ) -> Result<NetworkPDU, DriverError> {
+        let network_key_handle = cleartext_pdu.meta().network_key_handle();
+        let credentials = match self.lpn_friendship_credentials(cleartext_pdu) {
+            Some(credentials) => Credentials::Friendship(network_key_handle, credentials),
+            None => Credentials::Master(network_key_handle),
+        };
+        self.encrypt_network_pdu_with_credentials(cleartext_pdu, &credentials)
+    }
+
+    /// Friendship credentials of the friendship of this low power node, if the PDU
+    /// is to be secured using them and the friendship is on the subnet of the PDU.
+    #[cfg(feature = "low_power")]
+    fn lpn_friendship_credentials(
+        &self,
+        cleartext_pdu: &CleartextNetworkPDU<ProvisionedStack>,
+    ) -> Option<NetworkKey> {
+        match self.low_power.credentials() {
+            Some((network_key_handle, credentials))
+                if cleartext_pdu.meta().is_lpn_friendship()
+                    && network_key_handle == cleartext_pdu.meta().network_key_handle() =>
+            {
+                Some(credentials)
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg(not(feature = "low_power"))]
+    fn lpn_friendship_credentials(
+        &self,
+        _cleartext_pdu: &CleartextNetworkPDU<ProvisionedStack>,
+    ) -> Option<NetworkKey> {
+        None
+    }
+
+    /// Encrypt using the credentials given rather than the master credentials of the subnet.
+    pub fn encrypt_network_pdu_with_credentials(
+        &mut self,
+        cleartext_pdu: &CleartextNetworkPDU<ProvisionedStack>,
+        credentials: &Credentials,
+    ) -> Result<NetworkPDU, DriverError> {
+        let network_key = self.credentials_network_key(credentials)?;
+        self.encrypt(cleartext_pdu, &network_key, false)
+    }
+
+    /// Encrypt a proxy configuration PDU destined for the connected proxy client.
+    #[cfg(feature = "proxy")]
+    pub fn encrypt_proxy_configuration_pdu(
+        &mut self,
+        cleartext_pdu: &CleartextNetworkPDU<ProvisionedStack>,
+    ) -> Result<NetworkPDU, DriverError> {
+        let network_key = self.network_key(cleartext_pdu.meta().network_key_handle())?;
+        self.encrypt(cleartext_pdu, &network_key, true)
+    }
+
+    /// Encrypt using either the network nonce or, for proxy configuration
+    /// PDUs, the proxy nonce.
+    fn encrypt(
+        &mut self,
+        cleartext_pdu: &CleartextNetworkPDU<ProvisionedStack>,
+        network_key: &NetworkKey,
+        proxy: bool,
     ) -> Result<NetworkPDU, DriverError> {
         let ctl_ttl = match cleartext_pdu.ctl() {
             Ctl::Access => 0,
@@ -108,47 +218,32 @@ impl ProvisionedStack {
             .extend_from_slice(cleartext_pdu.transport_pdu())
             .map_err(|_| DriverError::InsufficientSpace)?;
 
-        let network_key = self.network_key(cleartext_pdu.meta().network_key_handle())?;
-
-        let nonce = NetworkNonce::new(
-            ctl_ttl,
-            cleartext_pdu.seq(),
-            cleartext_pdu.src(),
-            cleartext_pdu.meta().iv_index(),
-        );
-
-        match cleartext_pdu.ctl() {
-            Ctl::Access => {
-                let mut mic = NetMic::new_access();
-
-                crypto::network::encrypt_network(
-                    &network_key,
-                    &nonce,
-                    &mut encrypted_and_mic,
-                    &mut mic,
-                )
-                .map_err(|_| DriverError::CryptoError)?;
-
-                encrypted_and_mic
-                    .extend_from_slice(mic.as_ref())
-                    .map_err(|_| DriverError::InsufficientSpace)?;
-            }
-            Ctl::Control => {
-                let mut mic = NetMic::new_control();
-
-                crypto::network::encrypt_network(
-                    &network_key,
-                    &nonce,
-                    &mut encrypted_and_mic,
-                    &mut mic,
-                )
-                .map_err(|_| DriverError::CryptoError)?;
-
-                encrypted_and_mic
-                    .extend_from_slice(mic.as_ref())
-                    .map_err(|_| DriverError::InsufficientSpace)?;
-            }
+        let mut mic = match cleartext_pdu.ctl() {
+            Ctl::Access => NetMic::new_access(),
+            Ctl::Control => NetMic::new_control(),
+        };
+
+        if proxy {
+            let nonce = ProxyNonce::new(
+                cleartext_pdu.seq(),
+                cleartext_pdu.src(),
+                cleartext_pdu.meta().iv_index(),
+            );
+            crypto::network::encrypt_proxy(network_key, &nonce, &mut encrypted_and_mic, &mut mic)
+        } else {
+            let nonce = NetworkNonce::new(
+                ctl_ttl,
+                cleartext_pdu.seq(),
+                cleartext_pdu.src(),
+                cleartext_pdu.meta().iv_index(),
+            );
+            crypto::network::encrypt_network(network_key, &nonce, &mut encrypted_and_mic, &mut mic)
         }
+        .map_err(|_| DriverError::CryptoError)?;
+
+        encrypted_and_mic
+            .extend_from_slice(mic.as_ref())
+            .map_err(|_| DriverError::InsufficientSpace)?;
 
         let privacy_plaintext =
             crypto::privacy_plaintext(cleartext_pdu.meta().iv_index(), &encrypted_and_mic);
@@ -169,9 +264,10 @@ impl ProvisionedStack {
         unobfuscated[5] = src_bytes[1];
         let obfuscated = crypto::pecb_xor(pecb, unobfuscated);
 
+        // the NID identifies the credentials used, which a relayed PDU may not share.
         let network_pdu = NetworkPDU::new(
             cleartext_pdu.ivi(),
-            cleartext_pdu.nid(),
+            network_key.nid(),
             obfuscated,
             &*encrypted_and_mic,
         )?;
@@ -193,6 +289,10 @@ impl ProvisionedStack {
         pdu: &NetworkPDU,
         iv_index: IvIndex,
     ) -> Result<Option<CleartextNetworkPDU<ProvisionedStack>>, DriverError> {
+        if self.network.message_cache.has_seen(pdu) {
+            return Ok(None);
+        }
+
         let mut result = None;
         for network_key in self.network_keys_by_nid(pdu.nid()) {
             if let Ok(pdu) = self.try_decrypt_network_pdu_with_key(pdu, iv_index, network_key) {
@@ -201,20 +301,132 @@ impl ProvisionedStack {
             }
         }
 
+        #[cfg(any(feature = "friend", feature = "low_power"))]
+        if result.is_none() {
+            for (network_key_handle, credentials) in self.friendship_credentials_iter() {
+                if credentials.nid() != pdu.nid() {
+                    continue;
+                }
+                if let Ok(pdu) =
+                    self.try_decrypt(pdu, iv_index, network_key_handle, &credentials, false)
+                {
+                    #[cfg(feature = "low_power")]
+                    let mut pdu = pdu;
+                    #[cfg(feature = "low_power")]
+                    pdu.meta_mut().lpn_friendship(
+                        self.low_power.credentials() == Some((network_key_handle, credentials)),
+                    );
+                    result.replace(pdu);
+                    break;
+                }
+            }
+        }
+
+        if let Some(result) = &mut result {
+            // only authenticated PDUs are cached.
+            self.network.message_cache.add(pdu);
+            self.validate_cleartext_network_pdu(result);
+        }
+
+        Ok(result)
+    }
+
+    /// Decrypt a proxy configuration PDU received from the connected proxy client.
+    #[cfg(feature = "proxy")]
+    pub fn try_decrypt_proxy_configuration_pdu(
+        &mut self,
+        pdu: &NetworkPDU,
+        iv_index: IvIndex,
+    ) -> Result<Option<CleartextNetworkPDU<ProvisionedStack>>, DriverError> {
+        let mut result = None;
+        for network_key in self.network_keys_by_nid(pdu.nid()) {
+            if let Ok(pdu) = self.try_decrypt_with_key(pdu, iv_index, network_key, true) {
+                result.replace(pdu);
+                break;
+            }
+        }
+
         if let Some(result) = &mut result {
+            // proxy configuration messages are unsegmented control messages
+            // with a TTL of zero, sent to the unassigned address.
+            if !matches!(result.ctl(), Ctl::Control)
+                || result.ttl().value() != 0
+                || result.dst() != Address::Unassigned
+            {
+                return Ok(None);
+            }
             self.validate_cleartext_network_pdu(result);
+            if result.meta().is_replay_protected() {
+                return Ok(None);
+            }
         }
 
         Ok(result)
     }
 
+    /// Produce the network PDU to retransmit on behalf of other nodes, if the
+    /// inbound PDU is eligible for relaying or proxying.
+    pub fn relay_network_pdu(
+        &mut self,
+        pdu: &CleartextNetworkPDU<ProvisionedStack>,
+    ) -> Result<Option<NetworkPDU>, DriverError> {
+        if !self.network.is_relayable(pdu) {
+            return Ok(None);
+        }
+
+        // relayed PDUs are secured using master credentials.
+        let mut meta = *pdu.meta();
+        meta.lpn_friendship(false);
+        let relayed_pdu = CleartextNetworkPDU::new(
+            pdu.ivi(),
+            pdu.nid(),
+            pdu.ctl(),
+            Ttl::new(pdu.ttl().value() - 1),
+            pdu.seq(),
+            pdu.src(),
+            pdu.dst(),
+            pdu.transport_pdu(),
+            meta,
+        )?;
+
+        let relayed_pdu = self.encrypt_network_pdu(&relayed_pdu)?;
+        // hearing our own relayed PDU from another relay must not cause it to be relayed again.
+        self.network.message_cache.add(&relayed_pdu);
+        Ok(Some(relayed_pdu))
+    }
+
     pub fn try_decrypt_network_pdu_with_key(
         &self,
         pdu: &NetworkPDU,
         iv_index: IvIndex,
         network_key_handle: NetworkKeyHandle,
+    ) -> Result<CleartextNetworkPDU<ProvisionedStack>, DriverError> {
+        self.try_decrypt_with_key(pdu, iv_index, network_key_handle, false)
+    }
+
+    /// Decrypt using either the network nonce or, for proxy configuration
+    /// PDUs, the proxy nonce.
+    fn try_decrypt_with_key(
+        &self,
+        pdu: &NetworkPDU,
+        iv_index: IvIndex,
+        network_key_handle: NetworkKeyHandle,
+        proxy: bool,
     ) -> Result<CleartextNetworkPDU<ProvisionedStack>, DriverError> {
         let network_key = self.network_key(network_key_handle)?;
+        self.try_decrypt(pdu, iv_index, network_key_handle, &network_key, proxy)
+    }
+
+    /// Decrypt using the credentials given, attributing the PDU to the subnet
+    /// of the network key handle.
+    fn try_decrypt(
+        &self,
+        pdu: &NetworkPDU,
+        iv_index: IvIndex,
+        network_key_handle: NetworkKeyHandle,
+        network_key: &NetworkKey,
+        proxy: bool,
+    ) -> Result<CleartextNetworkPDU<ProvisionedStack>, DriverError> {
         let mut encrypted_and_mic = Vec::<_, 28>::from_slice(pdu.encrypted_and_mic())
             .map_err(|_| DriverError::InsufficientSpace)?;
         let privacy_plaintext = crypto::privacy_plaintext(iv_index, &encrypted_and_mic);
@@ -232,12 +444,7 @@ impl ProvisionedStack {
             unobfuscated[3],
         ]))?;
 
-        let nonce = NetworkNonce::new(
-            unobfuscated[0],
-            seq,
-            UnicastAddress::parse([unobfuscated[4], unobfuscated[5]])?,
-            iv_index,
-        );
+        let src = UnicastAddress::parse([unobfuscated[4], unobfuscated[5]])?;
 
         let encrypted_len = encrypted_and_mic.len();
 
@@ -245,7 +452,15 @@ impl ProvisionedStack {
 
         let mic = NetMic::parse(mic)?;
 
-        if crypto::network::try_decrypt_network(&network_key, &nonce, payload, &mic).is_ok() {
+        let decrypted = if proxy {
+            let nonce = ProxyNonce::new(seq, src, iv_index);
+            crypto::network::try_decrypt_proxy(network_key, &nonce, payload, &mic)
+        } else {
+            let nonce = NetworkNonce::new(unobfuscated[0], seq, src, iv_index);
+            crypto::network::try_decrypt_network(network_key, &nonce, payload, &mic)
+        };
+
+        if decrypted.is_ok() {
             let ttl = Ttl::parse(unobfuscated[0] & 0b01111111)?;
             /*
             let seq = Seq::parse(u32::from_be_bytes([
@@ -256,7 +471,6 @@ impl ProvisionedStack {
             ]))?;
              */
 
-            let src = UnicastAddress::parse([unobfuscated[4], unobfuscated[5]])?;
             let dst = Address::parse([payload[0], payload[1]]);
             let transport_pdu = &payload[2..];
 
@@ -283,8 +497,13 @@ impl ProvisionedStack {
 
 #[cfg(test)]
 mod test {
-    use crate::stack::provisioned::network::DeviceInfo;
+    use crate::stack::provisioned::network::{DeviceInfo, NetworkDriver};
+    use crate::stack::provisioned::system::NetworkMetadata;
     use btmesh_common::address::{Address, GroupAddress, UnicastAddress};
+    use btmesh_common::crypto::network::Nid;
+    use btmesh_common::{Ctl, IvIndex, Ivi, Seq, Ttl};
+    use btmesh_device::NetworkKeyHandle;
+    use btmesh_pdu::provisioned::network::CleartextNetworkPDU;
 
     #[test]
     fn local_element_index() {
@@ -331,4 +550,52 @@ mod test {
             device_info.local_element_index(Address::Group(GroupAddress::AllNodes))
         );
     }
+
+    #[test]
+    fn relay_exclusions() {
+        let network: NetworkDriver = NetworkDriver::new(DeviceInfo::new(
+            UnicastAddress::parse([0x00, 0x0A]).unwrap(),
+            2,
+        ));
+
+        let pdu = |ttl: u8, src: [u8; 2], dst: [u8; 2], replay_protected: bool| {
+            let src = UnicastAddress::parse(src).unwrap();
+            let dst = UnicastAddress::parse(dst).unwrap().into();
+            let mut meta = NetworkMetadata::new(
+                IvIndex::new(0),
+                network.local_element_index(dst),
+                NetworkKeyHandle(0, Nid::new(0x42)),
+            );
+            meta.replay_protected(replay_protected);
+            CleartextNetworkPDU::new(
+                Ivi::Zero,
+                Nid::new(0x42),
+                Ctl::Access,
+                Ttl::new(ttl),
+                Seq::new(1),
+                src,
+                dst,
+                &[0x00, 0x01, 0x02],
+                meta,
+            )
+            .unwrap()
+        };
+
+        // from and to other nodes, with hops to spare.
+        assert!(network.is_relayable(&pdu(5, [0x00, 0x01], [0x00, 0x02], false)));
+        assert!(network.is_relayable(&pdu(2, [0x00, 0x01], [0x00, 0x02], false)));
+
+        // unable to travel any further.
+        assert!(!network.is_relayable(&pdu(1, [0x00, 0x01], [0x00, 0x02], false)));
+        assert!(!network.is_relayable(&pdu(0, [0x00, 0x01], [0x00, 0x02], false)));
+
+        // seen before.
+        assert!(!network.is_relayable(&pdu(5, [0x00, 0x01], [0x00, 0x02], true)));
+
+        // sent from a local element.
+        assert!(!network.is_relayable(&pdu(5, [0x00, 0x0B], [0x00, 0x02], false)));
+
+        // sent to a local element.
+        assert!(!network.is_relayable(&pdu(5, [0x00, 0x01], [0x00, 0x0A], false)));
+    }
 }
@@ -0,0 +1,268 @@
+use crate::stack::provisioned::sequence::Sequence;
+use crate::stack::provisioned::system::NetworkMetadata;
+use crate::stack::provisioned::{DriverError, ProvisionedStack};
+use btmesh_common::address::{Address, UnicastAddress};
+use btmesh_common::{Ctl, InsufficientBuffer, Ttl};
+use btmesh_pdu::provisioned::network::{CleartextNetworkPDU, NetworkPDU};
+use btmesh_pdu::provisioned::proxy::{FilterType, ProxyConfigurationMessage};
+use heapless::Vec;
+
+/// Filter of the connected proxy client, deciding which network PDUs
+/// are passed on to it.
+///
+/// A filter only lives as long as the connection it was configured
+/// through, starting out as an empty whitelist for every new connection.
+pub struct ProxyFilter<const N: usize = 16> {
+    connection: Option<u32>,
+    filter_type: FilterType,
+    addresses: Vec<Address, N>,
+}
+
+impl<const N: usize> Default for ProxyFilter<N> {
+    fn default() -> Self {
+        Self {
+            connection: None,
+            filter_type: FilterType::Whitelist,
+            addresses: Vec::new(),
+        }
+    }
+}
+
+impl<const N: usize> ProxyFilter<N> {
+    /// Reset the filter if the proxy client has changed since it was configured.
+    pub fn connection(&mut self, connection: u32) {
+        if self.connection != Some(connection) {
+            *self = Self {
+                connection: Some(connection),
+                ..Default::default()
+            };
+        }
+    }
+
+    pub fn set_filter_type(&mut self, filter_type: FilterType) {
+        self.filter_type = filter_type;
+        self.addresses.clear();
+    }
+
+    /// Add an address to the filter, failing once the filter is full. The filter
+    /// status reports the actual list size, so the client learns of any dropped address.
+    pub fn add(&mut self, address: Address) -> Result<(), InsufficientBuffer> {
+        if address != Address::Unassigned && !self.addresses.contains(&address) {
+            self.addresses
+                .push(address)
+                .map_err(|_| InsufficientBuffer)?;
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, address: Address) {
+        if let Some(index) = self.addresses.iter().position(|e| *e == address) {
+            self.addresses.swap_remove(index);
+        }
+    }
+
+    /// Account for a network PDU sent by the proxy client itself, so that
+    /// responses to it are not filtered out.
+    pub fn client_address(&mut self, src: UnicastAddress) {
+        match self.filter_type {
+            FilterType::Whitelist => {
+                if self.add(src.into()).is_err() {
+                    warn!("proxy filter full, not adding client address {}", src);
+                }
+            }
+            FilterType::Blacklist => self.remove(src.into()),
+        }
+    }
+
+    pub fn accepts(&self, dst: Address) -> bool {
+        match self.filter_type {
+            FilterType::Whitelist => self.addresses.contains(&dst),
+            FilterType::Blacklist => !self.addresses.contains(&dst),
+        }
+    }
+
+    pub fn status(&self) -> ProxyConfigurationMessage {
+        ProxyConfigurationMessage::FilterStatus {
+            filter_type: self.filter_type,
+            list_size: self.addresses.len() as u16,
+        }
+    }
+}
+
+impl ProvisionedStack {
+    /// Note the current connection of the proxy client, resetting the filter
+    /// when a new client has connected.
+    pub fn proxy_connection(&mut self, connection: u32) {
+        self.proxy_filter.connection(connection);
+    }
+
+    pub fn proxy_filter_accepts(&self, dst: Address) -> bool {
+        self.proxy_filter.accepts(dst)
+    }
+
+    /// Apply a proxy configuration message from the proxy client, producing
+    /// the filter status PDU to send back to it.
+    pub fn process_inbound_proxy_configuration_pdu(
+        &mut self,
+        sequence: &Sequence,
+        pdu: &NetworkPDU,
+    ) -> Result<Option<NetworkPDU>, DriverError> {
+        let iv_index = self
+            .network_state
+            .iv_index_state
+            .accepted_iv_index(pdu.ivi());
+
+        let cleartext_pdu =
+            if let Some(pdu) = self.try_decrypt_proxy_configuration_pdu(pdu, iv_index)? {
+                pdu
+            } else {
+                return Ok(None);
+            };
+
+        let message =
+            if let Ok(message) = ProxyConfigurationMessage::parse(cleartext_pdu.transport_pdu()) {
+                message
+            } else {
+                warn!("ignoring invalid proxy configuration message");
+                return Ok(None);
+            };
+        debug!("inbound proxy configuration message: {}", message);
+
+        match message {
+            ProxyConfigurationMessage::SetFilterType(filter_type) => {
+                self.proxy_filter.set_filter_type(filter_type);
+            }
+            ProxyConfigurationMessage::AddAddresses(addresses) => {
+                for address in addresses {
+                    if self.proxy_filter.add(address).is_err() {
+                        warn!("proxy filter full, dropping address {}", address);
+                    }
+                }
+            }
+            ProxyConfigurationMessage::RemoveAddresses(addresses) => {
+                for address in addresses {
+                    self.proxy_filter.remove(address);
+                }
+            }
+            ProxyConfigurationMessage::FilterStatus { .. } => {
+                // only sent by proxy servers.
+                return Ok(None);
+            }
+        }
+
+        let mut transport_pdu = Vec::<u8, 16>::new();
+        self.proxy_filter.status().emit(&mut transport_pdu)?;
+
+        let iv_index = self.network_state.iv_index_state.transmission_iv_index();
+        let network_key_handle = cleartext_pdu.meta().network_key_handle();
+        let src = self
+            .network
+            .device_info()
+            .local_element_address(0)
+            .ok_or(DriverError::InvalidState)?;
+
+        let status_pdu = CleartextNetworkPDU::new(
+            iv_index.ivi(),
+            self.secrets.network_key(network_key_handle)?.nid(),
+            Ctl::Control,
+            Ttl::new(0),
+            sequence.next(),
+            src,
+            Address::Unassigned,
+            &transport_pdu,
+            NetworkMetadata::new(iv_index, None, network_key_handle),
+        )?;
+
+        Ok(Some(self.encrypt_proxy_configuration_pdu(&status_pdu)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::stack::provisioned::proxy::ProxyFilter;
+    use btmesh_common::address::{Address, GroupAddress, UnicastAddress};
+    use btmesh_pdu::provisioned::proxy::{FilterType, ProxyConfigurationMessage};
+
+    fn unicast(address: u8) -> UnicastAddress {
+        UnicastAddress::parse([0x00, address]).unwrap()
+    }
+
+    #[test]
+    fn new_connection_starts_with_empty_whitelist() {
+        let mut filter = ProxyFilter::<4>::default();
+        filter.connection(1);
+        filter.set_filter_type(FilterType::Blacklist);
+        assert!(filter.accepts(unicast(0x0A).into()));
+
+        filter.connection(1);
+        assert!(filter.accepts(unicast(0x0A).into()));
+
+        filter.connection(2);
+        assert!(!filter.accepts(unicast(0x0A).into()));
+        assert_eq!(
+            ProxyConfigurationMessage::FilterStatus {
+                filter_type: FilterType::Whitelist,
+                list_size: 0
+            },
+            filter.status()
+        );
+    }
+
+    #[test]
+    fn whitelist() {
+        let mut filter = ProxyFilter::<4>::default();
+        filter.add(Address::Group(GroupAddress::AllNodes)).unwrap();
+        filter.add(unicast(0x0A).into()).unwrap();
+        filter.add(unicast(0x0A).into()).unwrap();
+
+        assert!(filter.accepts(Address::Group(GroupAddress::AllNodes)));
+        assert!(filter.accepts(unicast(0x0A).into()));
+        assert!(!filter.accepts(unicast(0x0B).into()));
+
+        filter.remove(unicast(0x0A).into());
+        assert!(!filter.accepts(unicast(0x0A).into()));
+
+        // the client's own address is added for it.
+        filter.client_address(unicast(0x0B));
+        assert!(filter.accepts(unicast(0x0B).into()));
+    }
+
+    #[test]
+    fn blacklist() {
+        let mut filter = ProxyFilter::<4>::default();
+        filter.set_filter_type(FilterType::Blacklist);
+        filter.add(unicast(0x0A).into()).unwrap();
+        filter.add(unicast(0x0B).into()).unwrap();
+
+        assert!(!filter.accepts(unicast(0x0A).into()));
+        assert!(filter.accepts(unicast(0x0C).into()));
+
+        // the client's own address is never blacklisted.
+        filter.client_address(unicast(0x0B));
+        assert!(filter.accepts(unicast(0x0B).into()));
+        assert_eq!(
+            ProxyConfigurationMessage::FilterStatus {
+                filter_type: FilterType::Blacklist,
+                list_size: 1
+            },
+            filter.status()
+        );
+    }
+
+    #[test]
+    fn full_filter_reports_actual_list_size() {
+        let mut filter = ProxyFilter::<2>::default();
+        filter.add(unicast(0x0A).into()).unwrap();
+        filter.add(unicast(0x0B).into()).unwrap();
+        assert!(filter.add(unicast(0x0C).into()).is_err());
+
+        assert!(!filter.accepts(unicast(0x0C).into()));
+        assert_eq!(
+            ProxyConfigurationMessage::FilterStatus {
+                filter_type: FilterType::Whitelist,
+                list_size: 2
+            },
+            filter.status()
+        );
+    }
+}
@@ -1,15 +1,52 @@
 use crate::stack::provisioned::DriverError;
 use btmesh_common::crypto::application::{Aid, ApplicationKey};
+use btmesh_models::foundation::configuration::{AppKeyIndex, NetKeyIndex};
 use heapless::Vec;
 
 use btmesh_device::ApplicationKeyHandle;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// An application key, along with its key index and the network key it is bound to.
+///
+/// During the key refresh procedure of the bound network key, the new key
+/// distributed to replace it is held alongside.
+#[derive(Copy, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct ApplicationKeyEntry {
+    pub(crate) app_key_index: AppKeyIndex,
+    pub(crate) net_key_index: NetKeyIndex,
+    pub(crate) key: ApplicationKey,
+    pub(crate) new_key: Option<ApplicationKey>,
+}
+
+impl ApplicationKeyEntry {
+    /// The key used for transmission, given whether the bound network key
+    /// has moved on to using its new key.
+    pub(crate) fn transmit_key(&self, use_new_key: bool) -> ApplicationKey {
+        match self.new_key {
+            Some(new_key) if use_new_key => new_key,
+            _ => self.key,
+        }
+    }
+
+    /// The key material identified by `aid`, preferring the key used for transmission.
+    pub(crate) fn key_by_aid(&self, aid: Aid, use_new_key: bool) -> Option<ApplicationKey> {
+        let transmit_key = self.transmit_key(use_new_key);
+        if transmit_key.aid() == aid {
+            Some(transmit_key)
+        } else {
+            core::iter::once(self.key)
+                .chain(self.new_key)
+                .find(|key| key.aid() == aid)
+        }
+    }
+}
+
 #[derive(Clone, Debug, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) struct ApplicationKeys<const N: usize = 4> {
-    pub(crate) keys: Vec<Option<ApplicationKey>, N>,
+    pub(crate) keys: Vec<Option<ApplicationKeyEntry>, N>,
 }
 
 impl<const N: usize> Default for ApplicationKeys<N> {
@@ -21,13 +58,23 @@ impl<const N: usize> Default for ApplicationKeys<N> {
 }
 
 impl<const N: usize> ApplicationKeys<N> {
+    pub fn display(&self) {
+        for entry in self.keys.iter().flatten() {
+            info!(
+                "application_key[{}]: bound to network_key[{}]",
+                entry.app_key_index.value(),
+                entry.net_key_index.value()
+            );
+        }
+    }
+
     pub(crate) fn by_aid_iter(&self, aid: Aid) -> impl Iterator<Item = ApplicationKeyHandle> + '_ {
         self.keys
             .iter()
             .enumerate()
             .filter(move |e| {
-                if let (_, Some(application_key)) = e {
-                    application_key.aid() == aid
+                if let (_, Some(entry)) = e {
+                    entry.key_by_aid(aid, false).is_some()
                 } else {
                     false
                 }
@@ -35,17 +82,174 @@ impl<const N: usize> ApplicationKeys<N> {
             .map(move |(index, _)| ApplicationKeyHandle(index as u8, aid))
     }
 
-    pub(crate) fn set(
+    /// Locate the slot and entry holding the key with the given key index.
+    pub(crate) fn get(&self, app_key_index: AppKeyIndex) -> Option<(u8, &ApplicationKeyEntry)> {
+        self.keys
+            .iter()
+            .enumerate()
+            .find_map(|(slot, entry)| match entry {
+                Some(entry) if entry.app_key_index == app_key_index => Some((slot as u8, entry)),
+                _ => None,
+            })
+    }
+
+    pub(crate) fn indexes_iter(
+        &self,
+        net_key_index: NetKeyIndex,
+    ) -> impl Iterator<Item = AppKeyIndex> + '_ {
+        self.keys
+            .iter()
+            .flatten()
+            .filter(move |entry| entry.net_key_index == net_key_index)
+            .map(|entry| entry.app_key_index)
+    }
+
+    /// Store a key in the first free slot, or replace the key already stored for its key index.
+    pub(crate) fn add(
         &mut self,
-        index: u8,
+        app_key_index: AppKeyIndex,
+        net_key_index: NetKeyIndex,
         application_key: ApplicationKey,
     ) -> Result<(), DriverError> {
-        if index as usize >= N {
-            return Err(DriverError::InsufficientSpace);
-        }
+        let slot = if let Some((slot, _)) = self.get(app_key_index) {
+            slot as usize
+        } else {
+            self.keys
+                .iter()
+                .position(Option::is_none)
+                .ok_or(DriverError::InsufficientSpace)?
+        };
 
-        self.keys[index as usize].replace(application_key);
+        self.keys[slot].replace(ApplicationKeyEntry {
+            app_key_index,
+            net_key_index,
+            key: application_key,
+            new_key: None,
+        });
 
         Ok(())
     }
+
+    /// Distribute the new key for the given key index, during the key refresh
+    /// procedure of the network key it is bound to.
+    ///
+    /// Distributing the same new key again is a successful no-op.
+    pub(crate) fn update(
+        &mut self,
+        app_key_index: AppKeyIndex,
+        application_key: ApplicationKey,
+    ) -> Result<(), DriverError> {
+        let (slot, _) = self
+            .get(app_key_index)
+            .ok_or(DriverError::InvalidKeyHandle)?;
+        let entry = self.keys[slot as usize]
+            .as_mut()
+            .ok_or(DriverError::InvalidKeyHandle)?;
+
+        match entry.new_key {
+            None => {
+                entry.new_key.replace(application_key);
+                Ok(())
+            }
+            Some(new_key) if *new_key == *application_key => Ok(()),
+            Some(_) => Err(DriverError::InvalidState),
+        }
+    }
+
+    /// Remove all keys bound to the given network key, returning their key indexes.
+    pub(crate) fn remove_bound(&mut self, net_key_index: NetKeyIndex) -> Vec<AppKeyIndex, N> {
+        let mut removed = Vec::new();
+        for entry in self.keys.iter_mut() {
+            if matches!(entry, Some(bound) if bound.net_key_index == net_key_index) {
+                if let Some(entry) = entry.take() {
+                    // at most N keys can be removed.
+                    removed.push(entry.app_key_index).ok();
+                }
+            }
+        }
+        removed
+    }
+
+    /// Revoke the old keys bound to the given network key, once its key refresh
+    /// procedure has completed.
+    pub(crate) fn revoke(&mut self, net_key_index: NetKeyIndex) {
+        for entry in self.keys.iter_mut().flatten() {
+            if entry.net_key_index == net_key_index {
+                if let Some(new_key) = entry.new_key.take() {
+                    entry.key = new_key;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, app_key_index: AppKeyIndex) {
+        if let Some((slot, _)) = self.get(app_key_index) {
+            self.keys[slot as usize].take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stack::provisioned::secrets::application::ApplicationKeys;
+    use btmesh_common::crypto::application::ApplicationKey;
+    use btmesh_models::foundation::configuration::{AppKeyIndex, NetKeyIndex};
+
+    #[test]
+    fn application_keys_by_key_index() {
+        let mut keys = ApplicationKeys::<2>::default();
+        let key = ApplicationKey::new([0x63; 16]).unwrap();
+
+        keys.add(AppKeyIndex::new(0x123), NetKeyIndex::new(0), key)
+            .unwrap();
+        keys.add(AppKeyIndex::new(0x456), NetKeyIndex::new(1), key)
+            .unwrap();
+        assert!(keys
+            .add(AppKeyIndex::new(0x789), NetKeyIndex::new(0), key)
+            .is_err());
+
+        let (slot, entry) = keys.get(AppKeyIndex::new(0x456)).unwrap();
+        assert_eq!(1, slot);
+        assert_eq!(NetKeyIndex::new(1), entry.net_key_index);
+
+        assert_eq!(2, keys.by_aid_iter(key.aid()).count());
+        assert_eq!(1, keys.indexes_iter(NetKeyIndex::new(0)).count());
+
+        keys.remove(AppKeyIndex::new(0x123));
+        assert!(keys.get(AppKeyIndex::new(0x123)).is_none());
+        assert!(keys
+            .add(AppKeyIndex::new(0x789), NetKeyIndex::new(0), key)
+            .is_ok());
+
+        let removed = keys.remove_bound(NetKeyIndex::new(1));
+        assert_eq!(&[AppKeyIndex::new(0x456)], &*removed);
+        assert!(keys.get(AppKeyIndex::new(0x456)).is_none());
+        assert!(keys.get(AppKeyIndex::new(0x789)).is_some());
+    }
+
+    #[test]
+    fn application_key_refresh() {
+        let mut keys = ApplicationKeys::<2>::default();
+        let old_key = ApplicationKey::new([0x63; 16]).unwrap();
+        let new_key = ApplicationKey::new([0x64; 16]).unwrap();
+
+        keys.add(AppKeyIndex::new(0x123), NetKeyIndex::new(0), old_key)
+            .unwrap();
+        assert!(keys.update(AppKeyIndex::new(0x456), new_key).is_err());
+
+        keys.update(AppKeyIndex::new(0x123), new_key).unwrap();
+        keys.update(AppKeyIndex::new(0x123), new_key).unwrap();
+        assert!(keys.update(AppKeyIndex::new(0x123), old_key).is_err());
+
+        let (_, entry) = keys.get(AppKeyIndex::new(0x123)).unwrap();
+        assert_eq!(*old_key, *entry.transmit_key(false));
+        assert_eq!(*new_key, *entry.transmit_key(true));
+        assert_eq!(1, keys.by_aid_iter(old_key.aid()).count());
+        assert_eq!(1, keys.by_aid_iter(new_key.aid()).count());
+
+        keys.revoke(NetKeyIndex::new(0));
+        let (_, entry) = keys.get(AppKeyIndex::new(0x123)).unwrap();
+        assert_eq!(*new_key, *entry.key);
+        assert!(entry.new_key.is_none());
+    }
 }
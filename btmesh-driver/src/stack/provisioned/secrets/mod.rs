@@ -1,10 +1,16 @@
 use crate::stack::provisioned::secrets::application::ApplicationKeys;
-use crate::stack::provisioned::secrets::network::NetworkKeys;
+use crate::stack::provisioned::secrets::network::{NetworkKeyEntry, NetworkKeys};
 use crate::stack::provisioned::DriverError;
 use btmesh_common::crypto::application::{Aid, ApplicationKey};
 use btmesh_common::crypto::device::DeviceKey;
 use btmesh_common::crypto::network::{NetworkKey, Nid};
+use btmesh_common::NetworkId;
+use btmesh_models::foundation::configuration::key_refresh_phase::{
+    KeyRefreshPhase, KeyRefreshTransition,
+};
+use btmesh_models::foundation::configuration::{AppKeyIndex, NetKeyIndex};
 use btmesh_pdu::provisioning::ProvisioningData;
+use heapless::Vec;
 
 use btmesh_device::{ApplicationKeyHandle, NetworkKeyHandle};
 #[cfg(feature = "serde")]
@@ -35,6 +41,7 @@ impl Secrets {
     pub fn display(&self) {
         info!("device_key: {}", self.device_key);
         self.network_keys.display();
+        self.application_keys.display();
     }
 
     pub(crate) fn new(
@@ -60,22 +67,84 @@ impl Secrets {
         self.network_keys.by_nid_iter(nid)
     }
 
+    pub(crate) fn network_key_by_network_id(
+        &self,
+        network_id: NetworkId,
+    ) -> Option<NetworkKeyHandle> {
+        self.network_keys.by_network_id(network_id)
+    }
+
+    /// Resolve the key material referenced by a handle, which during the key
+    /// refresh procedure may be either the old or the new key.
     pub(crate) fn network_key(
         &self,
         network_key: NetworkKeyHandle,
     ) -> Result<NetworkKey, DriverError> {
-        self.network_keys.keys[network_key.0 as usize]
-            .as_ref()
+        self.network_keys
+            .slot(network_key.0)
+            .and_then(|entry| entry.key_by_nid(network_key.1))
+            .ok_or(DriverError::InvalidKeyHandle)
+    }
+
+    /// Resolve the handle of the key currently used for transmission on the
+    /// subnet with the given key index.
+    pub(crate) fn network_key_handle(
+        &self,
+        index: NetKeyIndex,
+    ) -> Result<NetworkKeyHandle, DriverError> {
+        self.network_keys
+            .get(index)
+            .map(|(slot, entry)| NetworkKeyHandle(slot, entry.transmit_key().nid()))
+            .ok_or(DriverError::InvalidKeyHandle)
+    }
+
+    /// Resolve the entry of the subnet referenced by a handle.
+    pub(crate) fn network_key_entry(
+        &self,
+        network_key: NetworkKeyHandle,
+    ) -> Result<&NetworkKeyEntry, DriverError> {
+        self.network_keys
+            .slot(network_key.0)
             .ok_or(DriverError::InvalidKeyHandle)
-            .cloned()
     }
 
-    pub(crate) fn network_key_by_index(&self, index: u8) -> Result<NetworkKey, DriverError> {
-        if let Some(network_key) = self.network_keys.keys[index as usize] {
-            Ok(network_key)
-        } else {
-            Err(DriverError::InvalidKeyHandle)
+    pub(crate) fn has_network_key(&self, net_key_index: NetKeyIndex) -> bool {
+        self.network_keys.get(net_key_index).is_some()
+    }
+
+    pub(crate) fn key_refresh_phase(&self, net_key_index: NetKeyIndex) -> Option<KeyRefreshPhase> {
+        self.network_keys
+            .get(net_key_index)
+            .map(|(_, entry)| entry.phase)
+    }
+
+    /// Move the key refresh procedure of a network key along, revoking the
+    /// old application keys bound to it once the procedure completes.
+    pub(crate) fn key_refresh_transition(
+        &mut self,
+        net_key_index: NetKeyIndex,
+        transition: KeyRefreshTransition,
+    ) -> Result<KeyRefreshPhase, DriverError> {
+        let phase = self.network_keys.transition(net_key_index, transition)?;
+        if let KeyRefreshTransition::RevokeOldKeys = transition {
+            self.application_keys.revoke(net_key_index);
         }
+        Ok(phase)
+    }
+
+    /// Remove a network key, along with the application keys bound to it,
+    /// returning the key indexes of those application keys.
+    pub(crate) fn remove_network_key(&mut self, net_key_index: NetKeyIndex) -> Vec<AppKeyIndex, 4> {
+        self.network_keys.remove(net_key_index);
+        self.application_keys.remove_bound(net_key_index)
+    }
+
+    /// Whether keys bound to the given network key are transmitted using their new keys.
+    fn uses_new_keys(&self, net_key_index: NetKeyIndex) -> bool {
+        matches!(
+            self.key_refresh_phase(net_key_index),
+            Some(KeyRefreshPhase::Second)
+        )
     }
 
     pub(crate) fn application_keys_by_aid(
@@ -85,13 +154,73 @@ impl Secrets {
         self.application_keys.by_aid_iter(aid)
     }
 
+    /// Resolve the handle of the application key with the given key index.
+    pub(crate) fn application_key_handle(
+        &self,
+        index: AppKeyIndex,
+    ) -> Result<ApplicationKeyHandle, DriverError> {
+        self.application_keys
+            .get(index)
+            .map(|(slot, entry)| {
+                let application_key = entry.transmit_key(self.uses_new_keys(entry.net_key_index));
+                ApplicationKeyHandle(slot, application_key.aid())
+            })
+            .ok_or(DriverError::InvalidKeyHandle)
+    }
+
+    /// Resolve the handle of the network key the application key with the given key index is bound to.
+    pub(crate) fn bound_network_key_handle(
+        &self,
+        index: AppKeyIndex,
+    ) -> Result<NetworkKeyHandle, DriverError> {
+        let (_, entry) = self
+            .application_keys
+            .get(index)
+            .ok_or(DriverError::InvalidKeyHandle)?;
+        self.network_key_handle(entry.net_key_index)
+    }
+
+    /// Resolve the key material referenced by a handle, which during the key
+    /// refresh procedure may be either the old or the new key.
     pub(crate) fn application_key(
         &self,
         application_key: ApplicationKeyHandle,
     ) -> Result<ApplicationKey, DriverError> {
-        self.application_keys.keys[application_key.0 as usize]
-            .as_ref()
+        self.application_keys
+            .keys
+            .get(application_key.0 as usize)
+            .and_then(|entry| entry.as_ref())
+            .and_then(|entry| {
+                entry.key_by_aid(application_key.1, self.uses_new_keys(entry.net_key_index))
+            })
             .ok_or(DriverError::InvalidKeyHandle)
-            .cloned()
+    }
+
+    /// Resolve the key index of the application key referenced by a handle.
+    pub(crate) fn application_key_index(
+        &self,
+        application_key: ApplicationKeyHandle,
+    ) -> Option<AppKeyIndex> {
+        self.application_keys
+            .keys
+            .get(application_key.0 as usize)
+            .and_then(|entry| entry.as_ref())
+            .map(|entry| entry.app_key_index)
+    }
+
+    pub(crate) fn network_keys(&self) -> &NetworkKeys {
+        &self.network_keys
+    }
+
+    pub(crate) fn network_keys_mut(&mut self) -> &mut NetworkKeys {
+        &mut self.network_keys
+    }
+
+    pub(crate) fn application_keys(&self) -> &ApplicationKeys {
+        &self.application_keys
+    }
+
+    pub(crate) fn application_keys_mut(&mut self) -> &mut ApplicationKeys {
+        &mut self.application_keys
     }
 }
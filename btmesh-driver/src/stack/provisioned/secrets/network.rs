@@ -1,15 +1,96 @@
 use crate::stack::provisioned::DriverError;
 use btmesh_common::crypto::network::{NetworkKey, Nid};
+use btmesh_common::{KeyRefreshFlag, NetworkId};
 use btmesh_device::NetworkKeyHandle;
+use btmesh_models::foundation::configuration::key_refresh_phase::{
+    KeyRefreshPhase, KeyRefreshTransition,
+};
+use btmesh_models::foundation::configuration::NetKeyIndex;
 use btmesh_pdu::provisioning::ProvisioningData;
 use heapless::Vec;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// A network key and its key index, along with the new key distributed to
+/// replace it during the key refresh procedure.
+#[derive(Copy, Clone, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct NetworkKeyEntry {
+    pub(crate) net_key_index: NetKeyIndex,
+    pub(crate) key: NetworkKey,
+    pub(crate) new_key: Option<NetworkKey>,
+    pub(crate) phase: KeyRefreshPhase,
+}
+
+impl NetworkKeyEntry {
+    fn new(net_key_index: NetKeyIndex, key: NetworkKey) -> Self {
+        Self {
+            net_key_index,
+            key,
+            new_key: None,
+            phase: KeyRefreshPhase::Normal,
+        }
+    }
+
+    /// The key used for transmission, which is the new key once in the second phase.
+    pub(crate) fn transmit_key(&self) -> NetworkKey {
+        match (self.phase, self.new_key) {
+            (KeyRefreshPhase::Second, Some(new_key)) => new_key,
+            _ => self.key,
+        }
+    }
+
+    /// The key material identified by `nid`, preferring the key used for transmission.
+    pub(crate) fn key_by_nid(&self, nid: Nid) -> Option<NetworkKey> {
+        let transmit_key = self.transmit_key();
+        if transmit_key.nid() == nid {
+            Some(transmit_key)
+        } else {
+            self.keys_iter().find(|key| key.nid() == nid)
+        }
+    }
+
+    /// Whether `network_key` is the new key distributed during the key refresh procedure.
+    pub(crate) fn is_new_key(&self, network_key: &NetworkKey) -> bool {
+        matches!(self.new_key, Some(new_key) if new_key == *network_key)
+    }
+
+    /// The old key, and the new key while the key refresh procedure is in progress.
+    fn keys_iter(&self) -> impl Iterator<Item = NetworkKey> {
+        core::iter::once(self.key).chain(self.new_key)
+    }
+
+    /// Apply a key refresh phase transition, returning the resulting phase.
+    ///
+    /// Revoking the old key completes the procedure, returning to normal operation
+    /// using the new key only.
+    fn transition(
+        &mut self,
+        transition: KeyRefreshTransition,
+    ) -> Result<KeyRefreshPhase, DriverError> {
+        match (self.phase, transition) {
+            (KeyRefreshPhase::Normal, KeyRefreshTransition::UseNewKeys) => {
+                return Err(DriverError::InvalidState);
+            }
+            (KeyRefreshPhase::First, KeyRefreshTransition::UseNewKeys) => {
+                self.phase = KeyRefreshPhase::Second;
+            }
+            (KeyRefreshPhase::Second, KeyRefreshTransition::UseNewKeys) => {}
+            (_, KeyRefreshTransition::RevokeOldKeys) => {
+                if let Some(new_key) = self.new_key.take() {
+                    self.key = new_key;
+                }
+                self.phase = KeyRefreshPhase::Normal;
+            }
+        }
+        Ok(self.phase)
+    }
+}
+
 #[derive(Clone, Debug, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub(crate) struct NetworkKeys<const N: usize = 4> {
-    pub(crate) keys: Vec<Option<NetworkKey>, N>,
+    pub(crate) keys: Vec<Option<NetworkKeyEntry>, N>,
 }
 
 impl<const N: usize> Default for NetworkKeys<N> {
@@ -23,49 +104,151 @@ impl<const N: usize> Default for NetworkKeys<N> {
 impl<const N: usize> From<ProvisioningData> for NetworkKeys<N> {
     fn from(data: ProvisioningData) -> Self {
         let mut keys = Self::default();
-        keys.keys[0].replace(NetworkKey::new(data.network_key).unwrap());
+        let network_key = NetworkKey::new(data.network_key).unwrap();
+        let mut entry = NetworkKeyEntry::new(NetKeyIndex::new(data.key_index), network_key);
+        if let KeyRefreshFlag(true) = data.key_refresh_flag {
+            // provisioned in the middle of the key refresh procedure,
+            // with only the new key having been handed over.
+            entry.new_key.replace(network_key);
+            entry.phase = KeyRefreshPhase::Second;
+        }
+        keys.keys[0].replace(entry);
         keys
     }
 }
 
 impl<const N: usize> NetworkKeys<N> {
     pub fn display(&self) {
-        for (index, key) in self.keys.iter().enumerate() {
-            if let Some(key) = key {
-                info!("network_key[{}]: {}", index, key);
+        for entry in self.keys.iter().flatten() {
+            info!(
+                "network_key[{}]: {}",
+                entry.net_key_index.value(),
+                entry.key
+            );
+            if let Some(new_key) = &entry.new_key {
+                info!(
+                    "network_key[{}]: {} (new, phase {})",
+                    entry.net_key_index.value(),
+                    new_key,
+                    entry.phase as u8
+                );
             }
         }
     }
 
-    pub(crate) fn by_nid_iter(&self, nid: Nid) -> impl Iterator<Item = NetworkKeyHandle> + '_ {
+    /// Locate the slot and entry holding the key with the given key index.
+    pub(crate) fn get(&self, net_key_index: NetKeyIndex) -> Option<(u8, &NetworkKeyEntry)> {
+        self.iter()
+            .find(|(_, entry)| entry.net_key_index == net_key_index)
+    }
+
+    /// Locate the entry held in the given slot.
+    pub(crate) fn slot(&self, slot: u8) -> Option<&NetworkKeyEntry> {
+        self.keys.get(slot as usize).and_then(Option::as_ref)
+    }
+
+    fn get_mut(&mut self, net_key_index: NetKeyIndex) -> Option<&mut NetworkKeyEntry> {
+        self.keys
+            .iter_mut()
+            .flatten()
+            .find(|entry| entry.net_key_index == net_key_index)
+    }
+
+    /// Iterate the slots and entries of all stored keys.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (u8, &NetworkKeyEntry)> + '_ {
         self.keys
             .iter()
             .enumerate()
-            .filter(move |e| {
-                if let (_, Some(network_key)) = e {
-                    network_key.nid() == nid
-                } else {
-                    false
-                }
-            })
-            .map(move |(index, _)| NetworkKeyHandle(index as u8, nid))
+            .filter_map(|(slot, entry)| entry.as_ref().map(|entry| (slot as u8, entry)))
     }
 
-    pub(crate) fn set(&mut self, index: u8, network_key: NetworkKey) -> Result<(), DriverError> {
-        if index as usize >= N {
-            return Err(DriverError::InsufficientSpace);
-        }
+    pub(crate) fn indexes_iter(&self) -> impl Iterator<Item = NetKeyIndex> + '_ {
+        self.iter().map(|(_, entry)| entry.net_key_index)
+    }
+
+    pub(crate) fn by_nid_iter(&self, nid: Nid) -> impl Iterator<Item = NetworkKeyHandle> + '_ {
+        self.iter()
+            .filter(move |(_, entry)| entry.key_by_nid(nid).is_some())
+            .map(move |(slot, _)| NetworkKeyHandle(slot, nid))
+    }
+
+    pub(crate) fn by_network_id(&self, network_id: NetworkId) -> Option<NetworkKeyHandle> {
+        self.iter().find_map(|(slot, entry)| {
+            entry
+                .keys_iter()
+                .find(|network_key| network_key.network_id() == network_id)
+                .map(|network_key| NetworkKeyHandle(slot, network_key.nid()))
+        })
+    }
+
+    /// Store a key in the first free slot, or replace the key already stored for its key index.
+    pub(crate) fn add(
+        &mut self,
+        net_key_index: NetKeyIndex,
+        network_key: NetworkKey,
+    ) -> Result<(), DriverError> {
+        let slot = if let Some((slot, _)) = self.get(net_key_index) {
+            slot as usize
+        } else {
+            self.keys
+                .iter()
+                .position(Option::is_none)
+                .ok_or(DriverError::InsufficientSpace)?
+        };
 
-        self.keys[index as usize].replace(network_key);
+        self.keys[slot].replace(NetworkKeyEntry::new(net_key_index, network_key));
 
         Ok(())
     }
+
+    pub(crate) fn remove(&mut self, net_key_index: NetKeyIndex) {
+        if let Some((slot, _)) = self.get(net_key_index) {
+            self.keys[slot as usize].take();
+        }
+    }
+
+    /// Distribute the new key for the given key index, starting the key refresh procedure.
+    ///
+    /// Distributing the same new key again is a successful no-op.
+    pub(crate) fn update(
+        &mut self,
+        net_key_index: NetKeyIndex,
+        network_key: NetworkKey,
+    ) -> Result<(), DriverError> {
+        let entry = self
+            .get_mut(net_key_index)
+            .ok_or(DriverError::InvalidKeyHandle)?;
+
+        match (entry.phase, entry.new_key) {
+            (KeyRefreshPhase::Normal, _) => {
+                entry.new_key.replace(network_key);
+                entry.phase = KeyRefreshPhase::First;
+                Ok(())
+            }
+            (KeyRefreshPhase::First, Some(new_key)) if new_key == network_key => Ok(()),
+            _ => Err(DriverError::InvalidState),
+        }
+    }
+
+    pub(crate) fn transition(
+        &mut self,
+        net_key_index: NetKeyIndex,
+        transition: KeyRefreshTransition,
+    ) -> Result<KeyRefreshPhase, DriverError> {
+        self.get_mut(net_key_index)
+            .ok_or(DriverError::InvalidKeyHandle)?
+            .transition(transition)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::stack::provisioned::secrets::network::NetworkKeys;
     use btmesh_common::crypto::network::{EncryptionKey, NetworkKey, Nid, PrivacyKey};
+    use btmesh_models::foundation::configuration::key_refresh_phase::{
+        KeyRefreshPhase, KeyRefreshTransition,
+    };
+    use btmesh_models::foundation::configuration::NetKeyIndex;
 
     #[test]
     fn network_key_iteration_empty() {
@@ -80,6 +263,30 @@ mod tests {
         assert_eq!(0, found)
     }
 
+    #[test]
+    fn network_keys_by_key_index() {
+        let mut keys = NetworkKeys::<2>::default();
+        let key = NetworkKey::new([0x63; 16]).unwrap();
+
+        keys.add(NetKeyIndex::new(0x123), key).unwrap();
+        keys.add(NetKeyIndex::new(0x456), key).unwrap();
+        assert!(keys.add(NetKeyIndex::new(0x789), key).is_err());
+        // replacing the key of a stored key index takes no extra slot.
+        keys.add(NetKeyIndex::new(0x123), key).unwrap();
+
+        let (slot, entry) = keys.get(NetKeyIndex::new(0x456)).unwrap();
+        assert_eq!(1, slot);
+        assert_eq!(NetKeyIndex::new(0x456), entry.net_key_index);
+        assert!(keys.get(NetKeyIndex::new(0x001)).is_none());
+        assert_eq!(2, keys.by_nid_iter(key.nid()).count());
+        assert_eq!(2, keys.indexes_iter().count());
+
+        keys.remove(NetKeyIndex::new(0x123));
+        assert!(keys.get(NetKeyIndex::new(0x123)).is_none());
+        assert!(keys.add(NetKeyIndex::new(0x789), key).is_ok());
+        assert_eq!(0, keys.get(NetKeyIndex::new(0x789)).unwrap().0);
+    }
+
     #[test]
     fn network_key_derivation() {
         // 8.2.2 Encryption and privacy keys (Master)
@@ -103,4 +310,50 @@ mod tests {
         assert_eq!(privacy_key, network_key.privacy_key());
         assert_eq!(encryption_key, network_key.encryption_key());
     }
+
+    #[test]
+    fn key_refresh() {
+        let mut keys = NetworkKeys::<4>::default();
+        let old_key = NetworkKey::new([0x11; 16]).unwrap();
+        let new_key = NetworkKey::new([0x22; 16]).unwrap();
+        let index = NetKeyIndex::new(0x123);
+        keys.add(index, old_key).unwrap();
+
+        // the new keys cannot be used before having been distributed.
+        assert!(keys
+            .transition(index, KeyRefreshTransition::UseNewKeys)
+            .is_err());
+
+        keys.update(index, new_key).unwrap();
+        keys.update(index, new_key).unwrap();
+        assert!(keys.update(index, old_key).is_err());
+
+        let (_, entry) = keys.get(index).unwrap();
+        assert_eq!(KeyRefreshPhase::First, entry.phase);
+        assert_eq!(old_key, entry.transmit_key());
+        assert_eq!(1, keys.by_nid_iter(old_key.nid()).count());
+        assert_eq!(1, keys.by_nid_iter(new_key.nid()).count());
+        assert_eq!(
+            Some(new_key.nid()),
+            keys.by_network_id(new_key.network_id())
+                .map(|handle| handle.1)
+        );
+
+        assert_eq!(
+            KeyRefreshPhase::Second,
+            keys.transition(index, KeyRefreshTransition::UseNewKeys)
+                .unwrap()
+        );
+        assert_eq!(new_key, keys.get(index).unwrap().1.transmit_key());
+
+        assert_eq!(
+            KeyRefreshPhase::Normal,
+            keys.transition(index, KeyRefreshTransition::RevokeOldKeys)
+                .unwrap()
+        );
+        let (_, entry) = keys.get(index).unwrap();
+        assert_eq!(new_key, entry.key);
+        assert!(entry.new_key.is_none());
+        assert_eq!(0, keys.by_nid_iter(old_key.nid()).count());
+    }
 }
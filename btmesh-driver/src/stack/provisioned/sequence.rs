@@ -16,6 +16,11 @@ impl Sequence {
         Seq::new(self.seq.fetch_add(1, Ordering::Relaxed))
     }
 
+    /// Start over from zero, once a new IV index is used for transmission.
+    pub fn reset(&self) {
+        self.seq.store(0, Ordering::Relaxed);
+    }
+
     pub fn current(&self) -> u32 {
         self.seq.load(Ordering::Relaxed)
     }
@@ -7,6 +7,7 @@ use btmesh_device::{
     ApplicationKeyHandle, InboundMetadata, KeyHandle, NetworkKeyHandle, OutboundMetadata,
 };
 use btmesh_pdu::provisioned::access::AccessMessage;
+use btmesh_pdu::provisioned::control::ControlMessage;
 use btmesh_pdu::provisioned::lower::{LowerPDU, SegmentedLowerPDU, UnsegmentedLowerPDU};
 use btmesh_pdu::provisioned::network::CleartextNetworkPDU;
 use btmesh_pdu::provisioned::upper::access::UpperAccessPDU;
@@ -23,6 +24,7 @@ pub struct NetworkMetadata {
     should_relay: bool,
     local_element_index: Option<u8>,
     network_key_handle: NetworkKeyHandle,
+    lpn_friendship: bool,
 }
 
 impl NetworkMetadata {
@@ -37,6 +39,7 @@ impl NetworkMetadata {
             should_relay: false,
             local_element_index,
             network_key_handle: network_key,
+            lpn_friendship: false,
         }
     }
 
@@ -52,6 +55,19 @@ impl NetworkMetadata {
         self.replay_protected = protected;
     }
 
+    pub fn is_replay_protected(&self) -> bool {
+        self.replay_protected
+    }
+
+    /// Secured using the friendship credentials of the friendship of this low power node.
+    pub fn lpn_friendship(&mut self, lpn_friendship: bool) {
+        self.lpn_friendship = lpn_friendship;
+    }
+
+    pub fn is_lpn_friendship(&self) -> bool {
+        self.lpn_friendship
+    }
+
     pub fn should_relay(&mut self, relay: bool) {
         self.should_relay = relay;
     }
@@ -67,6 +83,7 @@ impl NetworkMetadata {
             should_relay: false,
             local_element_index: pdu.meta().local_element_index(),
             network_key_handle: pdu.meta().network_key_handle(),
+            lpn_friendship: pdu.meta().friendship_credentials(),
         }
     }
 }
@@ -169,6 +186,7 @@ pub struct UpperMetadata {
     dst: Address,
     ttl: Ttl,
     label_uuids: Vec<LabelUuid, 3>,
+    friendship_credentials: bool,
 }
 
 impl UpperMetadata {
@@ -187,6 +205,7 @@ impl UpperMetadata {
             dst: pdu.meta().dst(),
             ttl: pdu.meta().ttl(),
             label_uuids: Default::default(),
+            friendship_credentials: false,
         }
     }
 
@@ -205,6 +224,7 @@ impl UpperMetadata {
             dst: pdu.meta().dst(),
             ttl: pdu.meta().ttl(),
             label_uuids: Default::default(),
+            friendship_credentials: false,
         }
     }
 
@@ -229,6 +249,22 @@ impl UpperMetadata {
             dst: message.meta().dst(),
             ttl: message.meta().ttl(),
             label_uuids: Default::default(),
+            friendship_credentials: message.meta().friendship_credentials(),
+        }
+    }
+
+    pub fn from_control_message(message: &ControlMessage<ProvisionedStack>, seq: Seq) -> Self {
+        Self {
+            network_key_handle: message.meta().network_key_handle(),
+            iv_index: message.meta().iv_index(),
+            local_element_index: None,
+            akf_aid: None,
+            seq,
+            src: message.meta().src(),
+            dst: message.meta().dst(),
+            ttl: message.meta().ttl(),
+            label_uuids: Default::default(),
+            friendship_credentials: false,
         }
     }
 
@@ -268,6 +304,11 @@ impl UpperMetadata {
         &*self.label_uuids
     }
 
+    /// Outbound PDUs secured using the friendship credentials of this low power node.
+    pub fn friendship_credentials(&self) -> bool {
+        self.friendship_credentials
+    }
+
     pub fn add_label_uuid(&mut self, label_uuid: LabelUuid) -> Result<(), DriverError> {
         self.label_uuids
             .push(label_uuid)
@@ -287,6 +328,7 @@ pub struct AccessMetadata {
     dst: Address,
     ttl: Ttl,
     label_uuid: Option<LabelUuid>,
+    friendship_credentials: bool,
 }
 
 impl From<(UnicastAddress, OutboundMetadata, Ttl)> for AccessMetadata {
@@ -303,7 +345,8 @@ impl From<(UnicastAddress, OutboundMetadata, Ttl)> for AccessMetadata {
             src,
             dst: meta.dst(),
             ttl: meta.ttl().unwrap_or(default_ttl),
-            label_uuid: None,
+            label_uuid: meta.label_uuid(),
+            friendship_credentials: meta.friendship_credentials(),
         }
     }
 }
@@ -323,6 +366,7 @@ impl AccessMetadata {
             dst: pdu.meta().dst(),
             ttl: pdu.meta().ttl(),
             label_uuid,
+            friendship_credentials: false,
         }
     }
 
@@ -357,6 +401,10 @@ impl AccessMetadata {
     pub fn local_element_index(&self) -> Option<u8> {
         self.local_element_index
     }
+
+    pub fn friendship_credentials(&self) -> bool {
+        self.friendship_credentials
+    }
 }
 
 impl From<&AccessMetadata> for InboundMetadata {
@@ -375,11 +423,59 @@ impl From<&AccessMetadata> for InboundMetadata {
 
 #[derive(Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct ControlMetadata {}
+pub struct ControlMetadata {
+    network_key_handle: NetworkKeyHandle,
+    iv_index: IvIndex,
+    src: UnicastAddress,
+    dst: Address,
+    ttl: Ttl,
+}
 
 impl ControlMetadata {
-    pub fn from_upper_control_pdu(_pdu: &UpperControlPDU<ProvisionedStack>) -> Self {
-        Self {}
+    pub fn new(
+        network_key_handle: NetworkKeyHandle,
+        iv_index: IvIndex,
+        src: UnicastAddress,
+        dst: Address,
+        ttl: Ttl,
+    ) -> Self {
+        Self {
+            network_key_handle,
+            iv_index,
+            src,
+            dst,
+            ttl,
+        }
+    }
+
+    pub fn from_upper_control_pdu(pdu: &UpperControlPDU<ProvisionedStack>) -> Self {
+        Self {
+            network_key_handle: pdu.meta().network_key_handle(),
+            iv_index: pdu.meta().iv_index(),
+            src: pdu.meta().src(),
+            dst: pdu.meta().dst(),
+            ttl: pdu.meta().ttl(),
+        }
+    }
+
+    pub fn network_key_handle(&self) -> NetworkKeyHandle {
+        self.network_key_handle
+    }
+
+    pub fn iv_index(&self) -> IvIndex {
+        self.iv_index
+    }
+
+    pub fn src(&self) -> UnicastAddress {
+        self.src
+    }
+
+    pub fn dst(&self) -> Address {
+        self.dst
+    }
+
+    pub fn ttl(&self) -> Ttl {
+        self.ttl
     }
 }
 
@@ -1,37 +1,137 @@
 use crate::stack::provisioned::ProvisionedStack;
-use btmesh_common::{InsufficientBuffer, SeqZero};
+use btmesh_common::address::{Address, UnicastAddress};
+use btmesh_common::{InsufficientBuffer, SeqZero, Ttl};
+use btmesh_device::CompletionToken;
 use btmesh_pdu::provisioned::lower::BlockAck;
 use btmesh_pdu::provisioned::upper::UpperPDU;
+use embassy_executor::time::{Duration, Instant};
 use heapless::Vec;
 
+/// Times unacknowledged segments are retransmitted towards a unicast address
+/// before the transfer is cancelled.
+const UNICAST_RETRANSMISSIONS: u8 = 4;
+
+/// Times segments towards a group or virtual address are repeated,
+/// since such transfers are never acknowledged.
+const MULTICAST_RETRANSMISSIONS: u8 = 2;
+
+/// Segment transmission timer, allowing acknowledgements more time
+/// to arrive from nodes further away.
+fn segment_transmission_interval(ttl: Ttl) -> Duration {
+    Duration::from_millis(200 + 50 * ttl.value() as u64)
+}
+
+/// Segmented upper PDUs in flight, retransmitted until acknowledged.
 #[derive(Default)]
 pub struct TransmitQueue<const N: usize = 5> {
-    queue: Vec<Option<QueueEntry>, N>,
+    queue: Vec<QueueEntry, N>,
 }
 
 struct QueueEntry {
     upper_pdu: UpperPDU<ProvisionedStack>,
     acked: Acked,
+    retransmissions: u8,
+    deadline: Instant,
+    completion_token: Option<CompletionToken>,
 }
 
 impl<const N: usize> TransmitQueue<N> {
+    /// Track a segmented upper PDU, once its segments have been transmitted for the first time.
     pub fn add(
         &mut self,
         upper_pdu: UpperPDU<ProvisionedStack>,
         num_segments: u8,
+        completion_token: Option<CompletionToken>,
+        now: Instant,
     ) -> Result<(), InsufficientBuffer> {
-        let slot = self.queue.iter_mut().find(|e| matches!(e, None));
-
         let seq_zero = upper_pdu.meta().seq().into();
+        let retransmissions = if let Address::Unicast(_) = upper_pdu.meta().dst() {
+            UNICAST_RETRANSMISSIONS
+        } else {
+            MULTICAST_RETRANSMISSIONS
+        };
+        let deadline = now + segment_transmission_interval(upper_pdu.meta().ttl());
 
-        if let Some(slot) = slot {
-            slot.replace(QueueEntry {
+        self.queue
+            .push(QueueEntry {
                 upper_pdu,
                 acked: Acked::new(seq_zero, num_segments),
-            });
+                retransmissions,
+                deadline,
+                completion_token,
+            })
+            .map_err(|_| InsufficientBuffer)
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.queue.iter().map(|entry| entry.deadline).min()
+    }
+
+    /// Process a segment acknowledgement received from `src`.
+    pub fn receive_ack(&mut self, src: UnicastAddress, block_ack: BlockAck, now: Instant) {
+        let index = if let Some(index) = self.queue.iter().position(|entry| {
+            entry.upper_pdu.meta().dst() == Address::Unicast(src)
+                && entry.acked.seq_zero() == block_ack.seq_zero()
+        }) {
+            index
+        } else {
+            return;
+        };
+
+        if block_ack.value() == 0 {
+            // the destination is too busy to receive the segments.
+            warn!("segmented transfer cancelled by destination");
+            self.conclude(index, false);
+            return;
+        }
+
+        let entry = &mut self.queue[index];
+        if entry.acked.ack(&block_ack) {
+            if entry.acked.is_complete() {
+                self.conclude(index, true);
+            } else {
+                // retransmit the remaining segments right away.
+                entry.retransmissions = UNICAST_RETRANSMISSIONS;
+                entry.deadline = now;
+            }
         }
+    }
+
+    /// Select the upper PDU whose segment transmission timer has expired,
+    /// along with its segments acknowledged so far, so the remainder can be
+    /// retransmitted. Transfers out of retransmissions are concluded along the way.
+    pub fn retransmit(&mut self, now: Instant) -> Option<(&UpperPDU<ProvisionedStack>, BlockAck)> {
+        loop {
+            let index = self.queue.iter().position(|entry| entry.deadline <= now)?;
 
-        Ok(())
+            if self.queue[index].retransmissions == 0 {
+                // transfers towards a group or virtual address are never acknowledged,
+                // and are complete once their segments have been repeated.
+                if let Address::Unicast(_) = self.queue[index].upper_pdu.meta().dst() {
+                    warn!("segmented transfer cancelled, segments remain unacknowledged");
+                    self.conclude(index, false);
+                } else {
+                    self.conclude(index, true);
+                }
+                continue;
+            }
+
+            let entry = &mut self.queue[index];
+            entry.retransmissions -= 1;
+            entry.deadline = now + segment_transmission_interval(entry.upper_pdu.meta().ttl());
+            return Some((&entry.upper_pdu, entry.acked.block_ack));
+        }
+    }
+
+    fn conclude(&mut self, index: usize, complete: bool) {
+        let entry = self.queue.swap_remove(index);
+        if let Some(completion_token) = entry.completion_token {
+            if complete {
+                completion_token.complete();
+            } else {
+                completion_token.incomplete();
+            }
+        }
     }
 }
 
@@ -47,4 +147,195 @@ impl Acked {
             block_ack: BlockAck::new(seq_zero),
         }
     }
+
+    fn seq_zero(&self) -> SeqZero {
+        self.block_ack.seq_zero()
+    }
+
+    /// Merge the segments acknowledged, returning whether any had not been acknowledged before.
+    fn ack(&mut self, block_ack: &BlockAck) -> bool {
+        let mut acked = false;
+        for seg_o in 0..self.num_segments {
+            if matches!(block_ack.is_acked(seg_o), Ok(true))
+                && matches!(self.block_ack.is_acked(seg_o), Ok(false))
+                && self.block_ack.ack(seg_o).is_ok()
+            {
+                acked = true;
+            }
+        }
+        acked
+    }
+
+    fn is_complete(&self) -> bool {
+        (0..self.num_segments).all(|seg_o| matches!(self.block_ack.is_acked(seg_o), Ok(true)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stack::provisioned::system::{ControlMetadata, UpperMetadata};
+    use crate::stack::provisioned::transmit_queue::{
+        TransmitQueue, MULTICAST_RETRANSMISSIONS, UNICAST_RETRANSMISSIONS,
+    };
+    use crate::stack::provisioned::ProvisionedStack;
+    use btmesh_common::address::{Address, UnicastAddress};
+    use btmesh_common::crypto::network::Nid;
+    use btmesh_common::{IvIndex, Seq, SeqZero, Ttl};
+    use btmesh_device::NetworkKeyHandle;
+    use btmesh_pdu::provisioned::control::ControlMessage;
+    use btmesh_pdu::provisioned::lower::BlockAck;
+    use btmesh_pdu::provisioned::upper::control::{ControlOpcode, UpperControlPDU};
+    use btmesh_pdu::provisioned::upper::UpperPDU;
+    use embassy_executor::time::{Duration, Instant};
+
+    fn destination() -> UnicastAddress {
+        UnicastAddress::parse([0x00, 0x0B]).unwrap()
+    }
+
+    fn upper_pdu(dst: Address) -> UpperPDU<ProvisionedStack> {
+        let message = ControlMessage::<ProvisionedStack>::new(
+            ControlOpcode::FriendOffer,
+            &[0; 20],
+            ControlMetadata::new(
+                NetworkKeyHandle(0, Nid::new(42)),
+                IvIndex::parse(&[1, 2, 3, 4]).unwrap(),
+                UnicastAddress::parse([0x00, 0x0A]).unwrap(),
+                dst,
+                Ttl::new(5),
+            ),
+        )
+        .unwrap();
+
+        UpperControlPDU::new(
+            message.opcode(),
+            message.parameters(),
+            UpperMetadata::from_control_message(&message, Seq::parse(1000).unwrap()),
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn block_ack(segments: &[u8]) -> BlockAck {
+        let mut block_ack = BlockAck::new(SeqZero::new(1000));
+        for seg_o in segments {
+            block_ack.ack(*seg_o).unwrap();
+        }
+        block_ack
+    }
+
+    #[test]
+    fn acknowledged_transfer() {
+        let mut queue = TransmitQueue::<2>::default();
+        let now = Instant::from_secs(10);
+        queue
+            .add(upper_pdu(destination().into()), 3, None, now)
+            .unwrap();
+
+        // 200ms + 50ms per hop.
+        assert_eq!(
+            Some(now + Duration::from_millis(450)),
+            queue.next_deadline()
+        );
+        assert!(queue.retransmit(now).is_none());
+
+        queue.receive_ack(destination(), block_ack(&[0, 2]), now);
+        assert_eq!(Some(now), queue.next_deadline());
+
+        let (_, acked) = queue.retransmit(now).unwrap();
+        assert_eq!(Ok(true), acked.is_acked(0));
+        assert_eq!(Ok(false), acked.is_acked(1));
+        assert_eq!(Ok(true), acked.is_acked(2));
+
+        queue.receive_ack(destination(), block_ack(&[0, 1, 2]), now);
+        assert_eq!(None, queue.next_deadline());
+    }
+
+    #[test]
+    fn ack_from_elsewhere_ignored() {
+        let mut queue = TransmitQueue::<2>::default();
+        let now = Instant::from_secs(10);
+        queue
+            .add(upper_pdu(destination().into()), 3, None, now)
+            .unwrap();
+
+        queue.receive_ack(
+            UnicastAddress::parse([0x00, 0x0C]).unwrap(),
+            block_ack(&[0, 1, 2]),
+            now,
+        );
+        assert!(queue.next_deadline().is_some());
+
+        let mut other_seq_zero = BlockAck::new(SeqZero::new(999));
+        other_seq_zero.ack(0).unwrap();
+        queue.receive_ack(destination(), other_seq_zero, now);
+        assert!(queue.next_deadline().is_some());
+    }
+
+    #[test]
+    fn cancelled_by_destination() {
+        let mut queue = TransmitQueue::<2>::default();
+        let now = Instant::from_secs(10);
+        queue
+            .add(upper_pdu(destination().into()), 3, None, now)
+            .unwrap();
+
+        queue.receive_ack(destination(), block_ack(&[]), now);
+        assert_eq!(None, queue.next_deadline());
+    }
+
+    #[test]
+    fn unacknowledged_transfer_cancelled() {
+        let mut queue = TransmitQueue::<2>::default();
+        let mut now = Instant::from_secs(10);
+        queue
+            .add(upper_pdu(destination().into()), 3, None, now)
+            .unwrap();
+
+        let mut retransmissions = 0;
+        loop {
+            now += Duration::from_secs(1);
+            if queue.retransmit(now).is_none() {
+                break;
+            }
+            retransmissions += 1;
+        }
+
+        assert_eq!(UNICAST_RETRANSMISSIONS, retransmissions);
+        assert_eq!(None, queue.next_deadline());
+    }
+
+    #[test]
+    fn multicast_transfer_repeated() {
+        let mut queue = TransmitQueue::<2>::default();
+        let mut now = Instant::from_secs(10);
+        queue
+            .add(upper_pdu(Address::parse([0xC0, 0x01])), 3, None, now)
+            .unwrap();
+
+        let mut retransmissions = 0;
+        loop {
+            now += Duration::from_secs(1);
+            if let Some((_, acked)) = queue.retransmit(now) {
+                assert_eq!(0, acked.value());
+            } else {
+                break;
+            }
+            retransmissions += 1;
+        }
+
+        assert_eq!(MULTICAST_RETRANSMISSIONS, retransmissions);
+        assert_eq!(None, queue.next_deadline());
+    }
+
+    #[test]
+    fn full_queue() {
+        let mut queue = TransmitQueue::<1>::default();
+        let now = Instant::from_secs(10);
+        assert!(queue
+            .add(upper_pdu(destination().into()), 3, None, now)
+            .is_ok());
+        assert!(queue
+            .add(upper_pdu(destination().into()), 3, None, now)
+            .is_err());
+    }
 }
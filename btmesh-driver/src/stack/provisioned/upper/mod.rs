@@ -9,19 +9,53 @@ use btmesh_device::KeyHandle;
 use btmesh_pdu::provisioned::access::AccessMessage;
 use btmesh_pdu::provisioned::control::ControlMessage;
 use btmesh_pdu::provisioned::upper::access::UpperAccessPDU;
+use btmesh_pdu::provisioned::upper::control::UpperControlPDU;
 use btmesh_pdu::provisioned::upper::UpperPDU;
 use btmesh_pdu::provisioned::Message;
 use core::ops::ControlFlow;
 use heapless::Vec;
 
-#[derive(Default)]
 pub struct UpperDriver<const N: usize = 20> {
     label_uuids: Vec<Option<LabelUuid>, N>,
 }
 
-impl UpperDriver {}
+impl<const N: usize> Default for UpperDriver<N> {
+    fn default() -> Self {
+        let mut label_uuids = Vec::new();
+        label_uuids.resize(N, None).ok();
+        Self { label_uuids }
+    }
+}
 
 impl ProvisionedStack {
+    /// Register exactly the given label-uuids as candidates for
+    /// virtually-addressed inbound PDUs.
+    pub(crate) fn sync_label_uuids(
+        &mut self,
+        label_uuids: impl Iterator<Item = LabelUuid> + Clone,
+    ) -> Result<(), DriverError> {
+        let stale: Vec<LabelUuid, 20> = self
+            .upper
+            .label_uuids
+            .iter()
+            .flatten()
+            .filter(|known| !label_uuids.clone().any(|e| e == **known))
+            .cloned()
+            .collect();
+
+        for label_uuid in stale {
+            self.remove_label_uuid(label_uuid);
+        }
+
+        for label_uuid in label_uuids {
+            if !self.upper.label_uuids.contains(&Some(label_uuid)) {
+                self.add_label_uuid(label_uuid)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn add_label_uuid(&mut self, label_uuid: LabelUuid) -> Result<(), DriverError> {
         if let Some(empty_slot) = self
             .upper
@@ -75,9 +109,12 @@ impl ProvisionedStack {
     ) -> Result<UpperPDU<ProvisionedStack>, DriverError> {
         match message {
             Message::Access(access) => Ok(self.encrypt_access(sequence, access)?.into()),
-            Message::Control(_control) => {
-                todo!()
-            }
+            Message::Control(control) => Ok(UpperControlPDU::new(
+                control.opcode(),
+                control.parameters(),
+                UpperMetadata::from_control_message(control, sequence.next()),
+            )?
+            .into()),
         }
     }
 
@@ -158,13 +195,12 @@ impl ProvisionedStack {
 
                 let application_key = self.secrets.application_key(key_handle)?;
 
-                let mut bytes = [0; 379];
                 let mut transmic = TransMic::new32();
 
                 crypto::application::encrypt_application_key(
                     &application_key,
                     nonce,
-                    &mut bytes,
+                    &mut *payload,
                     &mut transmic,
                     message.meta().label_uuid(),
                 )
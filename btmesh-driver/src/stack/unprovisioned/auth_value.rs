@@ -1,3 +1,4 @@
+use super::oob::{OobEvent, OobInput};
 use btmesh_common::ParseError;
 use btmesh_pdu::provisioning::{InputOOBAction, OOBAction, OOBSize, OutputOOBAction, Start};
 use heapless::Vec;
@@ -13,6 +14,7 @@ pub enum AuthValue {
     OutputNumeric(u32),
     InputAlphanumeric(Vec<u8, 8>),
     OutputAlphanumeric(Vec<u8, 8>),
+    Static([u8; 16]),
 }
 
 impl AuthValue {
@@ -37,10 +39,74 @@ impl AuthValue {
                     bytes[i] = *byte
                 }
             }
+            AuthValue::Static(value) => bytes = *value,
         }
 
         bytes
     }
+
+    /// Whether the value is entered by the user on the device.
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            AuthValue::InputEvents(_)
+                | AuthValue::InputNumeric(_)
+                | AuthValue::InputAlphanumeric(_)
+        )
+    }
+
+    /// Replace the value of an input action by the value the user entered, provided
+    /// it fits within the authentication size: fewer digits than `size` for numbers,
+    /// and at most `size` uppercase letters and digits for strings.
+    pub fn input(&self, input: OobInput, size: u8) -> Option<AuthValue> {
+        match (self, input) {
+            (AuthValue::InputEvents(_), OobInput::Numeric(num)) if num < power_of_ten(size) => {
+                Some(AuthValue::InputEvents(num))
+            }
+            (AuthValue::InputNumeric(_), OobInput::Numeric(num)) if num < power_of_ten(size) => {
+                Some(AuthValue::InputNumeric(num))
+            }
+            (AuthValue::InputAlphanumeric(_), OobInput::Alphanumeric(chars))
+                if chars.len() <= size as usize && chars.iter().all(is_alphanumeric) =>
+            {
+                Some(AuthValue::InputAlphanumeric(chars))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The action the device performs, or asks of the user, for the provisioner
+/// to learn of the value, or for the user to enter it.
+pub fn oob_event(start: &Start, auth_value: &AuthValue) -> Option<OobEvent> {
+    let size = match start.authentication_size {
+        OOBSize::MaximumSize(size) => size,
+        OOBSize::NotSupported => return None,
+    };
+    match (&start.authentication_action, auth_value) {
+        (OOBAction::Output(OutputOOBAction::Blink), AuthValue::OutputEvents(num)) => {
+            Some(OobEvent::Blink(*num))
+        }
+        (OOBAction::Output(OutputOOBAction::Beep), AuthValue::OutputEvents(num)) => {
+            Some(OobEvent::Beep(*num))
+        }
+        (OOBAction::Output(OutputOOBAction::Vibrate), AuthValue::OutputEvents(num)) => {
+            Some(OobEvent::Vibrate(*num))
+        }
+        (_, AuthValue::OutputNumeric(num)) => Some(OobEvent::DisplayNumeric(*num)),
+        (_, AuthValue::OutputAlphanumeric(chars)) => {
+            Some(OobEvent::DisplayAlphanumeric(chars.clone()))
+        }
+        (OOBAction::Input(InputOOBAction::Push), AuthValue::InputEvents(_)) => {
+            Some(OobEvent::Push(size))
+        }
+        (OOBAction::Input(InputOOBAction::Twist), AuthValue::InputEvents(_)) => {
+            Some(OobEvent::Twist(size))
+        }
+        (_, AuthValue::InputNumeric(_)) => Some(OobEvent::InputNumeric(size)),
+        (_, AuthValue::InputAlphanumeric(_)) => Some(OobEvent::InputAlphanumeric(size)),
+        _ => None,
+    }
 }
 
 pub fn determine_auth_value<RNG: RngCore>(
@@ -69,7 +135,6 @@ pub fn determine_auth_value<RNG: RngCore>(
                 let auth_raw = random_numeric(rng, *size);
                 AuthValue::OutputNumeric(auth_raw)
             }
-            // TODO actually dispatch to device/app/thing's UI for inputs instead of just making up shit.
             (OOBAction::Input(InputOOBAction::InputNumeric), OOBSize::MaximumSize(size)) => {
                 let auth_raw = random_numeric(rng, *size);
                 AuthValue::InputNumeric(auth_raw)
@@ -97,87 +162,46 @@ fn random_physical_oob<RNG: RngCore>(rng: &mut RNG, size: u8) -> u32 {
     // "select a random integer between 0 and 10 to the power of the Authentication Size exclusive"
     //
     // ... which could be an absolute metric tonne of beeps/twists/pushes if AuthSize is large-ish.
-    let mut max = 1;
-    for _ in 0..size {
-        max *= 10;
-    }
-
-    loop {
-        let candidate = rng.next_u32();
-        if candidate > 0 && candidate < max {
-            return candidate;
-        }
-    }
+    // zero events could not be told apart from no events, so at least one is output.
+    1 + random_below(rng, power_of_ten(size) - 1)
 }
 
 fn random_numeric<RNG: RngCore>(rng: &mut RNG, size: u8) -> u32 {
+    random_below(rng, power_of_ten(size))
+}
+
+/// 10 to the power of an authentication size of 1 to 8.
+fn power_of_ten(size: u8) -> u32 {
+    10u32.pow(size.clamp(1, 8) as u32)
+}
+
+/// A uniformly distributed random integer between 0 and `max` exclusive.
+fn random_below<RNG: RngCore>(rng: &mut RNG, max: u32) -> u32 {
+    // candidates beyond the last whole multiple of `max` would skew the distribution.
+    let limit = u32::MAX - u32::MAX % max;
     loop {
         let candidate = rng.next_u32();
-
-        match size {
-            1 => {
-                if candidate < 10 {
-                    return candidate;
-                }
-            }
-            2 => {
-                if candidate < 100 {
-                    return candidate;
-                }
-            }
-            3 => {
-                if candidate < 1_000 {
-                    return candidate;
-                }
-            }
-            4 => {
-                if candidate < 10_000 {
-                    return candidate;
-                }
-            }
-            5 => {
-                if candidate < 100_000 {
-                    return candidate;
-                }
-            }
-            6 => {
-                if candidate < 1_000_000 {
-                    return candidate;
-                }
-            }
-            7 => {
-                if candidate < 10_000_000 {
-                    return candidate;
-                }
-            }
-            8 => {
-                if candidate < 100_000_000 {
-                    return candidate;
-                }
-            }
-            _ => {
-                // should never get here, but...
-                return 0;
-            }
+        if candidate < limit {
+            return candidate % max;
         }
     }
 }
 
+/// Capital ASCII letters A-Z, or ASCII numbers 0-9.
+fn is_alphanumeric(byte: &u8) -> bool {
+    byte.is_ascii_uppercase() || byte.is_ascii_digit()
+}
+
 fn random_alphanumeric<RNG: RngCore>(rng: &mut RNG, size: u8) -> Result<Vec<u8, 8>, ParseError> {
     let mut random = Vec::new();
     for _ in 0..size {
         loop {
             let candidate = (rng.next_u32() & 0xFF) as u8;
-            if (64..=90).contains(&candidate) {
-                // Capital ASCII letters A-Z
-                random
-                    .push(candidate)
-                    .map_err(|_| ParseError::InsufficientBuffer)?;
-            } else if (48..=57).contains(&candidate) {
-                // ASCII numbers 0-9
+            if is_alphanumeric(&candidate) {
                 random
                     .push(candidate)
                     .map_err(|_| ParseError::InsufficientBuffer)?;
+                break;
             }
         }
     }
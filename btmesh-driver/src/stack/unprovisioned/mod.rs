@@ -1,3 +1,4 @@
+use crate::stack::unprovisioned::oob::{OobConfig, OobEvent, OobInput};
 use crate::stack::unprovisioned::provisionee::Provisionee;
 use crate::util::deadline::{Deadline, DeadlineFuture};
 use crate::util::hash::FnvHasher;
@@ -10,6 +11,7 @@ use embassy_executor::time::{Duration, Timer};
 use rand_core::{CryptoRng, RngCore};
 
 mod auth_value;
+pub mod oob;
 mod provisionee;
 mod provisioner;
 mod transcript;
@@ -24,14 +26,17 @@ pub struct UnprovisionedStack {
     provisionee: Option<Provisionee>,
     last_transmit_hash: Option<u64>,
     beacon: Deadline,
+    /// Whether an out-of-band action has been taken, to be cleared once provisioning is over.
+    oob_event_raised: bool,
 }
 
 impl UnprovisionedStack {
-    pub fn new(capabilities: Capabilities) -> Self {
+    pub fn new(capabilities: Capabilities, oob: &OobConfig) -> Self {
         Self {
-            provisionee: Some(Provisionee::new(capabilities)),
+            provisionee: Some(Provisionee::new(capabilities, oob)),
             last_transmit_hash: None,
             beacon: Deadline::new(Duration::from_secs(3), true),
+            oob_event_raised: false,
         }
     }
 
@@ -67,6 +72,39 @@ impl UnprovisionedStack {
         }
     }
 
+    /// Take the out-of-band action the device should perform, or ask of the user.
+    pub fn take_oob_event(&mut self) -> Option<OobEvent> {
+        let event = self
+            .provisionee
+            .as_mut()
+            .and_then(|provisionee| provisionee.take_oob_event());
+        self.oob_event_raised |= event.is_some();
+        event
+    }
+
+    pub fn oob_event_raised(&self) -> bool {
+        self.oob_event_raised
+    }
+
+    pub fn awaiting_input(&self) -> bool {
+        if let Some(provisionee) = &self.provisionee {
+            provisionee.awaiting_input()
+        } else {
+            false
+        }
+    }
+
+    /// Process the value the user entered, producing the PDU telling the
+    /// provisioner that input is complete.
+    pub fn input(&mut self, input: OobInput) -> Result<Option<ProvisioningPDU>, DriverError> {
+        if let Some(provisionee) = &mut self.provisionee {
+            provisionee.input(input)?;
+            Ok(provisionee.response())
+        } else {
+            Err(DriverError::InvalidState)
+        }
+    }
+
     pub fn process<RNG: RngCore + CryptoRng>(
         &mut self,
         pdu: &ProvisioningPDU,
@@ -0,0 +1,54 @@
+use btmesh_pdu::provisioning::{InputOOBActions, OOBSize, OutputOOBActions};
+use heapless::Vec;
+use p256::SecretKey;
+
+/// Out-of-band information the device supports to authenticate the provisioner,
+/// as advertised in its provisioning capabilities.
+#[derive(Clone, Default, Debug)]
+pub struct OobConfig {
+    pub output_oob_action: OutputOOBActions,
+    pub output_oob_size: OOBSize,
+    pub input_oob_action: InputOOBActions,
+    pub input_oob_size: OOBSize,
+    /// Static authentication value, such as a factory-programmed secret.
+    pub static_oob: Option<[u8; 16]>,
+    /// Private key of the P-256 key pair whose public key the provisioner
+    /// obtains out-of-band rather than through the provisioning protocol.
+    pub private_key: Option<SecretKey>,
+}
+
+/// Action the device performs, or asks of the user, while being provisioned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OobEvent {
+    /// Blink the number of times given.
+    Blink(u32),
+    /// Beep the number of times given.
+    Beep(u32),
+    /// Vibrate the number of times given.
+    Vibrate(u32),
+    /// Display the number given.
+    DisplayNumeric(u32),
+    /// Display the string of uppercase letters and digits given.
+    DisplayAlphanumeric(Vec<u8, 8>),
+    /// Count the times the user pushes a button, at most as many digits as given.
+    Push(u8),
+    /// Count the times the user twists a knob, at most as many digits as given.
+    Twist(u8),
+    /// Ask the user for a number of at most as many digits as given.
+    InputNumeric(u8),
+    /// Ask the user for a string of at most as many uppercase letters and digits as given.
+    InputAlphanumeric(u8),
+    /// Provisioning is over, anything output may be cleared.
+    Complete,
+}
+
+/// Value the user entered on the device, in response to an input event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OobInput {
+    /// A number, or the count of pushes or twists.
+    Numeric(u32),
+    /// A string of uppercase letters and digits.
+    Alphanumeric(Vec<u8, 8>),
+}
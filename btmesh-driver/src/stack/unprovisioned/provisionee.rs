@@ -1,4 +1,5 @@
-use super::auth_value::{determine_auth_value, AuthValue};
+use super::auth_value::{determine_auth_value, oob_event, AuthValue};
+use super::oob::{OobConfig, OobEvent, OobInput};
 use super::transcript::Transcript;
 use crate::DriverError;
 use btmesh_common::crypto::device::DeviceKey;
@@ -8,8 +9,8 @@ use btmesh_common::crypto::{
     s1,
 };
 use btmesh_pdu::provisioning::{
-    Capabilities, Confirmation, Data, ErrorCode, Failed, Invite, ProvisioningData, ProvisioningPDU,
-    PublicKey, Random, Start,
+    AuthenticationMethod, Capabilities, Confirmation, Data, ErrorCode, Failed, Invite, OOBSize,
+    ProvisioningData, ProvisioningPDU, PublicKey, PublicKeySelected, Random, Start,
 };
 use heapless::Vec;
 use p256::elliptic_curve::ecdh::diffie_hellman;
@@ -27,8 +28,8 @@ pub enum Provisionee {
 }
 
 impl Provisionee {
-    pub fn new(capabilities: Capabilities) -> Self {
-        Self::Beaconing(Phase::<Beaconing>::new(capabilities))
+    pub fn new(capabilities: Capabilities, oob: &OobConfig) -> Self {
+        Self::Beaconing(Phase::<Beaconing>::new(capabilities, oob))
     }
 
     pub fn in_progress(&self) -> bool {
@@ -38,6 +39,29 @@ impl Provisionee {
         )
     }
 
+    /// Take the out-of-band action due, once public keys have been exchanged.
+    pub fn take_oob_event(&mut self) -> Option<OobEvent> {
+        if let Self::Authentication(phase) = self {
+            phase.state.oob_event.take()
+        } else {
+            None
+        }
+    }
+
+    /// Whether authentication waits for the user to enter a value on the device.
+    pub fn awaiting_input(&self) -> bool {
+        matches!(self, Self::Authentication(phase) if phase.state.awaiting_input)
+    }
+
+    /// Complete an input action with the value the user entered.
+    pub fn input(&mut self, input: OobInput) -> Result<(), DriverError> {
+        if let Self::Authentication(phase) = self {
+            phase.input(input)
+        } else {
+            Err(DriverError::InvalidState)
+        }
+    }
+
     pub fn response(&self) -> Option<ProvisioningPDU> {
         match self {
             Self::Beaconing(phase) => phase.response.clone(),
@@ -66,10 +90,10 @@ impl Provisionee {
             // START
             (Provisionee::Invitation(mut phase), ProvisioningPDU::Start(start)) => {
                 // TODO: spec says to set the "Attention Timer" to 0x00
-                phase.start(start, rng)?;
-                // TODO: actually let the device/app/thingy know what
-                // it is so that it can blink/flash/accept input
-                Ok(Provisionee::KeyExchange(phase.try_into()?))
+                match phase.start(start, rng) {
+                    Ok(_) => Ok(Provisionee::KeyExchange(phase.try_into()?)),
+                    Err(_) => Provisionee::fail(ErrorCode::InvalidFormat),
+                }
             }
             // PUBLIC KEY
             (Provisionee::KeyExchange(mut phase), ProvisioningPDU::PublicKey(peer_key)) => {
@@ -79,11 +103,18 @@ impl Provisionee {
                     Err(_) => Provisionee::fail(ErrorCode::UnexpectedError),
                 }
             }
-            // CONFIRMATION
-            (Provisionee::Authentication(mut phase), ProvisioningPDU::Confirmation(value)) => {
+            // CONFIRMATION, only once the user has entered any input value.
+            (Provisionee::Authentication(mut phase), ProvisioningPDU::Confirmation(value))
+                if !phase.state.awaiting_input =>
+            {
                 phase.confirmation(value, rng)?;
                 Ok(Provisionee::Authentication(phase))
             }
+            // CONFIRMATION ahead of the input, ignored without responding.
+            (Provisionee::Authentication(mut phase), ProvisioningPDU::Confirmation(_)) => {
+                phase.response.take();
+                Ok(Provisionee::Authentication(phase))
+            }
             // RANDOM
             (Provisionee::Authentication(mut phase), ProvisioningPDU::Random(value)) => {
                 match phase.check(value) {
@@ -118,20 +149,34 @@ pub struct Phase<S> {
 #[derive(Default)]
 pub struct Beaconing {
     capabilities: Capabilities,
+    static_oob: Option<[u8; 16]>,
+    private_key: Option<SecretKey>,
 }
 #[derive(Default)]
 pub struct Invitation {
     auth_value: AuthValue,
+    authentication_size: u8,
+    oob_event: Option<OobEvent>,
+    static_oob: Option<[u8; 16]>,
+    private_key: Option<SecretKey>,
 }
 #[derive(Default)]
 pub struct KeyExchange {
     auth_value: AuthValue,
+    authentication_size: u8,
+    oob_event: Option<OobEvent>,
+    /// The key pair of the device, if its public key was obtained out-of-band.
+    private_key: Option<SecretKey>,
     shared_secret: Option<[u8; 32]>,
     random_provisioner: [u8; 16],
 }
 #[derive(Default)]
 pub struct Authentication {
     auth_value: AuthValue,
+    /// Digits or characters of an input value at most.
+    authentication_size: u8,
+    oob_event: Option<OobEvent>,
+    awaiting_input: bool,
     shared_secret: [u8; 32],
     confirmation: Option<[u8; 16]>,
     random_device: [u8; 16],
@@ -144,9 +189,13 @@ pub struct DataDistribution {
 }
 
 impl Phase<Beaconing> {
-    pub fn new(capabilities: Capabilities) -> Self {
+    pub fn new(capabilities: Capabilities, oob: &OobConfig) -> Self {
         Phase {
-            state: Beaconing { capabilities },
+            state: Beaconing {
+                capabilities,
+                static_oob: oob.static_oob,
+                private_key: oob.private_key.clone(),
+            },
             ..Default::default()
         }
     }
@@ -162,8 +211,26 @@ impl Phase<Invitation> {
         start: &Start,
         rng: &mut RNG,
     ) -> Result<(), DriverError> {
+        // out-of-band information the device does not have cannot be selected.
+        if matches!(start.public_key, PublicKeySelected::OOBPublicKey)
+            && self.state.private_key.is_none()
+        {
+            return Err(DriverError::InvalidState);
+        }
+        if !matches!(start.public_key, PublicKeySelected::OOBPublicKey) {
+            self.state.private_key.take();
+        }
         self.transcript.add_start(start)?;
-        self.state.auth_value = determine_auth_value(rng, start)?;
+        self.state.auth_value = if let AuthenticationMethod::Static = start.authentication_method {
+            AuthValue::Static(self.state.static_oob.ok_or(DriverError::InvalidState)?)
+        } else {
+            determine_auth_value(rng, start)?
+        };
+        self.state.oob_event = oob_event(start, &self.state.auth_value);
+        self.state.authentication_size = match start.authentication_size {
+            OOBSize::MaximumSize(size) => size,
+            OOBSize::NotSupported => 0,
+        };
         Ok(())
     }
 }
@@ -178,18 +245,38 @@ impl Phase<KeyExchange> {
             Ok(v) => Ok(v),
             Err(_) => Err(DriverError::InvalidFormat),
         }?;
-        let private = SecretKey::random(rng);
+        // a public key obtained out-of-band is not sent again.
+        let (private, response) = if let Some(private) = self.state.private_key.take() {
+            (private, false)
+        } else {
+            (SecretKey::random(rng), true)
+        };
         let secret = &diffie_hellman(private.to_nonzero_scalar(), public.as_affine());
         self.state.shared_secret = Some(secret.as_bytes()[0..].try_into()?);
         let pk = private.public_key().try_into()?;
         self.transcript.add_pubkey_provisioner(key)?;
         self.transcript.add_pubkey_device(&pk)?;
-        self.response = Some(ProvisioningPDU::PublicKey(pk));
+        if response {
+            self.response = Some(ProvisioningPDU::PublicKey(pk));
+        }
         Ok(pk)
     }
 }
 
 impl Phase<Authentication> {
+    pub fn input(&mut self, input: OobInput) -> Result<(), DriverError> {
+        if !self.state.awaiting_input {
+            return Err(DriverError::InvalidState);
+        }
+        self.state.auth_value = self
+            .state
+            .auth_value
+            .input(input, self.state.authentication_size)
+            .ok_or(DriverError::InvalidFormat)?;
+        self.state.awaiting_input = false;
+        self.response = Some(ProvisioningPDU::InputComplete);
+        Ok(())
+    }
     pub fn confirmation<RNG: RngCore + CryptoRng>(
         &mut self,
         value: &Confirmation,
@@ -250,7 +337,11 @@ impl TryFrom<Phase<Beaconing>> for Phase<Invitation> {
         Ok(Phase {
             transcript: p.transcript,
             response: Some(ProvisioningPDU::Capabilities(p.state.capabilities)),
-            ..Default::default()
+            state: Invitation {
+                static_oob: p.state.static_oob,
+                private_key: p.state.private_key,
+                ..Default::default()
+            },
         })
     }
 }
@@ -263,6 +354,9 @@ impl TryFrom<Phase<Invitation>> for Phase<KeyExchange> {
             response: None,
             state: KeyExchange {
                 auth_value: p.state.auth_value,
+                authentication_size: p.state.authentication_size,
+                oob_event: p.state.oob_event,
+                private_key: p.state.private_key,
                 ..Default::default()
             },
         })
@@ -276,7 +370,10 @@ impl TryFrom<Phase<KeyExchange>> for Phase<Authentication> {
             transcript: p.transcript,
             response: p.response,
             state: Authentication {
+                awaiting_input: p.state.auth_value.is_input(),
                 auth_value: p.state.auth_value,
+                authentication_size: p.state.authentication_size,
+                oob_event: p.state.oob_event,
                 shared_secret: p.state.shared_secret.unwrap(),
                 random_provisioner: p.state.random_provisioner,
                 ..Default::default()
@@ -304,7 +401,10 @@ impl TryFrom<Phase<Authentication>> for Phase<DataDistribution> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use btmesh_pdu::provisioning::{Confirmation, Invite, PublicKey, Random, Start};
+    use btmesh_pdu::provisioning::{
+        AuthenticationMethod, Confirmation, InputOOBAction, Invite, OOBAction, OOBSize,
+        OutputOOBAction, PublicKey, Random, Start,
+    };
     use p256::SecretKey;
     use rand_core::OsRng;
 
@@ -315,7 +415,7 @@ mod tests {
             number_of_elements: size,
             ..Default::default()
         };
-        let mut fsm = Provisionee::new(caps);
+        let mut fsm = Provisionee::new(caps, &OobConfig::default());
         assert!(matches!(fsm, Provisionee::Beaconing(_)));
         let pdu = ProvisioningPDU::Invite(Invite {
             attention_duration: 30,
@@ -374,12 +474,185 @@ mod tests {
         assert!(matches!(fsm, Provisionee::Failure(..)));
     }
 
+    #[test]
+    fn output_oob() {
+        let mut fsm = authentication(Start {
+            authentication_method: AuthenticationMethod::Output,
+            authentication_action: OOBAction::Output(OutputOOBAction::OutputNumeric),
+            authentication_size: OOBSize::MaximumSize(4),
+            ..Default::default()
+        });
+        assert!(!fsm.awaiting_input());
+        match fsm.take_oob_event() {
+            Some(OobEvent::DisplayNumeric(num)) => assert!(num < 10_000),
+            _ => panic!("expected a number to display"),
+        }
+        assert!(fsm.take_oob_event().is_none());
+    }
+
+    #[test]
+    fn input_oob() {
+        let mut fsm = authentication(Start {
+            authentication_method: AuthenticationMethod::Input,
+            authentication_action: OOBAction::Input(InputOOBAction::Push),
+            authentication_size: OOBSize::MaximumSize(1),
+            ..Default::default()
+        });
+        assert_eq!(Some(OobEvent::Push(1)), fsm.take_oob_event());
+        assert!(fsm.awaiting_input());
+
+        // the provisioner is not answered until the user has entered the value.
+        let pdu = ProvisioningPDU::Confirmation(Confirmation {
+            confirmation: [0; 16],
+        });
+        fsm = fsm.next(&pdu, &mut OsRng).unwrap();
+        assert!(fsm.response().is_none());
+
+        // more digits than the authentication size are rejected.
+        assert!(fsm.input(OobInput::Numeric(10)).is_err());
+        assert!(fsm.awaiting_input());
+
+        fsm.input(OobInput::Numeric(3)).unwrap();
+        assert!(!fsm.awaiting_input());
+        assert!(matches!(
+            fsm.response(),
+            Some(ProvisioningPDU::InputComplete)
+        ));
+        match &fsm {
+            Provisionee::Authentication(phase) => {
+                assert_eq!(3, phase.state.auth_value.get_bytes()[15])
+            }
+            _ => panic!("wrong state returned"),
+        }
+    }
+
+    #[test]
+    fn input_alphanumeric_oob() {
+        let mut fsm = authentication(Start {
+            authentication_method: AuthenticationMethod::Input,
+            authentication_action: OOBAction::Input(InputOOBAction::InputAlphanumeric),
+            authentication_size: OOBSize::MaximumSize(2),
+            ..Default::default()
+        });
+        assert_eq!(Some(OobEvent::InputAlphanumeric(2)), fsm.take_oob_event());
+
+        for invalid in [&b"AB1"[..], &b"a1"[..], &b"A-"[..]] {
+            let input = OobInput::Alphanumeric(Vec::from_slice(invalid).unwrap());
+            assert!(fsm.input(input).is_err());
+            assert!(fsm.awaiting_input());
+        }
+
+        fsm.input(OobInput::Alphanumeric(Vec::from_slice(b"A1").unwrap()))
+            .unwrap();
+        match &fsm {
+            Provisionee::Authentication(phase) => {
+                assert_eq!(b"A1", &phase.state.auth_value.get_bytes()[..2])
+            }
+            _ => panic!("wrong state returned"),
+        }
+    }
+
+    #[test]
+    fn static_oob() {
+        let static_oob = [0x42; 16];
+        let oob = OobConfig {
+            static_oob: Some(static_oob),
+            ..Default::default()
+        };
+        let mut fsm = start_keyexchange_with(
+            Start {
+                authentication_method: AuthenticationMethod::Static,
+                ..Default::default()
+            },
+            &oob,
+        );
+        let private = SecretKey::random(OsRng);
+        let pdu = ProvisioningPDU::PublicKey(PublicKey::try_from(private.public_key()).unwrap());
+        fsm = fsm.next(&pdu, &mut OsRng).unwrap();
+        assert!(fsm.take_oob_event().is_none());
+        match &fsm {
+            Provisionee::Authentication(phase) => {
+                assert_eq!(static_oob, phase.state.auth_value.get_bytes())
+            }
+            _ => panic!("wrong state returned"),
+        }
+    }
+
+    #[test]
+    fn oob_public_key() {
+        let device = SecretKey::random(OsRng);
+        let oob = OobConfig {
+            private_key: Some(device.clone()),
+            ..Default::default()
+        };
+        let mut fsm = start_keyexchange_with(
+            Start {
+                public_key: PublicKeySelected::OOBPublicKey,
+                ..Default::default()
+            },
+            &oob,
+        );
+        let private = SecretKey::random(OsRng);
+        let pdu = ProvisioningPDU::PublicKey(PublicKey::try_from(private.public_key()).unwrap());
+        fsm = fsm.next(&pdu, &mut OsRng).unwrap();
+        // the provisioner already has the public key of the device.
+        assert!(matches!(fsm.response(), None));
+        let secret = diffie_hellman(private.to_nonzero_scalar(), device.public_key().as_affine());
+        match &fsm {
+            Provisionee::Authentication(phase) => {
+                assert_eq!(secret.as_bytes()[..], phase.state.shared_secret[..])
+            }
+            _ => panic!("wrong state returned"),
+        }
+    }
+
+    #[test]
+    fn unavailable_oob() {
+        for start in [
+            Start {
+                public_key: PublicKeySelected::OOBPublicKey,
+                ..Default::default()
+            },
+            Start {
+                authentication_method: AuthenticationMethod::Static,
+                ..Default::default()
+            },
+        ] {
+            let mut fsm = Provisionee::new(Capabilities::default(), &OobConfig::default());
+            let invite = ProvisioningPDU::Invite(Invite::default());
+            fsm = fsm.next(&invite, &mut OsRng).unwrap();
+            fsm = fsm
+                .next(&ProvisioningPDU::Start(start), &mut OsRng)
+                .unwrap();
+            assert!(
+                matches!(fsm.response(), Some(ProvisioningPDU::Failed(e)) if matches!(e.error_code, ErrorCode::InvalidFormat))
+            );
+        }
+    }
+
+    fn authentication(start: Start) -> Provisionee {
+        let mut fsm = start_keyexchange(start);
+        let private = SecretKey::random(OsRng);
+        let pdu = ProvisioningPDU::PublicKey(PublicKey::try_from(private.public_key()).unwrap());
+        fsm = fsm.next(&pdu, &mut OsRng).unwrap();
+        assert!(matches!(fsm, Provisionee::Authentication(_)));
+        fsm
+    }
+
     fn keyexchange() -> Provisionee {
-        let mut fsm = Provisionee::new(Capabilities::default());
+        start_keyexchange(Start::default())
+    }
+
+    fn start_keyexchange(start: Start) -> Provisionee {
+        start_keyexchange_with(start, &OobConfig::default())
+    }
+
+    fn start_keyexchange_with(start: Start, oob: &OobConfig) -> Provisionee {
+        let mut fsm = Provisionee::new(Capabilities::default(), oob);
         let invite = ProvisioningPDU::Invite(Invite::default());
         fsm = fsm.next(&invite, &mut OsRng).unwrap();
         assert!(matches!(fsm, Provisionee::Invitation(_)));
-        let start = ProvisioningPDU::Start(Start::default());
+        let start = ProvisioningPDU::Start(start);
         fsm = fsm.next(&start, &mut OsRng).unwrap();
         assert!(matches!(fsm, Provisionee::KeyExchange(_)));
         assert!(matches!(fsm.response(), None));
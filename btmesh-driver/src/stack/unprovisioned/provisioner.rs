@@ -344,10 +344,13 @@ mod tests {
             ..Default::default()
         };
         let mut provisioner = Provisioner::new(fixture, 60).unwrap();
-        let mut device = Provisionee::new(Capabilities {
-            number_of_elements: 1,
-            ..Default::default()
-        });
+        let mut device = Provisionee::new(
+            Capabilities {
+                number_of_elements: 1,
+                ..Default::default()
+            },
+            &Default::default(),
+        );
         loop {
             for pdu in provisioner.response().into_iter() {
                 device = match device.next(pdu, rng) {
@@ -1,3 +1,4 @@
+use crate::storage::legacy::LegacyConfiguration;
 use crate::storage::{BackingStore, Configuration, StorageError};
 use crate::util::hash::hash_of;
 use core::future::Future;
@@ -7,6 +8,17 @@ use postcard::{from_bytes, to_slice};
 #[repr(align(4))]
 struct AlignedBytePage([u8; 4096]);
 
+/// Marks a page holding a versioned configuration.
+const FORMAT_MAGIC: [u8; 2] = *b"BM";
+
+/// Version of the stored configuration, to be bumped whenever its
+/// serialized form changes, along with a migration in `decode`.
+const FORMAT_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = FORMAT_MAGIC.len() + 1;
+
+const ERASED: u8 = 0xFF;
+
 #[derive(Copy, Clone)]
 pub enum LatestLoad {
     None,
@@ -47,7 +59,7 @@ impl<F: AsyncNorFlash> BackingStore for FlashBackingStore<F> {
                 .read(self.base_address, &mut bytes)
                 .await
                 .map_err(|_| StorageError::Load)?;
-            let config = from_bytes(&bytes).map_err(|_| StorageError::Serialization)?;
+            let config = decode(&bytes)?;
 
             match &config {
                 Configuration::Unprovisioned(_) => {
@@ -70,7 +82,7 @@ impl<F: AsyncNorFlash> BackingStore for FlashBackingStore<F> {
         async move {
             if should_writeback(self.latest_load, config, self.sequence_threshold) {
                 let mut bytes = AlignedBytePage([0; 4096]);
-                to_slice(config, &mut bytes.0).map_err(|_| StorageError::Serialization)?;
+                encode(config, &mut bytes.0)?;
                 self.flash
                     .write(self.base_address, &bytes.0)
                     .await
@@ -89,6 +101,50 @@ impl<F: AsyncNorFlash> BackingStore for FlashBackingStore<F> {
     }
 }
 
+fn encode(config: &Configuration, bytes: &mut [u8]) -> Result<(), StorageError> {
+    bytes[..FORMAT_MAGIC.len()].copy_from_slice(&FORMAT_MAGIC);
+    bytes[FORMAT_MAGIC.len()] = FORMAT_VERSION;
+    to_slice(config, &mut bytes[HEADER_LEN..]).map_err(|_| StorageError::Serialization)?;
+    Ok(())
+}
+
+/// Decode a stored configuration, migrating it from earlier formats.
+///
+/// Anything which can not be understood is reported as an incompatible
+/// format rather than as missing, so a provisioned node is never silently
+/// replaced by a fresh unprovisioned configuration.
+fn decode(bytes: &[u8]) -> Result<Configuration, StorageError> {
+    if bytes[..FORMAT_MAGIC.len()] == FORMAT_MAGIC {
+        let version = bytes[FORMAT_MAGIC.len()];
+        return match version {
+            FORMAT_VERSION => {
+                from_bytes(&bytes[HEADER_LEN..]).map_err(|_| StorageError::IncompatibleFormat)
+            }
+            _ => {
+                error!("unsupported storage format version {}", version);
+                Err(StorageError::IncompatibleFormat)
+            }
+        };
+    }
+
+    if bytes[0] == ERASED {
+        // nothing was ever stored.
+        return Err(StorageError::Load);
+    }
+
+    // an unversioned configuration, as stored by earlier releases.
+    match from_bytes::<LegacyConfiguration>(bytes) {
+        Ok(legacy) => {
+            info!("migrating unversioned stored config");
+            Ok(legacy.into())
+        }
+        Err(_) => {
+            error!("unversioned stored config can not be migrated");
+            Err(StorageError::IncompatibleFormat)
+        }
+    }
+}
+
 #[allow(clippy::needless_bool)]
 pub fn should_writeback(current: LatestLoad, new: &Configuration, sequence_threshold: u32) -> bool {
     match (current, new) {
@@ -129,14 +185,162 @@ pub fn should_writeback(current: LatestLoad, new: &Configuration, sequence_thres
 mod test {
     use crate::stack::provisioned::secrets::application::ApplicationKeys;
     use crate::stack::provisioned::secrets::network::NetworkKeys;
-    use crate::storage::flash::{should_writeback, LatestLoad};
+    use crate::storage::flash::{decode, encode, should_writeback, LatestLoad};
     use crate::storage::provisioned::ProvisionedConfiguration;
     use crate::storage::unprovisioned::UnprovisionedConfiguration;
+    use crate::storage::StorageError;
     use crate::util::hash::hash_of;
     use crate::{Configuration, DeviceInfo, NetworkState, Secrets};
     use btmesh_common::address::UnicastAddress;
     use btmesh_common::crypto::device::DeviceKey;
-    use btmesh_common::{IvIndex, IvUpdateFlag, Uuid};
+    use btmesh_common::crypto::network::NetworkKey;
+    use btmesh_common::{IvIndex, IvUpdateFlag, Ttl, Uuid};
+    use btmesh_models::foundation::configuration::{AppKeyIndex, NetKeyIndex};
+    use heapless::Vec;
+    use postcard::to_slice;
+
+    fn provisioned_config() -> Configuration {
+        Configuration::Provisioned(ProvisionedConfiguration {
+            network_state: NetworkState::new(IvIndex::new(100), IvUpdateFlag::Normal),
+            secrets: Secrets::new(
+                DeviceKey::new([
+                    0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD,
+                    0xEE, 0xFF, 0x00,
+                ]),
+                NetworkKeys::default(),
+                ApplicationKeys::default(),
+            ),
+            device_info: DeviceInfo::new(UnicastAddress::new(0x00A1).unwrap(), 1),
+            sequence: 100,
+            foundation: Default::default(),
+        })
+    }
+
+    #[test]
+    pub fn versioned_round_trip() {
+        let config = provisioned_config();
+        let mut bytes = [0xFF; 4096];
+        encode(&config, &mut bytes).unwrap();
+
+        assert_eq!(hash_of(&config), hash_of(&decode(&bytes).unwrap()));
+    }
+
+    #[test]
+    pub fn erased_is_not_loaded() {
+        assert_eq!(Err(StorageError::Load), decode(&[0xFF; 4096]).map(|_| ()));
+    }
+
+    #[test]
+    pub fn unknown_version_is_incompatible() {
+        let mut bytes = [0xFF; 4096];
+        encode(&provisioned_config(), &mut bytes).unwrap();
+        bytes[2] += 1;
+
+        assert_eq!(
+            Err(StorageError::IncompatibleFormat),
+            decode(&bytes).map(|_| ())
+        );
+    }
+
+    #[test]
+    pub fn legacy_configurations() {
+        let unprovisioned_config = Configuration::Unprovisioned(UnprovisionedConfiguration {
+            uuid: Uuid::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]),
+        });
+        let mut bytes = [0xFF; 4096];
+        to_slice(&unprovisioned_config, &mut bytes).unwrap();
+
+        assert_eq!(
+            hash_of(&unprovisioned_config),
+            hash_of(&decode(&bytes).unwrap())
+        );
+    }
+
+    #[test]
+    pub fn legacy_provisioned_configuration_is_migrated() {
+        let network_key = [
+            0x7d, 0xd7, 0x36, 0x4c, 0xd8, 0x42, 0xad, 0x18, 0xc1, 0x7c, 0x2b, 0x82, 0x0c, 0x84,
+            0xc3, 0xd6,
+        ];
+        let application_key = [
+            0x63, 0x96, 0x47, 0x71, 0x73, 0x4f, 0xbd, 0x76, 0xe3, 0xb4, 0x05, 0x19, 0xd1, 0xd9,
+            0x4a, 0x48,
+        ];
+
+        let mut legacy = Vec::<u8, 256>::new();
+        // provisioned, IV index 0x0102, normal operation.
+        legacy.extend_from_slice(&[0x01, 0x82, 0x02, 0x00]).unwrap();
+        // device key.
+        legacy.extend_from_slice(&[0x11; 16]).unwrap();
+        // network keys: the key, privacy key, encryption key, NID and network ID.
+        legacy.extend_from_slice(&[0x04, 0x01]).unwrap();
+        legacy.extend_from_slice(&network_key).unwrap();
+        legacy
+            .extend_from_slice(&[
+                0x8b, 0x84, 0xee, 0xde, 0xc1, 0x00, 0x06, 0x7d, 0x67, 0x09, 0x71, 0xdd, 0x2a, 0xa7,
+                0x00, 0xcf,
+            ])
+            .unwrap();
+        legacy
+            .extend_from_slice(&[
+                0x09, 0x53, 0xfa, 0x93, 0xe7, 0xca, 0xac, 0x96, 0x38, 0xf5, 0x88, 0x20, 0x22, 0x0a,
+                0x39, 0x8e,
+            ])
+            .unwrap();
+        legacy.push(0x68).unwrap();
+        legacy
+            .extend_from_slice(&[0x3e, 0xca, 0xff, 0x67, 0x2f, 0x67, 0x33, 0x70])
+            .unwrap();
+        legacy.extend_from_slice(&[0x00, 0x00, 0x00]).unwrap();
+        // application keys: the key and AID.
+        legacy.extend_from_slice(&[0x04, 0x01]).unwrap();
+        legacy.extend_from_slice(&application_key).unwrap();
+        legacy.extend_from_slice(&[0x26, 0x00, 0x00, 0x00]).unwrap();
+        // 2 elements at 0x00A1, sequence 1000, default TTL 5.
+        legacy
+            .extend_from_slice(&[0x02, 0xA1, 0x01, 0xE8, 0x07, 0x05])
+            .unwrap();
+
+        let mut bytes = [0xFF; 4096];
+        bytes[..legacy.len()].copy_from_slice(&legacy);
+
+        let config = match decode(&bytes) {
+            Ok(Configuration::Provisioned(config)) => config,
+            _ => panic!("legacy provisioned configuration not migrated"),
+        };
+
+        assert_eq!(IvIndex::new(0x0102), config.network_state().iv_index());
+        assert_eq!(
+            IvUpdateFlag::Normal,
+            config.network_state().iv_update_flag()
+        );
+        assert_eq!(2, config.device_info().number_of_elements());
+        assert_eq!(
+            Some(UnicastAddress::new(0x00A1).unwrap()),
+            config.device_info().local_element_address(0)
+        );
+        assert_eq!(1000, config.sequence());
+        assert_eq!(
+            &Ttl::new(5),
+            config.foundation().configuration().default_ttl()
+        );
+
+        let secrets = config.secrets();
+        assert_eq!(
+            hash_of(&DeviceKey::new([0x11; 16])),
+            hash_of(&secrets.device_key())
+        );
+        let (_, entry) = secrets.network_keys().get(NetKeyIndex::new(0)).unwrap();
+        assert_eq!(NetworkKey::new(network_key).unwrap(), entry.transmit_key());
+
+        let (_, entry) = secrets.application_keys().get(AppKeyIndex::new(0)).unwrap();
+        assert_eq!(NetKeyIndex::new(0), entry.net_key_index);
+        assert_eq!(application_key, *entry.key);
+        assert!(secrets
+            .application_keys()
+            .get(AppKeyIndex::new(1))
+            .is_none());
+    }
 
     #[test]
     pub fn hashing() {
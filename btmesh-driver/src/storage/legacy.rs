@@ -0,0 +1,111 @@
+//! Configuration as stored, unversioned, by earlier releases, and its migration.
+//!
+//! Earlier releases held network keys in slots without their key indexes, along
+//! with application keys bound to no network key, and a foundation configuration
+//! holding nothing but the default TTL. Since serialized structures are not
+//! delimited, their nesting is flattened here.
+
+use crate::stack::provisioned::secrets::application::ApplicationKeys;
+use crate::stack::provisioned::secrets::network::NetworkKeys;
+use crate::storage::provisioned::foundation::Foundation;
+use crate::storage::provisioned::ProvisionedConfiguration;
+use crate::storage::unprovisioned::UnprovisionedConfiguration;
+use crate::storage::Configuration;
+use crate::{DeviceInfo, NetworkState, Secrets};
+use btmesh_common::crypto::application::ApplicationKey;
+use btmesh_common::crypto::device::DeviceKey;
+use btmesh_common::crypto::network::NetworkKey;
+use btmesh_common::{IvIndex, IvUpdateFlag, Ttl};
+use btmesh_models::foundation::configuration::{AppKeyIndex, NetKeyIndex};
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum LegacyConfiguration {
+    Unprovisioned(UnprovisionedConfiguration),
+    Provisioned(LegacyProvisionedConfiguration),
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LegacyProvisionedConfiguration {
+    pub(crate) network_state: LegacyNetworkState,
+    pub(crate) secrets: LegacySecrets,
+    pub(crate) device_info: DeviceInfo,
+    pub(crate) sequence: u32,
+    pub(crate) default_ttl: Ttl,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LegacyNetworkState {
+    pub(crate) iv_index: IvIndex,
+    pub(crate) iv_update_flag: IvUpdateFlag,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LegacySecrets {
+    pub(crate) device_key: DeviceKey,
+    pub(crate) network_keys: Vec<Option<NetworkKey>, 4>,
+    pub(crate) application_keys: Vec<Option<ApplicationKey>, 4>,
+}
+
+impl From<LegacyConfiguration> for Configuration {
+    fn from(legacy: LegacyConfiguration) -> Self {
+        match legacy {
+            LegacyConfiguration::Unprovisioned(config) => Configuration::Unprovisioned(config),
+            LegacyConfiguration::Provisioned(config) => Configuration::Provisioned(config.into()),
+        }
+    }
+}
+
+impl From<LegacyProvisionedConfiguration> for ProvisionedConfiguration {
+    /// Network keys take the index of the slot they were held in, which for the
+    /// primary subnet handed over during provisioning is the usual key index 0.
+    /// Application keys likewise take the index of their slot, bound to the
+    /// first network key.
+    fn from(legacy: LegacyProvisionedConfiguration) -> Self {
+        let mut network_keys = NetworkKeys::default();
+        for (slot, network_key) in legacy.secrets.network_keys.iter().enumerate() {
+            if let Some(network_key) = network_key {
+                // both hold the same number of slots.
+                network_keys
+                    .add(NetKeyIndex::new(slot as u16), *network_key)
+                    .ok();
+            }
+        }
+
+        let mut application_keys = ApplicationKeys::default();
+        if let Some(net_key_index) = legacy
+            .secrets
+            .network_keys
+            .iter()
+            .position(Option::is_some)
+            .map(|slot| NetKeyIndex::new(slot as u16))
+        {
+            for (slot, application_key) in legacy.secrets.application_keys.iter().enumerate() {
+                if let Some(application_key) = application_key {
+                    application_keys
+                        .add(
+                            AppKeyIndex::new(slot as u16),
+                            net_key_index,
+                            *application_key,
+                        )
+                        .ok();
+                }
+            }
+        }
+
+        let mut foundation = Foundation::default();
+        *foundation.configuration_mut().default_ttl_mut() = legacy.default_ttl;
+
+        Self {
+            network_state: NetworkState::new(
+                legacy.network_state.iv_index,
+                legacy.network_state.iv_update_flag,
+            ),
+            secrets: Secrets::new(legacy.secrets.device_key, network_keys, application_keys),
+            device_info: legacy.device_info,
+            sequence: legacy.sequence,
+            foundation,
+        }
+    }
+}
@@ -15,6 +15,8 @@ pub(crate) mod unprovisioned;
 
 #[cfg(feature = "flash")]
 pub mod flash;
+#[cfg(feature = "flash")]
+mod legacy;
 #[cfg(feature = "memory")]
 pub mod memory;
 
@@ -25,6 +27,8 @@ pub enum StorageError {
     Store,
     Serialization,
     Deserialization,
+    /// Stored configuration of a format which can not be migrated.
+    IncompatibleFormat,
 }
 
 pub trait BackingStore {
@@ -102,34 +106,47 @@ impl<B: BackingStore> Storage<B> {
         }
     }
 
-    #[allow(clippy::await_holding_refcell_ref)]
     pub async fn put(&self, config: &Configuration) -> Result<(), StorageError> {
-        if matches!(config, Configuration::Provisioned(_)) {
-            // only write it back if it's provisioned.
-            // unprovisioned config is ephemeral.
-            self.backing_store.borrow_mut().store(config).await?;
-        }
         let mut locked_config = self.config.lock().await;
-        locked_config.replace(config.clone());
-        Ok(())
+        self.store(&mut locked_config, config).await
     }
 
+    /// Modify the provisioned configuration while holding the lock,
+    /// so concurrent modifications can not clobber each other.
     pub async fn modify<F: FnOnce(&mut ProvisionedConfiguration) -> Result<(), ()>>(
         &self,
         modification: F,
     ) -> Result<(), StorageError> {
-        let config = self.config.lock().await;
+        let mut locked_config = self.config.lock().await;
 
-        if let Some(Configuration::Provisioned(config)) = &*config {
+        if let Some(Configuration::Provisioned(config)) = &*locked_config {
             let mut config = config.clone();
             if modification(&mut config).is_ok() {
-                self.put(&Configuration::Provisioned(config)).await?;
+                self.store(&mut locked_config, &Configuration::Provisioned(config))
+                    .await?;
             }
         }
 
         Ok(())
     }
 
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn store(
+        &self,
+        locked_config: &mut Option<Configuration>,
+        config: &Configuration,
+    ) -> Result<(), StorageError> {
+        let reset = matches!(locked_config, Some(Configuration::Provisioned(_)));
+        if reset || matches!(config, Configuration::Provisioned(_)) {
+            // only write it back if it's provisioned, or if it replaces
+            // a provisioned config upon reset. unprovisioned config is
+            // otherwise ephemeral.
+            self.backing_store.borrow_mut().store(config).await?;
+        }
+        locked_config.replace(config.clone());
+        Ok(())
+    }
+
     #[allow(clippy::await_holding_refcell_ref)]
     async fn load_if_needed(&self) -> Result<(), StorageError> {
         let mut config = self.config.lock().await;
@@ -0,0 +1,76 @@
+use crate::DriverError;
+use btmesh_common::ModelIdentifier;
+use btmesh_models::foundation::configuration::AppKeyIndex;
+use heapless::Vec;
+
+/// Application keys bound to models, as configured through
+/// Config Model App Bind and Unbind.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Clone, Debug, Default, Hash)]
+pub struct Bindings<const N: usize = 16> {
+    entries: Vec<(u8, ModelIdentifier, AppKeyIndex), N>,
+}
+
+impl<const N: usize> Bindings<N> {
+    pub fn is_bound(
+        &self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        app_key_index: AppKeyIndex,
+    ) -> bool {
+        self.entries.iter().any(|(index, model, key)| {
+            *index == element_index && *model == model_identifier && *key == app_key_index
+        })
+    }
+
+    /// Bind an application key to a model. Binding an already-bound key is a no-op.
+    pub fn bind(
+        &mut self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        app_key_index: AppKeyIndex,
+    ) -> Result<(), DriverError> {
+        if self.is_bound(element_index, model_identifier, app_key_index) {
+            return Ok(());
+        }
+        self.entries
+            .push((element_index, model_identifier, app_key_index))
+            .map_err(|_| DriverError::InsufficientSpace)
+    }
+
+    pub fn unbind(
+        &mut self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        app_key_index: AppKeyIndex,
+    ) {
+        if let Some(position) = self.entries.iter().position(|(index, model, key)| {
+            *index == element_index && *model == model_identifier && *key == app_key_index
+        }) {
+            self.entries.swap_remove(position);
+        }
+    }
+
+    /// Remove every binding of the given application key.
+    pub fn remove_app_key(&mut self, app_key_index: AppKeyIndex) {
+        while let Some(position) = self
+            .entries
+            .iter()
+            .position(|(_, _, key)| *key == app_key_index)
+        {
+            self.entries.swap_remove(position);
+        }
+    }
+
+    /// Iterate the models of an element which are bound to the given application key.
+    pub fn models_iter(
+        &self,
+        element_index: u8,
+        app_key_index: AppKeyIndex,
+    ) -> impl Iterator<Item = ModelIdentifier> + '_ {
+        self.entries
+            .iter()
+            .filter(move |(index, _, key)| *index == element_index && *key == app_key_index)
+            .map(|(_, model, _)| *model)
+    }
+}
@@ -0,0 +1,138 @@
+use crate::storage::provisioned::foundation::configuration::bindings::Bindings;
+use crate::storage::provisioned::foundation::configuration::publications::Publications;
+use crate::storage::provisioned::foundation::configuration::subscriptions::Subscriptions;
+use btmesh_common::Ttl;
+#[cfg(feature = "proxy")]
+use btmesh_models::foundation::configuration::gatt_proxy::GattProxy;
+use btmesh_models::foundation::configuration::heartbeat::{
+    HeartbeatPublication, HeartbeatSubscription,
+};
+use btmesh_models::foundation::configuration::network_transmit::NetworkTransmitConfig;
+#[cfg(feature = "relay")]
+use btmesh_models::foundation::configuration::relay::RelayConfig;
+
+pub mod bindings;
+pub mod publications;
+pub mod subscriptions;
+
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Clone, Debug, Hash)]
+pub struct Configuration {
+    beacon: bool,
+    default_ttl: Ttl,
+    network_transmit: NetworkTransmitConfig,
+    #[cfg(feature = "relay")]
+    relay: RelayConfig,
+    #[cfg(feature = "proxy")]
+    gatt_proxy: GattProxy,
+    bindings: Bindings,
+    publications: Publications,
+    subscriptions: Subscriptions,
+    heartbeat_publication: HeartbeatPublication,
+    heartbeat_subscription: HeartbeatSubscription,
+}
+
+impl Configuration {
+    pub fn beacon(&self) -> &bool {
+        &self.beacon
+    }
+
+    pub fn beacon_mut(&mut self) -> &mut bool {
+        &mut self.beacon
+    }
+
+    pub fn default_ttl(&self) -> &Ttl {
+        &self.default_ttl
+    }
+
+    pub fn default_ttl_mut(&mut self) -> &mut Ttl {
+        &mut self.default_ttl
+    }
+
+    pub fn network_transmit(&self) -> &NetworkTransmitConfig {
+        &self.network_transmit
+    }
+
+    pub fn network_transmit_mut(&mut self) -> &mut NetworkTransmitConfig {
+        &mut self.network_transmit
+    }
+
+    #[cfg(feature = "relay")]
+    pub fn relay(&self) -> &RelayConfig {
+        &self.relay
+    }
+
+    #[cfg(feature = "relay")]
+    pub fn relay_mut(&mut self) -> &mut RelayConfig {
+        &mut self.relay
+    }
+
+    #[cfg(feature = "proxy")]
+    pub fn gatt_proxy(&self) -> &GattProxy {
+        &self.gatt_proxy
+    }
+
+    #[cfg(feature = "proxy")]
+    pub fn gatt_proxy_mut(&mut self) -> &mut GattProxy {
+        &mut self.gatt_proxy
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+
+    pub fn publications(&self) -> &Publications {
+        &self.publications
+    }
+
+    pub fn publications_mut(&mut self) -> &mut Publications {
+        &mut self.publications
+    }
+
+    pub fn subscriptions(&self) -> &Subscriptions {
+        &self.subscriptions
+    }
+
+    pub fn subscriptions_mut(&mut self) -> &mut Subscriptions {
+        &mut self.subscriptions
+    }
+
+    pub fn heartbeat_publication(&self) -> &HeartbeatPublication {
+        &self.heartbeat_publication
+    }
+
+    pub fn heartbeat_publication_mut(&mut self) -> &mut HeartbeatPublication {
+        &mut self.heartbeat_publication
+    }
+
+    pub fn heartbeat_subscription(&self) -> &HeartbeatSubscription {
+        &self.heartbeat_subscription
+    }
+
+    pub fn heartbeat_subscription_mut(&mut self) -> &mut HeartbeatSubscription {
+        &mut self.heartbeat_subscription
+    }
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            beacon: true,
+            default_ttl: Ttl::new(127),
+            network_transmit: Default::default(),
+            #[cfg(feature = "relay")]
+            relay: Default::default(),
+            #[cfg(feature = "proxy")]
+            gatt_proxy: Default::default(),
+            bindings: Default::default(),
+            publications: Default::default(),
+            subscriptions: Default::default(),
+            heartbeat_publication: Default::default(),
+            heartbeat_subscription: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::DriverError;
+use btmesh_common::ModelIdentifier;
+use btmesh_models::foundation::configuration::model_publication::{
+    ModelPublicationSetMessage, PublishAddress,
+};
+use btmesh_models::foundation::configuration::AppKeyIndex;
+use embassy_executor::time::Duration;
+use heapless::Vec;
+
+/// Publication state of a single model, as configured through
+/// Config Model Publication Set.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Copy, Clone, Debug, Hash)]
+pub struct Publication {
+    publish_address: PublishAddress,
+    app_key_index: AppKeyIndex,
+    credential_flag: bool,
+    publish_ttl: Option<u8>,
+    publish_period: u8,
+    publish_retransmit_count: u8,
+    publish_retransmit_interval_steps: u8,
+}
+
+impl Publication {
+    pub fn publish_address(&self) -> PublishAddress {
+        self.publish_address
+    }
+
+    pub fn app_key_index(&self) -> AppKeyIndex {
+        self.app_key_index
+    }
+
+    pub fn credential_flag(&self) -> bool {
+        self.credential_flag
+    }
+
+    /// The configured TTL, or `None` if the default TTL should be used.
+    pub fn publish_ttl(&self) -> Option<u8> {
+        self.publish_ttl
+    }
+
+    pub fn publish_period(&self) -> u8 {
+        self.publish_period
+    }
+
+    pub fn publish_retransmit_count(&self) -> u8 {
+        self.publish_retransmit_count
+    }
+
+    pub fn publish_retransmit_interval_steps(&self) -> u8 {
+        self.publish_retransmit_interval_steps
+    }
+
+    /// The decoded publish period, or `None` if periodic publishing is disabled.
+    pub fn period(&self) -> Option<Duration> {
+        let steps = (self.publish_period & 0b00111111) as u64;
+        if steps == 0 {
+            return None;
+        }
+        let resolution_ms = match self.publish_period >> 6 {
+            0b00 => 100,
+            0b01 => 1_000,
+            0b10 => 10_000,
+            _ => 600_000,
+        };
+        Some(Duration::from_millis(steps * resolution_ms))
+    }
+
+    /// The decoded interval between retransmissions of a published message.
+    pub fn retransmit_interval(&self) -> Duration {
+        Duration::from_millis((self.publish_retransmit_interval_steps as u64 + 1) * 50)
+    }
+}
+
+impl From<&ModelPublicationSetMessage> for Publication {
+    fn from(set: &ModelPublicationSetMessage) -> Self {
+        Self {
+            publish_address: set.publish_address,
+            app_key_index: set.app_key_index,
+            credential_flag: set.credential_flag,
+            publish_ttl: set.publish_ttl,
+            publish_period: set.publish_period,
+            publish_retransmit_count: set.publish_retransmit_count,
+            publish_retransmit_interval_steps: set.publish_retransmit_interval_steps,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Clone, Debug, Default, Hash)]
+pub struct Publications<const N: usize = 8> {
+    entries: Vec<(u8, ModelIdentifier, Publication), N>,
+}
+
+impl<const N: usize> Publications<N> {
+    pub fn get(
+        &self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+    ) -> Option<&Publication> {
+        self.entries
+            .iter()
+            .find(|(index, model, _)| *index == element_index && *model == model_identifier)
+            .map(|(_, _, publication)| publication)
+    }
+
+    /// Set or replace the publication of a model.
+    ///
+    /// Setting an unassigned publish address removes the publication.
+    pub fn set(
+        &mut self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        publication: Publication,
+    ) -> Result<(), DriverError> {
+        self.remove(element_index, model_identifier);
+        if matches!(publication.publish_address, PublishAddress::Unassigned) {
+            return Ok(());
+        }
+        self.entries
+            .push((element_index, model_identifier, publication))
+            .map_err(|_| DriverError::InsufficientSpace)
+    }
+
+    pub fn remove(&mut self, element_index: u8, model_identifier: ModelIdentifier) {
+        if let Some(position) = self
+            .entries
+            .iter()
+            .position(|(index, model, _)| *index == element_index && *model == model_identifier)
+        {
+            self.entries.swap_remove(position);
+        }
+    }
+
+    /// Remove every publication using the given application key.
+    pub fn remove_app_key(&mut self, app_key_index: AppKeyIndex) {
+        while let Some(position) = self
+            .entries
+            .iter()
+            .position(|(_, _, publication)| publication.app_key_index() == app_key_index)
+        {
+            self.entries.swap_remove(position);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(u8, ModelIdentifier, Publication)> + '_ {
+        self.entries.iter()
+    }
+}
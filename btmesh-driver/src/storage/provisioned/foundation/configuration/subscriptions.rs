@@ -0,0 +1,100 @@
+use crate::DriverError;
+use btmesh_common::address::LabelUuid;
+use btmesh_common::ModelIdentifier;
+use btmesh_models::foundation::configuration::model_subscription::SubscriptionAddress;
+use heapless::Vec;
+
+/// Subscription lists of models, as configured through
+/// Config Model Subscription Add, Delete and Overwrite.
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[derive(Clone, Debug, Default, Hash)]
+pub struct Subscriptions<const N: usize = 16> {
+    entries: Vec<(u8, ModelIdentifier, SubscriptionAddress), N>,
+}
+
+impl<const N: usize> Subscriptions<N> {
+    pub fn is_subscribed(
+        &self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        address: SubscriptionAddress,
+    ) -> bool {
+        self.entries.iter().any(|(index, model, addr)| {
+            *index == element_index && *model == model_identifier && *addr == address
+        })
+    }
+
+    /// Subscribe a model to an address. Subscribing to an already-subscribed address is a no-op.
+    pub fn add(
+        &mut self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        address: SubscriptionAddress,
+    ) -> Result<(), DriverError> {
+        if self.is_subscribed(element_index, model_identifier, address) {
+            return Ok(());
+        }
+        self.entries
+            .push((element_index, model_identifier, address))
+            .map_err(|_| DriverError::InsufficientSpace)
+    }
+
+    pub fn remove(
+        &mut self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        address: SubscriptionAddress,
+    ) {
+        if let Some(position) = self.entries.iter().position(|(index, model, addr)| {
+            *index == element_index && *model == model_identifier && *addr == address
+        }) {
+            self.entries.swap_remove(position);
+        }
+    }
+
+    /// Remove every subscription of a model.
+    pub fn remove_all(&mut self, element_index: u8, model_identifier: ModelIdentifier) {
+        while let Some(position) = self
+            .entries
+            .iter()
+            .position(|(index, model, _)| *index == element_index && *model == model_identifier)
+        {
+            self.entries.swap_remove(position);
+        }
+    }
+
+    /// Replace the subscription list of a model with a single address.
+    pub fn overwrite(
+        &mut self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+        address: SubscriptionAddress,
+    ) -> Result<(), DriverError> {
+        self.remove_all(element_index, model_identifier);
+        self.add(element_index, model_identifier, address)
+    }
+
+    /// Iterate the addresses a model is subscribed to.
+    pub fn addresses_iter(
+        &self,
+        element_index: u8,
+        model_identifier: ModelIdentifier,
+    ) -> impl Iterator<Item = SubscriptionAddress> + '_ {
+        self.entries
+            .iter()
+            .filter(move |(index, model, _)| *index == element_index && *model == model_identifier)
+            .map(|(_, _, addr)| *addr)
+    }
+
+    /// Iterate the addresses subscribed to by any model, possibly more than once.
+    pub fn all_addresses_iter(&self) -> impl Iterator<Item = SubscriptionAddress> + '_ {
+        self.entries.iter().map(|(_, _, addr)| *addr)
+    }
+
+    /// Iterate the label-uuids of all virtual addresses subscribed to.
+    pub fn label_uuids_iter(&self) -> impl Iterator<Item = LabelUuid> + '_ {
+        self.entries
+            .iter()
+            .filter_map(|(_, _, addr)| addr.label_uuid())
+    }
+}
@@ -3,7 +3,7 @@ use crate::storage::provisioned::foundation::configuration::Configuration;
 pub mod configuration;
 
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Hash)]
 pub struct Foundation {
     configuration: Configuration,
 }
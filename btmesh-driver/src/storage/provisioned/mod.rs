@@ -3,7 +3,7 @@ use crate::{Configuration, DeviceInfo, NetworkState, Secrets};
 use btmesh_common::Composition;
 use core::hash::{Hash, Hasher};
 
-mod foundation;
+pub(crate) mod foundation;
 
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 #[derive(Clone, Debug)]
@@ -53,6 +53,7 @@ impl Hash for ProvisionedConfiguration {
         self.network_state.hash(state);
         self.secrets.hash(state);
         self.device_info.hash(state);
+        self.foundation.hash(state);
         // explicitly skip sequence, checked separately.
     }
 }
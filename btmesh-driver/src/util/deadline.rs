@@ -33,6 +33,16 @@ impl Deadline {
         }
     }
 
+    /// Whether the deadline has passed, advancing it if so.
+    pub fn elapsed(&self) -> bool {
+        if self.next.get() <= Instant::now() {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
     fn advance(&self) {
         self.next.replace(Instant::now() + self.every);
     }
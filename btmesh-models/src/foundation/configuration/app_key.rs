@@ -1,5 +1,5 @@
 use crate::foundation::configuration::{
-    AppKeyIndex, KeyIndex, NetKeyAppKeyIndexesPair, NetKeyIndex,
+    AppKeyIndex, ConfigurationMessage, KeyIndex, NetKeyAppKeyIndexesPair, NetKeyIndex,
 };
 use crate::{Message, Status};
 use btmesh_common::opcode::Opcode;
@@ -24,6 +24,12 @@ pub enum AppKeyMessage {
     Update(AppKeyUpdateMessage),
 }
 
+impl From<AppKeyMessage> for ConfigurationMessage {
+    fn from(inner: AppKeyMessage) -> Self {
+        ConfigurationMessage::AppKey(inner)
+    }
+}
+
 impl AppKeyMessage {
     pub fn parse_add(parameters: &[u8]) -> Result<Self, ParseError> {
         if parameters.len() == 19 {
@@ -37,6 +43,31 @@ impl AppKeyMessage {
         }
     }
 
+    pub fn parse_update(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 19 {
+            let indexes = NetKeyAppKeyIndexesPair::parse(&parameters[0..=2])?;
+            let app_key = parameters[3..]
+                .try_into()
+                .map_err(|_| ParseError::InvalidLength)?;
+            Ok(Self::Update(AppKeyUpdateMessage {
+                net_key_index: indexes.net_key(),
+                app_key_index: indexes.app_key(),
+                app_key,
+            }))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
+    pub fn parse_delete(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 3 {
+            let indexes = NetKeyAppKeyIndexesPair::parse(parameters)?;
+            Ok(Self::Delete(AppKeyDeleteMessage { indexes }))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
     pub fn parse_get(parameters: &[u8]) -> Result<Self, ParseError> {
         if parameters.len() == 2 {
             let net_key_index = NetKeyIndex(KeyIndex::parse_one(parameters)?);
@@ -55,7 +86,7 @@ impl Message for AppKeyMessage {
             Self::Get(_) => CONFIG_APPKEY_GET,
             Self::List(_) => CONFIG_APPKEY_LIST,
             Self::Status(_) => CONFIG_APPKEY_STATUS,
-            Self::Update(_) => CONFIG_APPKEY_STATUS,
+            Self::Update(_) => CONFIG_APPKEY_UPDATE,
         }
     }
 
@@ -76,52 +107,69 @@ impl Message for AppKeyMessage {
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct AppKeyAddMessage {
-    pub(crate) indexes: NetKeyAppKeyIndexesPair,
-    pub(crate) app_key: [u8; 16],
+    pub indexes: NetKeyAppKeyIndexesPair,
+    pub app_key: [u8; 16],
 }
 
 impl AppKeyAddMessage {
     fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!();
+        self.indexes.emit(xmit)?;
+        xmit.extend_from_slice(&self.app_key)
+            .map_err(|_| InsufficientBuffer)?;
+        Ok(())
+    }
+
+    pub fn create_status_response(&self, status: Status) -> AppKeyStatusMessage {
+        AppKeyStatusMessage {
+            status,
+            indexes: self.indexes,
+        }
     }
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct AppKeyDeleteMessage {
-    pub(crate) indexes: NetKeyAppKeyIndexesPair,
+    pub indexes: NetKeyAppKeyIndexesPair,
 }
 
 impl AppKeyDeleteMessage {
     fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!();
+        self.indexes.emit(xmit)
+    }
+
+    pub fn create_status_response(&self, status: Status) -> AppKeyStatusMessage {
+        AppKeyStatusMessage {
+            status,
+            indexes: self.indexes,
+        }
     }
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct AppKeyGetMessage {
-    pub(crate) net_key_index: NetKeyIndex,
+    pub net_key_index: NetKeyIndex,
 }
 
 impl AppKeyGetMessage {
     fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        self.net_key_index.emit(xmit)
     }
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct AppKeyListMessage {
-    pub(crate) status: Status,
-    pub(crate) net_key_index: NetKeyIndex,
-    pub(crate) app_key_indexes: Vec<AppKeyIndex, 10>,
+    pub status: Status,
+    pub net_key_index: NetKeyIndex,
+    pub app_key_indexes: Vec<AppKeyIndex, 10>,
 }
 
 impl AppKeyListMessage {
@@ -133,15 +181,6 @@ impl AppKeyListMessage {
             .map_err(|_| InsufficientBuffer)?;
         self.net_key_index.emit(xmit)?;
 
-        /*
-        for (i, app_key_index) in self.app_key_indexes.iter().enumerate() {
-            if (i + 1) % 2 == 0 {
-                app_key_index.0.emit_second(xmit)?;
-            } else {
-                app_key_index.0.emit_first(xmit)?;
-            }
-        }
-         */
         for chunk in self.app_key_indexes.chunks(2) {
             if chunk.len() == 2 {
                 KeyIndex::emit_two((&chunk[0].0, &chunk[1].0), xmit)?;
@@ -156,8 +195,8 @@ impl AppKeyListMessage {
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct AppKeyStatusMessage {
-    pub(crate) status: Status,
-    pub(crate) indexes: NetKeyAppKeyIndexesPair,
+    pub status: Status,
+    pub indexes: NetKeyAppKeyIndexesPair,
 }
 
 impl AppKeyStatusMessage {
@@ -174,16 +213,26 @@ impl AppKeyStatusMessage {
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct AppKeyUpdateMessage {
-    pub(crate) net_key_index: NetKeyIndex,
-    pub(crate) app_key_index: AppKeyIndex,
-    pub(crate) app_key: [u8; 16],
+    pub net_key_index: NetKeyIndex,
+    pub app_key_index: AppKeyIndex,
+    pub app_key: [u8; 16],
 }
 
 impl AppKeyUpdateMessage {
     fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        NetKeyAppKeyIndexesPair(self.net_key_index, self.app_key_index).emit(xmit)?;
+        xmit.extend_from_slice(&self.app_key)
+            .map_err(|_| InsufficientBuffer)?;
+        Ok(())
+    }
+
+    pub fn create_status_response(&self, status: Status) -> AppKeyStatusMessage {
+        AppKeyStatusMessage {
+            status,
+            indexes: NetKeyAppKeyIndexesPair(self.net_key_index, self.app_key_index),
+        }
     }
 }
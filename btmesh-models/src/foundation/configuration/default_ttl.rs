@@ -1,3 +1,4 @@
+use crate::foundation::configuration::ConfigurationMessage;
 use crate::Message;
 use btmesh_common::opcode::Opcode;
 use btmesh_common::{opcode, InsufficientBuffer, ParseError};
@@ -14,6 +15,12 @@ pub enum DefaultTTLMessage {
     Status(u8),
 }
 
+impl From<DefaultTTLMessage> for ConfigurationMessage {
+    fn from(inner: DefaultTTLMessage) -> Self {
+        ConfigurationMessage::DefaultTTL(inner)
+    }
+}
+
 #[allow(unused)]
 impl Message for DefaultTTLMessage {
     fn opcode(&self) -> Opcode {
@@ -46,11 +53,67 @@ impl DefaultTTLMessage {
         }
     }
 
+    /// Parse a set message, rejecting the prohibited TTL values `0x01` and `0x80..=0xFF`.
     pub fn parse_set(parameters: &[u8]) -> Result<Self, ParseError> {
         if parameters.len() == 1 {
-            Ok(Self::Set(parameters[0]))
+            match parameters[0] {
+                0x01 | 0x80..=0xFF => Err(ParseError::InvalidValue),
+                ttl => Ok(Self::Set(ttl)),
+            }
         } else {
             Err(ParseError::InvalidLength)
         }
     }
+    pub fn parse_status(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 1 {
+            Ok(Self::Status(parameters[0]))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_set() {
+        for ttl in [0x00, 0x02, 0x05, 0x7F] {
+            assert!(matches!(
+                DefaultTTLMessage::parse_set(&[ttl]),
+                Ok(DefaultTTLMessage::Set(val)) if val == ttl
+            ));
+        }
+
+        for ttl in [0x01, 0x80, 0xAA, 0xFF] {
+            assert!(matches!(
+                DefaultTTLMessage::parse_set(&[ttl]),
+                Err(ParseError::InvalidValue)
+            ));
+        }
+
+        assert!(matches!(
+            DefaultTTLMessage::parse_set(&[]),
+            Err(ParseError::InvalidLength)
+        ));
+        assert!(matches!(
+            DefaultTTLMessage::parse_set(&[0x05, 0x05]),
+            Err(ParseError::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn status_round_trip() {
+        let mut xmit: Vec<u8, 1> = Vec::new();
+        DefaultTTLMessage::Status(0x3F)
+            .emit_parameters(&mut xmit)
+            .unwrap();
+        assert_eq!(&[0x3F], &xmit[..]);
+
+        assert!(matches!(
+            DefaultTTLMessage::parse_status(&xmit),
+            Ok(DefaultTTLMessage::Status(0x3F))
+        ));
+    }
 }
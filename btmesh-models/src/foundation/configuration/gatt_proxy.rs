@@ -0,0 +1,160 @@
+use crate::foundation::configuration::ConfigurationMessage;
+use crate::Message;
+use btmesh_common::opcode::Opcode;
+use btmesh_common::{opcode, InsufficientBuffer, ParseError};
+use heapless::Vec;
+
+opcode!( CONFIG_GATT_PROXY_GET 0x80, 0x12 );
+opcode!( CONFIG_GATT_PROXY_SET 0x80, 0x13 );
+opcode!( CONFIG_GATT_PROXY_STATUS 0x80, 0x14 );
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GattProxy {
+    Disabled = 0x00,
+    Enabled = 0x01,
+    NotSupported = 0x02,
+}
+
+impl Default for GattProxy {
+    #[cfg(feature = "proxy")]
+    fn default() -> Self {
+        Self::Enabled
+    }
+
+    #[cfg(not(feature = "proxy"))]
+    fn default() -> Self {
+        Self::NotSupported
+    }
+}
+
+impl GattProxy {
+    pub fn parse(data: u8) -> Result<Self, ParseError> {
+        match data {
+            0x00 => Ok(Self::Disabled),
+            0x01 => Ok(Self::Enabled),
+            0x02 => Ok(Self::NotSupported),
+            _ => Err(ParseError::InvalidValue),
+        }
+    }
+
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        xmit.push(*self as u8).map_err(|_| InsufficientBuffer)?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GattProxyMessage {
+    Get,
+    Set(GattProxy),
+    Status(GattProxy),
+}
+
+impl From<GattProxyMessage> for ConfigurationMessage {
+    fn from(inner: GattProxyMessage) -> Self {
+        ConfigurationMessage::GattProxy(inner)
+    }
+}
+
+impl Message for GattProxyMessage {
+    fn opcode(&self) -> Opcode {
+        match self {
+            Self::Get => CONFIG_GATT_PROXY_GET,
+            Self::Set(_) => CONFIG_GATT_PROXY_SET,
+            Self::Status(_) => CONFIG_GATT_PROXY_STATUS,
+        }
+    }
+
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        match self {
+            Self::Get => {}
+            Self::Set(inner) => inner.emit(xmit)?,
+            Self::Status(inner) => inner.emit(xmit)?,
+        }
+        Ok(())
+    }
+}
+
+#[allow(unused)]
+impl GattProxyMessage {
+    pub fn parse_get(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.is_empty() {
+            Ok(Self::Get)
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
+    pub fn parse_set(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 1 {
+            Ok(Self::Set(GattProxy::parse(parameters[0])?))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
+    pub fn parse_status(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 1 {
+            Ok(Self::Status(GattProxy::parse(parameters[0])?))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_set() {
+        assert!(matches!(
+            GattProxyMessage::parse_set(&[0x00]),
+            Ok(GattProxyMessage::Set(GattProxy::Disabled))
+        ));
+        assert!(matches!(
+            GattProxyMessage::parse_set(&[0x01]),
+            Ok(GattProxyMessage::Set(GattProxy::Enabled))
+        ));
+        assert!(matches!(
+            GattProxyMessage::parse_set(&[0x03]),
+            Err(ParseError::InvalidValue)
+        ));
+        assert!(matches!(
+            GattProxyMessage::parse_set(&[]),
+            Err(ParseError::InvalidLength)
+        ));
+        assert!(matches!(
+            GattProxyMessage::parse_get(&[0x00]),
+            Err(ParseError::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn status_round_trip() {
+        let mut xmit: Vec<u8, 1> = Vec::new();
+        GattProxyMessage::Status(GattProxy::Disabled)
+            .emit_parameters(&mut xmit)
+            .unwrap();
+        assert_eq!(&[0x00], &xmit[..]);
+
+        assert!(matches!(
+            GattProxyMessage::parse_status(&xmit),
+            Ok(GattProxyMessage::Status(GattProxy::Disabled))
+        ));
+    }
+
+    #[test]
+    fn default_follows_proxy_feature() {
+        if cfg!(feature = "proxy") {
+            assert_eq!(GattProxy::Enabled, GattProxy::default());
+        } else {
+            assert_eq!(GattProxy::NotSupported, GattProxy::default());
+        }
+    }
+}
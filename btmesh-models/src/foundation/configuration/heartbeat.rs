@@ -0,0 +1,329 @@
+use crate::foundation::configuration::{ConfigurationMessage, NetKeyIndex};
+use crate::{Message, Status};
+use btmesh_common::address::Address;
+use btmesh_common::opcode::Opcode;
+use btmesh_common::{opcode, Features, InsufficientBuffer, ParseError};
+use heapless::Vec;
+
+opcode!( CONFIG_HEARTBEAT_PUBLICATION_GET 0x80, 0x38 );
+opcode!( CONFIG_HEARTBEAT_PUBLICATION_SET 0x80, 0x39 );
+opcode!( CONFIG_HEARTBEAT_PUBLICATION_STATUS 0x06 );
+opcode!( CONFIG_HEARTBEAT_SUBSCRIPTION_GET 0x80, 0x3A );
+opcode!( CONFIG_HEARTBEAT_SUBSCRIPTION_SET 0x80, 0x3B );
+opcode!( CONFIG_HEARTBEAT_SUBSCRIPTION_STATUS 0x80, 0x3C );
+
+/// Largest logarithmic period accepted, and largest logarithmic count
+/// besides the indefinite one.
+const MAX_LOG: u8 = 0x11;
+
+/// Logarithmic publication count meaning "publish indefinitely".
+pub const INDEFINITE_COUNT_LOG: u8 = 0xFF;
+
+/// Largest TTL of a heartbeat.
+const MAX_TTL: u8 = 0x7F;
+
+/// Expand a logarithmic count or period into its value.
+///
+/// Values too large to be represented saturate at `0xFFFF`, which
+/// also expresses an indefinite count.
+pub fn from_log(log: u8) -> u16 {
+    match log {
+        0x00 => 0x0000,
+        0x01..=0x10 => 1 << (log - 1),
+        _ => 0xFFFF,
+    }
+}
+
+/// Compress a count or period into its logarithmic representation,
+/// the exponent of the largest power of two not larger than the value, plus one.
+///
+/// A count of `0xFFFF` is represented as `0xFF`, mirroring `from_log`.
+pub fn to_log(value: u32) -> u8 {
+    match value {
+        0xFFFF => INDEFINITE_COUNT_LOG,
+        _ => (u32::BITS - value.leading_zeros()) as u8,
+    }
+}
+
+/// Heartbeat publication state.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeartbeatPublication {
+    pub destination: Address,
+    pub count_log: u8,
+    pub period_log: u8,
+    pub ttl: u8,
+    pub features: Features,
+    pub net_key_index: NetKeyIndex,
+}
+
+impl Default for HeartbeatPublication {
+    fn default() -> Self {
+        Self {
+            destination: Address::Unassigned,
+            count_log: 0,
+            period_log: 0,
+            ttl: 0,
+            features: Features::parse(0),
+            net_key_index: NetKeyIndex::new(0),
+        }
+    }
+}
+
+impl HeartbeatPublication {
+    pub fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() != 9 {
+            return Err(ParseError::InvalidLength);
+        }
+
+        let destination = Address::parse([parameters[1], parameters[0]]);
+        let count_log = parameters[2];
+        let period_log = parameters[3];
+        let ttl = parameters[4];
+
+        if matches!(destination, Address::Virtual(_))
+            || (count_log > MAX_LOG && count_log != INDEFINITE_COUNT_LOG)
+            || period_log > MAX_LOG
+            || ttl > MAX_TTL
+        {
+            return Err(ParseError::InvalidValue);
+        }
+
+        Ok(Self {
+            destination,
+            count_log,
+            period_log,
+            ttl,
+            features: Features::parse(u16::from_le_bytes([parameters[5], parameters[6]])),
+            net_key_index: NetKeyIndex::parse(&parameters[7..9])?,
+        })
+    }
+
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        let destination = self.destination.as_bytes();
+        xmit.push(destination[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(destination[0]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.count_log).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.period_log).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.ttl).map_err(|_| InsufficientBuffer)?;
+        self.features.emit(xmit)?;
+        self.net_key_index.emit(xmit)?;
+        Ok(())
+    }
+}
+
+/// Heartbeat subscription state, along with the statistics
+/// gathered from the heartbeats received.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeartbeatSubscription {
+    pub source: Address,
+    pub destination: Address,
+    pub period_log: u8,
+    pub count_log: u8,
+    pub min_hops: u8,
+    pub max_hops: u8,
+}
+
+impl Default for HeartbeatSubscription {
+    fn default() -> Self {
+        Self {
+            source: Address::Unassigned,
+            destination: Address::Unassigned,
+            period_log: 0,
+            count_log: 0,
+            min_hops: 0,
+            max_hops: 0,
+        }
+    }
+}
+
+impl HeartbeatSubscription {
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        let source = self.source.as_bytes();
+        xmit.push(source[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(source[0]).map_err(|_| InsufficientBuffer)?;
+        let destination = self.destination.as_bytes();
+        xmit.push(destination[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(destination[0]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.period_log).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.count_log).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.min_hops).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.max_hops).map_err(|_| InsufficientBuffer)?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HeartbeatPublicationMessage {
+    Get,
+    Set(HeartbeatPublication),
+    Status(HeartbeatPublicationStatusMessage),
+}
+
+impl From<HeartbeatPublicationMessage> for ConfigurationMessage {
+    fn from(inner: HeartbeatPublicationMessage) -> Self {
+        ConfigurationMessage::HeartbeatPublication(inner)
+    }
+}
+
+impl Message for HeartbeatPublicationMessage {
+    fn opcode(&self) -> Opcode {
+        match self {
+            Self::Get => CONFIG_HEARTBEAT_PUBLICATION_GET,
+            Self::Set(_) => CONFIG_HEARTBEAT_PUBLICATION_SET,
+            Self::Status(_) => CONFIG_HEARTBEAT_PUBLICATION_STATUS,
+        }
+    }
+
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        match self {
+            Self::Get => Ok(()),
+            Self::Set(inner) => inner.emit(xmit),
+            Self::Status(inner) => inner.emit_parameters(xmit),
+        }
+    }
+}
+
+impl HeartbeatPublicationMessage {
+    pub fn parse_get(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.is_empty() {
+            Ok(Self::Get)
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
+    pub fn parse_set(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::Set(HeartbeatPublication::parse(parameters)?))
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HeartbeatPublicationStatusMessage {
+    pub status: Status,
+    pub publication: HeartbeatPublication,
+}
+
+impl HeartbeatPublicationStatusMessage {
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        xmit.push(self.status as u8)
+            .map_err(|_| InsufficientBuffer)?;
+        self.publication.emit(xmit)
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HeartbeatSubscriptionMessage {
+    Get,
+    Set(HeartbeatSubscriptionSetMessage),
+    Status(HeartbeatSubscriptionStatusMessage),
+}
+
+impl From<HeartbeatSubscriptionMessage> for ConfigurationMessage {
+    fn from(inner: HeartbeatSubscriptionMessage) -> Self {
+        ConfigurationMessage::HeartbeatSubscription(inner)
+    }
+}
+
+impl Message for HeartbeatSubscriptionMessage {
+    fn opcode(&self) -> Opcode {
+        match self {
+            Self::Get => CONFIG_HEARTBEAT_SUBSCRIPTION_GET,
+            Self::Set(_) => CONFIG_HEARTBEAT_SUBSCRIPTION_SET,
+            Self::Status(_) => CONFIG_HEARTBEAT_SUBSCRIPTION_STATUS,
+        }
+    }
+
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        match self {
+            Self::Get => Ok(()),
+            Self::Set(inner) => inner.emit_parameters(xmit),
+            Self::Status(inner) => inner.emit_parameters(xmit),
+        }
+    }
+}
+
+impl HeartbeatSubscriptionMessage {
+    pub fn parse_get(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.is_empty() {
+            Ok(Self::Get)
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
+    pub fn parse_set(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() != 5 {
+            return Err(ParseError::InvalidLength);
+        }
+
+        let source = Address::parse([parameters[1], parameters[0]]);
+        let destination = Address::parse([parameters[3], parameters[2]]);
+        let period_log = parameters[4];
+
+        if !matches!(source, Address::Unassigned | Address::Unicast(_))
+            || matches!(destination, Address::Virtual(_))
+            || period_log > MAX_LOG
+        {
+            return Err(ParseError::InvalidValue);
+        }
+
+        Ok(Self::Set(HeartbeatSubscriptionSetMessage {
+            source,
+            destination,
+            period_log,
+        }))
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HeartbeatSubscriptionSetMessage {
+    pub source: Address,
+    pub destination: Address,
+    pub period_log: u8,
+}
+
+impl HeartbeatSubscriptionSetMessage {
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        let source = self.source.as_bytes();
+        xmit.push(source[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(source[0]).map_err(|_| InsufficientBuffer)?;
+        let destination = self.destination.as_bytes();
+        xmit.push(destination[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(destination[0]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.period_log).map_err(|_| InsufficientBuffer)?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HeartbeatSubscriptionStatusMessage {
+    pub status: Status,
+    pub subscription: HeartbeatSubscription,
+}
+
+impl HeartbeatSubscriptionStatusMessage {
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        xmit.push(self.status as u8)
+            .map_err(|_| InsufficientBuffer)?;
+        self.subscription.emit(xmit)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::foundation::configuration::{ConfigurationMessage, NetKeyIndex};
+use crate::{Message, Status};
+use btmesh_common::opcode::Opcode;
+use btmesh_common::{opcode, InsufficientBuffer, ParseError};
+use heapless::Vec;
+
+opcode!( CONFIG_KEY_REFRESH_PHASE_GET 0x80, 0x15 );
+opcode!( CONFIG_KEY_REFRESH_PHASE_SET 0x80, 0x16 );
+opcode!( CONFIG_KEY_REFRESH_PHASE_STATUS 0x80, 0x17 );
+
+/// Phase of the key refresh procedure of a subnet.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyRefreshPhase {
+    /// Normal operation, using the old keys only.
+    Normal = 0x00,
+    /// New keys have been distributed, but are not yet used for transmission.
+    First = 0x01,
+    /// New keys are used for transmission, while old keys are still accepted.
+    Second = 0x02,
+}
+
+impl Default for KeyRefreshPhase {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+impl KeyRefreshPhase {
+    pub fn parse(data: u8) -> Result<Self, ParseError> {
+        match data {
+            0x00 => Ok(Self::Normal),
+            0x01 => Ok(Self::First),
+            0x02 => Ok(Self::Second),
+            _ => Err(ParseError::InvalidValue),
+        }
+    }
+}
+
+/// Transition requested of the key refresh procedure.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeyRefreshTransition {
+    /// Start transmitting with the new keys.
+    UseNewKeys = 0x02,
+    /// Revoke the old keys, completing the procedure.
+    RevokeOldKeys = 0x03,
+}
+
+impl KeyRefreshTransition {
+    pub fn parse(data: u8) -> Result<Self, ParseError> {
+        match data {
+            0x02 => Ok(Self::UseNewKeys),
+            0x03 => Ok(Self::RevokeOldKeys),
+            _ => Err(ParseError::InvalidValue),
+        }
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeyRefreshPhaseMessage {
+    Get(NetKeyIndex),
+    Set(KeyRefreshPhaseSetMessage),
+    Status(KeyRefreshPhaseStatusMessage),
+}
+
+impl From<KeyRefreshPhaseMessage> for ConfigurationMessage {
+    fn from(inner: KeyRefreshPhaseMessage) -> Self {
+        ConfigurationMessage::KeyRefreshPhase(inner)
+    }
+}
+
+impl Message for KeyRefreshPhaseMessage {
+    fn opcode(&self) -> Opcode {
+        match self {
+            Self::Get(_) => CONFIG_KEY_REFRESH_PHASE_GET,
+            Self::Set(_) => CONFIG_KEY_REFRESH_PHASE_SET,
+            Self::Status(_) => CONFIG_KEY_REFRESH_PHASE_STATUS,
+        }
+    }
+
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        match self {
+            Self::Get(net_key_index) => net_key_index.emit(xmit),
+            Self::Set(inner) => inner.emit_parameters(xmit),
+            Self::Status(inner) => inner.emit_parameters(xmit),
+        }
+    }
+}
+
+impl KeyRefreshPhaseMessage {
+    pub fn parse_get(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 2 {
+            Ok(Self::Get(NetKeyIndex::parse(parameters)?))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
+    pub fn parse_set(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 3 {
+            Ok(Self::Set(KeyRefreshPhaseSetMessage {
+                net_key_index: NetKeyIndex::parse(&parameters[0..2])?,
+                transition: KeyRefreshTransition::parse(parameters[2])?,
+            }))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct KeyRefreshPhaseSetMessage {
+    pub net_key_index: NetKeyIndex,
+    pub transition: KeyRefreshTransition,
+}
+
+impl KeyRefreshPhaseSetMessage {
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        self.net_key_index.emit(xmit)?;
+        xmit.push(self.transition as u8)
+            .map_err(|_| InsufficientBuffer)?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct KeyRefreshPhaseStatusMessage {
+    pub status: Status,
+    pub net_key_index: NetKeyIndex,
+    pub phase: KeyRefreshPhase,
+}
+
+impl KeyRefreshPhaseStatusMessage {
+    fn emit_parameters<const N: usize>(
+        &self,
+        xmit: &mut Vec<u8, N>,
+    ) -> Result<(), InsufficientBuffer> {
+        xmit.push(self.status as u8)
+            .map_err(|_| InsufficientBuffer)?;
+        self.net_key_index.emit(xmit)?;
+        xmit.push(self.phase as u8)
+            .map_err(|_| InsufficientBuffer)?;
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use crate::foundation::configuration::app_key::{
-    AppKeyMessage, CONFIG_APPKEY_ADD, CONFIG_APPKEY_GET,
+    AppKeyMessage, CONFIG_APPKEY_ADD, CONFIG_APPKEY_DELETE, CONFIG_APPKEY_GET, CONFIG_APPKEY_UPDATE,
 };
 use crate::foundation::configuration::beacon::{
     BeaconMessage, CONFIG_BEACON_GET, CONFIG_BEACON_SET,
@@ -10,19 +10,40 @@ use crate::foundation::configuration::composition_data::{
 use crate::foundation::configuration::default_ttl::{
     DefaultTTLMessage, CONFIG_DEFAULT_TTL_GET, CONFIG_DEFAULT_TTL_SET,
 };
+use crate::foundation::configuration::gatt_proxy::{
+    GattProxyMessage, CONFIG_GATT_PROXY_GET, CONFIG_GATT_PROXY_SET,
+};
+use crate::foundation::configuration::heartbeat::{
+    HeartbeatPublicationMessage, HeartbeatSubscriptionMessage, CONFIG_HEARTBEAT_PUBLICATION_GET,
+    CONFIG_HEARTBEAT_PUBLICATION_SET, CONFIG_HEARTBEAT_SUBSCRIPTION_GET,
+    CONFIG_HEARTBEAT_SUBSCRIPTION_SET,
+};
+use crate::foundation::configuration::key_refresh_phase::{
+    KeyRefreshPhaseMessage, CONFIG_KEY_REFRESH_PHASE_GET, CONFIG_KEY_REFRESH_PHASE_SET,
+};
 use crate::foundation::configuration::model_app::{
     ModelAppMessage, CONFIG_MODEL_APP_BIND, CONFIG_MODEL_APP_UNBIND,
 };
 use crate::foundation::configuration::model_publication::{
-    ModelPublicationMessage, CONFIG_MODEL_PUBLICATION_SET,
+    ModelPublicationMessage, CONFIG_MODEL_PUBLICATION_GET, CONFIG_MODEL_PUBLICATION_SET,
     CONFIG_MODEL_PUBLICATION_VIRTUAL_ADDRESS_SET,
 };
 
 use crate::foundation::configuration::model_subscription::{
-    ModelSubscriptionMessage, CONFIG_MODEL_SUBSCRIPTION_ADD,
+    ModelSubscriptionMessage, CONFIG_MODEL_SUBSCRIPTION_ADD, CONFIG_MODEL_SUBSCRIPTION_DELETE,
+    CONFIG_MODEL_SUBSCRIPTION_DELETE_ALL, CONFIG_MODEL_SUBSCRIPTION_OVERWRITE,
     CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_ADD,
+    CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_DELETE,
+    CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_OVERWRITE, CONFIG_SIG_MODEL_SUBSCRIPTION_GET,
+    CONFIG_VENDOR_MODEL_SUBSCRIPTION_GET,
 };
 
+use crate::foundation::configuration::net_key::{
+    NetKeyMessage, CONFIG_NETKEY_ADD, CONFIG_NETKEY_DELETE, CONFIG_NETKEY_GET, CONFIG_NETKEY_UPDATE,
+};
+use crate::foundation::configuration::network_transmit::{
+    NetworkTransmitMessage, CONFIG_NETWORK_TRANSMIT_GET, CONFIG_NETWORK_TRANSMIT_SET,
+};
 use crate::foundation::configuration::node_reset::{NodeResetMessage, CONFIG_NODE_RESET};
 
 #[cfg(feature = "relay")]
@@ -38,9 +59,13 @@ pub mod app_key;
 pub mod beacon;
 pub mod composition_data;
 pub mod default_ttl;
+pub mod gatt_proxy;
+pub mod heartbeat;
+pub mod key_refresh_phase;
 pub mod model_app;
 pub mod model_publication;
 pub mod model_subscription;
+pub mod net_key;
 pub mod network_transmit;
 pub mod node_reset;
 
@@ -57,9 +82,15 @@ pub enum ConfigurationMessage {
     NodeReset(NodeResetMessage),
     CompositionData(CompositionDataMessage),
     AppKey(AppKeyMessage),
+    GattProxy(GattProxyMessage),
+    HeartbeatPublication(HeartbeatPublicationMessage),
+    HeartbeatSubscription(HeartbeatSubscriptionMessage),
+    KeyRefreshPhase(KeyRefreshPhaseMessage),
     ModelApp(ModelAppMessage),
     ModelPublication(ModelPublicationMessage),
     ModelSubscription(ModelSubscriptionMessage),
+    NetKey(NetKeyMessage),
+    NetworkTransmit(NetworkTransmitMessage),
     #[cfg(feature = "relay")]
     Relay(RelayMessage),
 }
@@ -72,9 +103,15 @@ impl Message for ConfigurationMessage {
             ConfigurationMessage::NodeReset(inner) => inner.opcode(),
             ConfigurationMessage::CompositionData(inner) => inner.opcode(),
             ConfigurationMessage::AppKey(inner) => inner.opcode(),
+            ConfigurationMessage::GattProxy(inner) => inner.opcode(),
+            ConfigurationMessage::HeartbeatPublication(inner) => inner.opcode(),
+            ConfigurationMessage::HeartbeatSubscription(inner) => inner.opcode(),
+            ConfigurationMessage::KeyRefreshPhase(inner) => inner.opcode(),
             ConfigurationMessage::ModelApp(inner) => inner.opcode(),
             ConfigurationMessage::ModelPublication(inner) => inner.opcode(),
             ConfigurationMessage::ModelSubscription(inner) => inner.opcode(),
+            ConfigurationMessage::NetKey(inner) => inner.opcode(),
+            ConfigurationMessage::NetworkTransmit(inner) => inner.opcode(),
             #[cfg(feature = "relay")]
             ConfigurationMessage::Relay(inner) => inner.opcode(),
         }
@@ -90,9 +127,15 @@ impl Message for ConfigurationMessage {
             ConfigurationMessage::NodeReset(inner) => inner.emit_parameters(xmit),
             ConfigurationMessage::CompositionData(inner) => inner.emit_parameters(xmit),
             ConfigurationMessage::AppKey(inner) => inner.emit_parameters(xmit),
+            ConfigurationMessage::GattProxy(inner) => inner.emit_parameters(xmit),
+            ConfigurationMessage::HeartbeatPublication(inner) => inner.emit_parameters(xmit),
+            ConfigurationMessage::HeartbeatSubscription(inner) => inner.emit_parameters(xmit),
+            ConfigurationMessage::KeyRefreshPhase(inner) => inner.emit_parameters(xmit),
             ConfigurationMessage::ModelApp(inner) => inner.emit_parameters(xmit),
             ConfigurationMessage::ModelPublication(inner) => inner.emit_parameters(xmit),
             ConfigurationMessage::ModelSubscription(inner) => inner.emit_parameters(xmit),
+            ConfigurationMessage::NetKey(inner) => inner.emit_parameters(xmit),
+            ConfigurationMessage::NetworkTransmit(inner) => inner.emit_parameters(xmit),
             #[cfg(feature = "relay")]
             ConfigurationMessage::Relay(inner) => inner.emit_parameters(xmit),
         }
@@ -137,9 +180,51 @@ impl Model for ConfigurationServer {
             CONFIG_APPKEY_ADD => Ok(Some(ConfigurationMessage::AppKey(
                 AppKeyMessage::parse_add(parameters)?,
             ))),
+            CONFIG_APPKEY_DELETE => Ok(Some(ConfigurationMessage::AppKey(
+                AppKeyMessage::parse_delete(parameters)?,
+            ))),
             CONFIG_APPKEY_GET => Ok(Some(ConfigurationMessage::AppKey(
                 AppKeyMessage::parse_get(parameters)?,
             ))),
+            CONFIG_APPKEY_UPDATE => Ok(Some(ConfigurationMessage::AppKey(
+                AppKeyMessage::parse_update(parameters)?,
+            ))),
+            // GATT Proxy
+            CONFIG_GATT_PROXY_GET => Ok(Some(ConfigurationMessage::GattProxy(
+                GattProxyMessage::parse_get(parameters)?,
+            ))),
+            CONFIG_GATT_PROXY_SET => Ok(Some(ConfigurationMessage::GattProxy(
+                GattProxyMessage::parse_set(parameters)?,
+            ))),
+            // Heartbeat Publication
+            CONFIG_HEARTBEAT_PUBLICATION_GET => {
+                Ok(Some(ConfigurationMessage::HeartbeatPublication(
+                    HeartbeatPublicationMessage::parse_get(parameters)?,
+                )))
+            }
+            CONFIG_HEARTBEAT_PUBLICATION_SET => {
+                Ok(Some(ConfigurationMessage::HeartbeatPublication(
+                    HeartbeatPublicationMessage::parse_set(parameters)?,
+                )))
+            }
+            // Heartbeat Subscription
+            CONFIG_HEARTBEAT_SUBSCRIPTION_GET => {
+                Ok(Some(ConfigurationMessage::HeartbeatSubscription(
+                    HeartbeatSubscriptionMessage::parse_get(parameters)?,
+                )))
+            }
+            CONFIG_HEARTBEAT_SUBSCRIPTION_SET => {
+                Ok(Some(ConfigurationMessage::HeartbeatSubscription(
+                    HeartbeatSubscriptionMessage::parse_set(parameters)?,
+                )))
+            }
+            // Key Refresh Phase
+            CONFIG_KEY_REFRESH_PHASE_GET => Ok(Some(ConfigurationMessage::KeyRefreshPhase(
+                KeyRefreshPhaseMessage::parse_get(parameters)?,
+            ))),
+            CONFIG_KEY_REFRESH_PHASE_SET => Ok(Some(ConfigurationMessage::KeyRefreshPhase(
+                KeyRefreshPhaseMessage::parse_set(parameters)?,
+            ))),
             // Model App
             CONFIG_MODEL_APP_BIND => Ok(Some(ConfigurationMessage::ModelApp(
                 ModelAppMessage::parse_bind(parameters)?,
@@ -148,6 +233,9 @@ impl Model for ConfigurationServer {
                 ModelAppMessage::parse_unbind(parameters)?,
             ))),
             // Model Publication
+            CONFIG_MODEL_PUBLICATION_GET => Ok(Some(ConfigurationMessage::ModelPublication(
+                ModelPublicationMessage::parse_get(parameters)?,
+            ))),
             CONFIG_MODEL_PUBLICATION_SET => Ok(Some(ConfigurationMessage::ModelPublication(
                 ModelPublicationMessage::parse_set(parameters)?,
             ))),
@@ -160,11 +248,62 @@ impl Model for ConfigurationServer {
             CONFIG_MODEL_SUBSCRIPTION_ADD => Ok(Some(ConfigurationMessage::ModelSubscription(
                 ModelSubscriptionMessage::parse_add(parameters)?,
             ))),
+            CONFIG_MODEL_SUBSCRIPTION_DELETE => Ok(Some(ConfigurationMessage::ModelSubscription(
+                ModelSubscriptionMessage::parse_delete(parameters)?,
+            ))),
+            CONFIG_MODEL_SUBSCRIPTION_DELETE_ALL => {
+                Ok(Some(ConfigurationMessage::ModelSubscription(
+                    ModelSubscriptionMessage::parse_delete_all(parameters)?,
+                )))
+            }
+            CONFIG_MODEL_SUBSCRIPTION_OVERWRITE => {
+                Ok(Some(ConfigurationMessage::ModelSubscription(
+                    ModelSubscriptionMessage::parse_overwrite(parameters)?,
+                )))
+            }
             CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_ADD => {
                 Ok(Some(ConfigurationMessage::ModelSubscription(
                     ModelSubscriptionMessage::parse_virtual_address_add(parameters)?,
                 )))
             }
+            CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_DELETE => {
+                Ok(Some(ConfigurationMessage::ModelSubscription(
+                    ModelSubscriptionMessage::parse_virtual_address_delete(parameters)?,
+                )))
+            }
+            CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_OVERWRITE => {
+                Ok(Some(ConfigurationMessage::ModelSubscription(
+                    ModelSubscriptionMessage::parse_virtual_address_overwrite(parameters)?,
+                )))
+            }
+            CONFIG_SIG_MODEL_SUBSCRIPTION_GET => Ok(Some(ConfigurationMessage::ModelSubscription(
+                ModelSubscriptionMessage::parse_sig_get(parameters)?,
+            ))),
+            CONFIG_VENDOR_MODEL_SUBSCRIPTION_GET => {
+                Ok(Some(ConfigurationMessage::ModelSubscription(
+                    ModelSubscriptionMessage::parse_vendor_get(parameters)?,
+                )))
+            }
+            // Net Key
+            CONFIG_NETKEY_ADD => Ok(Some(ConfigurationMessage::NetKey(
+                NetKeyMessage::parse_add(parameters)?,
+            ))),
+            CONFIG_NETKEY_DELETE => Ok(Some(ConfigurationMessage::NetKey(
+                NetKeyMessage::parse_delete(parameters)?,
+            ))),
+            CONFIG_NETKEY_GET => Ok(Some(ConfigurationMessage::NetKey(
+                NetKeyMessage::parse_get(parameters)?,
+            ))),
+            CONFIG_NETKEY_UPDATE => Ok(Some(ConfigurationMessage::NetKey(
+                NetKeyMessage::parse_update(parameters)?,
+            ))),
+            // Network Transmit
+            CONFIG_NETWORK_TRANSMIT_GET => Ok(Some(ConfigurationMessage::NetworkTransmit(
+                NetworkTransmitMessage::parse_get(parameters)?,
+            ))),
+            CONFIG_NETWORK_TRANSMIT_SET => Ok(Some(ConfigurationMessage::NetworkTransmit(
+                NetworkTransmitMessage::parse_set(parameters)?,
+            ))),
             // Relay
             #[cfg(feature = "relay")]
             CONFIG_RELAY_GET => Ok(Some(ConfigurationMessage::Relay(RelayMessage::parse_get(
@@ -182,7 +321,7 @@ impl Model for ConfigurationServer {
 // ------------------------------------------------------------------------
 // ------------------------------------------------------------------------
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct KeyIndex(u16);
 
@@ -197,7 +336,7 @@ impl KeyIndex {
     fn parse_one(parameters: &[u8]) -> Result<Self, ParseError> {
         if parameters.len() >= 2 {
             let byte1 = parameters[0];
-            let byte2 = parameters[1] & 0b11110000 >> 4;
+            let byte2 = parameters[1] & 0b00001111;
             let val = u16::from_be_bytes([byte2, byte1]);
             Ok(Self(val))
         } else {
@@ -211,24 +350,19 @@ impl KeyIndex {
     ) -> Result<(), InsufficientBuffer> {
         let bytes = index.0.to_be_bytes();
         let byte1 = bytes[1];
-        let byte2 = bytes[0] << 4;
+        let byte2 = bytes[0] & 0b00001111;
         xmit.push(byte1).map_err(|_| InsufficientBuffer)?;
         xmit.push(byte2).map_err(|_| InsufficientBuffer)?;
         Ok(())
     }
 
+    /// Two indexes are packed little-endian into 3 octets,
+    /// with the first index occupying the lower 12 bits.
     fn parse_two(parameters: &[u8]) -> Result<(Self, Self), ParseError> {
         if parameters.len() >= 3 {
-            let byte1 = parameters[0];
-            let byte2 = (parameters[1] & 0b11110000) >> 4;
-
-            let index1 = u16::from_be_bytes([byte1, byte2]);
-
-            let byte1 = parameters[1] & 0b00001111;
-            let byte2 = parameters[2];
-
-            let index2 = u16::from_be_bytes([byte1, byte2]);
-            Ok((Self(index2), Self(index1)))
+            let index1 = u16::from_be_bytes([parameters[1] & 0b00001111, parameters[0]]);
+            let index2 = (u16::from(parameters[2]) << 4) | (u16::from(parameters[1]) >> 4);
+            Ok((Self(index1), Self(index2)))
         } else {
             Err(ParseError::InvalidLength)
         }
@@ -238,25 +372,23 @@ impl KeyIndex {
         indexes: (&KeyIndex, &KeyIndex),
         xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        let bytes = indexes.1 .0.to_be_bytes();
-        let byte1 = bytes[0];
-        xmit.push(byte1).map_err(|_| InsufficientBuffer)?;
+        let index1 = indexes.0 .0;
+        let index2 = indexes.1 .0;
 
-        let byte2 = bytes[1] << 4;
-        let bytes = indexes.0 .0.to_be_bytes();
-        let byte1 = byte2 | bytes[0];
+        let byte1 = (index1 & 0xFF) as u8;
+        let byte2 = ((index1 >> 8) & 0b00001111) as u8 | ((index2 & 0b00001111) << 4) as u8;
+        let byte3 = (index2 >> 4) as u8;
 
         xmit.push(byte1).map_err(|_| InsufficientBuffer)?;
-
-        let byte2 = bytes[1];
         xmit.push(byte2).map_err(|_| InsufficientBuffer)?;
+        xmit.push(byte3).map_err(|_| InsufficientBuffer)?;
 
         Ok(())
     }
 }
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
 pub struct NetKeyIndex(KeyIndex);
 
 impl NetKeyIndex {
@@ -264,7 +396,15 @@ impl NetKeyIndex {
         Self(KeyIndex(index))
     }
 
-    fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+    pub fn value(&self) -> u16 {
+        self.0 .0
+    }
+
+    pub fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self(KeyIndex::parse_one(parameters)?))
+    }
+
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
         KeyIndex::emit_one(&self.0, xmit)
     }
 }
@@ -277,10 +417,18 @@ impl defmt::Format for NetKeyIndex {
 }
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
 pub struct AppKeyIndex(KeyIndex);
 
 impl AppKeyIndex {
+    pub fn new(index: u16) -> Self {
+        Self(KeyIndex(index))
+    }
+
+    pub fn value(&self) -> u16 {
+        self.0 .0
+    }
+
     fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
         KeyIndex::emit_one(&self.0, xmit)
     }
@@ -298,6 +446,10 @@ impl defmt::Format for AppKeyIndex {
 pub struct NetKeyAppKeyIndexesPair(NetKeyIndex, AppKeyIndex);
 
 impl NetKeyAppKeyIndexesPair {
+    pub fn new(net_key: NetKeyIndex, app_key: AppKeyIndex) -> Self {
+        Self(net_key, app_key)
+    }
+
     fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
         KeyIndex::emit_two((&self.0 .0, &self.1 .0), xmit).map_err(|_| InsufficientBuffer)?;
         Ok(())
@@ -1,4 +1,4 @@
-use crate::foundation::configuration::{AppKeyIndex, KeyIndex};
+use crate::foundation::configuration::{AppKeyIndex, ConfigurationMessage, KeyIndex};
 use crate::{Message, Status};
 use btmesh_common::address::UnicastAddress;
 use btmesh_common::opcode::Opcode;
@@ -16,6 +16,12 @@ pub enum ModelAppMessage {
     Unbind(ModelAppPayload),
 }
 
+impl From<ModelAppMessage> for ConfigurationMessage {
+    fn from(inner: ModelAppMessage) -> Self {
+        ConfigurationMessage::ModelApp(inner)
+    }
+}
+
 impl ModelAppMessage {
     pub fn parse_bind(parameters: &[u8]) -> Result<Self, ParseError> {
         Ok(Self::Bind(ModelAppPayload::parse(parameters)?))
@@ -47,11 +53,12 @@ impl Message for ModelAppMessage {
     }
 }
 
+#[derive(Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct ModelAppPayload {
-    pub(crate) element_address: UnicastAddress,
-    pub(crate) app_key_index: AppKeyIndex,
-    pub(crate) model_identifier: ModelIdentifier,
+    pub element_address: UnicastAddress,
+    pub app_key_index: AppKeyIndex,
+    pub model_identifier: ModelIdentifier,
 }
 
 impl ModelAppPayload {
@@ -72,6 +79,13 @@ impl ModelAppPayload {
         }
     }
 
+    pub fn create_status_response(&self, status: Status) -> ModelAppStatusMessage {
+        ModelAppStatusMessage {
+            status,
+            payload: *self,
+        }
+    }
+
     fn emit_parameters<const N: usize>(
         &self,
         xmit: &mut Vec<u8, N>,
@@ -87,8 +101,8 @@ impl ModelAppPayload {
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct ModelAppStatusMessage {
-    pub(crate) status: Status,
-    pub(crate) payload: ModelAppPayload,
+    pub status: Status,
+    pub payload: ModelAppPayload,
 }
 
 impl ModelAppStatusMessage {
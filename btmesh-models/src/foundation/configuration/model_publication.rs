@@ -1,6 +1,6 @@
-use crate::foundation::configuration::{AppKeyIndex, KeyIndex};
+use crate::foundation::configuration::{AppKeyIndex, ConfigurationMessage, KeyIndex};
 use crate::{Message, Status};
-use btmesh_common::address::{GroupAddress, LabelUuid, UnicastAddress};
+use btmesh_common::address::{Address, GroupAddress, LabelUuid, UnicastAddress};
 use btmesh_common::opcode::Opcode;
 use btmesh_common::{opcode, InsufficientBuffer, ModelIdentifier, ParseError};
 use heapless::Vec;
@@ -18,6 +18,12 @@ pub enum ModelPublicationMessage {
     Status(ModelPublicationStatusMessage),
 }
 
+impl From<ModelPublicationMessage> for ConfigurationMessage {
+    fn from(inner: ModelPublicationMessage) -> Self {
+        ConfigurationMessage::ModelPublication(inner)
+    }
+}
+
 impl Message for ModelPublicationMessage {
     fn opcode(&self) -> Opcode {
         match self {
@@ -44,12 +50,16 @@ impl Message for ModelPublicationMessage {
 }
 
 impl ModelPublicationMessage {
+    pub fn parse_get(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::Get(ModelPublicationGetMessage::parse(parameters)?))
+    }
+
     pub fn parse_set(parameters: &[u8]) -> Result<Self, ParseError> {
         Ok(Self::Set(ModelPublicationSetMessage::parse(parameters)?))
     }
 
     pub fn parse_virtual_address_set(parameters: &[u8]) -> Result<Self, ParseError> {
-        Ok(Self::Set(
+        Ok(Self::VirtualAddressSet(
             ModelPublicationSetMessage::parse_virtual_address(parameters)?,
         ))
     }
@@ -57,38 +67,84 @@ impl ModelPublicationMessage {
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct ModelPublicationGetMessage {
-    element_address: UnicastAddress,
-    model_identifier: ModelIdentifier,
+    pub element_address: UnicastAddress,
+    pub model_identifier: ModelIdentifier,
 }
 
 impl ModelPublicationGetMessage {
+    fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() >= 4 {
+            let element_address = UnicastAddress::parse([parameters[1], parameters[0]])?;
+            let model_identifier = ModelIdentifier::parse(&parameters[2..])?;
+            Ok(Self {
+                element_address,
+                model_identifier,
+            })
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
     fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        let addr_bytes = self.element_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        self.model_identifier.emit(xmit)?;
+        Ok(())
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PublishAddress {
+    Unassigned,
     Unicast(UnicastAddress),
     Group(GroupAddress),
     Virtual(LabelUuid),
 }
 
-/*
-impl Into<Address> for PublishAddress {
-    fn into(self) -> Address {
-        match self {
-            Unicast(inner) => Address::Unicast(inner),
+impl PublishAddress {
+    /// Parse a non-virtual publish address.
+    ///
+    /// Virtual addresses must be configured through the label-uuid bearing
+    /// virtual-address set message, so a bare virtual address is rejected.
+    fn parse(data: [u8; 2]) -> Result<Self, ParseError> {
+        match Address::parse(data) {
+            Address::Unassigned => Ok(Self::Unassigned),
+            Address::Unicast(inner) => Ok(Self::Unicast(inner)),
+            Address::Group(inner) => Ok(Self::Group(inner)),
+            Address::Virtual(_) => Err(ParseError::InvalidValue),
+        }
+    }
+
+    /// Big-endian 2-byte representation of the (possibly virtual) address.
+    pub fn as_bytes(&self) -> [u8; 2] {
+        Address::from(*self).as_bytes()
+    }
+
+    pub fn label_uuid(&self) -> Option<LabelUuid> {
+        if let PublishAddress::Virtual(label_uuid) = self {
+            Some(*label_uuid)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<PublishAddress> for Address {
+    fn from(addr: PublishAddress) -> Self {
+        match addr {
+            PublishAddress::Unassigned => Address::Unassigned,
+            PublishAddress::Unicast(inner) => Address::Unicast(inner),
             PublishAddress::Group(inner) => Address::Group(inner),
-            PublishAddress::Virtual(inner) => Address::Virtual(inner),
+            PublishAddress::Virtual(inner) => Address::Virtual(inner.virtual_address()),
         }
     }
 }
- */
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct ModelPublicationSetMessage {
@@ -106,39 +162,37 @@ pub struct ModelPublicationSetMessage {
 impl ModelPublicationSetMessage {
     fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        let addr_bytes = self.element_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        if let PublishAddress::Virtual(label_uuid) = self.publish_address {
+            xmit.extend_from_slice(label_uuid.label_uuid())
+                .map_err(|_| InsufficientBuffer)?;
+        } else {
+            let addr_bytes = self.publish_address.as_bytes();
+            xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+            xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        }
+        emit_publication_parameters(
+            xmit,
+            &self.app_key_index,
+            self.credential_flag,
+            self.publish_ttl,
+            self.publish_period,
+            self.publish_retransmit_count,
+            self.publish_retransmit_interval_steps,
+        )?;
+        self.model_identifier.emit(xmit)?;
+        Ok(())
     }
 
     fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
         if parameters.len() >= 11 {
             let element_address = UnicastAddress::parse([parameters[1], parameters[0]])?;
-            let publish_address =
-                PublishAddress::Unicast(UnicastAddress::parse([parameters[3], parameters[2]])?);
-            let app_key_index = AppKeyIndex(KeyIndex::parse_one(&parameters[4..=5])?);
-            let credential_flag = (parameters[5] & 0b0001000) != 0;
-            let publish_ttl = parameters[6];
-            let publish_ttl = if publish_ttl == 0xFF {
-                None
-            } else {
-                Some(publish_ttl)
-            };
-            let publish_period = parameters[7];
-            let publish_retransmit_count = (parameters[8] & 0b11100000) >> 5;
-            let publish_retransmit_interval_steps = parameters[8] & 0b00011111;
-            let model_identifier = ModelIdentifier::parse(&parameters[9..])?;
-            Ok(Self {
-                element_address,
-                publish_address,
-                app_key_index,
-                credential_flag,
-                publish_ttl,
-                publish_period,
-                publish_retransmit_count,
-                publish_retransmit_interval_steps,
-                model_identifier,
-            })
+            let publish_address = PublishAddress::parse([parameters[3], parameters[2]])?;
+            Self::parse_remainder(element_address, publish_address, &parameters[4..])
         } else {
             Err(ParseError::InvalidLength)
         }
@@ -148,35 +202,44 @@ impl ModelPublicationSetMessage {
         if parameters.len() >= 25 {
             let element_address = UnicastAddress::parse([parameters[1], parameters[0]])?;
             let publish_address = PublishAddress::Virtual(LabelUuid::parse(&parameters[2..=17])?);
-
-            let app_key_index = AppKeyIndex(KeyIndex::parse_one(&parameters[18..=19])?);
-            let credential_flag = (parameters[19] & 0b0001000) != 0;
-            let publish_ttl = parameters[20];
-            let publish_ttl = if publish_ttl == 0xFF {
-                None
-            } else {
-                Some(publish_ttl)
-            };
-            let publish_period = parameters[21];
-            let publish_retransmit_count = (parameters[22] & 0b11100000) >> 5;
-            let publish_retransmit_interval_steps = parameters[22] & 0b00011111;
-            let model_identifier = ModelIdentifier::parse(&parameters[23..])?;
-            Ok(Self {
-                element_address,
-                publish_address,
-                app_key_index,
-                credential_flag,
-                publish_ttl,
-                publish_period,
-                publish_retransmit_count,
-                publish_retransmit_interval_steps,
-                model_identifier,
-            })
+            Self::parse_remainder(element_address, publish_address, &parameters[18..])
         } else {
             Err(ParseError::InvalidLength)
         }
     }
 
+    /// Parse everything following the publish address, which is common
+    /// between the plain and virtual-address set messages.
+    fn parse_remainder(
+        element_address: UnicastAddress,
+        publish_address: PublishAddress,
+        parameters: &[u8],
+    ) -> Result<Self, ParseError> {
+        let app_key_index = AppKeyIndex(KeyIndex::parse_one(&parameters[0..=1])?);
+        let credential_flag = (parameters[1] & 0b00010000) != 0;
+        let publish_ttl = parameters[2];
+        let publish_ttl = if publish_ttl == 0xFF {
+            None
+        } else {
+            Some(publish_ttl)
+        };
+        let publish_period = parameters[3];
+        let publish_retransmit_count = parameters[4] & 0b00000111;
+        let publish_retransmit_interval_steps = (parameters[4] & 0b11111000) >> 3;
+        let model_identifier = ModelIdentifier::parse(&parameters[5..])?;
+        Ok(Self {
+            element_address,
+            publish_address,
+            app_key_index,
+            credential_flag,
+            publish_ttl,
+            publish_period,
+            publish_retransmit_count,
+            publish_retransmit_interval_steps,
+            model_identifier,
+        })
+    }
+
     pub fn create_status_response(&self, status: Status) -> ModelPublicationStatusMessage {
         ModelPublicationStatusMessage {
             status,
@@ -195,19 +258,39 @@ impl ModelPublicationSetMessage {
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct ModelPublicationStatusMessage {
-    status: Status,
-    element_address: UnicastAddress,
-    publish_address: PublishAddress,
-    app_key_index: AppKeyIndex,
-    credential_flag: bool,
-    publish_ttl: Option<u8>,
-    publish_period: u8,
-    publish_retransmit_count: u8,
-    publish_retransmit_interval_steps: u8,
-    model_identifier: ModelIdentifier,
+    pub status: Status,
+    pub element_address: UnicastAddress,
+    pub publish_address: PublishAddress,
+    pub app_key_index: AppKeyIndex,
+    pub credential_flag: bool,
+    pub publish_ttl: Option<u8>,
+    pub publish_period: u8,
+    pub publish_retransmit_count: u8,
+    pub publish_retransmit_interval_steps: u8,
+    pub model_identifier: ModelIdentifier,
 }
 
 impl ModelPublicationStatusMessage {
+    /// Status reported for a model which currently has no publication configured.
+    pub fn unassigned(
+        status: Status,
+        element_address: UnicastAddress,
+        model_identifier: ModelIdentifier,
+    ) -> Self {
+        Self {
+            status,
+            element_address,
+            publish_address: PublishAddress::Unassigned,
+            app_key_index: AppKeyIndex::new(0),
+            credential_flag: false,
+            publish_ttl: Some(0),
+            publish_period: 0,
+            publish_retransmit_count: 0,
+            publish_retransmit_interval_steps: 0,
+            model_identifier,
+        }
+    }
+
     fn emit_parameters<const N: usize>(
         &self,
         xmit: &mut Vec<u8, N>,
@@ -217,41 +300,77 @@ impl ModelPublicationStatusMessage {
         let addr_bytes = self.element_address.as_bytes();
         xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
         xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
-        match self.publish_address {
-            PublishAddress::Unicast(addr) => {
-                let addr_bytes = addr.as_bytes();
-                xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
-                xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
-            }
-            PublishAddress::Group(_addr) => {
-                todo!("group address")
-            }
-            PublishAddress::Virtual(addr) => {
-                let addr_bytes = addr.virtual_address().as_bytes();
-                xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
-                xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
-            }
-        }
-        self.app_key_index.emit(xmit)?;
-        if self.credential_flag {
-            if let Some(last) = xmit.last_mut() {
-                *last |= 0b00001000;
-            } else {
-                return Err(InsufficientBuffer);
-            }
-        }
-        if let Some(ttl) = self.publish_ttl {
-            xmit.push(ttl).map_err(|_| InsufficientBuffer)?;
+        // status always carries the 2-octet address, virtual or otherwise.
+        let addr_bytes = self.publish_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        emit_publication_parameters(
+            xmit,
+            &self.app_key_index,
+            self.credential_flag,
+            self.publish_ttl,
+            self.publish_period,
+            self.publish_retransmit_count,
+            self.publish_retransmit_interval_steps,
+        )?;
+        self.model_identifier.emit(xmit)?;
+        Ok(())
+    }
+}
+
+fn emit_publication_parameters<const N: usize>(
+    xmit: &mut Vec<u8, N>,
+    app_key_index: &AppKeyIndex,
+    credential_flag: bool,
+    publish_ttl: Option<u8>,
+    publish_period: u8,
+    publish_retransmit_count: u8,
+    publish_retransmit_interval_steps: u8,
+) -> Result<(), InsufficientBuffer> {
+    app_key_index.emit(xmit)?;
+    if credential_flag {
+        if let Some(last) = xmit.last_mut() {
+            *last |= 0b00010000;
         } else {
-            xmit.push(0xFF).map_err(|_| InsufficientBuffer)?;
+            return Err(InsufficientBuffer);
         }
-        xmit.push(self.publish_period)
-            .map_err(|_| InsufficientBuffer)?;
+    }
+    xmit.push(publish_ttl.unwrap_or(0xFF))
+        .map_err(|_| InsufficientBuffer)?;
+    xmit.push(publish_period).map_err(|_| InsufficientBuffer)?;
 
-        let retransmit = (self.publish_retransmit_count << 5)
-            | (self.publish_retransmit_interval_steps & 0b00011111);
-        xmit.push(retransmit).map_err(|_| InsufficientBuffer)?;
-        self.model_identifier.emit(xmit)?;
-        Ok(())
+    let retransmit =
+        (publish_retransmit_interval_steps << 3) | (publish_retransmit_count & 0b00000111);
+    xmit.push(retransmit).map_err(|_| InsufficientBuffer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publish_retransmit() {
+        let parameters = [
+            0x01, 0x00, // element address
+            0x00, 0xC0, // publish address
+            0x00, 0x00, // app key index and credential flag
+            0x05, // publish ttl
+            0x00, // publish period
+            0x2A, // 5 interval steps, 2 retransmissions
+            0x00, 0x10, // model identifier
+        ];
+        let set = match ModelPublicationMessage::parse_set(&parameters).unwrap() {
+            ModelPublicationMessage::Set(set) => set,
+            _ => panic!("expected a set message"),
+        };
+        assert_eq!(2, set.publish_retransmit_count);
+        assert_eq!(5, set.publish_retransmit_interval_steps);
+
+        let mut xmit: Vec<u8, 32> = Vec::new();
+        set.create_status_response(Status::Success)
+            .emit_parameters(&mut xmit)
+            .unwrap();
+        assert_eq!(0x2A, xmit[9]);
     }
 }
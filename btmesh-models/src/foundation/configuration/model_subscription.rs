@@ -1,10 +1,9 @@
+use crate::foundation::configuration::ConfigurationMessage;
 use crate::{Message, Status};
 use btmesh_common::address::{Address, GroupAddress, LabelUuid, UnicastAddress};
 use btmesh_common::opcode::Opcode;
 use btmesh_common::{opcode, InsufficientBuffer, ModelIdentifier, ParseError};
-use core::convert::TryInto;
 use heapless::Vec;
-//use serde::{Deserialize, Serialize};
 
 opcode!( CONFIG_MODEL_SUBSCRIPTION_ADD 0x80, 0x1B);
 opcode!( CONFIG_MODEL_SUBSCRIPTION_DELETE 0x80, 0x1C);
@@ -14,20 +13,31 @@ opcode!( CONFIG_MODEL_SUBSCRIPTION_STATUS 0x80, 0x1F);
 opcode!( CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_ADD 0x80, 0x20);
 opcode!( CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_DELETE 0x80, 0x21);
 opcode!( CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_OVERWRITE 0x80, 0x22);
+opcode!( CONFIG_SIG_MODEL_SUBSCRIPTION_GET 0x80, 0x29);
+opcode!( CONFIG_SIG_MODEL_SUBSCRIPTION_LIST 0x80, 0x2A);
+opcode!( CONFIG_VENDOR_MODEL_SUBSCRIPTION_GET 0x80, 0x2B);
+opcode!( CONFIG_VENDOR_MODEL_SUBSCRIPTION_LIST 0x80, 0x2C);
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ModelSubscriptionMessage {
-    Add(ModelSubscriptionAddMessage),
-    Delete(ModelSubscriptionDeleteMessage),
+    Add(ModelSubscriptionPayload),
+    Delete(ModelSubscriptionPayload),
     DeleteAll(ModelSubscriptionDeleteAllMessage),
-    Overwrite(ModelSubscriptionOverwriteMessage),
+    Overwrite(ModelSubscriptionPayload),
     Status(ModelSubscriptionStatusMessage),
-    VirtualAddressAdd(ModelSubscriptionAddMessage),
-    VirtualAddressDelete(ModelSubscriptionDeleteMessage),
-    VirtualAddressOverwrite(ModelSubscriptionOverwriteMessage),
+    VirtualAddressAdd(ModelSubscriptionPayload),
+    VirtualAddressDelete(ModelSubscriptionPayload),
+    VirtualAddressOverwrite(ModelSubscriptionPayload),
+    Get(ModelSubscriptionGetMessage),
+    List(ModelSubscriptionListMessage),
+}
+
+impl From<ModelSubscriptionMessage> for ConfigurationMessage {
+    fn from(inner: ModelSubscriptionMessage) -> Self {
+        ConfigurationMessage::ModelSubscription(inner)
+    }
 }
 
-#[allow(unused)]
 impl Message for ModelSubscriptionMessage {
     fn opcode(&self) -> Opcode {
         match self {
@@ -39,6 +49,14 @@ impl Message for ModelSubscriptionMessage {
             Self::VirtualAddressAdd(_) => CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_ADD,
             Self::VirtualAddressDelete(_) => CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_DELETE,
             Self::VirtualAddressOverwrite(_) => CONFIG_MODEL_SUBSCRIPTION_VIRTUAL_ADDRESS_OVERWRITE,
+            Self::Get(inner) => match inner.model_identifier {
+                ModelIdentifier::SIG(_) => CONFIG_SIG_MODEL_SUBSCRIPTION_GET,
+                ModelIdentifier::Vendor(..) => CONFIG_VENDOR_MODEL_SUBSCRIPTION_GET,
+            },
+            Self::List(inner) => match inner.model_identifier {
+                ModelIdentifier::SIG(_) => CONFIG_SIG_MODEL_SUBSCRIPTION_LIST,
+                ModelIdentifier::Vendor(..) => CONFIG_VENDOR_MODEL_SUBSCRIPTION_LIST,
+            },
         }
     }
 
@@ -55,62 +73,133 @@ impl Message for ModelSubscriptionMessage {
             ModelSubscriptionMessage::VirtualAddressAdd(inner) => inner.emit_parameters(xmit),
             ModelSubscriptionMessage::VirtualAddressDelete(inner) => inner.emit_parameters(xmit),
             ModelSubscriptionMessage::VirtualAddressOverwrite(inner) => inner.emit_parameters(xmit),
+            ModelSubscriptionMessage::Get(inner) => inner.emit_parameters(xmit),
+            ModelSubscriptionMessage::List(inner) => inner.emit_parameters(xmit),
         }
     }
 }
 
 impl ModelSubscriptionMessage {
     pub fn parse_add(parameters: &[u8]) -> Result<Self, ParseError> {
-        Ok(Self::Add(ModelSubscriptionAddMessage::parse(parameters)?))
+        Ok(Self::Add(ModelSubscriptionPayload::parse(parameters)?))
+    }
+
+    pub fn parse_delete(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::Delete(ModelSubscriptionPayload::parse(parameters)?))
+    }
+
+    pub fn parse_delete_all(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::DeleteAll(ModelSubscriptionDeleteAllMessage::parse(
+            parameters,
+        )?))
+    }
+
+    pub fn parse_overwrite(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::Overwrite(ModelSubscriptionPayload::parse(
+            parameters,
+        )?))
     }
 
     pub fn parse_virtual_address_add(parameters: &[u8]) -> Result<Self, ParseError> {
-        Ok(Self::Add(
-            ModelSubscriptionAddMessage::parse_virtual_address(parameters)?,
+        Ok(Self::VirtualAddressAdd(
+            ModelSubscriptionPayload::parse_virtual_address(parameters)?,
+        ))
+    }
+
+    pub fn parse_virtual_address_delete(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::VirtualAddressDelete(
+            ModelSubscriptionPayload::parse_virtual_address(parameters)?,
         ))
     }
+
+    pub fn parse_virtual_address_overwrite(parameters: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::VirtualAddressOverwrite(
+            ModelSubscriptionPayload::parse_virtual_address(parameters)?,
+        ))
+    }
+
+    pub fn parse_sig_get(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 4 {
+            Ok(Self::Get(ModelSubscriptionGetMessage::parse(parameters)?))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
+
+    pub fn parse_vendor_get(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() == 6 {
+            Ok(Self::Get(ModelSubscriptionGetMessage::parse(parameters)?))
+        } else {
+            Err(ParseError::InvalidLength)
+        }
+    }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SubscriptionAddress {
-    Unicast(UnicastAddress),
+    Unassigned,
     Group(GroupAddress),
     Virtual(LabelUuid),
+    /// Never subscribed to, but parsed so that the request can be answered with a status.
+    Unicast(UnicastAddress),
 }
 
-impl TryInto<SubscriptionAddress> for Address {
-    type Error = ();
+impl SubscriptionAddress {
+    /// Parse a non-virtual subscription address.
+    ///
+    /// Virtual addresses must be configured through the label-uuid bearing
+    /// virtual-address messages. Unicast addresses may never be subscribed to,
+    /// which is left to the configuration server to refuse with a status.
+    fn parse(data: [u8; 2]) -> Result<Self, ParseError> {
+        match Address::parse(data) {
+            Address::Unassigned => Ok(Self::Unassigned),
+            Address::Group(inner) => Ok(Self::Group(inner)),
+            Address::Unicast(inner) => Ok(Self::Unicast(inner)),
+            Address::Virtual(_) => Err(ParseError::InvalidValue),
+        }
+    }
 
-    fn try_into(self) -> Result<SubscriptionAddress, Self::Error> {
-        match self {
-            Address::Unassigned => Err(()),
-            Address::Unicast(inner) => Ok(SubscriptionAddress::Unicast(inner)),
-            Address::Virtual(_) => Err(()),
-            Address::Group(inner) => Ok(SubscriptionAddress::Group(inner)),
-            //Address::LabelUuid(inner) => Ok(SubscriptionAddress::Virtual(inner)),
+    /// Big-endian 2-byte representation of the (possibly virtual) address.
+    pub fn as_bytes(&self) -> [u8; 2] {
+        Address::from(*self).as_bytes()
+    }
+
+    pub fn label_uuid(&self) -> Option<LabelUuid> {
+        if let SubscriptionAddress::Virtual(label_uuid) = self {
+            Some(*label_uuid)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<SubscriptionAddress> for Address {
+    fn from(addr: SubscriptionAddress) -> Self {
+        match addr {
+            SubscriptionAddress::Unassigned => Address::Unassigned,
+            SubscriptionAddress::Group(inner) => Address::Group(inner),
+            SubscriptionAddress::Virtual(inner) => Address::Virtual(inner.virtual_address()),
+            SubscriptionAddress::Unicast(inner) => Address::Unicast(inner),
         }
     }
 }
 
 #[derive(Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct ModelSubscriptionAddMessage {
+pub struct ModelSubscriptionPayload {
     pub element_address: UnicastAddress,
     pub subscription_address: SubscriptionAddress,
     pub model_identifier: ModelIdentifier,
 }
 
-impl ModelSubscriptionAddMessage {
+impl ModelSubscriptionPayload {
     pub fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
         if parameters.len() >= 6 {
             let element_address = UnicastAddress::parse([parameters[1], parameters[0]])?;
-            let subscription_address = SubscriptionAddress::Unicast(UnicastAddress::parse([
-                parameters[3],
-                parameters[2],
-            ])?);
-            let model_identifier = ModelIdentifier::parse(&parameters[9..])?;
+            let subscription_address = SubscriptionAddress::parse([parameters[3], parameters[2]])?;
+            let model_identifier = ModelIdentifier::parse(&parameters[4..])?;
             Ok(Self {
                 element_address,
                 subscription_address,
@@ -122,11 +211,10 @@ impl ModelSubscriptionAddMessage {
     }
 
     pub fn parse_virtual_address(parameters: &[u8]) -> Result<Self, ParseError> {
-        if parameters.len() >= 19 {
+        if parameters.len() >= 20 {
             let element_address = UnicastAddress::parse([parameters[1], parameters[0]])?;
             let subscription_address =
                 SubscriptionAddress::Virtual(LabelUuid::parse(&parameters[2..=17])?);
-
             let model_identifier = ModelIdentifier::parse(&parameters[18..])?;
             Ok(Self {
                 element_address,
@@ -140,9 +228,24 @@ impl ModelSubscriptionAddMessage {
 
     pub fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        let addr_bytes = self.element_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        match self.subscription_address {
+            SubscriptionAddress::Virtual(label_uuid) => {
+                xmit.extend_from_slice(label_uuid.label_uuid())
+                    .map_err(|_| InsufficientBuffer)?;
+            }
+            address => {
+                let addr_bytes = address.as_bytes();
+                xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+                xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+            }
+        }
+        self.model_identifier.emit(xmit)?;
+        Ok(())
     }
 
     pub fn create_status_response(&self, status: Status) -> ModelSubscriptionStatusMessage {
@@ -156,66 +259,109 @@ impl ModelSubscriptionAddMessage {
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct ModelSubscriptionDeleteMessage {}
+pub struct ModelSubscriptionDeleteAllMessage {
+    pub element_address: UnicastAddress,
+    pub model_identifier: ModelIdentifier,
+}
 
-impl ModelSubscriptionDeleteMessage {
-    pub fn parse(_parameters: &[u8]) -> Result<Self, ParseError> {
-        todo!()
+impl ModelSubscriptionDeleteAllMessage {
+    pub fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() >= 4 {
+            let element_address = UnicastAddress::parse([parameters[1], parameters[0]])?;
+            let model_identifier = ModelIdentifier::parse(&parameters[2..])?;
+            Ok(Self {
+                element_address,
+                model_identifier,
+            })
+        } else {
+            Err(ParseError::InvalidLength)
+        }
     }
 
     pub fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        let addr_bytes = self.element_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        self.model_identifier.emit(xmit)?;
+        Ok(())
+    }
+
+    pub fn create_status_response(&self, status: Status) -> ModelSubscriptionStatusMessage {
+        ModelSubscriptionStatusMessage {
+            status,
+            element_address: self.element_address,
+            subscription_address: SubscriptionAddress::Unassigned,
+            model_identifier: self.model_identifier,
+        }
     }
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct ModelSubscriptionDeleteAllMessage {}
-
-impl ModelSubscriptionDeleteAllMessage {
-    pub fn parse(_parameters: &[u8]) -> Result<Self, ParseError> {
-        todo!()
-    }
+pub struct ModelSubscriptionStatusMessage {
+    pub status: Status,
+    pub element_address: UnicastAddress,
+    pub subscription_address: SubscriptionAddress,
+    pub model_identifier: ModelIdentifier,
+}
 
+impl ModelSubscriptionStatusMessage {
     pub fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        xmit.push(self.status as u8)
+            .map_err(|_| InsufficientBuffer)?;
+        let addr_bytes = self.element_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        let addr_bytes = self.subscription_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        self.model_identifier.emit(xmit)?;
+        Ok(())
     }
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct ModelSubscriptionOverwriteMessage {}
+pub struct ModelSubscriptionGetMessage {
+    pub element_address: UnicastAddress,
+    pub model_identifier: ModelIdentifier,
+}
 
-impl ModelSubscriptionOverwriteMessage {
-    pub fn parse(_parameters: &[u8]) -> Result<Self, ParseError> {
-        todo!()
+impl ModelSubscriptionGetMessage {
+    fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
+        let element_address = UnicastAddress::parse([parameters[1], parameters[0]])?;
+        let model_identifier = ModelIdentifier::parse(&parameters[2..])?;
+        Ok(Self {
+            element_address,
+            model_identifier,
+        })
     }
 
     pub fn emit_parameters<const N: usize>(
         &self,
-        _xmit: &mut Vec<u8, N>,
+        xmit: &mut Vec<u8, N>,
     ) -> Result<(), InsufficientBuffer> {
-        todo!()
+        let addr_bytes = self.element_address.as_bytes();
+        xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+        xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        self.model_identifier.emit(xmit)?;
+        Ok(())
     }
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct ModelSubscriptionStatusMessage {
-    status: Status,
-    element_address: UnicastAddress,
-    subscription_address: SubscriptionAddress,
-    model_identifier: ModelIdentifier,
+pub struct ModelSubscriptionListMessage {
+    pub status: Status,
+    pub element_address: UnicastAddress,
+    pub model_identifier: ModelIdentifier,
+    pub addresses: Vec<SubscriptionAddress, 16>,
 }
 
-impl ModelSubscriptionStatusMessage {
-    pub fn parse(_parameters: &[u8]) -> Result<Self, ParseError> {
-        todo!("parse subscription status")
-    }
-
+impl ModelSubscriptionListMessage {
     pub fn emit_parameters<const N: usize>(
         &self,
         xmit: &mut Vec<u8, N>,
@@ -225,22 +371,38 @@ impl ModelSubscriptionStatusMessage {
         let addr_bytes = self.element_address.as_bytes();
         xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
         xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
-        match self.subscription_address {
-            SubscriptionAddress::Unicast(addr) => {
-                let addr_bytes = addr.as_bytes();
-                xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
-                xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
-            }
-            SubscriptionAddress::Group(_addr) => {
-                todo!("group address")
-            }
-            SubscriptionAddress::Virtual(addr) => {
-                let addr_bytes = addr.virtual_address().as_bytes();
-                xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
-                xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
-            }
-        }
         self.model_identifier.emit(xmit)?;
+        for address in &self.addresses {
+            let addr_bytes = address.as_bytes();
+            xmit.push(addr_bytes[1]).map_err(|_| InsufficientBuffer)?;
+            xmit.push(addr_bytes[0]).map_err(|_| InsufficientBuffer)?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicast_subscription_address() {
+        let parameters = [
+            0x01, 0x00, // element address
+            0x02, 0x00, // subscription address
+            0x00, 0x10, // model identifier
+        ];
+        let payload = ModelSubscriptionPayload::parse(&parameters).unwrap();
+        assert_eq!(
+            SubscriptionAddress::Unicast(UnicastAddress::parse([0x00, 0x02]).unwrap()),
+            payload.subscription_address
+        );
+
+        let mut xmit: Vec<u8, 32> = Vec::new();
+        payload
+            .create_status_response(Status::InvalidAddress)
+            .emit_parameters(&mut xmit)
+            .unwrap();
+        assert_eq!(&[0x02, 0x00], &xmit[3..5]);
+    }
+}
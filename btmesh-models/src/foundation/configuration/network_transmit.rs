@@ -1,3 +1,4 @@
+use crate::foundation::configuration::ConfigurationMessage;
 use crate::Message;
 use btmesh_common::opcode::Opcode;
 use btmesh_common::{opcode, InsufficientBuffer, ParseError};
@@ -9,6 +10,7 @@ opcode!( CONFIG_NETWORK_TRANSMIT_STATUS 0x80, 0x25);
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct NetworkTransmitConfig {
     pub network_retransmit_count: u8,
     pub network_retransmit_interval_steps: u8,
@@ -24,12 +26,22 @@ impl Default for NetworkTransmitConfig {
 }
 
 impl NetworkTransmitConfig {
+    /// Total number of transmissions of each network PDU.
+    pub fn transmissions(&self) -> u8 {
+        self.network_retransmit_count + 1
+    }
+
+    /// Interval between transmissions, in milliseconds.
+    pub fn interval_ms(&self) -> u64 {
+        (self.network_retransmit_interval_steps as u64 + 1) * 10
+    }
+
     pub fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
-        if parameters.len() < 2 {
+        if parameters.len() != 1 {
             Err(ParseError::InvalidLength)
         } else {
-            let network_retransmit_count = parameters[0] & 0b11100000 >> 5;
-            let network_retransmit_interval_steps = parameters[0] & 0b00011111;
+            let network_retransmit_count = parameters[0] & 0b00000111;
+            let network_retransmit_interval_steps = (parameters[0] & 0b11111000) >> 3;
 
             Ok(Self {
                 network_retransmit_count,
@@ -40,8 +52,8 @@ impl NetworkTransmitConfig {
 
     pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
         xmit.push(
-            self.network_retransmit_count & 0b111 << 5
-                | self.network_retransmit_interval_steps & 0b11111,
+            (self.network_retransmit_interval_steps & 0b11111) << 3
+                | self.network_retransmit_count & 0b111,
         )
         .map_err(|_| InsufficientBuffer)?;
 
@@ -56,6 +68,12 @@ pub enum NetworkTransmitMessage {
     Status(NetworkTransmitConfig),
 }
 
+impl From<NetworkTransmitMessage> for ConfigurationMessage {
+    fn from(inner: NetworkTransmitMessage) -> Self {
+        ConfigurationMessage::NetworkTransmit(inner)
+    }
+}
+
 impl Message for NetworkTransmitMessage {
     fn opcode(&self) -> Opcode {
         match self {
@@ -93,6 +111,61 @@ impl NetworkTransmitMessage {
     }
 
     pub fn parse_status(parameters: &[u8]) -> Result<Self, ParseError> {
-        Ok(Self::Set(NetworkTransmitConfig::parse(parameters)?))
+        Ok(Self::Status(NetworkTransmitConfig::parse(parameters)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        // 21 interval steps in bits 3-7, 3 retransmissions in bits 0-2.
+        let config = NetworkTransmitConfig::parse(&[0b1010_1011]).unwrap();
+        assert_eq!(3, config.network_retransmit_count);
+        assert_eq!(21, config.network_retransmit_interval_steps);
+        assert_eq!(4, config.transmissions());
+        assert_eq!(220, config.interval_ms());
+
+        assert_eq!(
+            Err(ParseError::InvalidLength),
+            NetworkTransmitConfig::parse(&[])
+        );
+        assert_eq!(
+            Err(ParseError::InvalidLength),
+            NetworkTransmitConfig::parse(&[0xAB, 0x00])
+        );
+    }
+
+    #[test]
+    fn emit() {
+        let config = NetworkTransmitConfig {
+            network_retransmit_count: 5,
+            network_retransmit_interval_steps: 2,
+        };
+        let mut xmit: Vec<u8, 1> = Vec::new();
+        config.emit(&mut xmit).unwrap();
+        assert_eq!(&[0b0001_0101], &xmit[..]);
+
+        assert_eq!(Ok(config), NetworkTransmitConfig::parse(&xmit));
+    }
+
+    #[test]
+    fn status_round_trip() {
+        let config = NetworkTransmitConfig {
+            network_retransmit_count: 7,
+            network_retransmit_interval_steps: 31,
+        };
+        let mut xmit: Vec<u8, 1> = Vec::new();
+        NetworkTransmitMessage::Status(config)
+            .emit_parameters(&mut xmit)
+            .unwrap();
+        assert_eq!(&[0xFF], &xmit[..]);
+
+        assert!(matches!(
+            NetworkTransmitMessage::parse_status(&xmit),
+            Ok(NetworkTransmitMessage::Status(status)) if status == config
+        ));
     }
 }
@@ -1,3 +1,4 @@
+use crate::foundation::configuration::ConfigurationMessage;
 use crate::Message;
 use btmesh_common::opcode::Opcode;
 use btmesh_common::{opcode, InsufficientBuffer, ParseError};
@@ -12,6 +13,12 @@ pub enum NodeResetMessage {
     Status,
 }
 
+impl From<NodeResetMessage> for ConfigurationMessage {
+    fn from(inner: NodeResetMessage) -> Self {
+        ConfigurationMessage::NodeReset(inner)
+    }
+}
+
 #[allow(unused)]
 impl NodeResetMessage {
     pub fn parse_reset(parameters: &[u8]) -> Result<Self, ParseError> {
@@ -1,13 +1,14 @@
-use crate::opcode::Opcode;
-use crate::{opcode, Message};
-use btmesh_common::{InsufficientBuffer, ParseError};
+use crate::foundation::configuration::ConfigurationMessage;
+use crate::Message;
+use btmesh_common::opcode::Opcode;
+use btmesh_common::{opcode, InsufficientBuffer, ParseError};
 use heapless::Vec;
 
 opcode!( CONFIG_RELAY_GET 0x80, 0x26);
 opcode!( CONFIG_RELAY_SET 0x80, 0x27);
 opcode!( CONFIG_RELAY_STATUS 0x80, 0x28);
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Relay {
@@ -32,7 +33,7 @@ impl Relay {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RelayConfig {
@@ -53,12 +54,12 @@ impl Default for RelayConfig {
 
 impl RelayConfig {
     pub fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
-        if parameters.len() < 2 {
+        if parameters.len() != 2 {
             Err(ParseError::InvalidLength)
         } else {
             let relay = Relay::parse(parameters[0])?;
-            let relay_retransmit_count = parameters[0] & 0b11100000 >> 5;
-            let relay_retransmit_interval_steps = parameters[0] & 0b00011111;
+            let relay_retransmit_count = parameters[1] & 0b00000111;
+            let relay_retransmit_interval_steps = (parameters[1] & 0b11111000) >> 3;
 
             Ok(Self {
                 relay,
@@ -72,8 +73,8 @@ impl RelayConfig {
         self.relay.emit(xmit)?;
 
         xmit.push(
-            self.relay_retransmit_count & 0b111 << 5
-                | self.relay_retransmit_interval_steps & 0b11111,
+            (self.relay_retransmit_interval_steps & 0b11111) << 3
+                | self.relay_retransmit_count & 0b111,
         )
         .map_err(|_| InsufficientBuffer)?;
 
@@ -88,6 +89,12 @@ pub enum RelayMessage {
     Status(RelayConfig),
 }
 
+impl From<RelayMessage> for ConfigurationMessage {
+    fn from(inner: RelayMessage) -> Self {
+        ConfigurationMessage::Relay(inner)
+    }
+}
+
 impl Message for RelayMessage {
     fn opcode(&self) -> Opcode {
         match self {
@@ -125,6 +132,55 @@ impl RelayMessage {
     }
 
     pub fn parse_status(parameters: &[u8]) -> Result<Self, ParseError> {
-        Ok(Self::Set(RelayConfig::parse(parameters)?))
+        Ok(Self::Status(RelayConfig::parse(parameters)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_set() {
+        // disabled, with 21 interval steps in bits 3-7 and 3 retransmissions in bits 0-2.
+        let set = match RelayMessage::parse_set(&[0x00, 0b1010_1011]).unwrap() {
+            RelayMessage::Set(set) => set,
+            _ => panic!("expected a set message"),
+        };
+        assert_eq!(Relay::SupportedDisabled, set.relay);
+        assert_eq!(3, set.relay_retransmit_count);
+        assert_eq!(21, set.relay_retransmit_interval_steps);
+
+        assert!(matches!(
+            RelayMessage::parse_set(&[0x03, 0x00]),
+            Err(ParseError::InvalidValue)
+        ));
+        assert!(matches!(
+            RelayMessage::parse_set(&[0x01]),
+            Err(ParseError::InvalidLength)
+        ));
+        assert!(matches!(
+            RelayMessage::parse_set(&[0x01, 0x00, 0x00]),
+            Err(ParseError::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn status_round_trip() {
+        let config = RelayConfig {
+            relay: Relay::SupportedEnabled,
+            relay_retransmit_count: 5,
+            relay_retransmit_interval_steps: 2,
+        };
+        let mut xmit: Vec<u8, 2> = Vec::new();
+        RelayMessage::Status(config)
+            .emit_parameters(&mut xmit)
+            .unwrap();
+        assert_eq!(&[0x01, 0b0001_0101], &xmit[..]);
+
+        assert!(matches!(
+            RelayMessage::parse_status(&xmit),
+            Ok(RelayMessage::Status(status)) if status == config
+        ));
     }
 }
@@ -4,6 +4,9 @@ use crate::rng::SoftdeviceRng;
 use btmesh_driver::interface::{
     AdvertisingAndGattNetworkInterfaces, AdvertisingOnlyNetworkInterfaces, NetworkInterfaces,
 };
+#[cfg(feature = "low_power")]
+use btmesh_driver::stack::provisioned::low_power::LowPowerConfig;
+use btmesh_driver::stack::unprovisioned::oob::OobConfig;
 use btmesh_driver::storage::flash::FlashBackingStore;
 use btmesh_driver::{BluetoothMeshDriver, DriverError, Driver as BaseDriver};
 use core::future::{Future, join};
@@ -80,6 +83,15 @@ impl<N: NetworkInterfaces> NrfSoftdeviceDriver<N> {
         }
     }
 
+    pub fn set_oob_config(&mut self, config: OobConfig) {
+        self.driver.set_oob_config(config);
+    }
+
+    #[cfg(feature = "low_power")]
+    pub fn set_low_power_config(&mut self, config: LowPowerConfig) -> Result<(), DriverError> {
+        self.driver.set_low_power_config(config)
+    }
+
     #[allow(unreachable_code)]
     pub async fn run<'r, D: BluetoothMeshDevice>(&'r mut self, device: &'r mut D) -> Result<(), DriverError> {
         // todo: turn it into a select?
@@ -113,6 +125,15 @@ impl NrfSoftdeviceAdvertisingOnlyDriver {
         ))
     }
 
+    pub fn set_oob_config(&mut self, config: OobConfig) {
+        self.0.set_oob_config(config);
+    }
+
+    #[cfg(feature = "low_power")]
+    pub fn set_low_power_config(&mut self, config: LowPowerConfig) -> Result<(), DriverError> {
+        self.0.set_low_power_config(config)
+    }
+
     pub async fn run<'r, D: BluetoothMeshDevice>(&'r mut self, device: &'r mut D) -> Result<(), DriverError> {
         self.0.run(device).await
     }
@@ -159,6 +180,10 @@ impl NrfSoftdeviceAdvertisingAndGattDriver {
             backing_store,
         ))
     }
+
+    pub fn set_oob_config(&mut self, config: OobConfig) {
+        self.0.set_oob_config(config);
+    }
 }
 
 impl BluetoothMeshDriver for NrfSoftdeviceAdvertisingAndGattDriver {
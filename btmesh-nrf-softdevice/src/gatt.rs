@@ -1,4 +1,4 @@
-use atomic_polyfill::AtomicBool;
+use atomic_polyfill::{AtomicBool, AtomicU32};
 use btmesh_bearer::{BearerError, GattBearer};
 use core::cell::RefCell;
 use core::future::Future;
@@ -22,6 +22,8 @@ pub struct SoftdeviceGattBearer {
     connection_channel: RefCell<Option<ConnectionChannel>>,
     server: MeshGattServer,
     connected: AtomicBool,
+    connections: AtomicU32,
+    att_mtu: AtomicU32,
     outbound: Channel<ThreadModeRawMutex, Vec<u8, 66>, 5>,
     inbound: Channel<ThreadModeRawMutex, Vec<u8, 66>, 5>,
 }
@@ -33,6 +35,8 @@ impl SoftdeviceGattBearer {
             server,
             connection: Signal::new(),
             connected: AtomicBool::new(false),
+            connections: AtomicU32::new(0),
+            att_mtu: AtomicU32::new(DEFAULT_ATT_MTU as u32),
             current_connection: RefCell::new(None),
             connection_channel: RefCell::new(None),
             outbound: Channel::new(),
@@ -40,10 +44,21 @@ impl SoftdeviceGattBearer {
         }
     }
 
+    /// Account for data written by the client, which it could only have done
+    /// once the ATT MTU of the connection was exchanged to fit it.
+    fn exchanged_att_mtu(&self, data: &Vec<u8, 66>) {
+        let att_mtu = core::cmp::min(data.len() + 3, ATT_MTU) as u32;
+        if att_mtu > self.att_mtu.load(Ordering::Relaxed) {
+            self.att_mtu.store(att_mtu, Ordering::Relaxed);
+        }
+    }
+
     #[allow(clippy::await_holding_refcell_ref)]
     async fn run(&self) -> Result<(), BearerError> {
         loop {
             let connection = self.connection.wait().await;
+            self.connections.fetch_add(1, Ordering::Relaxed);
+            self.att_mtu.store(DEFAULT_ATT_MTU as u32, Ordering::Relaxed);
             self.current_connection.borrow_mut().replace(connection);
             gatt_server::run(
                 self.current_connection.borrow().as_ref().unwrap(),
@@ -51,6 +66,7 @@ impl SoftdeviceGattBearer {
                 |e| match e {
                     MeshGattServerEvent::Proxy(event) => match event {
                         ProxyServiceEvent::DataInWrite(data) => {
+                            self.exchanged_att_mtu(&data);
                             self.inbound.try_send(data).ok();
                         }
                         ProxyServiceEvent::DataOutCccdWrite { notifications } => {
@@ -65,6 +81,7 @@ impl SoftdeviceGattBearer {
                     },
                     MeshGattServerEvent::Provisioning(event) => match event {
                         ProvisioningServiceEvent::DataInWrite(data) => {
+                            self.exchanged_att_mtu(&data);
                             self.inbound.try_send(data).ok();
                         }
                         ProvisioningServiceEvent::DataOutCccdWrite { notifications } => {
@@ -91,6 +108,9 @@ impl SoftdeviceGattBearer {
 
 pub const ATT_MTU: usize = 69;
 
+/// Smallest ATT MTU every client supports, leaving 20 bytes per notification.
+const DEFAULT_ATT_MTU: usize = 23;
+
 impl GattBearer<66> for SoftdeviceGattBearer {
     type RunFuture<'m> = impl Future<Output=Result<(), BearerError>> + 'm
     where
@@ -177,6 +197,16 @@ impl GattBearer<66> for SoftdeviceGattBearer {
             Ok(())
         }
     }
+
+    fn connection_count(&self) -> u32 {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    fn mtu(&self) -> usize {
+        // the softdevice answers the MTU exchange without surfacing it, so the
+        // MTU is tracked from the largest write the client has made so far.
+        self.att_mtu.load(Ordering::Relaxed) as usize - 3
+    }
 }
 
 #[nrf_softdevice::gatt_server]
@@ -17,6 +17,7 @@ mod rng;
 mod driver;
 
 pub use btmesh_driver::BluetoothMeshDriver;
+pub use btmesh_driver::{oob_event, oob_input};
 
 #[cfg(feature = "gatt")]
 pub use driver::NrfSoftdeviceAdvertisingAndGattDriver as Driver;
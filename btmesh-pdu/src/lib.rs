@@ -1,6 +1,7 @@
 #![cfg_attr(not(test), no_std)]
 #![allow(dead_code)]
 
+use crate::provisioned::beacon::SecureNetworkBeacon;
 use crate::provisioned::network::NetworkPDU;
 use crate::provisioning::ProvisioningPDU;
 
@@ -15,4 +16,8 @@ pub mod provisioning;
 pub enum PDU {
     Provisioning(ProvisioningPDU),
     Network(NetworkPDU),
+    /// Proxy configuration message exchanged with a proxy client, carried
+    /// in a network PDU secured with the proxy nonce.
+    ProxyConfiguration(NetworkPDU),
+    SecureNetworkBeacon(SecureNetworkBeacon),
 }
@@ -0,0 +1,182 @@
+use btmesh_common::crypto::network::{beacon_authentication_value, BeaconKey};
+use btmesh_common::crypto::InvalidKeyLength;
+use btmesh_common::{
+    InsufficientBuffer, IvIndex, IvUpdateFlag, KeyRefreshFlag, NetworkId, ParseError,
+};
+use heapless::Vec;
+
+const SECURE_NETWORK_BEACON: u8 = 0x01;
+
+/// Secure network beacon, identifying a subnet and conveying its
+/// key refresh and IV index state.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SecureNetworkBeacon {
+    /// Flags octet as received, RFU bits included, since it is authenticated as a whole.
+    flags: u8,
+    network_id: NetworkId,
+    iv_index: IvIndex,
+    authentication_value: [u8; 8],
+}
+
+impl SecureNetworkBeacon {
+    pub fn new(
+        key_refresh_flag: KeyRefreshFlag,
+        iv_update_flag: IvUpdateFlag,
+        network_id: NetworkId,
+        iv_index: IvIndex,
+        beacon_key: &BeaconKey,
+    ) -> Result<Self, InvalidKeyLength> {
+        let mut flags = 0;
+        key_refresh_flag.emit(&mut flags);
+        iv_update_flag.emit(&mut flags);
+        Ok(Self {
+            flags,
+            network_id,
+            iv_index,
+            authentication_value: beacon_authentication_value(
+                beacon_key,
+                flags,
+                &network_id,
+                iv_index,
+            )?,
+        })
+    }
+
+    pub fn key_refresh_flag(&self) -> KeyRefreshFlag {
+        KeyRefreshFlag::parse(self.flags & 0b00000001)
+    }
+
+    pub fn iv_update_flag(&self) -> IvUpdateFlag {
+        IvUpdateFlag::parse(self.flags & 0b00000010)
+    }
+
+    pub fn network_id(&self) -> NetworkId {
+        self.network_id
+    }
+
+    pub fn iv_index(&self) -> IvIndex {
+        self.iv_index
+    }
+
+    /// Verify the authentication value of a received beacon against the
+    /// beacon key of the subnet it claims to belong to.
+    pub fn authenticate(&self, beacon_key: &BeaconKey) -> Result<bool, InvalidKeyLength> {
+        let authentication_value =
+            beacon_authentication_value(beacon_key, self.flags, &self.network_id, self.iv_index)?;
+        Ok(authentication_value == self.authentication_value)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, ParseError> {
+        if data.len() != 22 {
+            return Err(ParseError::InvalidLength);
+        }
+        if data[0] != SECURE_NETWORK_BEACON {
+            return Err(ParseError::InvalidValue);
+        }
+
+        Ok(Self {
+            flags: data[1],
+            network_id: NetworkId::new(data[2..10].try_into()?),
+            iv_index: IvIndex::parse(&data[10..14])?,
+            authentication_value: data[14..22].try_into()?,
+        })
+    }
+
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        xmit.push(SECURE_NETWORK_BEACON)
+            .map_err(|_| InsufficientBuffer)?;
+        xmit.push(self.flags).map_err(|_| InsufficientBuffer)?;
+        xmit.extend_from_slice(&self.network_id)?;
+        xmit.extend_from_slice(&self.iv_index.to_be_bytes())?;
+        xmit.extend_from_slice(&self.authentication_value)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::provisioned::beacon::SecureNetworkBeacon;
+    use btmesh_common::crypto::network::{beacon_authentication_value, NetworkKey};
+    use btmesh_common::{IvIndex, IvUpdateFlag, KeyRefreshFlag};
+    use heapless::Vec;
+
+    // 8.4.6.1 Secure Network beacon
+    const BEACON: [u8; 22] = [
+        0x01, 0x00, 0x3e, 0xca, 0xff, 0x67, 0x2f, 0x67, 0x33, 0x70, 0x12, 0x34, 0x56, 0x78, 0x8e,
+        0xa2, 0x61, 0x58, 0x2f, 0x36, 0x4f, 0x6f,
+    ];
+
+    fn network_key() -> NetworkKey {
+        NetworkKey::new([
+            0x7d, 0xd7, 0x36, 0x4c, 0xd8, 0x42, 0xad, 0x18, 0xc1, 0x7c, 0x2b, 0x82, 0x0c, 0x84,
+            0xc3, 0xd6,
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_and_authenticate() {
+        let beacon = SecureNetworkBeacon::parse(&BEACON).unwrap();
+        let network_key = network_key();
+
+        assert_eq!(KeyRefreshFlag(false), beacon.key_refresh_flag());
+        assert_eq!(IvUpdateFlag::Normal, beacon.iv_update_flag());
+        assert_eq!(network_key.network_id(), beacon.network_id());
+        assert_eq!(IvIndex::new(0x12345678), beacon.iv_index());
+        assert!(beacon
+            .authenticate(&network_key.beacon_key().unwrap())
+            .unwrap());
+
+        let mut tampered = BEACON;
+        tampered[13] = 0x79;
+        let tampered = SecureNetworkBeacon::parse(&tampered).unwrap();
+        assert!(!tampered
+            .authenticate(&network_key.beacon_key().unwrap())
+            .unwrap());
+    }
+
+    #[test]
+    fn authenticate_rfu_flags() {
+        let network_key = network_key();
+        let beacon_key = network_key.beacon_key().unwrap();
+
+        // a newer stack may set flags this one does not know about.
+        let mut data = BEACON;
+        data[1] = 0b00000101;
+        let authentication_value = beacon_authentication_value(
+            &beacon_key,
+            data[1],
+            &network_key.network_id(),
+            IvIndex::new(0x12345678),
+        )
+        .unwrap();
+        data[14..22].copy_from_slice(&authentication_value);
+
+        let beacon = SecureNetworkBeacon::parse(&data).unwrap();
+        assert_eq!(KeyRefreshFlag(true), beacon.key_refresh_flag());
+        assert_eq!(IvUpdateFlag::Normal, beacon.iv_update_flag());
+        assert!(beacon.authenticate(&beacon_key).unwrap());
+
+        let mut xmit = Vec::<u8, 22>::new();
+        beacon.emit(&mut xmit).unwrap();
+        assert_eq!(&data, &*xmit);
+    }
+
+    #[test]
+    fn emit() {
+        let network_key = network_key();
+        let beacon = SecureNetworkBeacon::new(
+            KeyRefreshFlag(false),
+            IvUpdateFlag::Normal,
+            network_key.network_id(),
+            IvIndex::new(0x12345678),
+            &network_key.beacon_key().unwrap(),
+        )
+        .unwrap();
+
+        let mut xmit = Vec::<u8, 22>::new();
+        beacon.emit(&mut xmit).unwrap();
+        assert_eq!(&BEACON, &*xmit);
+    }
+}
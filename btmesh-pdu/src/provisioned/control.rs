@@ -22,6 +22,18 @@ impl<S: System> ControlMessage<S> {
             meta,
         })
     }
+
+    pub fn opcode(&self) -> ControlOpcode {
+        self.opcode
+    }
+
+    pub fn parameters(&self) -> &[u8] {
+        &*self.parameters
+    }
+
+    pub fn meta(&self) -> &S::ControlMetadata {
+        &self.meta
+    }
 }
 
 impl<S: System> From<ControlMessage<S>> for Message<S> {
@@ -0,0 +1,448 @@
+use crate::provisioned::upper::control::ControlOpcode;
+use btmesh_common::address::{Address, UnicastAddress};
+use btmesh_common::{InsufficientBuffer, IvIndex, IvUpdateFlag, KeyRefreshFlag, ParseError};
+use heapless::Vec;
+
+/// Largest number of addresses fitting a subscription list message,
+/// which is always sent unsegmented.
+pub const MAX_SUBSCRIPTION_LIST_ADDRESSES: usize = 5;
+
+/// Smallest receive delay, in milliseconds.
+const MIN_RECEIVE_DELAY: u8 = 0x0A;
+
+/// Range of the poll timeout, in units of 100 milliseconds.
+const MIN_POLL_TIMEOUT: u32 = 0x00000A;
+const MAX_POLL_TIMEOUT: u32 = 0x34BBFF;
+
+/// Requirements of a low power node towards its prospective friend.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FriendCriteria {
+    /// Weight of the RSSI in the offer delay, from 1 (`0b00`) to 2.5 (`0b11`) in steps of 0.5.
+    pub rssi_factor: u8,
+    /// Weight of the receive window in the offer delay, from 1 (`0b00`) to 2.5 (`0b11`) in steps of 0.5.
+    pub receive_window_factor: u8,
+    /// Logarithm of the smallest friend queue acceptable.
+    pub min_queue_size_log: u8,
+}
+
+impl FriendCriteria {
+    pub fn parse(data: u8) -> Result<Self, ParseError> {
+        let min_queue_size_log = data & 0b0000_0111;
+        if data & 0b1000_0000 != 0 || min_queue_size_log == 0 {
+            return Err(ParseError::InvalidValue);
+        }
+        Ok(Self {
+            rssi_factor: (data & 0b0110_0000) >> 5,
+            receive_window_factor: (data & 0b0001_1000) >> 3,
+            min_queue_size_log,
+        })
+    }
+
+    pub fn min_queue_size(&self) -> usize {
+        1 << self.min_queue_size_log
+    }
+
+    fn emit(&self) -> u8 {
+        (self.rssi_factor & 0b11) << 5
+            | (self.receive_window_factor & 0b11) << 3
+            | (self.min_queue_size_log & 0b111)
+    }
+}
+
+/// Sent by a low power node to all friends, looking for a friend.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FriendRequest {
+    pub criteria: FriendCriteria,
+    /// Delay between the friend poll and the response, in milliseconds.
+    pub receive_delay: u8,
+    /// Longest interval between two friend polls, in units of 100 milliseconds.
+    pub poll_timeout: u32,
+    pub previous_address: Option<UnicastAddress>,
+    pub num_elements: u8,
+    pub lpn_counter: u16,
+}
+
+/// Sent by a friend in response to a friend request.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FriendOffer {
+    /// Time the low power node listens for the response to a friend poll, in milliseconds.
+    pub receive_window: u8,
+    pub queue_size: u8,
+    pub subscription_list_size: u8,
+    pub rssi: i8,
+    pub friend_counter: u16,
+}
+
+/// Sent by a friend in response to a friend poll, if no other message is
+/// stored for the low power node, and whenever the security state changes.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FriendUpdate {
+    pub key_refresh_flag: KeyRefreshFlag,
+    pub iv_update_flag: IvUpdateFlag,
+    pub iv_index: IvIndex,
+    /// Whether more messages are stored for the low power node.
+    pub more_data: bool,
+}
+
+/// Identifies the friendship to terminate, in both the friend clear
+/// message and its confirmation.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FriendClear {
+    pub lpn_address: UnicastAddress,
+    pub lpn_counter: u16,
+}
+
+/// Addresses a low power node adds to or removes from its subscription list.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FriendSubscriptionList {
+    pub transaction_number: u8,
+    pub addresses: Vec<Address, MAX_SUBSCRIPTION_LIST_ADDRESSES>,
+}
+
+/// Transport control message exchanged between a friend and a low power node.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, PartialEq, Debug)]
+pub enum FriendMessage {
+    Poll { fsn: bool },
+    Update(FriendUpdate),
+    Request(FriendRequest),
+    Offer(FriendOffer),
+    Clear(FriendClear),
+    ClearConfirm(FriendClear),
+    SubscriptionListAdd(FriendSubscriptionList),
+    SubscriptionListRemove(FriendSubscriptionList),
+    SubscriptionListConfirm { transaction_number: u8 },
+}
+
+impl FriendMessage {
+    /// Parse from the opcode and parameters of a transport control message.
+    pub fn parse(opcode: ControlOpcode, parameters: &[u8]) -> Result<Self, ParseError> {
+        match opcode {
+            ControlOpcode::FriendPoll => {
+                if parameters.len() != 1 {
+                    return Err(ParseError::InvalidLength);
+                }
+                if parameters[0] & 0b1111_1110 != 0 {
+                    return Err(ParseError::InvalidValue);
+                }
+                Ok(Self::Poll {
+                    fsn: parameters[0] & 0b0000_0001 != 0,
+                })
+            }
+            ControlOpcode::FriendUpdate => {
+                if parameters.len() != 6 {
+                    return Err(ParseError::InvalidLength);
+                }
+                Ok(Self::Update(FriendUpdate {
+                    key_refresh_flag: KeyRefreshFlag::parse(parameters[0] & 0b0000_0001),
+                    iv_update_flag: IvUpdateFlag::parse(parameters[0] & 0b0000_0010),
+                    iv_index: IvIndex::parse(&parameters[1..5])?,
+                    more_data: match parameters[5] {
+                        0x00 => false,
+                        0x01 => true,
+                        _ => return Err(ParseError::InvalidValue),
+                    },
+                }))
+            }
+            ControlOpcode::FriendRequest => {
+                if parameters.len() != 10 {
+                    return Err(ParseError::InvalidLength);
+                }
+                let poll_timeout =
+                    u32::from_be_bytes([0, parameters[2], parameters[3], parameters[4]]);
+                let previous_address = match Address::parse([parameters[5], parameters[6]]) {
+                    Address::Unassigned => None,
+                    Address::Unicast(address) => Some(address),
+                    _ => return Err(ParseError::InvalidValue),
+                };
+                if parameters[1] < MIN_RECEIVE_DELAY
+                    || !(MIN_POLL_TIMEOUT..=MAX_POLL_TIMEOUT).contains(&poll_timeout)
+                    || parameters[7] == 0
+                {
+                    return Err(ParseError::InvalidValue);
+                }
+                Ok(Self::Request(FriendRequest {
+                    criteria: FriendCriteria::parse(parameters[0])?,
+                    receive_delay: parameters[1],
+                    poll_timeout,
+                    previous_address,
+                    num_elements: parameters[7],
+                    lpn_counter: u16::from_be_bytes([parameters[8], parameters[9]]),
+                }))
+            }
+            ControlOpcode::FriendOffer => {
+                if parameters.len() != 6 {
+                    return Err(ParseError::InvalidLength);
+                }
+                if parameters[0] == 0 {
+                    return Err(ParseError::InvalidValue);
+                }
+                Ok(Self::Offer(FriendOffer {
+                    receive_window: parameters[0],
+                    queue_size: parameters[1],
+                    subscription_list_size: parameters[2],
+                    rssi: parameters[3] as i8,
+                    friend_counter: u16::from_be_bytes([parameters[4], parameters[5]]),
+                }))
+            }
+            ControlOpcode::FriendClear => Ok(Self::Clear(Self::parse_clear(parameters)?)),
+            ControlOpcode::FriendClearConfirm => {
+                Ok(Self::ClearConfirm(Self::parse_clear(parameters)?))
+            }
+            ControlOpcode::FriendSubscriptionListAdd => Ok(Self::SubscriptionListAdd(
+                Self::parse_subscription_list(parameters)?,
+            )),
+            ControlOpcode::FriendSubscriptionListRemove => Ok(Self::SubscriptionListRemove(
+                Self::parse_subscription_list(parameters)?,
+            )),
+            ControlOpcode::FriendSubscriptionListConfirm => {
+                if parameters.len() != 1 {
+                    return Err(ParseError::InvalidLength);
+                }
+                Ok(Self::SubscriptionListConfirm {
+                    transaction_number: parameters[0],
+                })
+            }
+            _ => Err(ParseError::InvalidValue),
+        }
+    }
+
+    fn parse_clear(parameters: &[u8]) -> Result<FriendClear, ParseError> {
+        if parameters.len() != 4 {
+            return Err(ParseError::InvalidLength);
+        }
+        Ok(FriendClear {
+            lpn_address: UnicastAddress::parse([parameters[0], parameters[1]])?,
+            lpn_counter: u16::from_be_bytes([parameters[2], parameters[3]]),
+        })
+    }
+
+    fn parse_subscription_list(parameters: &[u8]) -> Result<FriendSubscriptionList, ParseError> {
+        if parameters.len() < 3 || parameters.len() % 2 != 1 {
+            return Err(ParseError::InvalidLength);
+        }
+
+        let mut addresses = Vec::new();
+        for address in parameters[1..].chunks_exact(2) {
+            let address = Address::parse([address[0], address[1]]);
+            if matches!(address, Address::Unassigned | Address::Unicast(_)) {
+                return Err(ParseError::InvalidValue);
+            }
+            addresses
+                .push(address)
+                .map_err(|_| ParseError::InvalidLength)?;
+        }
+
+        Ok(FriendSubscriptionList {
+            transaction_number: parameters[0],
+            addresses,
+        })
+    }
+
+    pub fn opcode(&self) -> ControlOpcode {
+        match self {
+            Self::Poll { .. } => ControlOpcode::FriendPoll,
+            Self::Update(_) => ControlOpcode::FriendUpdate,
+            Self::Request(_) => ControlOpcode::FriendRequest,
+            Self::Offer(_) => ControlOpcode::FriendOffer,
+            Self::Clear(_) => ControlOpcode::FriendClear,
+            Self::ClearConfirm(_) => ControlOpcode::FriendClearConfirm,
+            Self::SubscriptionListAdd(_) => ControlOpcode::FriendSubscriptionListAdd,
+            Self::SubscriptionListRemove(_) => ControlOpcode::FriendSubscriptionListRemove,
+            Self::SubscriptionListConfirm { .. } => ControlOpcode::FriendSubscriptionListConfirm,
+        }
+    }
+
+    /// Emit the parameters of the transport control message.
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        match self {
+            Self::Poll { fsn } => {
+                xmit.push(*fsn as u8).map_err(|_| InsufficientBuffer)?;
+            }
+            Self::Update(update) => {
+                let mut flags = 0;
+                update.key_refresh_flag.emit(&mut flags);
+                update.iv_update_flag.emit(&mut flags);
+                xmit.push(flags).map_err(|_| InsufficientBuffer)?;
+                xmit.extend_from_slice(&update.iv_index.to_be_bytes())?;
+                xmit.push(update.more_data as u8)
+                    .map_err(|_| InsufficientBuffer)?;
+            }
+            Self::Request(request) => {
+                xmit.push(request.criteria.emit())
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.push(request.receive_delay)
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.extend_from_slice(&request.poll_timeout.to_be_bytes()[1..])?;
+                let previous_address = request
+                    .previous_address
+                    .map(|address| address.into())
+                    .unwrap_or(Address::Unassigned);
+                xmit.extend_from_slice(&previous_address.as_bytes())?;
+                xmit.push(request.num_elements)
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.extend_from_slice(&request.lpn_counter.to_be_bytes())?;
+            }
+            Self::Offer(offer) => {
+                xmit.push(offer.receive_window)
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.push(offer.queue_size)
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.push(offer.subscription_list_size)
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.push(offer.rssi as u8)
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.extend_from_slice(&offer.friend_counter.to_be_bytes())?;
+            }
+            Self::Clear(clear) | Self::ClearConfirm(clear) => {
+                xmit.extend_from_slice(&clear.lpn_address.as_bytes())?;
+                xmit.extend_from_slice(&clear.lpn_counter.to_be_bytes())?;
+            }
+            Self::SubscriptionListAdd(list) | Self::SubscriptionListRemove(list) => {
+                xmit.push(list.transaction_number)
+                    .map_err(|_| InsufficientBuffer)?;
+                for address in &list.addresses {
+                    xmit.extend_from_slice(&address.as_bytes())?;
+                }
+            }
+            Self::SubscriptionListConfirm { transaction_number } => {
+                xmit.push(*transaction_number)
+                    .map_err(|_| InsufficientBuffer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::provisioned::friend::{FriendClear, FriendCriteria, FriendMessage, FriendRequest};
+    use crate::provisioned::upper::control::ControlOpcode;
+    use btmesh_common::address::{Address, UnicastAddress};
+    use btmesh_common::ParseError;
+    use heapless::Vec;
+
+    fn round_trip(message: FriendMessage) {
+        let mut xmit = Vec::<u8, 11>::new();
+        message.emit(&mut xmit).unwrap();
+        assert_eq!(
+            message,
+            FriendMessage::parse(message.opcode(), &xmit).unwrap()
+        );
+    }
+
+    // 8.3.11 Friend Request
+    #[test]
+    fn parse_friend_request() {
+        let message = FriendMessage::parse(
+            ControlOpcode::FriendRequest,
+            &[0x4b, 0x50, 0x05, 0x7e, 0x40, 0x00, 0x00, 0x01, 0x00, 0x00],
+        )
+        .unwrap();
+
+        assert_eq!(
+            FriendMessage::Request(FriendRequest {
+                criteria: FriendCriteria {
+                    rssi_factor: 0b10,
+                    receive_window_factor: 0b01,
+                    min_queue_size_log: 0b011,
+                },
+                receive_delay: 0x50,
+                poll_timeout: 0x057e40,
+                previous_address: None,
+                num_elements: 1,
+                lpn_counter: 0,
+            }),
+            message
+        );
+        round_trip(message);
+    }
+
+    #[test]
+    fn prohibited_friend_request() {
+        // receive delay below 10ms.
+        assert_eq!(
+            Err(ParseError::InvalidValue),
+            FriendMessage::parse(
+                ControlOpcode::FriendRequest,
+                &[0x4b, 0x09, 0x05, 0x7e, 0x40, 0x00, 0x00, 0x01, 0x00, 0x00],
+            )
+        );
+        // minimum queue size of zero.
+        assert_eq!(
+            Err(ParseError::InvalidValue),
+            FriendMessage::parse(
+                ControlOpcode::FriendRequest,
+                &[0x48, 0x50, 0x05, 0x7e, 0x40, 0x00, 0x00, 0x01, 0x00, 0x00],
+            )
+        );
+    }
+
+    // 8.3.12 Friend Offer
+    #[test]
+    fn parse_friend_offer() {
+        let message = FriendMessage::parse(
+            ControlOpcode::FriendOffer,
+            &[0xff, 0x02, 0x03, 0xff, 0x00, 0x00],
+        )
+        .unwrap();
+
+        if let FriendMessage::Offer(offer) = &message {
+            assert_eq!(0xff, offer.receive_window);
+            assert_eq!(2, offer.queue_size);
+            assert_eq!(3, offer.subscription_list_size);
+            assert_eq!(-1, offer.rssi);
+            assert_eq!(0, offer.friend_counter);
+        } else {
+            panic!("not a friend offer");
+        }
+        round_trip(message);
+    }
+
+    #[test]
+    fn subscription_list() {
+        let message = FriendMessage::parse(
+            ControlOpcode::FriendSubscriptionListAdd,
+            &[0x07, 0xc0, 0x00, 0xff, 0xff],
+        )
+        .unwrap();
+
+        if let FriendMessage::SubscriptionListAdd(list) = &message {
+            assert_eq!(7, list.transaction_number);
+            assert_eq!(
+                &[Address::parse([0xc0, 0x00]), Address::parse([0xff, 0xff])],
+                &*list.addresses
+            );
+        } else {
+            panic!("not a subscription list add");
+        }
+        round_trip(message);
+
+        // unicast addresses are not subscribed to.
+        assert_eq!(
+            Err(ParseError::InvalidValue),
+            FriendMessage::parse(
+                ControlOpcode::FriendSubscriptionListRemove,
+                &[0x07, 0x00, 0x01],
+            )
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(FriendMessage::Poll { fsn: true });
+        round_trip(FriendMessage::ClearConfirm(FriendClear {
+            lpn_address: UnicastAddress::parse([0x12, 0x34]).unwrap(),
+            lpn_counter: 0x0102,
+        }));
+        round_trip(FriendMessage::SubscriptionListConfirm {
+            transaction_number: 42,
+        });
+    }
+}
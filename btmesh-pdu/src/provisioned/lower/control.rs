@@ -11,6 +11,18 @@ pub struct UnsegmentedLowerControlPDU<S: System> {
 }
 
 impl<S: System> UnsegmentedLowerControlPDU<S> {
+    pub fn new(
+        opcode: ControlOpcode,
+        parameters: &[u8],
+        meta: S::LowerMetadata,
+    ) -> Result<Self, InsufficientBuffer> {
+        Ok(Self {
+            opcode,
+            parameters: Vec::from_slice(parameters)?,
+            meta,
+        })
+    }
+
     pub fn parse(data: &[u8], meta: S::LowerMetadata) -> Result<Self, ParseError> {
         let opcode = ControlOpcode::parse(data[0] & 0b01111111)?;
         let parameters = &data[1..];
@@ -21,6 +33,13 @@ impl<S: System> UnsegmentedLowerControlPDU<S> {
         })
     }
 
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        // SEG bit unset.
+        xmit.push(self.opcode as u8 & 0b01111111)?;
+        xmit.extend_from_slice(&self.parameters)?;
+        Ok(())
+    }
+
     pub fn opcode(&self) -> ControlOpcode {
         self.opcode
     }
@@ -86,6 +105,22 @@ impl<S: System> SegmentedLowerControlPDU<S> {
         })
     }
 
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        // set the SEGMENTED bit.
+        xmit.push(0b10000000 | self.opcode as u8)?;
+
+        let mut header = [0; 3];
+        // RFU + first 7 bits of seq_zero
+        header[0] = ((self.seq_zero & 0b1111111000000) >> 6) as u8;
+        // last 6 bits of seq_zero + first 2 bits of seg_o
+        header[1] =
+            ((self.seq_zero & 0b111111) << 2) as u8 | ((self.seg_o & 0b00011000) >> 3) as u8;
+        header[2] = ((self.seg_o & 0b00000111) << 5) | (self.seg_n & 0b00011111);
+        xmit.extend_from_slice(&header)?;
+        xmit.extend_from_slice(&*self.segment_m)?;
+        Ok(())
+    }
+
     pub fn opcode(&self) -> ControlOpcode {
         self.opcode
     }
@@ -144,6 +144,18 @@ impl BlockAck {
         Self(0, seq_zero)
     }
 
+    /// Parse the parameters of a segment acknowledgement control message.
+    pub fn parse(parameters: &[u8]) -> Result<Self, ParseError> {
+        if parameters.len() != 6 {
+            return Err(ParseError::InvalidLength);
+        }
+        let seq_zero =
+            SeqZero::parse(u16::from_be_bytes([parameters[0] & 0b01111111, parameters[1]]) >> 2)?;
+        let block_ack =
+            u32::from_be_bytes([parameters[2], parameters[3], parameters[4], parameters[5]]);
+        Ok(Self(block_ack, seq_zero))
+    }
+
     pub fn is_acked(&self, seg_o: u8) -> Result<bool, InvalidBlock> {
         if seg_o >= 32 {
             return Err(InvalidBlock);
@@ -170,8 +182,15 @@ impl BlockAck {
 
 #[cfg(test)]
 mod tests {
+    use crate::provisioned::lower::access::SegmentedLowerAccessPDU;
+    use crate::provisioned::lower::control::{
+        SegmentedLowerControlPDU, UnsegmentedLowerControlPDU,
+    };
     use crate::provisioned::lower::{BlockAck, InvalidBlock};
+    use crate::provisioned::upper::control::ControlOpcode;
+    use btmesh_common::mic::SzMic;
     use btmesh_common::SeqZero;
+    use heapless::Vec;
 
     #[test]
     pub fn block_ack_valid_blocks() {
@@ -206,6 +225,18 @@ mod tests {
         assert_eq!(Ok(true), block_ack.is_acked(31));
     }
 
+    #[test]
+    pub fn block_ack_parse() {
+        let block_ack = BlockAck::parse(&[0x80 | 0x06, 0xAC, 0x00, 0x00, 0x01, 0x05]).unwrap();
+        assert_eq!(0x1AB, block_ack.seq_zero().value());
+        assert_eq!(0x105, block_ack.value());
+        assert_eq!(Ok(true), block_ack.is_acked(0));
+        assert_eq!(Ok(false), block_ack.is_acked(1));
+        assert_eq!(Ok(true), block_ack.is_acked(8));
+
+        assert!(BlockAck::parse(&[0x00, 0x00, 0x00, 0x00, 0x00]).is_err());
+    }
+
     #[test]
     pub fn block_ack_invalid_blocks() {
         let mut block_ack = BlockAck::new(SeqZero::new(42));
@@ -216,4 +247,69 @@ mod tests {
         assert_eq!(Err(InvalidBlock), block_ack.ack(99));
         assert_eq!(Err(InvalidBlock), block_ack.is_acked(99));
     }
+
+    #[test]
+    pub fn unsegmented_control_round_trip() {
+        let pdu = UnsegmentedLowerControlPDU::<()>::new(
+            ControlOpcode::Heartbeat,
+            &[0x05, 0x00, 0x01],
+            (),
+        )
+        .unwrap();
+
+        let mut xmit = Vec::<u8, 16>::new();
+        pdu.emit(&mut xmit).unwrap();
+        assert_eq!(&[0x0A, 0x05, 0x00, 0x01], &*xmit);
+
+        let parsed = UnsegmentedLowerControlPDU::<()>::parse(&xmit, ()).unwrap();
+        assert_eq!(ControlOpcode::Heartbeat, parsed.opcode());
+        assert_eq!(&[0x05, 0x00, 0x01], parsed.parameters());
+    }
+
+    #[test]
+    pub fn segmented_access_round_trip() {
+        let pdu = SegmentedLowerAccessPDU::<()>::new(
+            None,
+            SzMic::Bit32,
+            SeqZero::new(0x0123),
+            8,
+            9,
+            &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+            (),
+        )
+        .unwrap();
+
+        let mut xmit = Vec::<u8, 16>::new();
+        pdu.emit(&mut xmit).unwrap();
+
+        let parsed = SegmentedLowerAccessPDU::<()>::parse(&xmit, ()).unwrap();
+        assert_eq!(0x0123, parsed.seq_zero().value());
+        assert_eq!(8, parsed.seg_o());
+        assert_eq!(9, parsed.seg_n());
+        assert_eq!(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12], parsed.segment_m());
+    }
+
+    #[test]
+    pub fn segmented_control_round_trip() {
+        let pdu = SegmentedLowerControlPDU::<()>::new(
+            ControlOpcode::FriendOffer,
+            SeqZero::new(0x1ABC),
+            9,
+            11,
+            &[1, 2, 3, 4, 5, 6, 7, 8],
+            (),
+        )
+        .unwrap();
+
+        let mut xmit = Vec::<u8, 16>::new();
+        pdu.emit(&mut xmit).unwrap();
+        assert_eq!(0b10000000 | ControlOpcode::FriendOffer as u8, xmit[0]);
+
+        let parsed = SegmentedLowerControlPDU::<()>::parse(&xmit, ()).unwrap();
+        assert_eq!(ControlOpcode::FriendOffer, parsed.opcode());
+        assert_eq!(0x1ABC, parsed.seq_zero().value());
+        assert_eq!(9, parsed.seg_o());
+        assert_eq!(11, parsed.seg_n());
+        assert_eq!(&[1, 2, 3, 4, 5, 6, 7, 8], parsed.segment_m());
+    }
 }
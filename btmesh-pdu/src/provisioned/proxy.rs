@@ -1,8 +1,9 @@
+use btmesh_common::address::Address;
 use btmesh_common::{InsufficientBuffer, ParseError};
 use heapless::Vec;
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum SAR {
     Complete,
     First,
@@ -34,7 +35,7 @@ impl From<SAR> for u8 {
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum MessageType {
     NetworkPDU,
     MeshBeacon,
@@ -99,3 +100,127 @@ impl ProxyPDU {
         })
     }
 }
+
+const SET_FILTER_TYPE: u8 = 0x00;
+const ADD_ADDRESSES: u8 = 0x01;
+const REMOVE_ADDRESSES: u8 = 0x02;
+const FILTER_STATUS: u8 = 0x03;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FilterType {
+    Whitelist,
+    Blacklist,
+}
+
+impl FilterType {
+    pub fn parse(data: u8) -> Result<Self, ParseError> {
+        match data {
+            0x00 => Ok(Self::Whitelist),
+            0x01 => Ok(Self::Blacklist),
+            _ => Err(ParseError::InvalidValue),
+        }
+    }
+}
+
+impl From<FilterType> for u8 {
+    fn from(filter_type: FilterType) -> Self {
+        match filter_type {
+            FilterType::Whitelist => 0x00,
+            FilterType::Blacklist => 0x01,
+        }
+    }
+}
+
+/// Proxy configuration message, exchanged between a proxy client and
+/// the proxy server to manage the filter of the connection.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, PartialEq, Debug)]
+pub enum ProxyConfigurationMessage {
+    SetFilterType(FilterType),
+    AddAddresses(Vec<Address, 8>),
+    RemoveAddresses(Vec<Address, 8>),
+    FilterStatus {
+        filter_type: FilterType,
+        list_size: u16,
+    },
+}
+
+impl ProxyConfigurationMessage {
+    /// Parse from the transport PDU of a decrypted proxy configuration PDU.
+    pub fn parse(data: &[u8]) -> Result<Self, ParseError> {
+        if data.is_empty() {
+            return Err(ParseError::InvalidLength);
+        }
+
+        let parameters = &data[1..];
+        match data[0] {
+            SET_FILTER_TYPE => {
+                if parameters.len() != 1 {
+                    return Err(ParseError::InvalidLength);
+                }
+                Ok(Self::SetFilterType(FilterType::parse(parameters[0])?))
+            }
+            ADD_ADDRESSES => Ok(Self::AddAddresses(Self::parse_addresses(parameters)?)),
+            REMOVE_ADDRESSES => Ok(Self::RemoveAddresses(Self::parse_addresses(parameters)?)),
+            FILTER_STATUS => {
+                if parameters.len() != 3 {
+                    return Err(ParseError::InvalidLength);
+                }
+                Ok(Self::FilterStatus {
+                    filter_type: FilterType::parse(parameters[0])?,
+                    list_size: u16::from_be_bytes([parameters[1], parameters[2]]),
+                })
+            }
+            _ => Err(ParseError::InvalidValue),
+        }
+    }
+
+    fn parse_addresses(parameters: &[u8]) -> Result<Vec<Address, 8>, ParseError> {
+        if parameters.len() % 2 != 0 {
+            return Err(ParseError::InvalidLength);
+        }
+
+        // the transport PDU of a single network PDU holds at most 7 addresses.
+        let mut addresses = Vec::new();
+        for address in parameters.chunks_exact(2) {
+            addresses
+                .push(Address::parse([address[0], address[1]]))
+                .map_err(|_| ParseError::InsufficientBuffer)?;
+        }
+        Ok(addresses)
+    }
+
+    pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
+        match self {
+            Self::SetFilterType(filter_type) => {
+                xmit.push(SET_FILTER_TYPE).map_err(|_| InsufficientBuffer)?;
+                xmit.push((*filter_type).into())
+                    .map_err(|_| InsufficientBuffer)?;
+            }
+            Self::AddAddresses(addresses) => {
+                xmit.push(ADD_ADDRESSES).map_err(|_| InsufficientBuffer)?;
+                for address in addresses {
+                    xmit.extend_from_slice(&address.as_bytes())?;
+                }
+            }
+            Self::RemoveAddresses(addresses) => {
+                xmit.push(REMOVE_ADDRESSES)
+                    .map_err(|_| InsufficientBuffer)?;
+                for address in addresses {
+                    xmit.extend_from_slice(&address.as_bytes())?;
+                }
+            }
+            Self::FilterStatus {
+                filter_type,
+                list_size,
+            } => {
+                xmit.push(FILTER_STATUS).map_err(|_| InsufficientBuffer)?;
+                xmit.push((*filter_type).into())
+                    .map_err(|_| InsufficientBuffer)?;
+                xmit.extend_from_slice(&list_size.to_be_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
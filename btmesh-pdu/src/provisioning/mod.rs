@@ -317,7 +317,7 @@ impl Data {
 /// The decrypted provisioning data wrapped in `Data` above.
 pub struct ProvisioningData {
     pub network_key: [u8; 16],
-    // TODO: pub key_index: NetKeyIndex,
+    pub key_index: u16,
     pub key_refresh_flag: KeyRefreshFlag,
     pub iv_update_flag: IvUpdateFlag,
     pub iv_index: u32,
@@ -330,7 +330,7 @@ impl ProvisioningData {
             Err(ParseError::InvalidLength)
         } else {
             let network_key = &data[0..16];
-            // TODO: let key_index = NetKeyIndex::new(u16::from_be_bytes([data[16], data[17]]));
+            let key_index = u16::from_be_bytes([data[16], data[17]]) & 0x0FFF;
             let flags = data[18];
             let iv_index = u32::from_be_bytes([data[19], data[20], data[21], data[22]]);
             let unicast_address = UnicastAddress::parse([data[23], data[24]])?;
@@ -339,7 +339,7 @@ impl ProvisioningData {
                 network_key: network_key
                     .try_into()
                     .map_err(|_| ParseError::InvalidLength)?,
-                // TODO: key_index,
+                key_index,
                 key_refresh_flag: KeyRefreshFlag::parse(flags & 0b00000001),
                 iv_update_flag: IvUpdateFlag::parse(flags & 0b00000010),
                 iv_index,
@@ -349,7 +349,7 @@ impl ProvisioningData {
     }
     pub fn emit<const N: usize>(&self, xmit: &mut Vec<u8, N>) -> Result<(), InsufficientBuffer> {
         xmit.extend_from_slice(&self.network_key)?;
-        xmit.extend_from_slice(&[0; 2])?; // TODO: key_index
+        xmit.extend_from_slice(&self.key_index.to_be_bytes())?;
         let mut flags = 0;
         self.key_refresh_flag.emit(&mut flags);
         self.iv_update_flag.emit(&mut flags);
@@ -365,9 +365,9 @@ impl defmt::Format for ProvisioningData {
     fn format(&self, fmt: defmt::Formatter) {
         defmt::write!(
             fmt,
-            "ProvisioningData( network_key={:x}, flags={}:{}, iv_index={}, unicast_address={:x}",
+            "ProvisioningData( network_key={:x}, key_index={}, flags={}:{}, iv_index={}, unicast_address={:x}",
             self.network_key,
-            //self.key_index,
+            self.key_index,
             self.key_refresh_flag,
             self.iv_update_flag,
             self.iv_index,